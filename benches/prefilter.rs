@@ -0,0 +1,98 @@
+//! # Color Prefilter Benchmark
+//!
+//! Scores 10,000 image sidecars against a reference with and without the
+//! color histogram prefilter used by `search --image --prefilter`.
+//! Run with `cargo bench --bench prefilter`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use rand::{Rng, SeedableRng};
+use scout::config::{DEFAULT_PREFILTER_DISTANCE, HISTOGRAM_BINS};
+use scout::core::FileHash;
+use scout::processing::image::histogram_distance;
+use scout::storage::ImageSidecar;
+use scout::Embedding;
+
+const DIM: usize = 1024;
+const CANDIDATES: usize = 10_000;
+const ROUNDS: usize = 20;
+/// Dominant colors per synthetic image
+const PALETTE: usize = 4;
+
+fn bench(name: &str, run: impl Fn() -> usize) -> Duration {
+	// Warm up caches before timing
+	let scored = run();
+
+	let start = Instant::now();
+	for _ in 0..ROUNDS {
+		black_box(run());
+	}
+	let elapsed = start.elapsed();
+
+	println!(
+		"{:<10} {:>8.2?}/query, {} of {} embeddings compared",
+		name,
+		elapsed / ROUNDS as u32,
+		scored,
+		CANDIDATES
+	);
+	elapsed
+}
+
+fn main() {
+	let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+	let bins = HISTOGRAM_BINS.pow(3);
+
+	// Photos concentrate their color mass in a few bins
+	let random_histogram = |rng: &mut rand::rngs::StdRng| -> Vec<f32> {
+		let mut histogram = vec![0.0f32; bins];
+		for _ in 0..PALETTE {
+			histogram[rng.random_range(0..bins)] += rng.random_range(0.1f32..1.0);
+		}
+		let total: f32 = histogram.iter().sum();
+		histogram.iter().map(|v| v / total).collect()
+	};
+
+	let reference = random_histogram(&mut rng);
+	let query = Embedding::new((0..DIM).map(|_| rng.random_range(-1.0f32..1.0)).collect());
+	let sidecars: Vec<ImageSidecar> = (0..CANDIDATES)
+		.map(|i| {
+			let embedding =
+				Embedding::new((0..DIM).map(|_| rng.random_range(-1.0f32..1.0)).collect());
+			ImageSidecar::new(FileHash::from_hex(format!("{:016x}", i)), embedding)
+				.with_histogram(random_histogram(&mut rng))
+		})
+		.collect();
+
+	println!(
+		"{} x {}-dim image sidecars, {} rounds, prefilter distance {}",
+		CANDIDATES, DIM, ROUNDS, DEFAULT_PREFILTER_DISTANCE
+	);
+
+	let full = bench("full", || {
+		sidecars.iter().fold(0, |scored, sidecar| {
+			black_box(query.similarity(&sidecar.embedding()));
+			scored + 1
+		})
+	});
+
+	let prefiltered = bench("prefilter", || {
+		sidecars
+			.iter()
+			.filter(|sidecar| {
+				sidecar.histogram().map_or(true, |histogram| {
+					histogram_distance(&reference, histogram) <= DEFAULT_PREFILTER_DISTANCE
+				})
+			})
+			.fold(0, |scored, sidecar| {
+				black_box(query.similarity(&sidecar.embedding()));
+				scored + 1
+			})
+	});
+
+	println!(
+		"speedup    {:.1}x",
+		full.as_secs_f64() / prefiltered.as_secs_f64()
+	);
+}
@@ -0,0 +1,51 @@
+//! # Similarity Benchmark
+//!
+//! Compares the scalar and lane-parallel dot products on 1024-dim vectors.
+//! Run with `cargo bench --features simd`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use rand::{Rng, SeedableRng};
+use scout::core::embedding::{dot_lanes, dot_scalar};
+
+const DIM: usize = 1024;
+const CANDIDATES: usize = 10_000;
+const ROUNDS: usize = 20;
+
+fn bench(name: &str, query: &[f32], candidates: &[Vec<f32>], dot: fn(&[f32], &[f32]) -> f32) {
+	// Warm up caches before timing
+	for candidate in candidates {
+		black_box(dot(query, candidate));
+	}
+
+	let start = Instant::now();
+	for _ in 0..ROUNDS {
+		for candidate in candidates {
+			black_box(dot(black_box(query), black_box(candidate)));
+		}
+	}
+	let elapsed = start.elapsed();
+
+	let per_call = elapsed.as_nanos() as f64 / (ROUNDS * candidates.len()) as f64;
+	println!(
+		"{:<8} {:>8.1} ns/call ({:.2?} total)",
+		name, per_call, elapsed
+	);
+}
+
+fn main() {
+	let mut rng = rand::rngs::StdRng::seed_from_u64(0);
+	let mut random_vector =
+		|| -> Vec<f32> { (0..DIM).map(|_| rng.random_range(-1.0f32..1.0)).collect() };
+
+	let query = random_vector();
+	let candidates: Vec<Vec<f32>> = (0..CANDIDATES).map(|_| random_vector()).collect();
+
+	println!(
+		"{} x {}-dim dot products, {} rounds",
+		CANDIDATES, DIM, ROUNDS
+	);
+	bench("scalar", &query, &candidates, dot_scalar);
+	bench("simd", &query, &candidates, dot_lanes);
+}
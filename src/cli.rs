@@ -4,9 +4,11 @@
 //! All commands and global flags are declared here.
 
 use clap::{Parser, Subcommand, ValueEnum};
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
-#[derive(Debug, Clone, Copy, ValueEnum)]
+#[derive(Debug, Clone, Copy, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum Provider {
 	Auto,
 	Cpu,
@@ -29,16 +31,134 @@ pub struct Cli {
 	#[arg(long, global = true, value_enum, help = "Compute provider to use")]
 	pub provider: Option<Provider>,
 
+	#[arg(
+		long,
+		global = true,
+		value_enum,
+		help = "How images are fitted to the model input (rescan after changing)"
+	)]
+	pub preprocess: Option<crate::models::PreprocessMode>,
+
+	#[arg(
+		long,
+		global = true,
+		help = "Rotate photos upright using their EXIF orientation (rescan after changing)"
+	)]
+	pub auto_orient: bool,
+
+	#[arg(
+		long,
+		global = true,
+		value_parser = crate::runtime::parse_threads,
+		help = "ONNX intra-op threads [default: one per CPU]"
+	)]
+	pub threads: Option<usize>,
+
+	#[arg(
+		long,
+		global = true,
+		value_parser = crate::runtime::parse_threads,
+		help = "ONNX inter-op threads (runs independent operators in parallel)"
+	)]
+	pub inter_threads: Option<usize>,
+
 	#[arg(long, global = true, help = "Path to models directory")]
 	pub model_dir: Option<PathBuf>,
 
 	#[arg(long, global = true, help = "Path to FFmpeg binary")]
 	pub ffmpeg_path: Option<PathBuf>,
 
+	#[arg(
+		long,
+		global = true,
+		value_name = "PATH",
+		help = "Keep all sidecars in this directory instead of .scout folders (or set SCOUT_STORE)"
+	)]
+	pub store: Option<PathBuf>,
+
+	#[arg(
+		long,
+		global = true,
+		help = "Skip symlinked directories when scanning and searching"
+	)]
+	pub no_follow_symlinks: bool,
+
 	#[command(subcommand)]
 	pub command: Command,
 }
 
+impl Cli {
+	/// Fill in flags left unset on the command line from the `.scoutconfig`
+	/// of the directory being scanned, searched or watched.
+	///
+	/// Search reads the config of its first `--dir`.
+	pub fn apply_dir_config(&mut self) -> anyhow::Result<()> {
+		let dir = match &self.command {
+			Command::Scan { dir, .. } | Command::Watch { dir, .. } => dir,
+			Command::Search { dir, .. } => match dir.first() {
+				Some(dir) => dir,
+				None => return Ok(()),
+			},
+			_ => return Ok(()),
+		};
+		let Some(config) = crate::config::DirConfig::load(dir)? else {
+			return Ok(());
+		};
+		crate::ui::debug(&format!(
+			"Using defaults from {}",
+			dir.join(crate::config::DIR_CONFIG_FILE).display()
+		));
+
+		self.recursive = self.recursive || config.recursive.unwrap_or(false);
+		self.provider = self.provider.or(config.provider);
+
+		match &mut self.command {
+			Command::Scan {
+				min_resolution,
+				max_size,
+				max_frames,
+				exclude_dir,
+				..
+			} => {
+				*min_resolution = min_resolution.or(config.min_resolution);
+				*max_size = max_size.or(config.max_size_mb);
+				*max_frames = max_frames.or(config.max_frames);
+				if exclude_dir.is_empty() {
+					*exclude_dir = config.exclude;
+				}
+			}
+			Command::Watch {
+				min_resolution,
+				max_size,
+				max_frames,
+				..
+			} => {
+				*min_resolution = min_resolution.or(config.min_resolution);
+				*max_size = max_size.or(config.max_size_mb);
+				*max_frames = max_frames.or(config.max_frames);
+			}
+			_ => {}
+		}
+		Ok(())
+	}
+}
+
+#[derive(Subcommand)]
+pub enum ModelsAction {
+	/// Download the default SigLIP2 models into the models directory
+	Download {
+		#[arg(
+			long,
+			default_value = crate::config::MODELS_URL,
+			help = "Base URL to fetch the model files from"
+		)]
+		url: String,
+
+		#[arg(short, long, help = "Download files that are already present again")]
+		force: bool,
+	},
+}
+
 #[derive(Subcommand)]
 pub enum Command {
 	/// Index media files in a directory
@@ -58,21 +178,128 @@ pub enum Command {
 		#[arg(long, help = "Skip video files")]
 		exclude_videos: bool,
 
-		#[arg(long, help = "Maximum frames to extract per video")]
+		#[arg(
+			long,
+			value_parser = crate::processing::video::parse_max_frames,
+			help = "Maximum frames to extract per video",
+			long_help = "Maximum frames to extract per video [default: 15]\n\n\
+				Frames are taken at scene changes, so short clips rarely reach the cap. \
+				Raise it for long videos with many cuts (films, vlogs) so later scenes \
+				stay searchable; lower it to keep sidecars of long recordings small."
+		)]
 		max_frames: Option<usize>,
 
-		#[arg(long, help = "Scene detection threshold (0.0-1.0)")]
+		#[arg(
+			long,
+			value_parser = crate::processing::video::parse_scene_threshold,
+			help = "Scene detection threshold (0.0-1.0)",
+			long_help = "Scene detection threshold (0.0-1.0) [default: 0.3]\n\n\
+				Lower values count smaller changes as new scenes. Try 0.1-0.2 for short \
+				or static clips that would otherwise yield a single frame, and 0.4-0.5 \
+				for long, fast-cut videos that hit --max-frames early."
+		)]
 		scene_threshold: Option<f32>,
+
+		#[arg(
+			long,
+			value_enum,
+			default_value_t = crate::processing::video::SamplingMode::Scene,
+			help = "How frames are picked from videos"
+		)]
+		sampling: crate::processing::video::SamplingMode,
+
+		#[arg(
+			long,
+			default_value_t = 0,
+			help = "Video stream to extract frames from (0 = first)"
+		)]
+		video_stream: usize,
+
+		#[arg(
+			long,
+			value_name = "SIM",
+			value_parser = crate::processing::video::parse_frame_dedup,
+			help = "Drop video frames more similar than this (0.0-1.0) to a frame already kept"
+		)]
+		frame_dedup: Option<f32>,
+
+		#[arg(
+			long,
+			value_name = "FRACTION",
+			value_parser = crate::processing::video::parse_strict_frames,
+			help = "Fail a video when fewer than this fraction (0.0-1.0) of its frames can be extracted"
+		)]
+		strict_frames: Option<f32>,
+
+		#[arg(
+			long,
+			value_name = "N",
+			value_parser = crate::processing::video::parse_max_embeddings,
+			help = "Keep at most N frame embeddings per video, one per cluster of similar frames"
+		)]
+		max_video_embeddings: Option<usize>,
+
+		#[arg(long, default_value_t = crate::config::DEFAULT_BATCH_SIZE, help = "Images per model forward pass")]
+		batch_size: usize,
+
+		#[arg(
+			long,
+			default_value_t = crate::config::DEFAULT_SCAN_WORKERS,
+			value_parser = crate::runtime::parse_threads,
+			help = "Files encoded in parallel, each worker loads its own model (may exhaust GPU memory)"
+		)]
+		workers: usize,
+
+		#[arg(long, help = "Store image embeddings as int8 (~4x smaller sidecars)")]
+		quantize: bool,
+
+		#[arg(
+			long,
+			help = "Store embeddings as f16 (~2x smaller sidecars, slightly lossy)"
+		)]
+		half: bool,
+
+		#[arg(long, help = "Report what would be processed without loading models")]
+		dry_run: bool,
+
+		#[arg(
+			long,
+			value_name = "GLOB",
+			help = "Exclude paths matching a gitignore-style glob (repeatable)"
+		)]
+		exclude_dir: Vec<String>,
+
+		#[arg(
+			long,
+			value_enum,
+			default_value_t = crate::core::HashMode::Fast,
+			help = "File identity hash: fast (first 64KB) or full (entire file)"
+		)]
+		hash: crate::core::HashMode,
 	},
 
 	/// Search indexed media
 	Search {
-		/// Search query text (optional if using --image)
-		query: Option<String>,
+		/// Search query text (optional if using --image). Several queries are averaged
+		query: Vec<String>,
 
-		#[arg(short, long, help = "Reference image path")]
+		#[arg(short, long, help = "Reference image path ('-' reads it from stdin)")]
 		image: Option<PathBuf>,
 
+		#[arg(
+			long,
+			conflicts_with = "image",
+			help = "Download the reference image (needs the 'http' feature)"
+		)]
+		image_url: Option<String>,
+
+		#[arg(
+			long,
+			value_name = "X,Y,W,H",
+			help = "Search with only this pixel region of the reference image"
+		)]
+		crop: Option<crate::processing::image::Region>,
+
 		#[arg(
 			short,
 			long,
@@ -81,17 +308,41 @@ pub enum Command {
 		)]
 		weight: f32,
 
+		#[arg(
+			long,
+			value_enum,
+			default_value_t = crate::commands::search::CombineMode::Blend,
+			help = "Combine text+image by blending the queries, or by the max/mean/min of their scores"
+		)]
+		combine_mode: crate::commands::search::CombineMode,
+
 		#[arg(long, help = "Negative query to exclude")]
 		not: Option<String>,
 
-		#[arg(short, long, default_value = ".")]
-		dir: PathBuf,
+		#[arg(
+			short,
+			long,
+			default_value = ".",
+			help = "Directory to search (repeat to search several)"
+		)]
+		dir: Vec<PathBuf>,
 
-		#[arg(short = 'n', long, default_value_t = crate::config::DEFAULT_LIMIT)]
+		#[arg(
+			short = 'n',
+			long,
+			default_value_t = crate::config::DEFAULT_LIMIT,
+			value_parser = crate::commands::search::parse_limit,
+			help = "Max results, or 'all' (also 0) for every match above --score"
+		)]
 		limit: usize,
 
-		#[arg(short, long, default_value_t = crate::config::DEFAULT_MIN_SCORE)]
-		score: f32,
+		#[arg(
+			short,
+			long,
+			default_value_t = crate::commands::search::ScoreThreshold::Fixed(crate::config::DEFAULT_MIN_SCORE),
+			help = "Minimum similarity score, or 'auto' to cut at the natural gap in scores"
+		)]
+		score: crate::commands::search::ScoreThreshold,
 
 		#[arg(short, long)]
 		open: bool,
@@ -105,8 +356,218 @@ pub enum Command {
 		#[arg(long, help = "Output only paths to stdout")]
 		paths: bool,
 
-		#[arg(long, help = "Export results as JSON to file")]
+		#[arg(long, help = "Export results to file ('-' for stdout)")]
 		export: Option<PathBuf>,
+
+		#[arg(
+			long,
+			value_enum,
+			help = "Output format for --export, or stdout when used alone [default: json]"
+		)]
+		format: Option<crate::commands::search::OutputFormat>,
+
+		#[arg(long, help = "Forward the query to a running 'scout daemon'")]
+		daemon: bool,
+
+		#[arg(long, default_value_t = crate::config::DEFAULT_DAEMON_PORT, help = "Daemon port")]
+		port: u16,
+
+		#[arg(
+			long,
+			help = "Use approximate nearest-neighbor search (faster on large libraries)"
+		)]
+		ann: bool,
+
+		#[arg(
+			long,
+			value_enum,
+			default_value_t = crate::storage::VideoAggregation::Best,
+			help = "How video frame scores are combined"
+		)]
+		video_agg: crate::storage::VideoAggregation,
+
+		#[arg(long, default_value_t = crate::config::DEFAULT_VIDEO_TOPK, help = "Frames averaged with --video-agg topk")]
+		video_topk: usize,
+
+		#[arg(
+			long,
+			value_name = "FRAMES",
+			help = "Average each video frame's score with its neighbours over a window of FRAMES, favoring sustained matches over single-frame spikes"
+		)]
+		temporal_smooth: Option<usize>,
+
+		#[arg(
+			long,
+			value_name = "MM:SS",
+			value_parser = crate::processing::video::parse_timestamp,
+			help = "Only match video frames at or after this timestamp"
+		)]
+		after: Option<f64>,
+
+		#[arg(
+			long,
+			value_name = "MM:SS",
+			value_parser = crate::processing::video::parse_timestamp,
+			help = "Only match video frames at or before this timestamp"
+		)]
+		before: Option<f64>,
+
+		#[arg(
+			long,
+			value_name = "YYYY-MM-DD",
+			value_parser = crate::core::media::parse_date,
+			help = "Only match photos taken on or after this date (EXIF), skipping undated files"
+		)]
+		after_date: Option<chrono::NaiveDate>,
+
+		#[arg(
+			long,
+			value_name = "YYYY-MM-DD",
+			value_parser = crate::core::media::parse_date,
+			help = "Only match photos taken on or before this date (EXIF), skipping undated files"
+		)]
+		before_date: Option<chrono::NaiveDate>,
+
+		#[arg(
+			long,
+			value_name = "M",
+			value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+			help = "List up to M matching moments per video instead of one"
+		)]
+		frames_per_video: Option<usize>,
+
+		#[arg(
+			long,
+			value_name = "SECS",
+			default_value_t = crate::config::DEFAULT_FRAME_GAP,
+			help = "Moments closer than this collapse into one with --frames-per-video"
+		)]
+		frame_gap: f64,
+
+		#[arg(
+			long,
+			help = "Show calibrated percentages and apply --score to them (exported scores stay raw)"
+		)]
+		calibrate: bool,
+
+		#[arg(
+			long,
+			default_value_t = crate::config::SCORE_CALIBRATION_TEMPERATURE,
+			help = "Steepness of --calibrate (higher spreads scores further)"
+		)]
+		temperature: f32,
+
+		#[arg(
+			long,
+			help = "Rescale shown percentages so the top result is 100% and the --score cutoff 0% (relative, not confidence)"
+		)]
+		normalize_scores: bool,
+
+		#[arg(
+			long,
+			conflicts_with = "hybrid",
+			help = "Group results into strong and possible matches and hide weaker ones"
+		)]
+		tiers: bool,
+
+		#[arg(
+			long,
+			value_name = "LAMBDA",
+			help = "Re-rank for variety: 1.0 = most relevant, lower = fewer near-duplicates (try 0.7)"
+		)]
+		diverse: Option<f32>,
+
+		#[arg(
+			long,
+			help = "With --image, skip images whose colors differ strongly before comparing embeddings"
+		)]
+		prefilter: bool,
+
+		#[arg(
+			long,
+			value_name = "DIST",
+			default_value_t = crate::config::DEFAULT_PREFILTER_DISTANCE,
+			help = "Color distance (0.0-1.0) beyond which --prefilter skips an image"
+		)]
+		prefilter_distance: f32,
+
+		#[arg(
+			long,
+			help = "Embed a base64 JPEG thumbnail of each result in JSON output"
+		)]
+		export_thumbnails: bool,
+
+		#[arg(
+			long,
+			value_name = "PX",
+			value_parser = crate::commands::search::parse_thumbnail_size,
+			default_value_t = crate::config::EXPORT_THUMBNAIL_SIZE,
+			help = "Longest edge of --export-thumbnails images (at most 512)"
+		)]
+		thumbnail_size: u32,
+
+		#[arg(
+			long,
+			value_parser = crate::commands::search::parse_jpeg_quality,
+			default_value_t = crate::config::EXPORT_THUMBNAIL_QUALITY,
+			help = "JPEG quality of --export-thumbnails images (1-100)"
+		)]
+		thumbnail_quality: u8,
+
+		#[arg(
+			long,
+			help = "Fail instead of warning when sidecars come from different models"
+		)]
+		strict: bool,
+
+		#[arg(
+			long,
+			help = "Read sidecars one at a time to bound memory (automatic above 250,000 files)"
+		)]
+		streaming: bool,
+
+		#[arg(
+			long,
+			help = "Keep results that are the same file under another path or a near-identical copy"
+		)]
+		no_dedup: bool,
+
+		#[arg(
+			long,
+			value_enum,
+			default_value_t = crate::models::LongTextMode::Truncate,
+			help = "Queries over 64 tokens: truncate them, or encode every chunk and average"
+		)]
+		long_text: crate::models::LongTextMode,
+
+		#[arg(
+			long,
+			value_name = "ALPHA",
+			requires = "query",
+			value_parser = crate::commands::search::parse_hybrid,
+			help = "Also match query words in filenames; ALPHA weights semantic vs keyword score (try 0.7)"
+		)]
+		hybrid: Option<f32>,
+	},
+
+	/// Browse by visual similarity, jumping from a result to more like it
+	Explore {
+		#[arg(short, long, help = "Image to start from")]
+		image: PathBuf,
+
+		#[arg(
+			short,
+			long,
+			default_value = ".",
+			help = "Directory to explore (repeat to explore several)"
+		)]
+		dir: Vec<PathBuf>,
+
+		#[arg(short = 'n', long, default_value_t = crate::config::DEFAULT_LIMIT)]
+		limit: usize,
+
+		#[arg(long, help = "Exclude videos from results")]
+		exclude_videos: bool,
 	},
 
 	/// Cluster media by visual similarity
@@ -117,14 +578,28 @@ pub enum Command {
 		#[arg(short, long, help = "Force reclustering even if cached")]
 		force: bool,
 
+		#[arg(
+			long,
+			conflicts_with = "force",
+			help = "Add new files to the cached clusters instead of reclustering"
+		)]
+		incremental: bool,
+
+		#[arg(
+			long,
+			default_value_t = crate::config::DEFAULT_JOIN_SIMILARITY,
+			help = "With --incremental, similarity to a cluster's centroid a new file needs to join it"
+		)]
+		join_similarity: f32,
+
 		#[arg(long, default_value_t = crate::config::DEFAULT_MIN_CLUSTER_SIZE, help = "Minimum images per cluster")]
 		min_cluster_size: usize,
 
 		#[arg(long, help = "Minimum samples for core points")]
 		min_samples: Option<usize>,
 
-		#[arg(long, default_value_t = crate::config::DEFAULT_COHESION_THRESHOLD, help = "Minimum cohesion threshold (0.0-1.0)")]
-		threshold: f32,
+		#[arg(long, alias = "threshold", default_value_t = crate::config::DEFAULT_COHESION_THRESHOLD, help = "Drop clusters below this cohesion into noise (0.0-1.0)")]
+		min_cohesion: f32,
 
 		#[arg(
 			long,
@@ -138,6 +613,17 @@ pub enum Command {
 		#[arg(long, default_value_t = crate::config::DEFAULT_UMAP_COMPONENTS, help = "UMAP n_components (target dimensions)")]
 		umap_components: usize,
 
+		#[arg(long, help = "Seed UMAP's initial layout for reproducible runs")]
+		seed: Option<u64>,
+
+		#[arg(
+			long,
+			value_enum,
+			default_value_t = crate::core::Metric::Cosine,
+			help = "Distance for HDBSCAN and cohesion (applies to UMAP output with --use-umap)"
+		)]
+		metric: crate::core::Metric,
+
 		#[arg(
 			short = 'p',
 			long,
@@ -148,12 +634,100 @@ pub enum Command {
 
 		#[arg(long, help = "Export results as JSON to file")]
 		export: Option<PathBuf>,
+
+		#[arg(long, value_enum, help = "Write results to stdout in this format")]
+		format: Option<crate::commands::cluster::ClusterFormat>,
+
+		#[arg(
+			long,
+			help = "Include each cluster's mean embedding in exported results"
+		)]
+		include_centroids: bool,
+
+		#[arg(
+			long,
+			value_name = "DIR",
+			help = "Write a contact sheet of member thumbnails per cluster to this directory"
+		)]
+		thumbnails: Option<PathBuf>,
+
+		#[arg(
+			long,
+			value_name = "FILE",
+			help = "Name clusters after the closest terms in this newline-separated vocabulary"
+		)]
+		labels: Option<PathBuf>,
+
+		#[arg(
+			long,
+			help = "Fail instead of warning when sidecars come from different models"
+		)]
+		strict: bool,
+	},
+
+	/// Rebuild the consolidated index used to speed up search
+	Reindex {
+		#[arg(short, long, default_value = ".")]
+		dir: PathBuf,
+	},
+
+	/// Write all sidecars under a directory into one archive file
+	Export {
+		#[arg(short, long, default_value = ".")]
+		dir: PathBuf,
+
+		#[arg(short, long, help = "Archive file to write")]
+		output: PathBuf,
+	},
+
+	/// Recreate sidecars from an archive written by 'scout export'
+	Import {
+		#[arg(short, long, help = "Archive file to read")]
+		input: PathBuf,
+
+		#[arg(
+			short,
+			long,
+			help = "Only restore sidecars for media under this directory"
+		)]
+		dir: Option<PathBuf>,
+
+		#[arg(
+			long,
+			value_name = "DIR",
+			help = "Find the media under DIR instead of the directory it was exported from"
+		)]
+		rebase: Option<PathBuf>,
+	},
+
+	/// Summarize the index of a directory
+	Info {
+		#[arg(short, long, default_value = ".")]
+		dir: PathBuf,
+
+		#[arg(long, help = "Export as JSON (use '-' for stdout)")]
+		export: Option<PathBuf>,
 	},
 
 	/// Remove orphaned sidecars
 	Clean {
 		#[arg(short, long, default_value = ".")]
 		dir: PathBuf,
+
+		#[arg(
+			long,
+			help = "Also delete unreadable sidecars so the next scan re-embeds them"
+		)]
+		repair: bool,
+	},
+
+	/// Check that every media file has a current sidecar
+	Verify {
+		#[arg(short, long, default_value = ".")]
+		dir: PathBuf,
+
+		#[arg(long, help = "Delete sidecars left behind by changed or removed files")]
+		fix: bool,
 	},
 
 	/// Watch directory for changes and auto-index
@@ -170,11 +744,160 @@ pub enum Command {
 		#[arg(long, help = "Skip video files")]
 		exclude_videos: bool,
 
-		#[arg(long, help = "Maximum frames to extract per video")]
+		#[arg(
+			long,
+			value_parser = crate::processing::video::parse_max_frames,
+			help = "Maximum frames to extract per video",
+			long_help = "Maximum frames to extract per video [default: 15]\n\n\
+				Frames are taken at scene changes, so short clips rarely reach the cap. \
+				Raise it for long videos with many cuts (films, vlogs) so later scenes \
+				stay searchable; lower it to keep sidecars of long recordings small."
+		)]
 		max_frames: Option<usize>,
 
-		#[arg(long, help = "Scene detection threshold (0.0-1.0)")]
+		#[arg(
+			long,
+			value_parser = crate::processing::video::parse_scene_threshold,
+			help = "Scene detection threshold (0.0-1.0)",
+			long_help = "Scene detection threshold (0.0-1.0) [default: 0.3]\n\n\
+				Lower values count smaller changes as new scenes. Try 0.1-0.2 for short \
+				or static clips that would otherwise yield a single frame, and 0.4-0.5 \
+				for long, fast-cut videos that hit --max-frames early."
+		)]
 		scene_threshold: Option<f32>,
+
+		#[arg(
+			long,
+			value_enum,
+			default_value_t = crate::processing::video::SamplingMode::Scene,
+			help = "How frames are picked from videos"
+		)]
+		sampling: crate::processing::video::SamplingMode,
+
+		#[arg(
+			long,
+			default_value_t = 0,
+			help = "Video stream to extract frames from (0 = first)"
+		)]
+		video_stream: usize,
+
+		#[arg(
+			long,
+			value_name = "SIM",
+			value_parser = crate::processing::video::parse_frame_dedup,
+			help = "Drop video frames more similar than this (0.0-1.0) to a frame already kept"
+		)]
+		frame_dedup: Option<f32>,
+
+		#[arg(
+			long,
+			value_name = "FRACTION",
+			value_parser = crate::processing::video::parse_strict_frames,
+			help = "Fail a video when fewer than this fraction (0.0-1.0) of its frames can be extracted"
+		)]
+		strict_frames: Option<f32>,
+
+		#[arg(
+			long,
+			value_name = "N",
+			value_parser = crate::processing::video::parse_max_embeddings,
+			help = "Keep at most N frame embeddings per video, one per cluster of similar frames"
+		)]
+		max_video_embeddings: Option<usize>,
+
+		#[arg(long, help = "Store image embeddings as int8 (~4x smaller sidecars)")]
+		quantize: bool,
+
+		#[arg(
+			long,
+			help = "Store embeddings as f16 (~2x smaller sidecars, slightly lossy)"
+		)]
+		half: bool,
+
+		#[arg(
+			long,
+			value_name = "N",
+			help = "Keep the consolidated index up to date, writing it every N new files"
+		)]
+		index_every: Option<usize>,
+	},
+
+	/// Show the video streams available in a media file
+	Probe {
+		/// Video file to inspect
+		file: PathBuf,
+	},
+
+	/// Measure image and text encoding speed on synthetic inputs
+	Bench {
+		#[arg(
+			long,
+			default_value_t = crate::config::DEFAULT_BENCH_IMAGES,
+			help = "Random 512x512 images to encode (0 skips the vision model)"
+		)]
+		images: usize,
+
+		#[arg(
+			long,
+			default_value_t = crate::config::DEFAULT_BENCH_TEXTS,
+			help = "Random queries to encode (0 skips the text model)"
+		)]
+		text: usize,
+	},
+
+	/// List execution providers and whether they can be used
+	Providers {
+		#[arg(long, help = "Export as JSON (use '-' for stdout)")]
+		export: Option<PathBuf>,
+	},
+
+	/// Manage the ONNX model files
+	Models {
+		#[command(subcommand)]
+		action: ModelsAction,
+	},
+
+	/// Run 'scout serve' on localhost for 'scout search --daemon'
+	Daemon {
+		#[arg(long, default_value_t = crate::config::DEFAULT_DAEMON_PORT, help = "Port to listen on (localhost only)")]
+		port: u16,
+	},
+
+	/// Serve encode and search requests to other programs as JSON lines
+	Serve {
+		#[arg(long, default_value = crate::config::DEFAULT_SERVE_ADDR, help = "Address to listen on")]
+		addr: SocketAddr,
+	},
+
+	/// Find near-duplicate media by embedding similarity
+	Dedup {
+		#[arg(short, long, default_value = ".")]
+		dir: PathBuf,
+
+		#[arg(short, long, default_value_t = crate::config::DEFAULT_DEDUP_THRESHOLD, help = "Minimum similarity to count as duplicate (0.0-1.0)")]
+		threshold: f32,
+
+		#[arg(long, help = "Export groups as JSON to file")]
+		export: Option<PathBuf>,
+
+		#[arg(long, help = "Delete duplicates, keeping one file per group")]
+		delete: bool,
+	},
+
+	/// Export pairwise similarities between all indexed media
+	Matrix {
+		#[arg(short, long, default_value = ".")]
+		dir: PathBuf,
+
+		#[arg(long, help = "Write the matrix as JSON to file ('-' for stdout)")]
+		export: PathBuf,
+
+		#[arg(
+			long,
+			value_name = "K",
+			help = "Only keep each file's K most similar neighbors"
+		)]
+		top_k: Option<usize>,
 	},
 
 	/// Find statistically unusual media (outliers)
@@ -190,5 +913,11 @@ pub enum Command {
 
 		#[arg(long, help = "Export results as JSON to file")]
 		export: Option<PathBuf>,
+
+		#[arg(
+			long,
+			help = "Rank outliers within each cached cluster instead of the whole library"
+		)]
+		within_cluster: bool,
 	},
 }
@@ -0,0 +1,172 @@
+//! # Bench Command
+//!
+//! Encode synthetic images and queries and report throughput and latency,
+//! so providers (`--provider cpu`, `cuda`, `coreml`, ...) and machines can be
+//! compared on the same workload.
+
+use anyhow::Result;
+use colored::*;
+use rand::seq::IndexedRandom;
+use rand::Rng;
+use std::time::{Duration, Instant};
+
+use crate::config::INPUT_SIZE;
+use crate::models::Models;
+use crate::ui;
+
+/// Words random benchmark queries are made of
+const WORDS: &[&str] = &[
+	"a", "red", "car", "dog", "beach", "sunset", "mountain", "city", "street", "at", "night",
+	"old", "photo", "of", "people", "walking", "in", "the", "rain", "snowy", "forest", "with",
+	"small", "boat", "on", "lake", "bright", "kitchen", "cat", "sleeping", "sofa", "crowd",
+];
+
+/// Latencies of one benchmark
+struct Timing {
+	count: usize,
+	total: Duration,
+	p50: Duration,
+	p99: Duration,
+}
+
+impl Timing {
+	fn from_samples(mut samples: Vec<Duration>) -> Self {
+		samples.sort();
+		Self {
+			count: samples.len(),
+			total: samples.iter().sum(),
+			p50: percentile(&samples, 50.0),
+			p99: percentile(&samples, 99.0),
+		}
+	}
+
+	fn per_second(&self) -> f64 {
+		self.count as f64 / self.total.as_secs_f64().max(f64::EPSILON)
+	}
+}
+
+pub fn run(images: usize, texts: usize) -> Result<()> {
+	let mut models = Models::new()?;
+	if images > 0 {
+		models.require_vision()?;
+	}
+	// Every query should reach the text encoder
+	models.set_text_cache(0);
+
+	let mut rng = rand::rng();
+	let inputs: Vec<image::DynamicImage> = (0..images).map(|_| random_image(&mut rng)).collect();
+	let queries: Vec<String> = (0..texts).map(|_| random_query(&mut rng)).collect();
+
+	// The first run of a session pays for graph optimization and allocation
+	ui::info("Warming up...");
+	if images > 0 {
+		models.encode_image(&random_image(&mut rng))?;
+	}
+	if texts > 0 {
+		models.encode_text(&random_query(&mut rng))?;
+	}
+
+	let mut results = Vec::new();
+	if images > 0 {
+		ui::info(&format!("Encoding {} images...", images));
+		let samples = inputs
+			.iter()
+			.map(|image| timed(|| models.encode_image(image)))
+			.collect::<Result<Vec<_>>>()?;
+		results.push(("Images", Timing::from_samples(samples)));
+	}
+	if texts > 0 {
+		ui::info(&format!("Encoding {} queries...", texts));
+		let samples = queries
+			.iter()
+			.map(|query| timed(|| models.encode_text(query)))
+			.collect::<Result<Vec<_>>>()?;
+		results.push(("Text", Timing::from_samples(samples)));
+	}
+
+	if results.is_empty() {
+		ui::warn("Nothing to benchmark. Pass --images or --text above 0");
+		return Ok(());
+	}
+
+	eprintln!();
+	println!(
+		"{:<8}  {:>6}  {:>10}  {:>9}  {:>9}",
+		"Input".bold(),
+		"Count".bold(),
+		"Per second".bold(),
+		"p50".bold(),
+		"p99".bold()
+	);
+	for (name, timing) in &results {
+		println!(
+			"{:<8}  {:>6}  {:>10}  {:>9}  {:>9}",
+			name,
+			timing.count,
+			format!("{:.1}", timing.per_second()).bright_green(),
+			format_ms(timing.p50),
+			format_ms(timing.p99)
+		);
+	}
+
+	Ok(())
+}
+
+fn timed<T>(encode: impl FnOnce() -> Result<T>) -> Result<Duration> {
+	let start = Instant::now();
+	encode()?;
+	Ok(start.elapsed())
+}
+
+/// Nearest-rank percentile of ascending `samples`
+fn percentile(samples: &[Duration], p: f64) -> Duration {
+	if samples.is_empty() {
+		return Duration::ZERO;
+	}
+	let rank = (p / 100.0 * samples.len() as f64).ceil() as usize;
+	samples[rank.clamp(1, samples.len()) - 1]
+}
+
+fn format_ms(duration: Duration) -> String {
+	format!("{:.1}ms", duration.as_secs_f64() * 1000.0)
+}
+
+fn random_image(rng: &mut impl Rng) -> image::DynamicImage {
+	image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(INPUT_SIZE, INPUT_SIZE, |_, _| {
+		image::Rgb(rng.random())
+	}))
+}
+
+fn random_query(rng: &mut impl Rng) -> String {
+	let length = rng.random_range(3..=10);
+	(0..length)
+		.map(|_| *WORDS.choose(rng).expect("non-empty vocabulary"))
+		.collect::<Vec<_>>()
+		.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_percentiles_use_nearest_rank() {
+		let samples: Vec<Duration> = (1..=200).map(Duration::from_millis).collect();
+		let timing = Timing::from_samples(samples.into_iter().rev().collect());
+
+		assert_eq!(timing.count, 200);
+		assert_eq!(timing.p50, Duration::from_millis(100));
+		assert_eq!(timing.p99, Duration::from_millis(198));
+		assert_eq!(
+			percentile(&[Duration::from_millis(7)], 99.0),
+			Duration::from_millis(7)
+		);
+		assert_eq!(percentile(&[], 50.0), Duration::ZERO);
+	}
+
+	#[test]
+	#[ignore = "requires model files (set SCOUT_MODELS_DIR)"]
+	fn test_bench_smoke() {
+		run(2, 2).unwrap();
+	}
+}
@@ -1,6 +1,8 @@
 //! # Clean Command
 //!
 //! Remove orphaned sidecar files whose source media no longer exists.
+//! With `--repair`, also remove sidecars that can no longer be read so the
+//! next scan re-embeds their media.
 
 use std::fs;
 use std::path::Path;
@@ -8,15 +10,25 @@ use std::path::Path;
 use crate::storage;
 use crate::ui;
 
-pub fn run(dir: &Path, recursive: bool) -> anyhow::Result<()> {
+pub fn run(dir: &Path, recursive: bool, repair: bool) -> anyhow::Result<()> {
 	ui::info(&format!("Scanning: {}", dir.display()));
 
 	let sidecars = storage::scan(dir, recursive);
 	let mut orphaned = Vec::new();
+	let mut corrupt = Vec::new();
 
 	for (sidecar_path, media_dir) in sidecars {
-		let Ok(sidecar) = storage::load(&sidecar_path) else {
-			continue;
+		let sidecar = match storage::load(&sidecar_path) {
+			Ok(sidecar) => sidecar,
+			Err(e) => {
+				ui::debug(&format!(
+					"Unreadable sidecar {}: {:#}",
+					sidecar_path.display(),
+					e
+				));
+				corrupt.push(sidecar_path);
+				continue;
+			}
 		};
 
 		let hash = sidecar.hash();
@@ -26,6 +38,24 @@ pub fn run(dir: &Path, recursive: bool) -> anyhow::Result<()> {
 		}
 	}
 
+	if !corrupt.is_empty() {
+		if repair {
+			for path in &corrupt {
+				fs::remove_file(path)?;
+				ui::debug(&format!("Deleted: {}", path.display()));
+			}
+			ui::success(&format!(
+				"Removed {} corrupt sidecars. Run 'scout scan' to re-embed their media",
+				corrupt.len()
+			));
+		} else {
+			ui::warn(&format!(
+				"Found {} unreadable sidecars. Rerun with --repair to remove them",
+				corrupt.len()
+			));
+		}
+	}
+
 	if orphaned.is_empty() {
 		ui::success("No orphaned sidecars found");
 		return Ok(());
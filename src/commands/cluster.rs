@@ -12,10 +12,12 @@ use anyhow::{Context, Result};
 use colored::*;
 use serde::{Deserialize, Serialize};
 
-use crate::config::{CLUSTERS_FILE, SIDECAR_DIR};
-use crate::core::{compute_content_hash, ClusterDatabase, ClusterParams};
-use crate::processing::cluster::cluster_embeddings;
-use crate::storage::index;
+use crate::config::{CLUSTERS_FILE, CLUSTER_LABEL_COUNT};
+use crate::core::{compute_content_hash, ClusterDatabase, ClusterParams, Embedding, Metric};
+use crate::processing::cluster::{
+	cluster_embeddings, encode_vocabulary, rank_labels, update_clusters,
+};
+use crate::storage::{self, index};
 use crate::ui;
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -33,6 +35,22 @@ struct ClusterInfo {
 	cohesion: f32,
 	representative: String,
 	members: Vec<String>,
+	/// Mean of the member embeddings (only with --include-centroids)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	centroid: Option<Vec<f32>>,
+	/// Best matching vocabulary terms, best first (only with --labels)
+	#[serde(skip_serializing_if = "Option::is_none")]
+	labels: Option<Vec<String>>,
+}
+
+/// Best vocabulary terms with their scores, by cluster ID
+type ClusterLabels = HashMap<usize, Vec<(String, f32)>>;
+
+/// Machine-readable cluster output written to stdout with `--format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ClusterFormat {
+	/// Pretty-printed JSON, same layout as --export
+	Json,
 }
 
 #[allow(clippy::too_many_arguments)]
@@ -40,16 +58,28 @@ pub fn run(
 	dir: &Path,
 	recursive: bool,
 	force: bool,
+	incremental: bool,
+	join_similarity: f32,
 	min_cluster_size: usize,
 	min_samples: Option<usize>,
 	cohesion_threshold: f32,
 	use_umap: bool,
 	umap_neighbors: usize,
 	umap_components: usize,
+	metric: Metric,
+	seed: Option<u64>,
 	preview_count: i32,
 	export: Option<&Path>,
+	format: Option<ClusterFormat>,
+	include_centroids: bool,
+	thumbnails: Option<&Path>,
+	labels: Option<&Path>,
+	strict: bool,
 ) -> Result<()> {
-	let clusters_path = dir.join(SIDECAR_DIR).join(CLUSTERS_FILE);
+	let clusters_path = storage::sidecar_dir(dir).join(CLUSTERS_FILE);
+
+	// A bare --format writes to stdout, like '--export -'
+	let export = export.or(format.map(|ClusterFormat::Json| Path::new("-")));
 
 	let start = Instant::now();
 
@@ -58,6 +88,9 @@ pub fn run(
 	param_strs.push(format!("dir={}", dir.display()));
 	param_strs.push(format!("recursive={}", recursive));
 	param_strs.push(format!("force={}", force));
+	if incremental {
+		param_strs.push("incremental=true".to_string());
+	}
 	
 	if min_cluster_size != crate::config::DEFAULT_MIN_CLUSTER_SIZE {
 		param_strs.push(format!("min_cluster_size={}", min_cluster_size));
@@ -76,6 +109,12 @@ pub fn run(
 		if umap_components != crate::config::DEFAULT_UMAP_COMPONENTS {
 			param_strs.push(format!("umap_components={}", umap_components));
 		}
+		if let Some(seed) = seed {
+			param_strs.push(format!("seed={}", seed));
+		}
+	}
+	if metric != Metric::Cosine {
+		param_strs.push(format!("metric={:?}", metric));
 	}
 
 	ui::debug(&format!("Starting clustering: {}", param_strs.join(", ")));
@@ -88,8 +127,13 @@ pub fn run(
 		use_umap,
 		umap_neighbors,
 		umap_components,
+		metric,
+		seed,
 	};
 
+	// With --incremental, the cached clusters new files are assigned to
+	let mut base = None;
+
 	// Check for cached clusters
 	if !force {
 		if let Some(cached_db) = load_cached_clusters(&clusters_path) {
@@ -110,10 +154,14 @@ pub fn run(
 				// Check if content has changed
 				if !cached_db.content_hash.is_empty() && cached_db.content_hash != current_content_hash {
 					ui::debug(&format!(
-						"Content changed (cached: {}, current: {}), reclustering...",
+						"Content changed (cached: {}, current: {}), {}...",
 						&cached_db.content_hash[..8.min(cached_db.content_hash.len())],
-						&current_content_hash[..8]
+						&current_content_hash[..8],
+						if incremental { "updating clusters" } else { "reclustering" }
 					));
+					if incremental {
+						base = Some(cached_db);
+					}
 				} else {
 					ui::debug(&format!(
 						"Found cached clusters: {} clusters, {} images",
@@ -121,13 +169,37 @@ pub fn run(
 						cached_db.total_images
 					));
 
+					if let Some(out_dir) = thumbnails {
+						write_contact_sheets(&cached_db, &hash_to_path, out_dir, preview_count)?;
+					}
+
+					let embeddings = ((export.is_some() && include_centroids) || labels.is_some())
+						.then(|| embeddings_by_hash(&sidecars));
+					let cluster_labels = match (labels, &embeddings) {
+						(Some(vocabulary), Some(embeddings)) => {
+							Some(label_clusters(&cached_db, embeddings, vocabulary)?)
+						}
+						_ => None,
+					};
+
 					// Handle --export flag
 					if let Some(export_path) = export {
-						return export_clusters(&cached_db, &hash_to_path, export_path);
+						return export_clusters(
+							&cached_db,
+							&hash_to_path,
+							embeddings.as_ref().filter(|_| include_centroids),
+							cluster_labels.as_ref(),
+							export_path,
+						);
 					}
 
 					ui::success("Using cached clusters");
-					print_clusters(&cached_db, &hash_to_path, preview_count);
+					print_clusters(
+						&cached_db,
+						&hash_to_path,
+						preview_count,
+						cluster_labels.as_ref(),
+					);
 					ui::debug(&format!(
 						"{}",
 						format!("Clustered at: {}", cached_db.timestamp).dimmed()
@@ -141,6 +213,9 @@ pub fn run(
 	} else {
 		ui::debug("Force flag set, skipping cache check");
 	}
+	if incremental && base.is_none() {
+		ui::info("No usable cached clusters to update, clustering everything");
+	}
 
 	// Load sidecars
 	ui::info(&format!(
@@ -158,12 +233,19 @@ pub fn run(
 	ui::success(&format!("Loaded {} embeddings", sidecars.len()));
 
 	// Log embedding statistics
-	if let Some((_, first_sidecar)) = sidecars.first() {
-		let emb = first_sidecar.primary_embedding();
-		ui::debug(&format!("Embedding dimension: {}D", emb.0.len()));
+	if let Some(dim) = storage::check_dimensions(&sidecars)? {
+		ui::debug(&format!("Embedding dimension: {}D", dim));
 	}
+	storage::check_models(sidecars.iter().map(|(_, sidecar)| sidecar), strict)?;
 
-	let cluster_db = cluster_embeddings(sidecars, params, use_umap, umap_neighbors, umap_components)?;
+	// Clustering consumes the sidecars, so keep embeddings for centroids and labels first
+	let embeddings = ((export.is_some() && include_centroids) || labels.is_some())
+		.then(|| embeddings_by_hash(&sidecars));
+
+	let cluster_db = match base {
+		Some(cached_db) => update_clusters(cached_db, sidecars, join_similarity)?,
+		None => cluster_embeddings(sidecars, params, use_umap, umap_neighbors, umap_components, metric, seed)?,
+	};
 
 	// Log clustering results
 	ui::debug(&format!(
@@ -198,13 +280,35 @@ pub fn run(
 	// Always save clusters
 	save_clusters(dir, &cluster_db)?;
 
+	if let Some(out_dir) = thumbnails {
+		write_contact_sheets(&cluster_db, &hash_to_path, out_dir, preview_count)?;
+	}
+
+	let cluster_labels = match (labels, &embeddings) {
+		(Some(vocabulary), Some(embeddings)) => {
+			Some(label_clusters(&cluster_db, embeddings, vocabulary)?)
+		}
+		_ => None,
+	};
+
 	// Handle --export flag
 	if let Some(export_path) = export {
-		return export_clusters(&cluster_db, &hash_to_path, export_path);
+		return export_clusters(
+			&cluster_db,
+			&hash_to_path,
+			embeddings.as_ref().filter(|_| include_centroids),
+			cluster_labels.as_ref(),
+			export_path,
+		);
 	}
 
 	// Print results
-	print_clusters(&cluster_db, &hash_to_path, preview_count);
+	print_clusters(
+		&cluster_db,
+		&hash_to_path,
+		preview_count,
+		cluster_labels.as_ref(),
+	);
 	eprintln!(
 		"\n{}",
 		format!("Completed in {:.1}s", duration.as_secs_f32()).dimmed()
@@ -213,7 +317,7 @@ pub fn run(
 	Ok(())
 }
 
-fn load_cached_clusters(path: &Path) -> Option<ClusterDatabase> {
+pub(crate) fn load_cached_clusters(path: &Path) -> Option<ClusterDatabase> {
 	if !path.exists() {
 		return None;
 	}
@@ -226,6 +330,7 @@ fn print_clusters(
 	db: &ClusterDatabase,
 	hash_to_path: &HashMap<String, PathBuf>,
 	preview_count: i32,
+	labels: Option<&ClusterLabels>,
 ) {
 	ui::success(&format!(
 		"{} clusters, {} images, {} noise ({:.1}%)",
@@ -250,6 +355,16 @@ fn print_clusters(
 			colored_cohesion
 		);
 
+		if let Some(terms) = labels.and_then(|labels| labels.get(&cluster.id)) {
+			let terms: Vec<String> = terms
+				.iter()
+				.map(|(term, score)| {
+					format!("{} {}", term, format!("{:.1}%", score * 100.0).dimmed())
+				})
+				.collect();
+			eprintln!("  {}: {}", "Labels".dimmed(), terms.join(", "));
+		}
+
 		// Show representative
 		if let Some(repr_path) = hash_to_path.get(&cluster.representative_hash) {
 			eprintln!(
@@ -305,7 +420,7 @@ fn print_clusters(
 }
 
 fn save_clusters(dir: &Path, db: &ClusterDatabase) -> Result<()> {
-	let scout_dir = dir.join(SIDECAR_DIR);
+	let scout_dir = storage::sidecar_dir(dir);
 	fs::create_dir_all(&scout_dir)?;
 
 	let clusters_path = scout_dir.join(CLUSTERS_FILE);
@@ -316,9 +431,155 @@ fn save_clusters(dir: &Path, db: &ClusterDatabase) -> Result<()> {
 	Ok(())
 }
 
+/// Tile thumbnails of each cluster's members into `cluster_<id>.png`.
+///
+/// The representative comes first. A negative `preview_count` includes every
+/// member. Files that can't be decoded (missing, unreadable, or videos) get a
+/// blank tile so the grid still reflects the cluster size.
+fn write_contact_sheets(
+	db: &ClusterDatabase,
+	hash_to_path: &HashMap<String, PathBuf>,
+	out_dir: &Path,
+	preview_count: i32,
+) -> Result<()> {
+	fs::create_dir_all(out_dir)
+		.with_context(|| format!("Failed to create {}", out_dir.display()))?;
+
+	for cluster in &db.clusters {
+		let ordered = std::iter::once(&cluster.representative_hash).chain(
+			cluster
+				.image_hashes
+				.iter()
+				.filter(|h| **h != cluster.representative_hash),
+		);
+		let count = if preview_count < 0 {
+			cluster.image_hashes.len()
+		} else {
+			preview_count as usize
+		};
+		let paths: Vec<Option<&PathBuf>> =
+			ordered.take(count).map(|h| hash_to_path.get(h)).collect();
+		if paths.is_empty() {
+			continue;
+		}
+
+		let sheet = contact_sheet(&paths);
+		let sheet_path = out_dir.join(format!("cluster_{}.png", cluster.id));
+		sheet
+			.save(&sheet_path)
+			.with_context(|| format!("Failed to write {}", sheet_path.display()))?;
+	}
+
+	ui::success(&format!(
+		"Wrote {} contact sheets to {}",
+		db.clusters.len(),
+		ui::path_link(out_dir, 60)
+	));
+	Ok(())
+}
+
+/// Lay out thumbnails in a near-square grid
+fn contact_sheet(paths: &[Option<&PathBuf>]) -> image::RgbImage {
+	use crate::config::{CONTACT_SHEET_GAP as GAP, CONTACT_SHEET_THUMB_SIZE as SIZE};
+	use rayon::prelude::*;
+
+	let thumbs: Vec<Option<image::RgbImage>> = paths
+		.par_iter()
+		.map(|path| {
+			let path = (*path)?;
+			match crate::processing::image::load_with(path, crate::config::auto_orient()) {
+				Ok(img) => Some(img.thumbnail(SIZE, SIZE).to_rgb8()),
+				Err(e) => {
+					ui::debug(&format!("No thumbnail for {}: {}", path.display(), e));
+					None
+				}
+			}
+		})
+		.collect();
+
+	let columns = (thumbs.len() as f64).sqrt().ceil() as u32;
+	let rows = (thumbs.len() as u32).div_ceil(columns);
+	let cell = SIZE + GAP;
+	let mut sheet = image::RgbImage::from_pixel(
+		columns * cell + GAP,
+		rows * cell + GAP,
+		image::Rgb([24, 24, 24]),
+	);
+
+	for (i, thumb) in thumbs.iter().enumerate() {
+		let (col, row) = (i as u32 % columns, i as u32 / columns);
+		let (x, y) = (GAP + col * cell, GAP + row * cell);
+		match thumb {
+			// Center the thumbnail within its square cell
+			Some(thumb) => image::imageops::overlay(
+				&mut sheet,
+				thumb,
+				(x + (SIZE - thumb.width()) / 2) as i64,
+				(y + (SIZE - thumb.height()) / 2) as i64,
+			),
+			None => {
+				let blank = image::RgbImage::from_pixel(SIZE, SIZE, image::Rgb([64, 64, 64]));
+				image::imageops::overlay(&mut sheet, &blank, x as i64, y as i64);
+			}
+		}
+	}
+
+	sheet
+}
+
+/// Name each cluster after the vocabulary terms closest to its centroid.
+///
+/// The vocabulary file holds one term per line; blank lines are skipped.
+fn label_clusters(
+	db: &ClusterDatabase,
+	embeddings: &HashMap<String, Embedding>,
+	vocabulary_path: &Path,
+) -> Result<ClusterLabels> {
+	let text = fs::read_to_string(vocabulary_path)
+		.with_context(|| format!("Failed to read labels from {}", vocabulary_path.display()))?;
+	let mut seen = std::collections::HashSet::new();
+	let vocabulary: Vec<&str> = text
+		.lines()
+		.map(str::trim)
+		.filter(|line| !line.is_empty() && seen.insert(*line))
+		.collect();
+	if vocabulary.is_empty() {
+		anyhow::bail!("No labels found in {}", vocabulary_path.display());
+	}
+
+	// Each term is encoded once and scored against every cluster
+	ui::debug(&format!("Encoding {} labels", vocabulary.len()));
+	let mut models = crate::models::Models::new()?;
+	let terms = encode_vocabulary(&mut models, &vocabulary)?;
+
+	Ok(db
+		.clusters
+		.iter()
+		.map(|cluster| {
+			let members: Vec<Embedding> = cluster
+				.image_hashes
+				.iter()
+				.filter_map(|hash| embeddings.get(hash).cloned())
+				.collect();
+			let mut ranked = rank_labels(&members, &terms);
+			ranked.truncate(CLUSTER_LABEL_COUNT);
+			(cluster.id, ranked)
+		})
+		.collect())
+}
+
+fn embeddings_by_hash(sidecars: &[(PathBuf, storage::Sidecar)]) -> HashMap<String, Embedding> {
+	sidecars
+		.iter()
+		.map(|(_, s)| (s.hash().to_string(), s.primary_embedding()))
+		.collect()
+}
+
 fn export_clusters(
 	db: &ClusterDatabase,
 	hash_to_path: &HashMap<String, PathBuf>,
+	embeddings: Option<&HashMap<String, Embedding>>,
+	labels: Option<&ClusterLabels>,
 	export_path: &Path,
 ) -> Result<()> {
 	let clusters_info: Vec<ClusterInfo> = db
@@ -340,12 +601,30 @@ fn export_clusters(
 				})
 				.collect();
 
+			let centroid = embeddings.and_then(|embeddings| {
+				let member_embeddings: Vec<Embedding> = cluster
+					.image_hashes
+					.iter()
+					.filter_map(|hash| embeddings.get(hash).cloned())
+					.collect();
+				Embedding::mean(&member_embeddings).map(|centroid| centroid.0)
+			});
+
 			ClusterInfo {
 				id: cluster.id,
 				size: cluster.image_hashes.len(),
 				cohesion: cluster.cohesion,
 				representative,
 				members,
+				centroid,
+				labels: labels.map(|labels| {
+					labels
+						.get(&cluster.id)
+						.into_iter()
+						.flatten()
+						.map(|(term, _)| term.clone())
+						.collect()
+				}),
 			}
 		})
 		.collect();
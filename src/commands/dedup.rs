@@ -0,0 +1,311 @@
+//! # Dedup Command
+//!
+//! Find near-duplicate media by embedding similarity and optionally
+//! remove everything but the best copy in each group.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+
+use anyhow::Result;
+use colored::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{DEDUP_ANN_MIN_ITEMS, DEDUP_ANN_NEIGHBORS};
+use crate::core::{AnnIndex, AnnParams, Embedding, FileHash, HashMode};
+use crate::storage;
+use crate::ui;
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DedupExport {
+	total_analyzed: usize,
+	threshold: f32,
+	groups: Vec<DuplicateGroup>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DuplicateGroup {
+	representative: String,
+	duplicates: Vec<DuplicateInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct DuplicateInfo {
+	path: String,
+	similarity: f32,
+}
+
+pub fn run(
+	dir: &Path,
+	recursive: bool,
+	threshold: f32,
+	export: Option<&Path>,
+	delete: bool,
+) -> Result<()> {
+	let start = Instant::now();
+
+	ui::info(&format!(
+		"Analyzing embeddings from {}",
+		ui::path_link(dir, 40)
+	));
+
+	let (sidecars, _) = storage::load_all_sidecars(dir, recursive);
+
+	if sidecars.len() < 2 {
+		ui::warn("Not enough indexed media to compare. Run 'scout scan' first.");
+		return Ok(());
+	}
+
+	ui::success(&format!("Loaded {} embeddings", sidecars.len()));
+	storage::check_dimensions(&sidecars)?;
+
+	let paths: Vec<PathBuf> = sidecars.iter().map(|(p, _)| p.clone()).collect();
+	let embeddings: Vec<Embedding> = sidecars
+		.iter()
+		.map(|(_, s)| s.primary_embedding())
+		.collect();
+
+	let pairs = if embeddings.len() >= DEDUP_ANN_MIN_ITEMS {
+		ui::debug("Using ANN index for comparison...");
+		similar_pairs_ann(&embeddings, threshold)
+	} else {
+		ui::debug("Comparing all pairs...");
+		similar_pairs_exact(&embeddings, threshold)
+	};
+
+	let groups = build_groups(&paths, &pairs);
+	let duration = start.elapsed();
+
+	// Handle --export flag
+	if let Some(export_path) = export {
+		let export_data = DedupExport {
+			total_analyzed: paths.len(),
+			threshold,
+			groups: groups
+				.iter()
+				.map(|(rep, dups)| DuplicateGroup {
+					representative: paths[*rep].to_string_lossy().to_string(),
+					duplicates: dups
+						.iter()
+						.map(|(i, similarity)| DuplicateInfo {
+							path: paths[*i].to_string_lossy().to_string(),
+							similarity: *similarity,
+						})
+						.collect(),
+				})
+				.collect(),
+		};
+
+		let json = serde_json::to_string_pretty(&export_data)?;
+		if export_path.to_str() == Some("-") || export_path.as_os_str().is_empty() {
+			println!("{}", json);
+		} else {
+			std::fs::write(export_path, json)?;
+			ui::success(&format!("Exported to {}", export_path.display()));
+		}
+		if !delete {
+			return Ok(());
+		}
+	}
+
+	if groups.is_empty() {
+		ui::success(&format!(
+			"No duplicates above {:.2} similarity ({:.1}s)",
+			threshold,
+			duration.as_secs_f32()
+		));
+		return Ok(());
+	}
+
+	if export.is_none() {
+		print_groups(&paths, &groups);
+	}
+
+	let duplicate_count: usize = groups.iter().map(|(_, dups)| dups.len()).sum();
+	ui::success(&format!(
+		"Found {} duplicates in {} groups in {:.1}s",
+		duplicate_count,
+		groups.len(),
+		duration.as_secs_f32()
+	));
+
+	if delete {
+		delete_duplicates(&paths, &groups)?;
+	} else {
+		ui::debug("Use --delete to remove duplicates and keep the representatives");
+	}
+
+	Ok(())
+}
+
+/// Brute-force comparison of every pair
+fn similar_pairs_exact(embeddings: &[Embedding], threshold: f32) -> Vec<(usize, usize, f32)> {
+	(0..embeddings.len())
+		.into_par_iter()
+		.flat_map_iter(|i| {
+			((i + 1)..embeddings.len())
+				.map(move |j| (i, j, embeddings[i].similarity(&embeddings[j])))
+				.filter(move |&(_, _, similarity)| similarity >= threshold)
+		})
+		.collect()
+}
+
+/// Compare each item against its approximate nearest neighbors only
+fn similar_pairs_ann(embeddings: &[Embedding], threshold: f32) -> Vec<(usize, usize, f32)> {
+	let index = AnnIndex::build(embeddings, AnnParams::default());
+
+	embeddings
+		.par_iter()
+		.enumerate()
+		.flat_map_iter(|(i, embedding)| {
+			index
+				.search(embedding, DEDUP_ANN_NEIGHBORS + 1)
+				.into_iter()
+				.filter(move |&(j, similarity)| j > i && similarity >= threshold)
+				.map(move |(j, similarity)| (i, j, similarity))
+		})
+		.collect()
+}
+
+/// Group similar pairs around the copy to keep.
+///
+/// Items are taken best-first; each one not yet grouped keeps every
+/// ungrouped item it is directly similar to. Grouping is not transitive, so
+/// a chain of A~B~C never puts C with A unless A~C holds as well.
+///
+/// Returns `(representative, [(duplicate, similarity to representative)])`,
+/// largest groups first.
+fn build_groups(
+	paths: &[PathBuf],
+	pairs: &[(usize, usize, f32)],
+) -> Vec<(usize, Vec<(usize, f32)>)> {
+	let mut neighbors: HashMap<usize, Vec<(usize, f32)>> = HashMap::new();
+	for &(a, b, similarity) in pairs {
+		neighbors.entry(a).or_default().push((b, similarity));
+		neighbors.entry(b).or_default().push((a, similarity));
+	}
+
+	let mut candidates: Vec<(usize, (u64, u64))> =
+		neighbors.keys().map(|&i| (i, quality(&paths[i]))).collect();
+	candidates.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+
+	let mut grouped = vec![false; paths.len()];
+	let mut groups: Vec<(usize, Vec<(usize, f32)>)> = Vec::new();
+
+	for (representative, _) in candidates {
+		if grouped[representative] {
+			continue;
+		}
+
+		let mut duplicates: Vec<(usize, f32)> = neighbors[&representative]
+			.iter()
+			.copied()
+			.filter(|&(i, _)| !grouped[i])
+			.collect();
+		if duplicates.is_empty() {
+			continue;
+		}
+
+		grouped[representative] = true;
+		for &(i, _) in &duplicates {
+			grouped[i] = true;
+		}
+		duplicates.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+		groups.push((representative, duplicates));
+	}
+
+	groups.sort_by_key(|(_, dups)| std::cmp::Reverse(dups.len()));
+	groups
+}
+
+/// Ranking key for picking the copy to keep: resolution first, then file size
+fn quality(path: &Path) -> (u64, u64) {
+	let pixels = image::image_dimensions(path)
+		.map(|(w, h)| w as u64 * h as u64)
+		.unwrap_or(0);
+	let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+	(pixels, size)
+}
+
+fn print_groups(paths: &[PathBuf], groups: &[(usize, Vec<(usize, f32)>)]) {
+	ui::header("Duplicates");
+
+	for (i, (representative, duplicates)) in groups.iter().enumerate() {
+		println!(
+			"{}. {} {}",
+			format!("{:2}", i + 1).bright_blue().bold(),
+			ui::path_link(&paths[*representative], 60).bright_white(),
+			"(keep)".bright_green()
+		);
+
+		for (dup, similarity) in duplicates {
+			println!(
+				"      {} {}",
+				ui::path_link(&paths[*dup], 60).dimmed(),
+				format!("{:.3}", similarity).bright_yellow()
+			);
+		}
+	}
+
+	println!();
+}
+
+fn delete_duplicates(paths: &[PathBuf], groups: &[(usize, Vec<(usize, f32)>)]) -> Result<()> {
+	let targets: Vec<&PathBuf> = groups
+		.iter()
+		.flat_map(|(_, dups)| dups.iter().map(|(i, _)| &paths[*i]))
+		.collect();
+
+	if !ui::confirm(&format!(
+		"Delete {} duplicate files (representatives are kept)?",
+		targets.len()
+	)) {
+		ui::info("Aborted, nothing deleted");
+		return Ok(());
+	}
+
+	let mut deleted = 0;
+	for path in targets {
+		// Hash before deleting so the matching sidecar can be removed too
+		let sidecar = [HashMode::Fast, HashMode::Full]
+			.into_iter()
+			.find_map(|mode| {
+				let hash = FileHash::compute_with(path, mode).ok()?;
+				path.parent()
+					.and_then(|media_dir| storage::find(media_dir, &hash))
+			});
+
+		if let Err(e) = fs::remove_file(path) {
+			ui::error(&format!("Failed to delete {}: {}", path.display(), e));
+			continue;
+		}
+		if let Some(sidecar_path) = sidecar {
+			let _ = fs::remove_file(sidecar_path);
+		}
+
+		ui::debug(&format!("Deleted: {}", path.display()));
+		deleted += 1;
+	}
+
+	ui::success(&format!("Deleted {} duplicates", deleted));
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_groups_are_not_transitive() {
+		let paths: Vec<PathBuf> = ["a.jpg", "b.jpg", "c.jpg"]
+			.iter()
+			.map(PathBuf::from)
+			.collect();
+
+		// a~b and b~c, but a and c are not similar
+		let groups = build_groups(&paths, &[(0, 1, 0.96), (1, 2, 0.96)]);
+		assert_eq!(groups, vec![(0, vec![(1, 0.96)])]);
+	}
+}
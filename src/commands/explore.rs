@@ -0,0 +1,240 @@
+//! # Explore Command
+//!
+//! Browse the library by visual similarity: start from an image, then keep
+//! jumping to "more like result #k", with `back` to retrace the path.
+
+use anyhow::Result;
+use colored::*;
+use std::io::{BufRead, Write};
+use std::path::{Path, PathBuf};
+
+use super::search::{Match, QueryImage, SearchEngine, SearchOptions};
+use crate::core::Embedding;
+use crate::models::Models;
+use crate::processing::video::format_timestamp;
+use crate::ui;
+
+/// One stop on the exploration path
+struct Step {
+	/// What the results are similar to
+	label: String,
+	matches: Vec<Match>,
+}
+
+/// A line typed at the prompt
+#[derive(Debug, PartialEq)]
+enum Action {
+	/// Show more like result #k (1-based)
+	Explore(usize),
+	/// Open result #k with the default application
+	Open(usize),
+	Back,
+	Quit,
+	Help,
+}
+
+pub fn run(
+	image: &Path,
+	dirs: &[PathBuf],
+	recursive: bool,
+	limit: usize,
+	exclude_videos: bool,
+) -> Result<()> {
+	let engine = SearchEngine::load(dirs, recursive, false)?;
+	if engine.is_empty() {
+		ui::warn("No indexed media found. Run 'scout scan' first.");
+		return Ok(());
+	}
+
+	let mut models = Models::new()?;
+	let reference = QueryImage::open(image)?;
+	let query = models.encode_image(&reference.image)?;
+
+	let options = SearchOptions {
+		limit,
+		exclude_videos,
+		..SearchOptions::default()
+	};
+
+	let first = explore(&engine, &query, reference.label, Some(image), &options)?;
+	let mut path = vec![first];
+	let mut show = true;
+
+	let stdin = std::io::stdin();
+	let mut lines = stdin.lock().lines();
+	loop {
+		if show {
+			print_step(&path);
+		}
+		show = false;
+
+		eprint!(
+			"{} {} ",
+			"?".bright_yellow().bold(),
+			"Number to explore, 'open N', 'back' or 'quit':".bright_white()
+		);
+		let _ = std::io::stderr().flush();
+		let Some(line) = lines.next().transpose()? else {
+			eprintln!();
+			break;
+		};
+
+		let current = path.last().expect("path starts with the reference");
+		match parse_action(&line) {
+			Some(Action::Quit) => break,
+			Some(Action::Back) if path.len() == 1 => ui::warn("Already at the starting image"),
+			Some(Action::Back) => {
+				path.pop();
+				show = true;
+			}
+			Some(Action::Explore(k)) => {
+				let Some(m) = pick(&current.matches, k) else {
+					continue;
+				};
+				let Some(embedding) = engine.embedding_of(m) else {
+					ui::warn(&format!("No embedding stored for result {}", k));
+					continue;
+				};
+				let step = explore(
+					&engine,
+					&embedding,
+					label(m),
+					Some(Path::new(&m.path)),
+					&options,
+				)?;
+				path.push(step);
+				show = true;
+			}
+			Some(Action::Open(k)) => {
+				if let Some(m) = pick(&current.matches, k) {
+					if let Err(e) = open::that(&m.path) {
+						ui::warn(&format!("Failed to open: {}", e));
+					}
+				}
+			}
+			None if line.trim().is_empty() => {}
+			Some(Action::Help) | None => {
+				ui::info("Type a result number to see more like it, 'open N' to open it, 'back' to return or 'quit'");
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Search for `query`, leaving `source` itself out of the results
+fn explore(
+	engine: &SearchEngine,
+	query: &Embedding,
+	label: String,
+	source: Option<&Path>,
+	options: &SearchOptions,
+) -> Result<Step> {
+	// Fetch one extra result in case the source itself is among them
+	let widened = SearchOptions {
+		limit: options.limit + 1,
+		..options.clone()
+	};
+	let source = source.and_then(|path| path.canonicalize().ok());
+	let mut matches = engine.query_embedding(query, None, &widened)?;
+	matches.retain(|m| match &source {
+		Some(source) => Path::new(&m.path).canonicalize().ok().as_ref() != Some(source),
+		None => true,
+	});
+	matches.truncate(options.limit);
+
+	Ok(Step { label, matches })
+}
+
+fn print_step(path: &[Step]) {
+	let step = path.last().expect("path starts with the reference");
+	ui::header(&format!("More like {}", step.label));
+	if path.len() > 1 {
+		let trail: Vec<&str> = path.iter().map(|step| step.label.as_str()).collect();
+		eprintln!("{}", trail.join(" → ").dimmed());
+	}
+
+	if step.matches.is_empty() {
+		ui::warn("No matches found");
+		return;
+	}
+
+	let min = step
+		.matches
+		.iter()
+		.map(|m| m.score)
+		.fold(f32::MAX, f32::min);
+	let max = step
+		.matches
+		.iter()
+		.map(|m| m.score)
+		.fold(f32::MIN, f32::max);
+	for (i, m) in step.matches.iter().enumerate() {
+		let location = match m.timestamp {
+			Some(ts) => format!(" @ {}", format_timestamp(ts).bright_yellow()),
+			None => String::new(),
+		};
+		let percentage = ui::log::color_gradient(m.score * 100.0, min * 100.0, max * 100.0, false);
+		println!(
+			"{}. {}{} {}%",
+			format!("{:2}", i + 1).bright_blue().bold(),
+			ui::path_link(Path::new(&m.path), 60).bright_white(),
+			location.dimmed(),
+			percentage
+		);
+	}
+}
+
+/// Result #k (1-based) of the current step, warning when there is none
+fn pick(matches: &[Match], k: usize) -> Option<&Match> {
+	let m = k.checked_sub(1).and_then(|i| matches.get(i));
+	if m.is_none() {
+		ui::warn(&format!("Pick a result between 1 and {}", matches.len()));
+	}
+	m
+}
+
+/// Short name of a match for the header and trail
+fn label(m: &Match) -> String {
+	let name = Path::new(&m.path)
+		.file_name()
+		.map_or_else(|| m.path.clone(), |name| name.to_string_lossy().to_string());
+	match m.timestamp {
+		Some(ts) => format!("{} @ {}", name, format_timestamp(ts)),
+		None => name,
+	}
+}
+
+fn parse_action(line: &str) -> Option<Action> {
+	let line = line.trim().to_lowercase();
+	let words: Vec<&str> = line.split_whitespace().collect();
+	let action = match words.as_slice() {
+		["q" | "quit" | "exit"] => Action::Quit,
+		["b" | "back"] => Action::Back,
+		["h" | "help" | "?"] => Action::Help,
+		["o" | "open", k] => Action::Open(k.parse().ok()?),
+		[k] => Action::Explore(k.parse().ok()?),
+		_ => return None,
+	};
+	Some(action)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_prompt_actions() {
+		assert_eq!(parse_action("3"), Some(Action::Explore(3)));
+		assert_eq!(parse_action("  12 \n"), Some(Action::Explore(12)));
+		assert_eq!(parse_action("open 2"), Some(Action::Open(2)));
+		assert_eq!(parse_action("o 1"), Some(Action::Open(1)));
+		assert_eq!(parse_action("Back"), Some(Action::Back));
+		assert_eq!(parse_action("q"), Some(Action::Quit));
+		assert_eq!(parse_action("?"), Some(Action::Help));
+
+		for invalid in ["", "open", "open two", "3 4", "-1", "forward"] {
+			assert_eq!(parse_action(invalid), None, "{:?} parsed", invalid);
+		}
+	}
+}
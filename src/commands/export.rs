@@ -0,0 +1,42 @@
+//! # Export Command
+//!
+//! Write every sidecar under a directory into one archive file, which
+//! `scout import` turns back into sidecars, here or on another machine.
+
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::storage::Archive;
+use crate::ui;
+
+pub fn run(dir: &Path, recursive: bool, output: &Path) -> Result<()> {
+	let start = Instant::now();
+
+	ui::info(&format!(
+		"Exporting sidecars from {}",
+		ui::path_link(dir, 40)
+	));
+
+	let archive = Archive::build(dir, recursive);
+
+	if archive.is_empty() {
+		ui::warn("No embeddings found. Run 'scout scan' first");
+		return Ok(());
+	}
+
+	archive.save(output)?;
+
+	ui::success(&format!(
+		"Exported {} sidecars to {}",
+		archive.len(),
+		output.display()
+	));
+	eprintln!(
+		"{}",
+		format!("Completed in {:.1}s", start.elapsed().as_secs_f32()).dimmed()
+	);
+
+	Ok(())
+}
@@ -0,0 +1,137 @@
+//! # Import Command
+//!
+//! Recreate sidecars from a `scout export` archive. Each file is hashed
+//! first, so embeddings are only restored for media that is unchanged.
+
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::core::FileHash;
+use crate::storage::{self, Archive};
+use crate::ui;
+
+/// Import `input`, resolving its paths against `rebase` instead of the
+/// directory it was exported from. With `dir`, only media under it is restored.
+pub fn run(input: &Path, dir: Option<&Path>, rebase: Option<&Path>) -> Result<()> {
+	let start = Instant::now();
+
+	let archive = Archive::load(input)?;
+	let root = rebase.unwrap_or(archive.root()).to_path_buf();
+	let within = dir.map(|dir| dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()));
+
+	ui::info(&format!(
+		"Importing {} sidecars into {}",
+		archive.len(),
+		ui::path_link(&root, 40)
+	));
+
+	let mut imported = 0;
+	let mut missing = 0;
+	let mut changed = 0;
+	for entry in archive.into_entries() {
+		let media = root.join(&entry.path);
+		let Ok(absolute) = media.canonicalize() else {
+			ui::debug(&format!("Not found: {}", media.display()));
+			missing += 1;
+			continue;
+		};
+		if within
+			.as_ref()
+			.is_some_and(|within| !absolute.starts_with(within))
+		{
+			continue;
+		}
+
+		let hash = FileHash::compute_with(&media, entry.sidecar.hash_mode())?;
+		if hash.as_str() != entry.sidecar.hash() {
+			ui::debug(&format!("Changed since export: {}", media.display()));
+			changed += 1;
+			continue;
+		}
+
+		let media_dir = media.parent().unwrap_or(&root);
+		storage::save(&entry.sidecar.with_source(&media), media_dir, &hash)?;
+		imported += 1;
+	}
+
+	ui::success(&format!("Imported {} sidecars", imported));
+	if missing > 0 {
+		ui::warn(&format!(
+			"{} files were not found. Use --rebase if the library moved",
+			missing
+		));
+	}
+	if changed > 0 {
+		ui::warn(&format!(
+			"{} files changed since the export. Run 'scout scan' to embed them",
+			changed
+		));
+	}
+	eprintln!(
+		"{}",
+		format!("Completed in {:.1}s", start.elapsed().as_secs_f32()).dimmed()
+	);
+
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::commands::search::{SearchEngine, SearchOptions};
+	use crate::config::SIDECAR_DIR;
+	use crate::core::Embedding;
+
+	#[test]
+	fn test_export_import_round_trip() {
+		let temp = tempfile::tempdir().unwrap();
+		let base = temp.path();
+		let dir = base.join("photos");
+		std::fs::create_dir_all(&dir).unwrap();
+		for (i, embedding) in [
+			vec![1.0, 0.2, 0.0],
+			vec![0.3, 1.0, 0.1],
+			vec![0.0, 0.4, 1.0],
+		]
+		.into_iter()
+		.enumerate()
+		{
+			let path = dir.join(format!("{}.png", i));
+			image::RgbImage::from_pixel(2, 2, image::Rgb([i as u8, 0, 0]))
+				.save(&path)
+				.unwrap();
+			let hash = FileHash::compute(&path).unwrap();
+			let sidecar = storage::ImageSidecar::new(hash.clone(), Embedding::new(embedding));
+			storage::save_image(&sidecar, &dir, &hash).unwrap();
+		}
+
+		let search = || -> Vec<(String, f32)> {
+			let engine = SearchEngine::load(std::slice::from_ref(&dir), false, false).unwrap();
+			let options = SearchOptions {
+				min_score: 0.0,
+				..SearchOptions::default()
+			};
+			let query = Embedding::new(vec![1.0, 0.5, 0.0]);
+			engine
+				.query_embedding(&query, None, &options)
+				.unwrap()
+				.into_iter()
+				.map(|m| (m.path, m.score))
+				.collect()
+		};
+		let before = search();
+
+		let archive = base.join("index.bin");
+		crate::commands::export::run(&dir, false, &archive).unwrap();
+		std::fs::remove_dir_all(dir.join(SIDECAR_DIR)).unwrap();
+		let wiped = search();
+		run(&archive, None, None).unwrap();
+		let after = search();
+
+		assert_eq!(before.len(), 3);
+		assert!(wiped.is_empty());
+		assert_eq!(after, before);
+	}
+}
@@ -0,0 +1,167 @@
+//! # Info Command
+//!
+//! Summarize the state of an index: what is embedded, what is stale or
+//! orphaned, and how much space the sidecars take.
+
+use std::collections::BTreeSet;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use colored::*;
+use serde::Serialize;
+use walkdir::WalkDir;
+
+use crate::storage::{self, Sidecar};
+use crate::ui;
+
+#[derive(Debug, Default, Serialize)]
+struct IndexInfo {
+	images: usize,
+	videos: usize,
+	/// Image embeddings plus every video frame
+	embeddings: usize,
+	avg_video_frames: f32,
+	/// Written by another model or Scout version; rescanned on the next scan
+	outdated: usize,
+	/// Media file no longer exists
+	orphaned: usize,
+	unreadable: usize,
+	/// Distinct embedding sizes; more than one means mixed models
+	dimensions: Vec<usize>,
+	/// Total size of every `.scout` directory in bytes
+	disk_bytes: u64,
+}
+
+pub fn run(dir: &Path, recursive: bool, export: Option<&Path>) -> Result<()> {
+	let fingerprint = crate::commands::scan::current_fingerprint();
+
+	let sidecar_paths = storage::scan(dir, recursive);
+	let loaded = storage::load_all_sidecars_checked(dir, recursive);
+
+	let mut info = IndexInfo {
+		unreadable: loaded.unreadable.len(),
+		orphaned: sidecar_paths
+			.len()
+			.saturating_sub(loaded.sidecars.len() + loaded.unreadable.len()),
+		disk_bytes: disk_usage(&sidecar_paths),
+		..Default::default()
+	};
+
+	let mut dimensions = BTreeSet::new();
+	let mut video_frames = 0;
+	for (_, sidecar) in &loaded.sidecars {
+		match sidecar {
+			Sidecar::Image(_) => {
+				info.images += 1;
+				info.embeddings += 1;
+			}
+			Sidecar::Video(vid) => {
+				info.videos += 1;
+				video_frames += vid.frame_count();
+			}
+		}
+		if !sidecar.is_current(fingerprint.as_deref()) {
+			info.outdated += 1;
+		}
+		if sidecar.dimension() > 0 {
+			dimensions.insert(sidecar.dimension());
+		}
+	}
+	info.embeddings += video_frames;
+	if info.videos > 0 {
+		info.avg_video_frames = video_frames as f32 / info.videos as f32;
+	}
+	info.dimensions = dimensions.into_iter().collect();
+
+	// Handle --export flag
+	if let Some(export_path) = export {
+		let json = serde_json::to_string_pretty(&info)?;
+		if export_path.to_str() == Some("-") || export_path.as_os_str().is_empty() {
+			println!("{}", json);
+		} else {
+			std::fs::write(export_path, json)?;
+			ui::success(&format!("Exported to {}", export_path.display()));
+		}
+		return Ok(());
+	}
+
+	print_info(dir, &info);
+	Ok(())
+}
+
+/// Bytes used by the `.scout` directories holding the given sidecars
+fn disk_usage(sidecar_paths: &[(PathBuf, PathBuf)]) -> u64 {
+	let scout_dirs: BTreeSet<&Path> = sidecar_paths
+		.iter()
+		.filter_map(|(path, _)| path.parent())
+		.collect();
+
+	scout_dirs
+		.into_iter()
+		.flat_map(WalkDir::new)
+		.filter_map(|entry| entry.ok())
+		.filter_map(|entry| entry.metadata().ok())
+		.filter(|metadata| metadata.is_file())
+		.map(|metadata| metadata.len())
+		.sum()
+}
+
+fn format_bytes(bytes: u64) -> String {
+	let kb = bytes as f64 / 1024.0;
+	if kb >= 1024.0 * 1024.0 {
+		format!("{:.1} GB", kb / 1024.0 / 1024.0)
+	} else if kb >= 1024.0 {
+		format!("{:.1} MB", kb / 1024.0)
+	} else {
+		format!("{:.0} KB", kb)
+	}
+}
+
+fn print_info(dir: &Path, info: &IndexInfo) {
+	ui::header(&format!("Index of {}", ui::path_link(dir, 60)));
+
+	let row = |label: &str, value: String| eprintln!("  {:<18} {}", label.dimmed(), value);
+
+	row("Images", info.images.to_string());
+	row(
+		"Videos",
+		format!(
+			"{} ({:.1} frames on average)",
+			info.videos, info.avg_video_frames
+		),
+	);
+	row("Embeddings", info.embeddings.to_string());
+	row(
+		"Dimension",
+		match info.dimensions.as_slice() {
+			[] => "-".to_string(),
+			[dim] => format!("{}D", dim),
+			dims => format!(
+				"mixed ({})",
+				dims.iter()
+					.map(|d| format!("{}D", d))
+					.collect::<Vec<_>>()
+					.join(", ")
+			)
+			.bright_red()
+			.to_string(),
+		},
+	);
+	row("Disk usage", format_bytes(info.disk_bytes));
+
+	let flag = |count: usize| match count {
+		0 => "0".bright_green().to_string(),
+		n => n.to_string().bright_yellow().to_string(),
+	};
+	row("Outdated", flag(info.outdated));
+	row("Orphaned", flag(info.orphaned));
+	row("Unreadable", flag(info.unreadable));
+
+	eprintln!();
+	if info.outdated > 0 {
+		ui::info("Run 'scout scan' to re-embed outdated sidecars");
+	}
+	if info.orphaned > 0 || info.unreadable > 0 {
+		ui::info("Run 'scout clean --repair' to remove orphaned and unreadable sidecars");
+	}
+}
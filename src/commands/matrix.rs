@@ -0,0 +1,135 @@
+//! # Matrix Command
+//!
+//! Export pairwise similarities between all indexed media, either as a
+//! full N×N matrix or as each file's top-k nearest neighbors.
+
+use std::path::Path;
+use std::time::Instant;
+
+use anyhow::Result;
+use colored::*;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::config::MATRIX_FULL_WARN_ITEMS;
+use crate::core::Embedding;
+use crate::storage;
+use crate::ui;
+
+/// Dense matrix: `matrix[i][j]` is the similarity of `labels[i]` and `labels[j]`
+#[derive(Debug, Serialize, Deserialize)]
+struct FullMatrix {
+	labels: Vec<String>,
+	matrix: Vec<Vec<f32>>,
+}
+
+/// Sparse matrix: `neighbors[i]` lists the `k` files most similar to `labels[i]`
+#[derive(Debug, Serialize, Deserialize)]
+struct SparseMatrix {
+	labels: Vec<String>,
+	k: usize,
+	neighbors: Vec<Vec<Neighbor>>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Neighbor {
+	/// Row/column index into `labels`
+	index: usize,
+	similarity: f32,
+}
+
+pub fn run(dir: &Path, recursive: bool, export: &Path, top_k: Option<usize>) -> Result<()> {
+	let start = Instant::now();
+
+	ui::info(&format!(
+		"Loading embeddings from {}",
+		ui::path_link(dir, 40)
+	));
+
+	let (sidecars, _) = storage::load_all_sidecars(dir, recursive);
+
+	if sidecars.is_empty() {
+		ui::warn("No embeddings found. Run 'scout scan' first");
+		return Ok(());
+	}
+
+	ui::success(&format!("Loaded {} embeddings", sidecars.len()));
+	storage::check_dimensions(&sidecars)?;
+
+	let labels: Vec<String> = sidecars
+		.iter()
+		.map(|(p, _)| p.to_string_lossy().to_string())
+		.collect();
+	let embeddings: Vec<Embedding> = sidecars
+		.iter()
+		.map(|(_, s)| s.primary_embedding())
+		.collect();
+
+	let json = match top_k {
+		Some(k) => {
+			let k = k.min(embeddings.len() - 1);
+			ui::debug(&format!("Keeping the {} nearest neighbors per file", k));
+			serde_json::to_string(&SparseMatrix {
+				labels,
+				k,
+				neighbors: top_k_neighbors(&embeddings, k),
+			})?
+		}
+		None => {
+			if embeddings.len() > MATRIX_FULL_WARN_ITEMS {
+				ui::warn(&format!(
+					"Full matrix for {} files holds {} values. Use --top-k for smaller output",
+					embeddings.len(),
+					embeddings.len() * embeddings.len()
+				));
+			}
+			serde_json::to_string(&FullMatrix {
+				labels,
+				matrix: full_matrix(&embeddings),
+			})?
+		}
+	};
+
+	if export.to_str() == Some("-") || export.as_os_str().is_empty() {
+		println!("{}", json);
+	} else {
+		std::fs::write(export, json)?;
+		ui::success(&format!("Exported to {}", export.display()));
+	}
+
+	eprintln!(
+		"{}",
+		format!("Completed in {:.1}s", start.elapsed().as_secs_f32()).dimmed()
+	);
+
+	Ok(())
+}
+
+fn full_matrix(embeddings: &[Embedding]) -> Vec<Vec<f32>> {
+	Embedding::similarity_matrix(embeddings, embeddings)
+}
+
+/// Each row's `k` most similar other files, best first
+fn top_k_neighbors(embeddings: &[Embedding], k: usize) -> Vec<Vec<Neighbor>> {
+	embeddings
+		.par_iter()
+		.enumerate()
+		.map(|(i, a)| {
+			let mut row: Vec<Neighbor> = embeddings
+				.iter()
+				.enumerate()
+				.filter(|&(j, _)| j != i)
+				.map(|(j, b)| Neighbor {
+					index: j,
+					similarity: a.similarity(b),
+				})
+				.collect();
+			if k < row.len() {
+				row.select_nth_unstable_by(k, |x, y| y.similarity.total_cmp(&x.similarity));
+				row.truncate(k);
+			}
+			row.sort_by(|x, y| y.similarity.total_cmp(&x.similarity));
+			row
+		})
+		.collect()
+}
@@ -2,9 +2,22 @@
 //!
 //! Each submodule handles one CLI command (scan, search, cluster, etc.).
 
+pub mod bench;
 pub mod clean;
 pub mod cluster;
+pub mod dedup;
+pub mod explore;
+pub mod export;
+pub mod import;
+pub mod info;
+pub mod matrix;
+pub mod models;
 pub mod outliers;
+pub mod probe;
+pub mod providers;
+pub mod reindex;
 pub mod scan;
 pub mod search;
+pub mod serve;
+pub mod verify;
 pub mod watch;
@@ -0,0 +1,25 @@
+//! # Models Command
+//!
+//! Download the default model files, so a fresh install can scan and
+//! search without placing them by hand.
+
+use anyhow::{Context, Result};
+
+use crate::config;
+use crate::models::download;
+use crate::ui;
+
+pub fn download(url: &str, force: bool) -> Result<()> {
+	let dir = config::models_download_dir().context("Could not determine the models directory")?;
+	ui::info(&format!("Models directory: {}", ui::path_link(&dir, 60)));
+
+	if !force && download::missing(&dir).is_empty() {
+		ui::success("All model files are present");
+		ui::info("Rerun with --force to download them again");
+		return Ok(());
+	}
+
+	download::download(&dir, url, force)?;
+	ui::success("Models ready");
+	Ok(())
+}
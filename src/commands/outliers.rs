@@ -3,7 +3,8 @@
 //! Find statistically unusual media in the embedding space using
 //! Local Outlier Factor (LOF) algorithm.
 
-use std::path::Path;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use anyhow::Result;
@@ -11,7 +12,8 @@ use colored::*;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
-use crate::core::Embedding;
+use crate::config::CLUSTERS_FILE;
+use crate::core::{compute_content_hash, Cluster, Embedding};
 use crate::storage;
 use crate::ui;
 
@@ -27,6 +29,27 @@ struct OutlierInfo {
 	score: f32,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct ClusterOutlierExport {
+	total_analyzed: usize,
+	clusters: Vec<ClusterOutlierInfo>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ClusterOutlierInfo {
+	cluster_id: usize,
+	size: usize,
+	outliers: Vec<OutlierInfo>,
+}
+
+/// Outliers of one cluster, most anomalous first
+struct ClusterOutliers {
+	id: usize,
+	/// Members with an embedding, all of which were scored
+	size: usize,
+	outliers: Vec<(String, f32)>,
+}
+
 #[allow(clippy::too_many_arguments)]
 pub fn run(
 	dir: &Path,
@@ -34,6 +57,7 @@ pub fn run(
 	limit: usize,
 	neighbors: usize,
 	export: Option<&Path>,
+	within_cluster: bool,
 ) -> Result<()> {
 	let start = Instant::now();
 
@@ -59,8 +83,13 @@ pub fn run(
 	}
 
 	ui::success(&format!("Loaded {} embeddings", sidecars.len()));
+	storage::check_dimensions(&sidecars)?;
 	ui::debug(&format!("Using k={} neighbors for LOF", neighbors));
 
+	if within_cluster {
+		return run_within_clusters(dir, &sidecars, &hash_cache, limit, neighbors, export, start);
+	}
+
 	// Extract embeddings with their hashes
 	let items: Vec<(String, Embedding)> = sidecars
 		.iter()
@@ -113,10 +142,165 @@ pub fn run(
 	// Print results
 	ui::header("Outliers");
 
-	// Calculate min/max scores for gradient
-	let min_lof = outliers.iter().map(|(_, score)| *score).min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)).unwrap_or(1.0);
-	let max_lof = outliers.iter().map(|(_, score)| *score).max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)).unwrap_or(5.0);
+	print_ranked(&outliers, &hash_cache, lof_range(outliers.iter()));
+
+	println!();
+	ui::success(&format!(
+		"Found {} outliers in {:.1}s",
+		outliers.len(),
+		duration.as_secs_f32()
+	));
+	ui::debug("Higher LOF scores indicate more unusual media");
+
+	Ok(())
+}
+
+/// Run LOF separately on the members of each cached cluster
+fn run_within_clusters(
+	dir: &Path,
+	sidecars: &[(PathBuf, storage::Sidecar)],
+	hash_cache: &HashMap<String, PathBuf>,
+	limit: usize,
+	neighbors: usize,
+	export: Option<&Path>,
+	start: Instant,
+) -> Result<()> {
+	let clusters_path = storage::sidecar_dir(dir).join(CLUSTERS_FILE);
+	let Some(db) = crate::commands::cluster::load_cached_clusters(&clusters_path) else {
+		anyhow::bail!("No clusters found. Run 'scout cluster' first");
+	};
+
+	let hashes: Vec<String> = sidecars.iter().map(|(_, s)| s.hash().to_string()).collect();
+	if !db.content_hash.is_empty() && db.content_hash != compute_content_hash(&hashes) {
+		ui::warn("Clusters are out of date; run 'scout cluster' to include new media");
+	}
+
+	let embeddings: HashMap<String, Embedding> = sidecars
+		.iter()
+		.map(|(_, s)| (s.hash().to_string(), s.primary_embedding()))
+		.collect();
+
+	ui::debug("Computing Local Outlier Factor scores per cluster...");
+	let results = cluster_outliers(&db.clusters, &embeddings, neighbors, limit);
+	let skipped = db.clusters.len() - results.len();
+	if skipped > 0 {
+		ui::debug(&format!(
+			"Skipped {} clusters with fewer than {} members",
+			skipped,
+			neighbors + 1
+		));
+	}
+
+	let duration = start.elapsed();
+
+	if let Some(export_path) = export {
+		let export_data = ClusterOutlierExport {
+			total_analyzed: results.iter().map(|r| r.size).sum(),
+			clusters: results
+				.iter()
+				.map(|result| ClusterOutlierInfo {
+					cluster_id: result.id,
+					size: result.size,
+					outliers: result
+						.outliers
+						.iter()
+						.filter_map(|(hash, score)| {
+							hash_cache.get(hash).map(|p| OutlierInfo {
+								path: p.to_string_lossy().to_string(),
+								score: *score,
+							})
+						})
+						.collect(),
+				})
+				.collect(),
+		};
 
+		let json = serde_json::to_string_pretty(&export_data)?;
+		if export_path.to_str() == Some("-") || export_path.as_os_str().is_empty() {
+			println!("{}", json);
+		} else {
+			std::fs::write(export_path, json)?;
+			ui::success(&format!("Exported to {}", export_path.display()));
+		}
+		return Ok(());
+	}
+
+	ui::header("Outliers within clusters");
+
+	// One color scale for every cluster, so mild outliers don't all look alike
+	let range = lof_range(results.iter().flat_map(|r| r.outliers.iter()));
+	for result in &results {
+		println!(
+			"\n{} {} ({} members)",
+			"Cluster".bright_white(),
+			result.id.to_string().bright_cyan(),
+			result.size
+		);
+		print_ranked(&result.outliers, hash_cache, range);
+	}
+
+	println!();
+	ui::success(&format!(
+		"Checked {} of {} clusters in {:.1}s",
+		results.len(),
+		db.clusters.len(),
+		duration.as_secs_f32()
+	));
+	ui::debug("Higher LOF scores indicate media that fits its cluster less");
+
+	Ok(())
+}
+
+/// Top `limit` LOF outliers of every cluster with at least `neighbors + 1`
+/// members that have an embedding. Other clusters are left out.
+fn cluster_outliers(
+	clusters: &[Cluster],
+	embeddings: &HashMap<String, Embedding>,
+	neighbors: usize,
+	limit: usize,
+) -> Vec<ClusterOutliers> {
+	clusters
+		.iter()
+		.filter_map(|cluster| {
+			let items: Vec<(String, Embedding)> = cluster
+				.image_hashes
+				.iter()
+				.filter_map(|hash| embeddings.get(hash).map(|e| (hash.clone(), e.clone())))
+				.collect();
+			if items.len() < neighbors + 1 {
+				return None;
+			}
+
+			let scores = compute_lof_scores(&items, neighbors);
+			let mut scored: Vec<(String, f32)> = items
+				.iter()
+				.zip(scores)
+				.map(|((hash, _), score)| (hash.clone(), score))
+				.collect();
+			scored.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+			scored.truncate(limit);
+
+			Some(ClusterOutliers {
+				id: cluster.id,
+				size: items.len(),
+				outliers: scored,
+			})
+		})
+		.collect()
+}
+
+/// Lowest and highest LOF score, for the color gradient
+fn lof_range<'a>(outliers: impl Iterator<Item = &'a (String, f32)> + Clone) -> (f32, f32) {
+	let min_lof = outliers.clone().map(|(_, score)| *score).min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)).unwrap_or(1.0);
+	let max_lof = outliers.map(|(_, score)| *score).max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)).unwrap_or(5.0);
+	(min_lof, max_lof)
+}
+
+fn print_ranked(
+	outliers: &[(String, f32)],
+	hash_cache: &HashMap<String, PathBuf>,
+	(min_lof, max_lof): (f32, f32),
+) {
 	for (i, (hash, score)) in outliers.iter().enumerate() {
 		if let Some(path) = hash_cache.get(hash) {
 			let link = ui::path_link(path, 60);
@@ -130,16 +314,6 @@ pub fn run(
 			);
 		}
 	}
-
-	println!();
-	ui::success(&format!(
-		"Found {} outliers in {:.1}s",
-		outliers.len(),
-		duration.as_secs_f32()
-	));
-	ui::debug("Higher LOF scores indicate more unusual media");
-
-	Ok(())
 }
 
 /// Compute Local Outlier Factor scores for all items.
@@ -210,3 +384,46 @@ fn compute_lof_scores(items: &[(String, Embedding)], k: usize) -> Vec<f32> {
 		})
 		.collect()
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn cluster(id: usize, hashes: &[&str]) -> Cluster {
+		Cluster {
+			id,
+			image_hashes: hashes.iter().map(|h| h.to_string()).collect(),
+			representative_hash: hashes[0].to_string(),
+			cohesion: 0.9,
+		}
+	}
+
+	#[test]
+	fn test_within_cluster_finds_planted_outlier() {
+		let mut embeddings = HashMap::new();
+		for i in 0..8 {
+			let embedding = Embedding::new(vec![1.0, i as f32 * 0.01, 0.0]);
+			embeddings.insert(format!("beach{}", i), embedding);
+		}
+		embeddings.insert("receipt".to_string(), Embedding::new(vec![0.5, 0.0, 0.9]));
+		for i in 0..3 {
+			let embedding = Embedding::new(vec![0.0, 1.0, i as f32]);
+			embeddings.insert(format!("cat{}", i), embedding);
+		}
+
+		let mut beach: Vec<String> = (0..8).map(|i| format!("beach{}", i)).collect();
+		beach.insert(3, "receipt".to_string());
+		let beach: Vec<&str> = beach.iter().map(String::as_str).collect();
+		let clusters = [cluster(0, &beach), cluster(1, &["cat0", "cat1", "cat2"])];
+
+		let results = cluster_outliers(&clusters, &embeddings, 3, 2);
+
+		// The three-member cluster is too small for k = 3
+		assert_eq!(results.len(), 1);
+		assert_eq!(results[0].id, 0);
+		assert_eq!(results[0].size, 9);
+		assert_eq!(results[0].outliers.len(), 2);
+		assert_eq!(results[0].outliers[0].0, "receipt");
+		assert!(results[0].outliers[0].1 > results[0].outliers[1].1 * 2.0);
+	}
+}
@@ -0,0 +1,58 @@
+//! # Probe Command
+//!
+//! List the video streams in a file so the right one can be picked
+//! with `--video-stream` when scanning.
+
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+
+use crate::processing::video;
+use crate::ui;
+
+pub fn run(file: &Path) -> Result<()> {
+	if !video::is_available() {
+		anyhow::bail!("FFmpeg not found in PATH");
+	}
+
+	let (duration, streams) = video::list_streams(file)?;
+
+	ui::info(&format!(
+		"{} ({})",
+		ui::path_link(file, 60).bright_white(),
+		video::format_timestamp(duration)
+	));
+
+	if streams.is_empty() {
+		ui::warn("No video streams found");
+		return Ok(());
+	}
+
+	ui::header("Video streams");
+
+	for stream in &streams {
+		let mut details = vec![
+			stream.codec.clone(),
+			format!("{}x{}", stream.width, stream.height),
+			format!("{:.2}fps", stream.fps),
+		];
+		if let Some(language) = &stream.language {
+			details.push(format!("lang={}", language));
+		}
+		if let Some(title) = &stream.title {
+			details.push(format!("\"{}\"", title));
+		}
+
+		eprintln!(
+			"  {} {} {}",
+			format!("[{}]", stream.video_index).bright_cyan(),
+			details.join(", "),
+			format!("(stream #{})", stream.index).dimmed()
+		);
+	}
+
+	eprintln!();
+	ui::debug("Use --video-stream <N> with scan or watch to pick a stream");
+
+	Ok(())
+}
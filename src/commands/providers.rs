@@ -0,0 +1,58 @@
+//! # Providers Command
+//!
+//! Report which execution providers can be used on this machine, to
+//! explain why `--provider cuda` (or auto-detection) falls back to CPU.
+
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+
+use crate::runtime;
+use crate::ui;
+
+pub fn run(export: Option<&Path>) -> Result<()> {
+	let statuses = runtime::probe_all();
+
+	// Handle --export flag
+	if let Some(export_path) = export {
+		let json = serde_json::to_string_pretty(&statuses)?;
+		if export_path.to_str() == Some("-") || export_path.as_os_str().is_empty() {
+			println!("{}", json);
+		} else {
+			std::fs::write(export_path, json)?;
+			ui::success(&format!("Exported to {}", export_path.display()));
+		}
+		return Ok(());
+	}
+
+	let name_width = statuses.iter().map(|s| s.name.len()).max().unwrap_or(0);
+
+	println!(
+		"{:<width$}  {:<9}  {}",
+		"Provider".bold(),
+		"Available".bold(),
+		"Details".bold(),
+		width = name_width
+	);
+	for status in &statuses {
+		let available = if status.available {
+			format!("{:<9}", "yes").bright_green()
+		} else {
+			format!("{:<9}", "no").bright_red()
+		};
+		println!(
+			"{:<width$}  {}  {}",
+			status.name,
+			available,
+			status.error.as_deref().unwrap_or("").dimmed(),
+			width = name_width
+		);
+	}
+
+	if let Some(best) = statuses.iter().find(|s| s.available) {
+		eprintln!();
+		ui::info(&format!("Auto-detection would use {}", best.name));
+	}
+
+	Ok(())
+}
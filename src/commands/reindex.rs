@@ -0,0 +1,39 @@
+//! # Reindex Command
+//!
+//! Rebuild the consolidated index from the sidecars under a directory.
+//! Search uses the index automatically while it is up to date.
+
+use anyhow::Result;
+use colored::*;
+use std::path::Path;
+use std::time::Instant;
+
+use crate::storage::IndexFile;
+use crate::ui;
+
+pub fn run(dir: &Path, recursive: bool) -> Result<()> {
+	let start = Instant::now();
+
+	ui::info(&format!("Building index from {}", ui::path_link(dir, 40)));
+
+	let index = IndexFile::build(dir, recursive);
+
+	if index.is_empty() {
+		ui::warn("No embeddings found. Run 'scout scan' first");
+		return Ok(());
+	}
+
+	index.save(dir)?;
+
+	ui::success(&format!(
+		"Indexed {} files to {}",
+		index.len(),
+		IndexFile::path(dir).display()
+	));
+	eprintln!(
+		"{}",
+		format!("Completed in {:.1}s", start.elapsed().as_secs_f32()).dimmed()
+	);
+
+	Ok(())
+}
@@ -5,45 +5,172 @@
 
 use anyhow::Result;
 use std::path::Path;
+use std::sync::{mpsc, Mutex};
 use std::time::Instant;
 
-use crate::config::{MAX_VIDEO_FRAMES, SCENE_THRESHOLD};
-use crate::core::MediaType;
+use crate::config::{
+	ANIMATION_MAX_FRAMES, DEFAULT_BATCH_SIZE, DEFAULT_SCAN_WORKERS, SIDECAR_ESTIMATE_BYTES,
+};
+use crate::core::{DegenerateEmbedding, Embedding, HashMode, MediaType};
 use crate::models::Models;
 use crate::processing;
+use crate::processing::video::FrameSampling;
 use crate::storage;
 use crate::ui;
 
-#[allow(clippy::too_many_arguments)]
-pub fn run(
-	dir: &Path,
-	recursive: bool,
-	force: bool,
-	min_resolution: Option<u32>,
-	max_size: Option<u64>,
-	exclude_videos: bool,
-	max_frames: Option<usize>,
-	scene_threshold: Option<f32>,
-) -> Result<()> {
+/// A unit of work for one scan worker
+enum Job {
+	/// Images encoded together in one forward pass
+	Images(Vec<processing::scan::MediaFile>),
+	Video(processing::scan::MediaFile),
+}
+
+/// Outcome of one file, sent from a worker back to the progress display
+struct Done {
+	file: processing::scan::MediaFile,
+	result: Result<Embedding>,
+	duration_ms: u128,
+}
+
+/// Summarize what a scan would encode, without loading any models
+fn report_dry_run(scan_result: &processing::scan::ScanResult, video_supported: bool) {
+	let to_process = &scan_result.to_process;
+	for file in to_process {
+		ui::debug(&format!("Would process: {}", file.path.display()));
+	}
+
+	let videos = to_process
+		.iter()
+		.filter(|file| file.media_type == MediaType::Video)
+		.count();
+	let images = to_process.len() - videos;
+
+	ui::info(&format!(
+		"Dry run: {} to process ({} images, {} videos), {} indexed, {} outdated, {} filtered",
+		to_process.len(),
+		images,
+		videos,
+		scan_result.already_indexed,
+		scan_result.outdated,
+		scan_result.filtered
+	));
+	if videos > 0 && !video_supported {
+		ui::warn(&format!(
+			"{} videos would be skipped without FFmpeg",
+			videos
+		));
+	}
+
+	let estimate_kb = to_process.len() as u64 * SIDECAR_ESTIMATE_BYTES / 1024;
+	let estimate = if estimate_kb >= 1024 {
+		format!("{:.1} MB", estimate_kb as f64 / 1024.0)
+	} else {
+		format!("{} KB", estimate_kb)
+	};
+	ui::info(&format!(
+		"Estimated sidecar size: ~{} (videos add ~4 KB per extracted frame)",
+		estimate
+	));
+}
+
+/// Fingerprint the installed vision model so sidecars written by older Scout
+/// versions with the same model are not re-embedded
+pub(crate) fn current_fingerprint() -> Option<String> {
+	let fingerprint = crate::config::get_vision_model_path()
+		.filter(|path| path.exists())
+		.and_then(|path| {
+			match crate::models::model_fingerprint(
+				&path,
+				crate::config::preprocess_mode(),
+				crate::models::Normalization::default(),
+				crate::config::auto_orient(),
+			) {
+				Ok(fingerprint) => Some(fingerprint),
+				Err(e) => {
+					ui::debug(&format!("Model fingerprint unavailable: {:#}", e));
+					None
+				}
+			}
+		});
+	if let Some(fingerprint) = &fingerprint {
+		ui::debug(&format!("Model fingerprint: {}", fingerprint));
+	}
+	fingerprint
+}
+
+/// What a scan indexes and how
+#[derive(Debug, Clone)]
+pub struct ScanOptions {
+	/// Re-embed files whose sidecars are still current
+	pub force: bool,
+	/// Skip images whose shortest side is below this many pixels
+	pub min_resolution: Option<u32>,
+	/// Skip files larger than this many MB
+	pub max_size: Option<u64>,
+	pub exclude_videos: bool,
+	pub video: VideoOptions,
+	/// Images per vision forward pass
+	pub batch_size: usize,
+	/// Model instances encoding in parallel
+	pub workers: usize,
+	pub format: storage::SidecarFormat,
+	/// Report what would be encoded without loading any models
+	pub dry_run: bool,
+	/// Directories to skip, matched like `.scoutignore` lines
+	pub exclude_dir: Vec<String>,
+	pub hash_mode: HashMode,
+}
+
+impl Default for ScanOptions {
+	fn default() -> Self {
+		Self {
+			force: false,
+			min_resolution: None,
+			max_size: None,
+			exclude_videos: false,
+			video: VideoOptions::default(),
+			batch_size: DEFAULT_BATCH_SIZE,
+			workers: DEFAULT_SCAN_WORKERS,
+			format: storage::SidecarFormat::default(),
+			dry_run: false,
+			exclude_dir: Vec::new(),
+			hash_mode: HashMode::default(),
+		}
+	}
+}
+
+pub fn run(dir: &Path, recursive: bool, options: &ScanOptions) -> Result<()> {
 	let start = Instant::now();
+	let format = options.format;
+	let fingerprint = current_fingerprint();
 
 	ui::info(&format!("Scanning: {}", dir.display()));
 
 	// Check FFmpeg availability for videos
-	let video_supported = if exclude_videos {
+	let video_supported = if options.exclude_videos {
 		false
 	} else {
 		processing::video::is_available()
 	};
 
-	if exclude_videos {
+	if options.exclude_videos {
 		ui::debug("Videos excluded by --exclude-videos flag");
 	} else if !video_supported {
 		ui::warn("FFmpeg not found - videos will be skipped");
 		ui::debug("Install FFmpeg to enable video support");
 	}
 
-	let scan_result = processing::scan_directory(dir, recursive, force, min_resolution, max_size);
+	let exclude = processing::scan::IgnoreRules::new(dir, &options.exclude_dir)?;
+	let scan_result = processing::scan_directory(
+		dir,
+		recursive,
+		options.force,
+		options.min_resolution,
+		options.max_size,
+		&exclude,
+		options.hash_mode,
+		fingerprint.as_deref(),
+	);
 
 	if scan_result.to_process.is_empty() {
 		ui::success(&format!(
@@ -56,6 +183,11 @@ pub fn run(
 		return Ok(());
 	}
 
+	if options.dry_run {
+		report_dry_run(&scan_result, video_supported);
+		return Ok(());
+	}
+
 	ui::info(&format!(
 		"Processing {} files ({} indexed, {} filtered)",
 		scan_result.to_process.len(),
@@ -70,54 +202,142 @@ pub fn run(
 		));
 	}
 
-	let mut models = Models::new()?;
+	// ONNX sessions can't be shared between threads, so every worker gets its own
+	let mut pool = Vec::with_capacity(options.workers);
+	for _ in 0..options.workers {
+		let mut models = Models::new()?;
+		models.require_vision()?;
+		models.set_batch_size(options.batch_size);
+		models.set_fingerprint(fingerprint.clone());
+		pool.push(models);
+	}
+	if options.workers > 1 {
+		ui::debug(&format!("Encoding with {} workers", options.workers));
+	}
+
 	let mut processed = 0;
 	let mut errors = 0;
+	let mut degenerate = 0;
 	let mut skipped_videos = 0;
 
-	let max_frames = max_frames.unwrap_or(MAX_VIDEO_FRAMES);
-	let scene_threshold = scene_threshold.unwrap_or(SCENE_THRESHOLD);
+	let (images, mut videos): (Vec<_>, Vec<_>) = scan_result
+		.to_process
+		.into_iter()
+		.partition(|file| file.media_type == MediaType::Image);
 
-	for file in scan_result.to_process {
-		let media_dir = file.path.parent().unwrap();
-		let file_start = Instant::now();
+	if !video_supported {
+		skipped_videos = videos.len();
+		videos.clear();
+	}
 
-		let result = match file.media_type {
-			MediaType::Image => process_image(&mut models, &file, media_dir),
-			MediaType::Video => {
-				if !video_supported {
-					skipped_videos += 1;
-					continue;
+	// Images are encoded in batches to keep the accelerator busy
+	let (job_tx, job_rx) = mpsc::channel();
+	for batch in images.chunks(pool[0].batch_size()) {
+		let _ = job_tx.send(Job::Images(batch.to_vec()));
+	}
+	for file in videos.iter().cloned() {
+		let _ = job_tx.send(Job::Video(file));
+	}
+	drop(job_tx);
+	let job_rx = Mutex::new(job_rx);
+
+	let total = images.len() + videos.len();
+	let progress = ui::Progress::start(total, "files");
+	// With a bar on screen, per-file lines are only shown in verbose mode
+	let log_file = |path: &Path, duration_ms: u128| {
+		if !progress.is_visible() || ui::Log::is_verbose() {
+			ui::log::file_processed(path, duration_ms);
+		}
+	};
+
+	let (done_tx, done_rx) = mpsc::channel::<Done>();
+	std::thread::scope(|scope| {
+		for mut models in pool {
+			let done_tx = done_tx.clone();
+			let job_rx = &job_rx;
+			scope.spawn(move || {
+				loop {
+					// Bound first so the lock is released before encoding
+					let job = job_rx.lock().unwrap().recv();
+					let Ok(job) = job else { break };
+					match job {
+						Job::Images(batch) => {
+							let batch_start = Instant::now();
+							let results = process_image_batch(&mut models, &batch, format);
+							let duration_ms =
+								batch_start.elapsed().as_millis() / batch.len() as u128;
+							for (file, result) in batch.into_iter().zip(results) {
+								let _ = done_tx.send(Done {
+									file,
+									result,
+									duration_ms,
+								});
+							}
+						}
+						Job::Video(file) => {
+							let file_start = Instant::now();
+							let media_dir = file.path.parent().unwrap();
+							let result = process_video(
+								&mut models,
+								&file,
+								media_dir,
+								&options.video,
+								format,
+							);
+							let duration_ms = file_start.elapsed().as_millis();
+							let _ = done_tx.send(Done {
+								file,
+								result,
+								duration_ms,
+							});
+						}
+					}
 				}
-				process_video(&mut models, &file, media_dir, max_frames, scene_threshold)
-			}
-		};
+			});
+		}
+		drop(done_tx);
 
-		match result {
-			Ok(_) => {
-				let duration_ms = file_start.elapsed().as_millis();
-				ui::log::file_processed(&file.path, duration_ms);
-				processed += 1;
-			}
-			Err(e) => {
-				ui::error(&format!("{}: {}", file.filename, e));
-				errors += 1;
+		for done in done_rx {
+			match done.result {
+				Ok(_) => {
+					log_file(&done.file.path, done.duration_ms);
+					processed += 1;
+				}
+				Err(e) if e.is::<DegenerateEmbedding>() => {
+					ui::warn(&format!("{}: {}", done.file.path.display(), e));
+					degenerate += 1;
+				}
+				Err(e) => {
+					ui::error(&format!("{}: {}", done.file.filename, e));
+					errors += 1;
+				}
 			}
+			progress.inc();
 		}
-	}
+	});
 
+	progress.finish();
 	let duration = start.elapsed().as_secs_f32();
 
 	println!();
 	ui::success(&format!(
-		"Processed {} files in {:.1}s",
-		processed, duration
+		"Processed {} files in {:.1}s ({:.1} files/s)",
+		processed,
+		duration,
+		processed as f32 / duration.max(f32::EPSILON)
 	));
 
 	if errors > 0 {
 		ui::warn(&format!("{} errors", errors));
 	}
 
+	if degenerate > 0 {
+		ui::warn(&format!(
+			"{} files encoded to zero or NaN embeddings and were not indexed. They may be corrupt",
+			degenerate
+		));
+	}
+
 	if skipped_videos > 0 {
 		ui::info(&format!(
 			"{} videos skipped (FFmpeg not available)",
@@ -128,35 +348,251 @@ pub fn run(
 	Ok(())
 }
 
+/// Encode an image and save its sidecar, returning the embedding
+/// (the first frame's for animations)
 pub fn process_image(
 	models: &mut Models,
 	file: &processing::scan::MediaFile,
 	media_dir: &Path,
-) -> Result<()> {
-	let embedding = processing::image::encode(models, &file.path)?;
-	let sidecar = storage::ImageSidecar::new(file.hash.clone(), embedding);
+	format: storage::SidecarFormat,
+) -> Result<Embedding> {
+	if let Some(frames) = load_animation(file) {
+		return process_animation(models, file, media_dir, frames, format);
+	}
+
+	ui::debug(&format!("Encoding image: {}", file.path.display()));
+	let img = processing::image::load_with(&file.path, models.auto_orient())?;
+	let embedding = models.encode_image(&img)?;
+	let info = storage::MediaInfo::read(&file.path, img.width(), img.height());
+	let sidecar = storage::ImageSidecar::new(file.hash.clone(), embedding.clone())
+		.with_info(info)
+		.with_histogram(processing::image::color_histogram(&img))
+		.with_source(&file.path)
+		.with_fingerprint(models.fingerprint())
+		.with_format(format);
 	storage::save_image(&sidecar, media_dir, &file.hash)?;
-	Ok(())
+	Ok(embedding)
+}
+
+/// Encode a group of images in one forward pass and save their sidecars.
+///
+/// Returns one result per input file, in order. Files that fail to load are
+/// reported individually without affecting the rest of the batch.
+pub fn process_image_batch(
+	models: &mut Models,
+	files: &[processing::scan::MediaFile],
+	format: storage::SidecarFormat,
+) -> Vec<Result<Embedding>> {
+	let mut results: Vec<Result<Embedding>> = Vec::with_capacity(files.len());
+	let mut images = Vec::with_capacity(files.len());
+	let mut loaded = Vec::with_capacity(files.len());
+	let mut details = Vec::with_capacity(files.len());
+
+	for (i, file) in files.iter().enumerate() {
+		if let Some(frames) = load_animation(file) {
+			let media_dir = file.path.parent().unwrap();
+			results.push(process_animation(models, file, media_dir, frames, format));
+			continue;
+		}
+
+		ui::debug(&format!("Encoding image: {}", file.path.display()));
+		match processing::image::load_with(&file.path, models.auto_orient()) {
+			Ok(img) => {
+				details.push((
+					storage::MediaInfo::read(&file.path, img.width(), img.height()),
+					processing::image::color_histogram(&img),
+				));
+				images.push(img);
+				loaded.push(i);
+				results.push(Err(anyhow::anyhow!("Image was not encoded")));
+			}
+			Err(e) => results.push(Err(e)),
+		}
+	}
+
+	match models.encode_images(&images) {
+		Ok(embeddings) => {
+			for ((i, embedding), (info, histogram)) in
+				loaded.into_iter().zip(embeddings).zip(details)
+			{
+				let file = &files[i];
+				if embedding.is_degenerate() {
+					results[i] = Err(DegenerateEmbedding.into());
+					continue;
+				}
+				let media_dir = file.path.parent().unwrap();
+				let sidecar = storage::ImageSidecar::new(file.hash.clone(), embedding.clone())
+					.with_info(info)
+					.with_histogram(histogram)
+					.with_source(&file.path)
+					.with_fingerprint(models.fingerprint())
+					.with_format(format);
+				results[i] =
+					storage::save_image(&sidecar, media_dir, &file.hash).map(|_| embedding);
+			}
+		}
+		Err(e) => {
+			for i in loaded {
+				results[i] = Err(anyhow::anyhow!("Batch encoding failed: {}", e));
+			}
+		}
+	}
+
+	results
+}
+
+/// Sampled frames of an animated GIF/WebP, or `None` to index it as a still image
+fn load_animation(file: &processing::scan::MediaFile) -> Option<Vec<(f64, image::DynamicImage)>> {
+	match processing::image::load_animation(&file.path, ANIMATION_MAX_FRAMES) {
+		Ok(frames) => frames,
+		Err(e) => {
+			ui::debug(&format!(
+				"Indexing {} as a still image: {}",
+				file.path.display(),
+				e
+			));
+			None
+		}
+	}
 }
 
+/// Encode the frames of an animated image and store them like a video
+fn process_animation(
+	models: &mut Models,
+	file: &processing::scan::MediaFile,
+	media_dir: &Path,
+	frames: Vec<(f64, image::DynamicImage)>,
+	format: storage::SidecarFormat,
+) -> Result<Embedding> {
+	ui::debug(&format!(
+		"Encoding {} frames of animation: {}",
+		frames.len(),
+		file.path.display()
+	));
+
+	let (timestamps, images): (Vec<f64>, Vec<image::DynamicImage>) = frames.into_iter().unzip();
+	let (embeddings, degenerate): (Vec<_>, Vec<_>) = timestamps
+		.into_iter()
+		.zip(models.encode_images(&images)?)
+		.partition(|(_, embedding)| !embedding.is_degenerate());
+	let degenerate: Vec<f64> = degenerate
+		.into_iter()
+		.map(|(timestamp, _)| timestamp)
+		.collect();
+	check_frames(&file.path, &degenerate, embeddings.len())?;
+
+	let first = embeddings
+		.first()
+		.map(|(_, embedding)| embedding.clone())
+		.unwrap_or_else(|| Embedding::raw(Vec::new()));
+
+	let sidecar = storage::VideoSidecar::new(file.hash.clone(), embeddings)
+		.with_source(&file.path)
+		.with_fingerprint(models.fingerprint())
+		.with_half(format.half);
+	storage::save_video(&sidecar, media_dir, &file.hash)?;
+	Ok(first)
+}
+
+/// How videos are turned into frame embeddings
+#[derive(Debug, Clone, Copy, Default)]
+pub struct VideoOptions {
+	pub sampling: FrameSampling,
+	/// Video stream frames are taken from (0 = first)
+	pub stream: usize,
+	/// Drop frames more similar than this to a frame already kept
+	pub frame_dedup: Option<f32>,
+	/// Fail when less than this fraction of the frames can be extracted
+	pub strict_frames: Option<f32>,
+	/// Keep at most this many frame embeddings per video
+	pub max_embeddings: Option<usize>,
+}
+
+/// Extract and encode video frames and save the sidecar, returning the first
+/// frame's embedding
 pub fn process_video(
 	models: &mut Models,
 	file: &processing::scan::MediaFile,
 	media_dir: &Path,
-	max_frames: usize,
-	scene_threshold: f32,
-) -> Result<()> {
-	let frames = processing::video::extract_frames_scene(&file.path, max_frames, scene_threshold)?;
+	options: &VideoOptions,
+	format: storage::SidecarFormat,
+) -> Result<Embedding> {
+	let extracted =
+		processing::video::extract_frames(&file.path, options.sampling, options.stream)?;
+	if let Some(min_fraction) = options.strict_frames {
+		extracted.require(min_fraction)?;
+	}
+	let frames = extracted.frames;
 
 	let mut encoded_frames = Vec::new();
+	let mut degenerate = Vec::new();
 	for (timestamp, frame_img) in frames {
 		let dynamic_img = image::DynamicImage::ImageRgb8(frame_img);
-		let embedding = processing::image::encode_image(models, &dynamic_img)?;
-		encoded_frames.push((timestamp, embedding));
+		match processing::image::encode_image(models, &dynamic_img) {
+			Ok(embedding) => encoded_frames.push((timestamp, embedding)),
+			Err(e) if e.is::<DegenerateEmbedding>() => degenerate.push(timestamp),
+			Err(e) => return Err(e),
+		}
 	}
+	check_frames(&file.path, &degenerate, encoded_frames.len())?;
 
-	let sidecar = storage::VideoSidecar::new(file.hash.clone(), encoded_frames);
+	if let Some(threshold) = options.frame_dedup {
+		let extracted = encoded_frames.len();
+		encoded_frames = processing::video::drop_similar_frames(encoded_frames, threshold);
+		if encoded_frames.len() < extracted {
+			ui::debug(&format!(
+				"Dropped {} near-duplicate frames from {}",
+				extracted - encoded_frames.len(),
+				file.filename
+			));
+		}
+	}
+
+	if let Some(max) = options.max_embeddings {
+		let encoded = encoded_frames.len();
+		encoded_frames = processing::video::cap_frames(encoded_frames, max);
+		if encoded_frames.len() < encoded {
+			ui::debug(&format!(
+				"Kept {} of {} frame embeddings from {}",
+				encoded_frames.len(),
+				encoded,
+				file.filename
+			));
+		}
+	}
+
+	let first = encoded_frames
+		.first()
+		.map(|(_, embedding)| embedding.clone())
+		.unwrap_or_else(|| Embedding::raw(Vec::new()));
+	let sidecar = storage::VideoSidecar::new(file.hash.clone(), encoded_frames)
+		.with_source(&file.path)
+		.with_fingerprint(models.fingerprint())
+		.with_half(format.half);
 
 	storage::save_video(&sidecar, media_dir, &file.hash)?;
+	Ok(first)
+}
+
+/// Warn about frames whose embedding came out degenerate and were left out,
+/// failing when no usable frame is left
+fn check_frames(path: &Path, degenerate: &[f64], kept: usize) -> Result<()> {
+	if degenerate.is_empty() {
+		return Ok(());
+	}
+	if kept == 0 {
+		return Err(DegenerateEmbedding.into());
+	}
+
+	let timestamps: Vec<String> = degenerate
+		.iter()
+		.map(|&timestamp| processing::video::format_timestamp(timestamp))
+		.collect();
+	ui::warn(&format!(
+		"{}: skipped {} frames with zero or NaN embeddings at {}",
+		path.display(),
+		degenerate.len(),
+		timestamps.join(", ")
+	));
 	Ok(())
 }
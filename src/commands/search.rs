@@ -3,16 +3,33 @@
 //! Semantic search using text queries, image references, or both.
 //! Supports negative prompts and exports results to JSON.
 
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, Context, Result};
+use base64::Engine;
+use chrono::NaiveDate;
 use colored::*;
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::path::Path;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::io::{IsTerminal, Read, Write};
+use std::net::Ipv4Addr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
 
-use crate::config::NEGATIVE_WEIGHT;
-use crate::core::Embedding;
-use crate::models::Models;
-use crate::storage;
+use crate::config::{
+	ANN_FILE, ANN_OVERSAMPLE, AUTO_SCORE_MIN_GAP, AUTO_SCORE_WINDOW, DAEMON_ENGINE_CACHE_SIZE,
+	DEDUP_SIMILARITY, DEDUP_WINDOW, DEFAULT_FRAME_GAP, DEFAULT_LIMIT, DEFAULT_MIN_SCORE,
+	DEFAULT_VIDEO_TOPK, MAX_EXPORT_THUMBNAIL_SIZE, MAX_LISTED_RESULTS, MAX_MMR_POOL,
+	MMR_POOL_FACTOR, NEGATIVE_WEIGHT, SCORE_CALIBRATION_TEMPERATURE, SCORE_HIGH, SCORE_MED,
+	STREAMING_THRESHOLD,
+};
+use crate::core::{
+	compute_content_hash, AnnIndex, AnnParams, Embedding, FileHash, HashMode, MediaType,
+};
+use crate::models::{LongTextMode, Models};
+use crate::processing::image::Region;
+use crate::storage::{self, VideoAggregation};
 use crate::ui;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,174 +40,708 @@ pub struct Match {
 	pub timestamp: Option<f64>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub hash: Option<String>,
+	/// Searched directory this match came from (only set with several roots)
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub root: Option<String>,
+	/// Image details stored in the sidecar at scan time
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub info: Option<storage::MediaInfo>,
+	/// Base64 JPEG preview, only with `--export-thumbnails`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub thumbnail: Option<String>,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-struct SearchExport {
-	query: String,
-	results: Vec<Match>,
+/// Everything needed to score the index for one query.
+///
+/// Serializable so the same request can be forwarded to a running daemon.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchRequest {
+	/// Text queries, mean-pooled into one embedding when there are several
+	#[serde(default)]
+	pub queries: Vec<String>,
+	pub image: Option<PathBuf>,
+	/// Reference image read from stdin or a URL, used instead of `image`
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub image_data: Option<ImageData>,
+	/// Region of the reference image to search with instead of the whole image
+	#[serde(default, skip_serializing_if = "Option::is_none")]
+	pub crop: Option<Region>,
+	pub weight: f32,
+	/// How text and image are combined when both are given
+	#[serde(default)]
+	pub combine: CombineMode,
+	pub negative: Option<String>,
+	/// Root directories to search; results are merged
+	pub dirs: Vec<PathBuf>,
+	pub recursive: bool,
+	/// Refuse to search sidecars embedded by different models
+	#[serde(default)]
+	pub strict: bool,
+	/// Read sidecars one at a time instead of loading them all, see
+	/// [`SearchEngine::stream`]. Large libraries stream automatically
+	#[serde(default)]
+	pub streaming: bool,
+	/// Truncate text queries beyond the token limit or encode them in chunks
+	#[serde(default)]
+	pub long_text: LongTextMode,
+	#[serde(flatten)]
+	pub options: SearchOptions,
 }
 
-#[allow(clippy::too_many_arguments)]
-pub fn run(
-	query_text: Option<&str>,
-	query_image: Option<&Path>,
-	weight: f32,
-	negative: Option<&str>,
-	dir: &Path,
-	recursive: bool,
-	limit: usize,
-	min_score: f32,
-	open_first: bool,
-	include_ref: bool,
-	exclude_videos: bool,
-	paths_only: bool,
-	export: Option<&Path>,
-) -> Result<()> {
-	let search_start = std::time::Instant::now();
-
-	// Build query embedding
-	let mut models = Models::new()?;
+/// Encoded image bytes and where they came from
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageData {
+	/// `stdin` or the URL, for messages
+	pub source: String,
+	pub bytes: Vec<u8>,
+}
 
-	let query_emb = match (query_text, query_image) {
-		(Some(text), None) => {
-			ui::info(&format!("Searching for: \"{}\"", text));
-			models.encode_text(text)?
+impl ImageData {
+	/// Read a piped image from stdin (`--image -`)
+	pub fn from_stdin() -> Result<Self> {
+		let mut stdin = std::io::stdin();
+		if stdin.is_terminal() {
+			anyhow::bail!("--image - reads from stdin, pipe an image in: cat photo.jpg | scout search --image -");
 		}
-		(None, Some(img_path)) => {
-			ui::info(&format!("Searching by image: {}", img_path.display()));
-			let img = image::open(img_path)?;
-			models.encode_image(&img)?
+		let mut bytes = Vec::new();
+		stdin.read_to_end(&mut bytes)?;
+		Self::checked("stdin", bytes)
+	}
+
+	/// Download an image (`--image-url`)
+	#[cfg(feature = "http")]
+	pub fn from_url(url: &str) -> Result<Self> {
+		ui::debug(&format!("Downloading {}", url));
+		let mut response = ureq::get(url)
+			.call()
+			.map_err(|e| anyhow!("Failed to download {}: {}", url, e))?;
+		let bytes = response
+			.body_mut()
+			.with_config()
+			.limit(crate::config::MAX_IMAGE_DOWNLOAD_BYTES)
+			.read_to_vec()
+			.map_err(|e| anyhow!("Failed to download {}: {}", url, e))?;
+		Self::checked(url, bytes)
+	}
+
+	/// Download an image (`--image-url`)
+	#[cfg(not(feature = "http"))]
+	pub fn from_url(_url: &str) -> Result<Self> {
+		anyhow::bail!("--image-url requires Scout to be built with the 'http' feature")
+	}
+
+	/// Reject empty input and unknown formats before any model is loaded
+	fn checked(source: &str, bytes: Vec<u8>) -> Result<Self> {
+		if bytes.is_empty() {
+			anyhow::bail!("No image data received from {}", source);
 		}
-		(Some(text), Some(img_path)) => {
-			let filename = img_path
-				.file_name()
-				.and_then(|n| n.to_str())
-				.unwrap_or("image");
-			ui::info(&format!(
-				"Combined search: \"{}\" + {} (weight: {:.2})",
-				text, filename, weight
-			));
-			let text_emb = models.encode_text(text)?;
-			let img = image::open(img_path)?;
-			let img_emb = models.encode_image(&img)?;
-			Embedding::blend(&text_emb, &img_emb, weight)
+		if image::guess_format(&bytes).is_err() {
+			anyhow::bail!(
+				"The {} bytes from {} are not a recognized image format",
+				bytes.len(),
+				source
+			);
 		}
-		(None, None) => {
-			return Err(anyhow!("Must provide either query text or --image"));
+		Ok(Self {
+			source: source.to_string(),
+			bytes,
+		})
+	}
+}
+
+/// Reference image for a search, decoded from a file, stdin or a URL
+pub struct QueryImage {
+	pub image: image::DynamicImage,
+	/// Shown in log output
+	pub label: String,
+	/// Source file, left out of the results unless `include_ref` is set
+	pub path: Option<PathBuf>,
+}
+
+impl QueryImage {
+	pub fn open(path: &Path) -> Result<Self> {
+		Ok(Self {
+			image: crate::processing::image::load_with(path, crate::config::auto_orient())?,
+			label: path.display().to_string(),
+			path: Some(path.to_path_buf()),
+		})
+	}
+
+	/// Decode piped or downloaded bytes, telling missing data apart from bad data
+	pub fn from_data(data: &ImageData) -> Result<Self> {
+		if data.bytes.is_empty() {
+			anyhow::bail!("No image data received from {}", data.source);
 		}
-	};
+		let image = crate::processing::image::load_from_memory_with(
+			&data.bytes,
+			crate::config::auto_orient(),
+		)
+		.map_err(|e| {
+			anyhow!(
+				"Could not decode the {} bytes from {} as an image: {}",
+				data.bytes.len(),
+				data.source,
+				e
+			)
+		})?;
+		Ok(Self {
+			image,
+			label: data.source.clone(),
+			path: None,
+		})
+	}
 
-	// Build negative embedding if provided
-	let negative_emb = if let Some(neg) = negative {
-		ui::debug(&format!("Negative prompt: \"{}\"", neg));
-		Some(models.encode_text(neg)?)
-	} else {
-		None
-	};
+	/// Keep only `region` of the image (`--crop`)
+	pub fn cropped(self, region: Region) -> Result<Self> {
+		let image = region
+			.crop(&self.image)
+			.with_context(|| format!("Cannot crop {}", self.label))?;
+		Ok(Self {
+			image,
+			label: format!("{} [{}]", self.label, region),
+			..self
+		})
+	}
+}
 
-	ui::info(&format!(
-		"Loading embeddings from {}",
-		ui::path_link(dir, 40)
-	));
-	let (sidecars, hash_cache) = storage::load_all_sidecars(dir, recursive);
+/// How indexed media is scored and filtered, independent of the query
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchOptions {
+	/// Most matches to return; `usize::MAX` keeps every one (`--limit all`)
+	pub limit: usize,
+	pub min_score: f32,
+	/// Ignore `min_score` and pick a cutoff with [`auto_threshold`]
+	#[serde(default)]
+	pub auto_score: bool,
+	pub include_ref: bool,
+	pub exclude_videos: bool,
+	#[serde(default)]
+	pub ann: bool,
+	#[serde(default)]
+	pub video_agg: VideoAggregation,
+	#[serde(default = "default_video_topk")]
+	pub video_topk: usize,
+	/// Average each video frame's score over a window of this many frames,
+	/// see [`storage::VideoSidecar::frame_scores`]
+	#[serde(default)]
+	pub temporal_smooth: Option<usize>,
+	/// Only consider video frames at or after this many seconds
+	#[serde(default)]
+	pub after: Option<f64>,
+	/// Only consider video frames at or before this many seconds
+	#[serde(default)]
+	pub before: Option<f64>,
+	/// Temperature for [`Embedding::calibrate_score`]. When set, `min_score` is
+	/// compared against the calibrated score; match scores stay raw
+	#[serde(default)]
+	pub calibration: Option<f32>,
+	/// λ for [`mmr_rerank`]: re-order the best matches to trade relevance for
+	/// diversity (1.0 = plain ranking, lower = more varied)
+	#[serde(default)]
+	pub diverse: Option<f32>,
+	/// Before scoring an image search, skip images whose color histogram is
+	/// further than this from the reference's (0.0-1.0)
+	#[serde(default)]
+	pub prefilter: Option<f32>,
+	/// Report up to this many moments per video instead of one aggregated
+	/// match, each its best frame within `frame_gap` seconds
+	#[serde(default)]
+	pub frames_per_video: Option<usize>,
+	#[serde(default = "default_frame_gap")]
+	pub frame_gap: f64,
+	/// Only consider images whose EXIF capture date is on or after this day.
+	/// With either date set, files without a capture date are skipped
+	#[serde(default)]
+	pub after_date: Option<NaiveDate>,
+	/// Only consider images whose EXIF capture date is on or before this day
+	#[serde(default)]
+	pub before_date: Option<NaiveDate>,
+	/// Drop results that are the same file reached through another path, or
+	/// whose embedding is near-identical to a better result
+	#[serde(default = "default_dedup")]
+	pub dedup: bool,
+	/// Blend a filename keyword score into the semantic score
+	#[serde(default)]
+	pub hybrid: Option<Hybrid>,
+}
 
-	if sidecars.is_empty() {
-		ui::warn("No indexed images found. Run 'scout scan' first.");
-		return Ok(());
+impl Default for SearchOptions {
+	/// The CLI's defaults
+	fn default() -> Self {
+		Self {
+			limit: DEFAULT_LIMIT,
+			min_score: DEFAULT_MIN_SCORE,
+			auto_score: false,
+			include_ref: false,
+			exclude_videos: false,
+			ann: false,
+			video_agg: VideoAggregation::default(),
+			video_topk: DEFAULT_VIDEO_TOPK,
+			temporal_smooth: None,
+			after: None,
+			before: None,
+			calibration: None,
+			diverse: None,
+			prefilter: None,
+			frames_per_video: None,
+			frame_gap: DEFAULT_FRAME_GAP,
+			after_date: None,
+			before_date: None,
+			dedup: true,
+			hybrid: None,
+		}
 	}
+}
 
-	ui::success(&format!("Loaded {} embeddings", sidecars.len()));
+fn default_video_topk() -> usize {
+	DEFAULT_VIDEO_TOPK
+}
 
-	let matches: Vec<Match> = sidecars
-		.into_par_iter()
-		.filter_map(|(_path, sidecar)| {
-			let hash = sidecar.hash().to_string();
+fn default_frame_gap() -> f64 {
+	DEFAULT_FRAME_GAP
+}
 
-			match sidecar {
-				storage::Sidecar::Image(img) => {
-					let mut score = query_emb.similarity(&img.embedding());
+fn default_dedup() -> bool {
+	true
+}
 
-					if let Some(ref neg_emb) = negative_emb {
-						let neg_score = neg_emb.similarity(&img.embedding());
-						score -= neg_score * NEGATIVE_WEIGHT;
-					}
+/// How a combined search scores candidates against its text and image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum CombineMode {
+	/// Blend the two query embeddings by `weight` and score against that
+	#[default]
+	Blend,
+	/// The higher of the text and image similarities: match either
+	Max,
+	/// The two similarities averaged by `weight`
+	Mean,
+	/// The lower of the text and image similarities: match both
+	Min,
+}
 
-					if score >= min_score {
-						if let Some(image_path) = hash_cache.get(&hash) {
-							return Some(Match {
-								path: image_path.to_string_lossy().to_string(),
-								score,
-								timestamp: None,
-								hash: Some(hash.clone()),
-							});
-						}
-					}
-					None
-				}
-				storage::Sidecar::Video(vid) => {
-					if exclude_videos {
-						return None;
-					}
+impl CombineMode {
+	/// Combine a candidate's text and image similarities. `weight` is the
+	/// text's share; [`CombineMode::Blend`] is scored on the blended
+	/// embedding instead, and treated like `Mean` here
+	pub fn combine(self, text: f32, image: f32, weight: f32) -> f32 {
+		match self {
+			CombineMode::Blend | CombineMode::Mean => weight * text + (1.0 - weight) * image,
+			CombineMode::Max => text.max(image),
+			CombineMode::Min => text.min(image),
+		}
+	}
+}
 
-					// Find best frame
-					let mut best_score = 0.0;
-					let mut best_timestamp = 0.0;
+/// Text and image queries scored separately against each candidate
+pub struct SplitQuery {
+	pub text: Embedding,
+	pub image: Embedding,
+	pub mode: CombineMode,
+	/// Share of the text score (0.0-1.0), for [`CombineMode::Mean`]
+	pub weight: f32,
+}
 
-					for (timestamp, frame_emb) in vid.frames() {
-						let mut score = query_emb.similarity(&frame_emb);
+impl SplitQuery {
+	fn score(&self, candidate: &Embedding) -> f32 {
+		self.mode.combine(
+			self.text.similarity(candidate),
+			self.image.similarity(candidate),
+			self.weight,
+		)
+	}
+}
 
-						if let Some(ref neg_emb) = negative_emb {
-							let neg_score = neg_emb.similarity(&frame_emb);
-							score -= neg_score * NEGATIVE_WEIGHT;
-						}
+/// `--hybrid`: a filename keyword score blended into the semantic score
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Hybrid {
+	/// Share of the semantic score (0.0-1.0); the rest is the keyword score
+	pub alpha: f32,
+	/// Query words looked up among the filename words
+	pub terms: Vec<String>,
+}
 
-						if score > best_score {
-							best_score = score;
-							best_timestamp = timestamp;
-						}
-					}
+impl Hybrid {
+	pub fn new(alpha: f32, queries: &[String]) -> Self {
+		let mut terms: Vec<String> = queries
+			.iter()
+			.flat_map(|query| keyword_tokens(query))
+			.filter(|term| term.len() > 1)
+			.collect();
+		terms.sort();
+		terms.dedup();
+		Self { alpha, terms }
+	}
+}
 
-					if best_score >= min_score {
-						if let Some(video_path) = hash_cache.get(&hash) {
-							return Some(Match {
-								path: video_path.to_string_lossy().to_string(),
-								score: best_score,
-								timestamp: Some(best_timestamp),
-								hash: Some(hash.clone()),
-							});
-						}
-					}
-					None
+/// [`Hybrid`] terms with their BM25 inverse document frequency over the
+/// searched filenames, so rare words like `beach` outweigh common ones like `img`
+struct KeywordScorer {
+	alpha: f32,
+	terms: Vec<(String, f32)>,
+	total: f32,
+}
+
+impl KeywordScorer {
+	fn new<'a>(hybrid: &Hybrid, paths: impl Iterator<Item = &'a Path>) -> Self {
+		let mut files = 0;
+		let mut frequency = vec![0usize; hybrid.terms.len()];
+		for path in paths {
+			files += 1;
+			let words = filename_tokens(path);
+			for (term, count) in hybrid.terms.iter().zip(&mut frequency) {
+				if words.contains(term) {
+					*count += 1;
 				}
 			}
-		})
+		}
+
+		let idf = |df: usize| {
+			let (n, df) = (files as f32, df as f32);
+			(1.0 + (n - df + 0.5) / (df + 0.5)).ln()
+		};
+		let terms: Vec<(String, f32)> = hybrid
+			.terms
+			.iter()
+			.cloned()
+			.zip(frequency.into_iter().map(idf))
+			.collect();
+		let total = terms.iter().map(|(_, weight)| weight).sum();
+		Self {
+			alpha: hybrid.alpha,
+			terms,
+			total,
+		}
+	}
+
+	/// Share of the query's term weight found in the filename (0.0-1.0)
+	fn keyword_score(&self, path: &Path) -> f32 {
+		if self.total <= 0.0 {
+			return 0.0;
+		}
+		let words = filename_tokens(path);
+		let found: f32 = self
+			.terms
+			.iter()
+			.filter(|(term, _)| words.contains(term))
+			.map(|(_, weight)| weight)
+			.sum();
+		found / self.total
+	}
+
+	/// Blend the calibrated semantic score with `keyword`
+	fn combine(&self, semantic: f32, keyword: f32) -> f32 {
+		let semantic = Embedding::calibrate_score(semantic, SCORE_CALIBRATION_TEMPERATURE);
+		self.alpha * semantic + (1.0 - self.alpha) * keyword
+	}
+}
+
+/// Lowercased alphanumeric words of `text`
+fn keyword_tokens(text: &str) -> impl Iterator<Item = String> + '_ {
+	text.split(|c: char| !c.is_alphanumeric())
+		.filter(|word| !word.is_empty())
+		.map(str::to_lowercase)
+}
+
+/// Words of a file name without its extension, used as pseudo-tags
+fn filename_tokens(path: &Path) -> Vec<String> {
+	path.file_stem()
+		.map(|stem| keyword_tokens(&stem.to_string_lossy()).collect())
+		.unwrap_or_default()
+}
+
+/// Confidence group of a result with `--tiers`, by raw score
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+	/// At least [`SCORE_HIGH`]
+	Strong,
+	/// At least [`SCORE_MED`]
+	Possible,
+}
+
+impl Tier {
+	/// The tier of a raw score, `None` below [`SCORE_MED`]
+	pub fn of(score: f32) -> Option<Self> {
+		if score >= SCORE_HIGH {
+			Some(Tier::Strong)
+		} else if score >= SCORE_MED {
+			Some(Tier::Possible)
+		} else {
+			None
+		}
+	}
+
+	fn label(self) -> &'static str {
+		match self {
+			Tier::Strong => "Strong matches",
+			Tier::Possible => "Possible matches",
+		}
+	}
+}
+
+/// Matches with a [`Tier`], strong ones first, otherwise in their original
+/// order. Matches below every tier are left out.
+fn tiered(matches: &[Match]) -> Vec<(Tier, &Match)> {
+	let mut rows: Vec<(Tier, &Match)> = matches
+		.iter()
+		.filter_map(|m| Tier::of(m.score).map(|tier| (tier, m)))
 		.collect();
+	rows.sort_by_key(|&(tier, _)| tier != Tier::Strong);
+	rows
+}
 
-	let mut matches = matches;
-
-	// Filter out reference image if not including it
-	if !include_ref {
-		if let Some(ref_path) = query_image {
-			if let Ok(canonical_ref) = ref_path.canonicalize() {
-				let canonical_ref_str = canonical_ref.to_string_lossy().to_string();
-				matches.retain(|m| {
-					if let Ok(canonical_match) = Path::new(&m.path).canonicalize() {
-						canonical_match.to_string_lossy() != canonical_ref_str
-					} else {
-						true
-					}
-				});
-			}
+/// Minimum score for `--score`: a fixed value or `auto`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreThreshold {
+	Fixed(f32),
+	/// Derive the cutoff from the score distribution, see [`auto_threshold`]
+	Auto,
+}
+
+impl std::str::FromStr for ScoreThreshold {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		if s == "auto" {
+			return Ok(Self::Auto);
 		}
+		s.parse()
+			.map(Self::Fixed)
+			.map_err(|_| format!("expected a number or 'auto', got '{}'", s))
 	}
+}
 
-	matches.sort_by(|a, b| {
-		b.score
-			.partial_cmp(&a.score)
-			.unwrap_or(std::cmp::Ordering::Equal)
-	});
-	matches.truncate(limit);
+impl std::fmt::Display for ScoreThreshold {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::Fixed(score) => write!(f, "{}", score),
+			Self::Auto => f.write_str("auto"),
+		}
+	}
+}
+
+/// Re-order candidates with Maximal Marginal Relevance.
+///
+/// Repeatedly picks the candidate maximizing
+/// `λ * sim(query, doc) - (1 - λ) * max sim(doc, already selected)`, so each
+/// pick must be relevant but also unlike the ones before it. `lambda` is
+/// clamped to 0.0-1.0; at 1.0 this is the plain similarity ranking.
+///
+/// Returns up to `k` indices into `candidates`, in selection order.
+pub fn mmr_rerank(
+	query: &Embedding,
+	candidates: &[Embedding],
+	lambda: f32,
+	k: usize,
+) -> Vec<usize> {
+	let lambda = lambda.clamp(0.0, 1.0);
+	let relevance: Vec<f32> = candidates.iter().map(|c| query.similarity(c)).collect();
+	// Highest similarity to any selected candidate so far (never below 0.0)
+	let mut redundancy = vec![0.0; candidates.len()];
+	let mut remaining: Vec<usize> = (0..candidates.len()).collect();
+	let mut selected = Vec::with_capacity(k.min(candidates.len()));
+
+	while selected.len() < k && !remaining.is_empty() {
+		let mmr = |i: usize| lambda * relevance[i] - (1.0 - lambda) * redundancy[i];
+		let (pos, _) = remaining
+			.iter()
+			.enumerate()
+			.max_by(|(_, &a), (_, &b)| {
+				mmr(a)
+					.partial_cmp(&mmr(b))
+					.unwrap_or(std::cmp::Ordering::Equal)
+					// Prefer the earlier candidate on ties
+					.then(b.cmp(&a))
+			})
+			.unwrap();
+		let pick = remaining.swap_remove(pos);
+		selected.push(pick);
+
+		for &i in &remaining {
+			redundancy[i] = redundancy[i].max(candidates[i].similarity(&candidates[pick]));
+		}
+	}
+
+	selected
+}
+
+/// Pick a score cutoff that keeps "the obviously good matches".
+///
+/// Only the best [`AUTO_SCORE_WINDOW`] scores are considered. The cutoff is
+/// placed at the largest drop between two consecutive scores, keeping
+/// everything above it. If no drop reaches [`AUTO_SCORE_MIN_GAP`] the scores
+/// form a smooth slope, and mean + 1σ of the window is used instead.
+///
+/// The result never exceeds the best score, so at least one match survives.
+/// Returns `0.0` for an empty slice.
+pub fn auto_threshold(scores: &[f32]) -> f32 {
+	let mut top: Vec<f32> = scores.iter().copied().filter(|s| s.is_finite()).collect();
+	top.sort_by(|a, b| b.partial_cmp(a).unwrap_or(std::cmp::Ordering::Equal));
+	top.truncate(AUTO_SCORE_WINDOW);
+
+	let Some(&best) = top.first() else {
+		return 0.0;
+	};
+
+	let largest_gap = top
+		.windows(2)
+		.map(|pair| (pair[0] - pair[1], pair[0]))
+		.max_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+
+	match largest_gap {
+		Some((gap, above)) if gap >= AUTO_SCORE_MIN_GAP => above,
+		_ => {
+			let n = top.len() as f32;
+			let mean = top.iter().sum::<f32>() / n;
+			let variance = top.iter().map(|s| (s - mean).powi(2)).sum::<f32>() / n;
+			(mean + variance.sqrt()).min(best)
+		}
+	}
+}
+
+/// HNSW index persisted next to the sidecars, tied to the set of indexed files
+#[derive(Serialize, Deserialize)]
+struct AnnCache {
+	content_hash: String,
+	/// File hash owning each vector in the index (video frames share a hash)
+	owners: Vec<String>,
+	index: AnnIndex,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SearchExport {
+	query: String,
+	results: Vec<Match>,
+}
+
+/// Machine-readable result formats for `--export` and `--format`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum OutputFormat {
+	/// Single pretty-printed JSON document with the query
+	Json,
+	/// One JSON match per line
+	Jsonl,
+	/// Comma-separated rank, path, score, timestamp and hash with a header row
+	Csv,
+	/// Bare paths, one per line
+	#[value(skip)]
+	Paths,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn run(
+	query_texts: &[String],
+	query_image: Option<&Path>,
+	image_url: Option<&str>,
+	weight: f32,
+	negative: Option<&str>,
+	dirs: &[PathBuf],
+	recursive: bool,
+	limit: usize,
+	min_score: ScoreThreshold,
+	open_first: bool,
+	include_ref: bool,
+	exclude_videos: bool,
+	paths_only: bool,
+	export: Option<&Path>,
+	format: Option<OutputFormat>,
+	daemon: bool,
+	port: u16,
+	ann: bool,
+	video_agg: VideoAggregation,
+	video_topk: usize,
+	temporal_smooth: Option<usize>,
+	after: Option<f64>,
+	before: Option<f64>,
+	after_date: Option<NaiveDate>,
+	before_date: Option<NaiveDate>,
+	calibration: Option<f32>,
+	normalize_scores: bool,
+	tiers: bool,
+	diverse: Option<f32>,
+	prefilter: Option<f32>,
+	frames_per_video: Option<usize>,
+	frame_gap: f64,
+	thumbnails: Option<(u32, u8)>,
+	strict: bool,
+	streaming: bool,
+	dedup: bool,
+	long_text: LongTextMode,
+	combine: CombineMode,
+	hybrid: Option<f32>,
+	crop: Option<Region>,
+) -> Result<()> {
+	let search_start = std::time::Instant::now();
+	if crop.is_some() && query_image.is_none() && image_url.is_none() {
+		anyhow::bail!("--crop needs a reference image (--image or --image-url)");
+	}
+
+	// `--image -` and `--image-url` are read here, so a daemon receives the bytes
+	let image_data = match (query_image, image_url) {
+		(_, Some(url)) => Some(ImageData::from_url(url)?),
+		(Some(path), None) if path == Path::new("-") => Some(ImageData::from_stdin()?),
+		_ => None,
+	};
+	let image_label = match &image_data {
+		Some(data) => Some(data.source.clone()),
+		None => query_image.map(|path| path.display().to_string()),
+	};
+
+	let request = SearchRequest {
+		queries: query_texts.to_vec(),
+		image: query_image
+			.filter(|_| image_data.is_none())
+			.map(Path::to_path_buf),
+		image_data,
+		crop,
+		weight,
+		combine,
+		negative: negative.map(str::to_string),
+		dirs: dirs.to_vec(),
+		recursive,
+		strict,
+		streaming,
+		long_text,
+		options: SearchOptions {
+			limit,
+			min_score: match min_score {
+				ScoreThreshold::Fixed(score) => score,
+				ScoreThreshold::Auto => 0.0,
+			},
+			auto_score: min_score == ScoreThreshold::Auto,
+			include_ref,
+			exclude_videos,
+			ann,
+			video_agg,
+			video_topk,
+			temporal_smooth,
+			after,
+			before,
+			calibration,
+			diverse,
+			prefilter,
+			frames_per_video,
+			frame_gap,
+			after_date,
+			before_date,
+			dedup,
+			hybrid: hybrid.map(|alpha| Hybrid::new(alpha, query_texts)),
+		},
+	};
+
+	let matches = if daemon {
+		// The daemon has its own working directory, so send absolute paths
+		let request = SearchRequest {
+			image: request.image.as_deref().map(absolute),
+			dirs: request.dirs.iter().map(|d| absolute(d)).collect(),
+			..request
+		};
+		ui::debug(&format!("Forwarding query to daemon on port {}", port));
+		super::serve::send_search((Ipv4Addr::LOCALHOST, port).into(), &request)?
+	} else {
+		let mut models = Models::new()?;
+		execute(&mut models, &request)?
+	};
 
 	if matches.is_empty() {
 		ui::warn("No matches found");
@@ -198,49 +749,117 @@ pub fn run(
 	}
 
 	// Build query string for export
-	let query_string = match (query_text, query_image) {
-		(Some(text), None) => text.to_string(),
-		(None, Some(img_path)) => format!("image:{}", img_path.display()),
-		(Some(text), Some(img_path)) => format!("{} + image:{}", text, img_path.display()),
-		(None, None) => String::new(),
+	let query_text = query_texts.join(" + ");
+	let query_string = match (query_texts.is_empty(), image_label) {
+		(false, None) => query_text,
+		(true, Some(label)) => format!("image:{}", label),
+		(false, Some(label)) => format!("{} + image:{}", query_text, label),
+		(true, None) => String::new(),
 	};
 
-	// Handle --export flag
-	if let Some(export_path) = export {
-		let export_data = SearchExport {
-			query: query_string,
-			results: matches.clone(),
-		};
-		let json = serde_json::to_string_pretty(&export_data)?;
+	// Machine-readable output: --export writes to a file (or stdout with '-'),
+	// --paths and a bare --format write to stdout
+	let output = match (export, paths_only, format) {
+		(Some(path), _, format) => Some((Some(path), format.unwrap_or(OutputFormat::Json))),
+		(None, true, _) => Some((None, OutputFormat::Paths)),
+		(None, false, Some(format)) => Some((None, format)),
+		(None, false, None) => None,
+	};
 
-		if export_path.to_str() == Some("-") || export_path.as_os_str().is_empty() {
-			// Output to stdout
-			println!("{}", json);
-		} else {
-			// Write to file
-			std::fs::write(export_path, json)?;
-			ui::success(&format!("Exported to {}", export_path.display()));
+	let matches = match thumbnails {
+		Some((size, quality)) if matches!(output, Some((_, OutputFormat::Json))) => {
+			attach_thumbnails(matches, size, quality)
 		}
-		return Ok(());
-	}
+		Some(_) => {
+			ui::warn("--export-thumbnails only applies to JSON output");
+			matches
+		}
+		None => matches,
+	};
 
-	// Handle --paths flag
-	if paths_only {
-		// Output paths to stdout, all logging already went to stderr
-		for m in &matches {
-			println!("{}", m.path);
+	if let Some((path, format)) = output {
+		match path {
+			Some(path) if path.to_str() != Some("-") && !path.as_os_str().is_empty() => {
+				let mut file = std::io::BufWriter::new(std::fs::File::create(path)?);
+				write_results(&mut file, &matches, &query_string, format)?;
+				file.flush()?;
+				ui::success(&format!("Exported to {}", path.display()));
+			}
+			_ => {
+				// All logging goes to stderr, so stdout stays clean for pipes
+				let stdout = std::io::stdout();
+				let written = write_results(&mut stdout.lock(), &matches, &query_string, format);
+				// A reader that stops early (`| head`) doesn't want the rest
+				match written {
+					Err(e) if is_broken_pipe(&e) => {}
+					written => written?,
+				}
+			}
 		}
 		return Ok(());
 	}
 
-	// Normal interactive output
-	ui::header("Results");
+	// `--limit all` is meant for pipes and exports, so the terminal only lists
+	// the best matches
+	let unlisted = match limit {
+		usize::MAX => matches.len().saturating_sub(MAX_LISTED_RESULTS),
+		_ => 0,
+	};
+	let matches = &matches[..matches.len() - unlisted];
+
+	// Normal interactive output. --tiers lists strong matches, then possible
+	// ones, each under its own header
+	let rows: Vec<(Option<Tier>, &Match)> = if tiers {
+		tiered(matches)
+			.into_iter()
+			.map(|(tier, m)| (Some(tier), m))
+			.collect()
+	} else {
+		ui::header("Results");
+		matches.iter().map(|m| (None, m)).collect()
+	};
+
+	// Percentages show calibrated scores when requested, exports stay raw.
+	// Hybrid scores are calibrated already
+	let calibrated = |raw: f32| match calibration.filter(|_| hybrid.is_none()) {
+		Some(temperature) => Embedding::calibrate_score(raw, temperature),
+		None => raw,
+	};
+
+	// --normalize-scores stretches the shown range from the cutoff to the top
+	// hit, which `--diverse` may have moved down the list
+	let scores = rows.iter().map(|(_, m)| calibrated(m.score));
+	let top = scores.clone().fold(f32::MIN, f32::max);
+	let floor = match min_score {
+		ScoreThreshold::Fixed(score) => score,
+		ScoreThreshold::Auto => scores.fold(top, f32::min),
+	};
+	let shown = |raw: f32| {
+		if normalize_scores {
+			normalized_score(calibrated(raw), floor, top)
+		} else {
+			calibrated(raw)
+		}
+	};
 
 	// Calculate min/max scores for gradient
-	let min_score = matches.iter().map(|m| m.score).min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)).unwrap_or(0.0);
-	let max_score = matches.iter().map(|m| m.score).max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal)).unwrap_or(1.0);
+	let min_score = rows
+		.iter()
+		.map(|(_, m)| shown(m.score))
+		.min_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+		.unwrap_or(0.0);
+	let max_score = rows
+		.iter()
+		.map(|(_, m)| shown(m.score))
+		.max_by(|a, b| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
+		.unwrap_or(1.0);
 
-	for (i, m) in matches.iter().enumerate() {
+	let mut current_tier = None;
+	for (i, &(tier, m)) in rows.iter().enumerate() {
+		if let Some(tier) = tier.filter(|&tier| current_tier != Some(tier)) {
+			ui::header(tier.label());
+			current_tier = Some(tier);
+		}
 		let path = Path::new(&m.path);
 
 		let link = ui::log::path_link(path, 60);
@@ -254,14 +873,32 @@ pub fn run(
 			String::new()
 		};
 
-		let colored_percentage = ui::log::color_gradient(m.score * 100.0, min_score * 100.0, max_score * 100.0, false);
+		let colored_percentage = ui::log::color_gradient(
+			shown(m.score) * 100.0,
+			min_score * 100.0,
+			max_score * 100.0,
+			false,
+		);
+
+		let root_str = match &m.root {
+			Some(root) => format!(" [{}]", root),
+			None => String::new(),
+		};
+
+		// Resolution from the sidecar, so the image itself isn't reopened
+		let info_str = match &m.info {
+			Some(info) if ui::Log::is_verbose() => format!(" {}x{}", info.width, info.height),
+			_ => String::new(),
+		};
 
 		println!(
-			"{}. {}{} {}%",
+			"{}. {}{} {}%{}{}",
 			format!("{:2}", i + 1).bright_blue().bold(),
 			link.bright_white(),
 			location_str.dimmed(),
-			colored_percentage
+			colored_percentage,
+			root_str.dimmed(),
+			info_str.dimmed()
 		);
 	}
 
@@ -269,6 +906,21 @@ pub fn run(
 
 	println!();
 
+	let hidden = matches.len() - rows.len();
+	if hidden > 0 {
+		ui::info(&format!(
+			"{} weaker matches hidden (below {:.0}%)",
+			hidden,
+			SCORE_MED * 100.0
+		));
+	}
+	if unlisted > 0 {
+		ui::info(&format!(
+			"{} more matches not listed. Use --paths or --export to get all of them",
+			unlisted
+		));
+	}
+
 	// Low score warning
 	if !matches.is_empty() && matches[0].score < 0.10 {
 		ui::warn("Top result has low similarity (<10%)");
@@ -283,7 +935,7 @@ pub fn run(
 
 	ui::success(&format!(
 		"Found {} matches in {:.0}ms",
-		matches.len(),
+		matches.len() + unlisted,
 		search_duration
 	));
 
@@ -295,3 +947,1847 @@ pub fn run(
 
 	Ok(())
 }
+
+/// Place `score` between the score cutoff `floor` (0.0) and the best result
+/// `top` (1.0), for display only. Relative to one result set, so it says
+/// nothing about how good the top match is.
+pub fn normalized_score(score: f32, floor: f32, top: f32) -> f32 {
+	if top <= floor {
+		return 1.0;
+	}
+	((score - floor) / (top - floor)).clamp(0.0, 1.0)
+}
+
+/// Fill in each match's `thumbnail`; files that fail to decode go without one
+fn attach_thumbnails(mut matches: Vec<Match>, size: u32, quality: u8) -> Vec<Match> {
+	ui::info(&format!("Rendering {} thumbnails", matches.len()));
+	matches.par_iter_mut().for_each(|m| {
+		match thumbnail_base64(Path::new(&m.path), m.timestamp, size, quality) {
+			Ok(encoded) => m.thumbnail = Some(encoded),
+			Err(e) => ui::debug(&format!("No thumbnail for {}: {}", m.path, e)),
+		}
+	});
+	matches
+}
+
+/// Base64 JPEG of a media file, at most `size` pixels on its long edge.
+///
+/// Videos use the frame at `timestamp`, which FFmpeg extracts again.
+pub fn thumbnail_base64(
+	path: &Path,
+	timestamp: Option<f64>,
+	size: u32,
+	quality: u8,
+) -> Result<String> {
+	let image = match (MediaType::detect(path), timestamp) {
+		(Some(MediaType::Video), Some(timestamp)) => image::DynamicImage::ImageRgb8(
+			crate::processing::video::extract_frame(path, timestamp)?,
+		),
+		_ => crate::processing::image::load_with(path, crate::config::auto_orient())?,
+	};
+
+	let mut jpeg = Vec::new();
+	image::codecs::jpeg::JpegEncoder::new_with_quality(&mut jpeg, quality)
+		.encode_image(&image.thumbnail(size, size).to_rgb8())?;
+	Ok(base64::engine::general_purpose::STANDARD.encode(jpeg))
+}
+
+/// Parse `--thumbnail-size`, capped at [`MAX_EXPORT_THUMBNAIL_SIZE`]
+pub fn parse_thumbnail_size(text: &str) -> Result<u32, String> {
+	match text.trim().parse::<u32>() {
+		Ok(size) if (1..=MAX_EXPORT_THUMBNAIL_SIZE).contains(&size) => Ok(size),
+		_ => Err(format!(
+			"expected a size from 1 to {} pixels, got '{}'",
+			MAX_EXPORT_THUMBNAIL_SIZE, text
+		)),
+	}
+}
+
+/// Parse a `--limit`: a count, or `all` (also `0`) for every match above the
+/// score threshold
+pub fn parse_limit(text: &str) -> Result<usize, String> {
+	match text.trim() {
+		"all" | "0" => Ok(usize::MAX),
+		count => count
+			.parse()
+			.map_err(|_| format!("expected a number or 'all', got '{}'", text)),
+	}
+}
+
+/// Parse a `--hybrid` semantic weight from 0.0 to 1.0
+pub fn parse_hybrid(text: &str) -> Result<f32, String> {
+	match text.trim().parse::<f32>() {
+		Ok(alpha) if (0.0..=1.0).contains(&alpha) => Ok(alpha),
+		_ => Err(format!("expected a weight from 0.0 to 1.0, got '{}'", text)),
+	}
+}
+
+/// Parse a JPEG quality from 1 to 100
+pub fn parse_jpeg_quality(text: &str) -> Result<u8, String> {
+	match text.trim().parse::<u8>() {
+		Ok(quality) if (1..=100).contains(&quality) => Ok(quality),
+		_ => Err(format!("expected a quality from 1 to 100, got '{}'", text)),
+	}
+}
+
+/// Write matches in a machine-readable format
+fn write_results(
+	out: &mut impl Write,
+	matches: &[Match],
+	query: &str,
+	format: OutputFormat,
+) -> Result<()> {
+	match format {
+		OutputFormat::Json => {
+			let export_data = SearchExport {
+				query: query.to_string(),
+				results: matches.to_vec(),
+			};
+			serde_json::to_writer_pretty(&mut *out, &export_data)?;
+			writeln!(out)?;
+		}
+		OutputFormat::Jsonl => {
+			for m in matches {
+				serde_json::to_writer(&mut *out, m)?;
+				writeln!(out)?;
+			}
+		}
+		OutputFormat::Csv => {
+			writeln!(out, "rank,path,score,timestamp,hash")?;
+			for (i, m) in matches.iter().enumerate() {
+				writeln!(
+					out,
+					"{},{},{:.6},{},{}",
+					i + 1,
+					csv_field(&m.path),
+					m.score,
+					m.timestamp.map(|t| format!("{:.3}", t)).unwrap_or_default(),
+					m.hash.as_deref().unwrap_or_default()
+				)?;
+			}
+		}
+		OutputFormat::Paths => {
+			for m in matches {
+				writeln!(out, "{}", m.path)?;
+			}
+		}
+	}
+	Ok(())
+}
+
+fn is_broken_pipe(error: &anyhow::Error) -> bool {
+	error
+		.downcast_ref::<std::io::Error>()
+		.is_some_and(|e| e.kind() == std::io::ErrorKind::BrokenPipe)
+}
+
+/// Quote a CSV field when it contains separators, quotes or line breaks
+fn csv_field(value: &str) -> String {
+	if value.contains([',', '"', '\n', '\r']) {
+		format!("\"{}\"", value.replace('"', "\"\""))
+	} else {
+		value.to_string()
+	}
+}
+
+/// Score all indexed media in `request.dirs` against the request's query.
+///
+/// Returns matches sorted by descending score and truncated to `request.options.limit`.
+pub fn execute(models: &mut Models, request: &SearchRequest) -> Result<Vec<Match>> {
+	let engine = open_engine(request, None)?;
+	query(models, &engine, request)
+}
+
+/// Load or stream the sidecars a request searches.
+///
+/// Loaded engines are taken from `cache` when given, so a long-running
+/// process only reads the sidecars again after they change.
+pub fn open_engine(
+	request: &SearchRequest,
+	cache: Option<&EngineCache>,
+) -> Result<Arc<SearchEngine>> {
+	let options = &request.options;
+	// These need every sidecar in memory
+	let can_stream = !options.ann && options.diverse.is_none() && options.hybrid.is_none();
+	if request.streaming && !can_stream {
+		anyhow::bail!("--streaming can't be combined with --ann, --diverse or --hybrid");
+	}
+
+	// The sidecar directories are walked once; the cache check, the streaming
+	// decision and a cold load all work from this listing
+	let found = scan_roots(&request.dirs, request.recursive);
+	let count: usize = found.iter().map(Vec::len).sum();
+	let streaming = can_stream && (request.streaming || count > STREAMING_THRESHOLD);
+
+	let (dirs, recursive, strict) = (&request.dirs, request.recursive, request.strict);
+	match cache {
+		Some(cache) => cache.get(dirs, recursive, strict, streaming, found),
+		None => SearchEngine::open(dirs, recursive, strict, streaming, found).map(Arc::new),
+	}
+}
+
+/// Sidecar files under each root, see [`storage::scan`]
+fn scan_roots(dirs: &[PathBuf], recursive: bool) -> Vec<Vec<(PathBuf, PathBuf)>> {
+	dirs.iter()
+		.map(|dir| storage::scan(dir, recursive))
+		.collect()
+}
+
+/// Encode the request's query and score it against `engine`
+pub fn query(
+	models: &mut Models,
+	engine: &SearchEngine,
+	request: &SearchRequest,
+) -> Result<Vec<Match>> {
+	let options = &request.options;
+	let negative = request.negative.as_deref();
+	models.set_long_text_mode(request.long_text);
+
+	let texts: Vec<&str> = request.queries.iter().map(String::as_str).collect();
+	let image = match (&request.image_data, request.image.as_deref()) {
+		(Some(data), _) => Some(QueryImage::from_data(data)?),
+		(None, Some(path)) => Some(QueryImage::open(path)?),
+		(None, None) => None,
+	};
+	let image = match (image, request.crop) {
+		(Some(image), Some(region)) => Some(image.cropped(region)?),
+		(image, _) => image,
+	};
+
+	match (texts.is_empty(), image) {
+		(false, None) => engine.query_text(models, &texts, negative, options),
+		(true, Some(image)) => engine.query_image(models, &image, negative, options),
+		(false, Some(image)) => {
+			let (weight, combine) = (request.weight, request.combine);
+			engine.query_combined(models, &texts, &image, weight, combine, negative, options)
+		}
+		(true, None) => Err(anyhow!("Must provide either query text or --image")),
+	}
+}
+
+/// Loaded and streamed search engines kept between requests by `scout serve`.
+///
+/// An engine is reused while the sidecars under its roots keep the same
+/// count and newest modification time, the check that also decides whether
+/// a consolidated index is fresh. The least recently used engine is dropped
+/// once [`DAEMON_ENGINE_CACHE_SIZE`] are kept.
+#[derive(Default)]
+pub struct EngineCache {
+	engines: Mutex<Vec<CachedEngine>>,
+}
+
+struct CachedEngine {
+	dirs: Vec<PathBuf>,
+	recursive: bool,
+	strict: bool,
+	streaming: bool,
+	stamps: Vec<SidecarStamp>,
+	engine: Arc<SearchEngine>,
+}
+
+/// Sidecar count and newest sidecar change under one root
+type SidecarStamp = (usize, Option<SystemTime>);
+
+fn sidecar_stamp(sidecars: &[(PathBuf, PathBuf)]) -> SidecarStamp {
+	let newest = sidecars
+		.iter()
+		.filter_map(|(path, _)| std::fs::metadata(path).and_then(|m| m.modified()).ok())
+		.max();
+	(sidecars.len(), newest)
+}
+
+impl EngineCache {
+	/// The engine for `dirs`, opening it again if any of their sidecars changed.
+	///
+	/// `found` holds the sidecar files under each of `dirs`, see
+	/// [`SearchEngine::stream`] for `streaming`. Loading happens without
+	/// holding the cache lock, so a slow load doesn't block requests for
+	/// other roots.
+	pub fn get(
+		&self,
+		dirs: &[PathBuf],
+		recursive: bool,
+		strict: bool,
+		streaming: bool,
+		found: Vec<Vec<(PathBuf, PathBuf)>>,
+	) -> Result<Arc<SearchEngine>> {
+		let stamps: Vec<SidecarStamp> = found.iter().map(|files| sidecar_stamp(files)).collect();
+		let matches = |cached: &CachedEngine| {
+			cached.dirs == dirs
+				&& cached.recursive == recursive
+				&& cached.strict == strict
+				&& cached.streaming == streaming
+		};
+
+		{
+			let mut engines = self.engines.lock().unwrap();
+			if let Some(pos) = engines.iter().position(matches) {
+				let cached = engines.remove(pos);
+				if cached.stamps == stamps {
+					ui::debug("Reusing loaded embeddings");
+					let engine = Arc::clone(&cached.engine);
+					engines.push(cached);
+					return Ok(engine);
+				}
+				ui::debug("Sidecars changed, reloading embeddings");
+			}
+		}
+
+		let engine = Arc::new(SearchEngine::open(
+			dirs, recursive, strict, streaming, found,
+		)?);
+
+		let mut engines = self.engines.lock().unwrap();
+		engines.retain(|cached| !matches(cached));
+		if engines.len() >= DAEMON_ENGINE_CACHE_SIZE {
+			engines.remove(0);
+		}
+		engines.push(CachedEngine {
+			dirs: dirs.to_vec(),
+			recursive,
+			strict,
+			streaming,
+			stamps,
+			engine: Arc::clone(&engine),
+		});
+		Ok(engine)
+	}
+}
+
+/// One searched root and its loaded sidecars
+struct LoadedRoot {
+	dir: PathBuf,
+	/// Shown next to matches when several roots are searched
+	tag: Option<String>,
+	sidecars: Vec<(PathBuf, storage::Sidecar)>,
+}
+
+/// One searched root whose sidecars are read while scoring
+struct StreamedRoot {
+	tag: Option<String>,
+	/// Sidecar file and the directory of its media file
+	files: Vec<(PathBuf, PathBuf)>,
+}
+
+/// Indexed media loaded once and scored against any number of queries.
+///
+/// Media paths are resolved per root, so equal hashes in different roots
+/// never overwrite each other.
+pub struct SearchEngine {
+	roots: Vec<LoadedRoot>,
+	/// Set instead of `roots` by [`SearchEngine::stream`]
+	streamed: Vec<StreamedRoot>,
+	dimension: Option<usize>,
+	/// Fail on sidecars from different models; checked while streaming
+	strict: bool,
+}
+
+impl SearchEngine {
+	/// Load sidecars from every root, preferring a fresh consolidated index.
+	///
+	/// Sidecars from different models are reported, or rejected if `strict`.
+	pub fn load(dirs: &[PathBuf], recursive: bool, strict: bool) -> Result<Self> {
+		Self::load_found(dirs, recursive, &scan_roots(dirs, recursive), strict)
+	}
+
+	/// Stream or load `dirs` from the sidecar files already `found` under each
+	fn open(
+		dirs: &[PathBuf],
+		recursive: bool,
+		strict: bool,
+		streaming: bool,
+		found: Vec<Vec<(PathBuf, PathBuf)>>,
+	) -> Result<Self> {
+		if streaming {
+			let streamed = Self::stream_found(dirs, found, strict);
+			ui::info(&format!("Streaming {} sidecars", streamed.len()));
+			return Ok(streamed);
+		}
+		Self::load_found(dirs, recursive, &found, strict)
+	}
+
+	/// [`SearchEngine::load`] without walking the sidecar directories again
+	fn load_found(
+		dirs: &[PathBuf],
+		recursive: bool,
+		found: &[Vec<(PathBuf, PathBuf)>],
+		strict: bool,
+	) -> Result<Self> {
+		let multi_root = dirs.len() > 1;
+		let mut roots = Vec::with_capacity(dirs.len());
+		let mut dimension: Option<(usize, &Path)> = None;
+
+		for (dir, files) in dirs.iter().zip(found) {
+			ui::info(&format!(
+				"Loading embeddings from {}",
+				ui::path_link(dir, 40)
+			));
+			let (sidecars, _) = match storage::IndexFile::load_fresh_for(dir, recursive, files) {
+				Some(index) => {
+					ui::debug(&format!(
+						"Using consolidated index ({} entries)",
+						index.len()
+					));
+					index.into_sidecars(dir)
+				}
+				None => storage::load_found_sidecars(dir, recursive, files),
+			};
+
+			if sidecars.is_empty() {
+				if multi_root {
+					ui::warn(&format!("No indexed media in {}", dir.display()));
+				}
+				continue;
+			}
+
+			ui::success(&format!("Loaded {} embeddings", sidecars.len()));
+
+			if let Some(dim) = storage::check_dimensions(&sidecars)? {
+				match dimension {
+					Some((expected, first)) if expected != dim => anyhow::bail!(
+						"{} holds {}D embeddings but {} holds {}D. Rescan with a single model",
+						first.display(),
+						expected,
+						dir.display(),
+						dim
+					),
+					Some(_) => {}
+					None => dimension = Some((dim, dir)),
+				}
+			}
+
+			roots.push(LoadedRoot {
+				dir: dir.clone(),
+				tag: multi_root.then(|| dir.to_string_lossy().to_string()),
+				sidecars,
+			});
+		}
+
+		storage::check_models(
+			roots
+				.iter()
+				.flat_map(|root| root.sidecars.iter().map(|(_, sidecar)| sidecar)),
+			strict,
+		)?;
+
+		Ok(Self {
+			roots,
+			streamed: Vec::new(),
+			dimension: dimension.map(|(dim, _)| dim),
+			strict,
+		})
+	}
+
+	/// Find the sidecars of every root without loading them.
+	///
+	/// Each query then reads them one at a time and keeps only the best
+	/// `limit` matches, so memory stays flat however large the library is.
+	/// Media paths are resolved only for matches good enough to be kept, so
+	/// missing files never take a slot, instead of building a hash cache of
+	/// the whole library.
+	/// Every query rereads the sidecars, so prefer [`SearchEngine::load`]
+	/// when running many queries. `--ann` and `--diverse` need it too.
+	pub fn stream(dirs: &[PathBuf], recursive: bool, strict: bool) -> Self {
+		Self::stream_found(dirs, scan_roots(dirs, recursive), strict)
+	}
+
+	/// [`SearchEngine::stream`] for the sidecar files already `found` under each root
+	fn stream_found(dirs: &[PathBuf], found: Vec<Vec<(PathBuf, PathBuf)>>, strict: bool) -> Self {
+		let multi_root = dirs.len() > 1;
+		let streamed = dirs
+			.iter()
+			.zip(found)
+			.map(|(dir, files)| StreamedRoot {
+				tag: multi_root.then(|| dir.to_string_lossy().to_string()),
+				files,
+			})
+			.collect();
+
+		Self {
+			roots: Vec::new(),
+			streamed,
+			dimension: None,
+			strict,
+		}
+	}
+
+	/// Number of indexed files across all roots
+	pub fn len(&self) -> usize {
+		self.roots.iter().map(|r| r.sidecars.len()).sum::<usize>()
+			+ self.streamed.iter().map(|r| r.files.len()).sum::<usize>()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// Search with a text query, averaging several phrasings if given
+	pub fn query_text(
+		&self,
+		models: &mut Models,
+		texts: &[&str],
+		negative: Option<&str>,
+		options: &SearchOptions,
+	) -> Result<Vec<Match>> {
+		ui::info(&format!("Searching for: {}", quote_queries(texts)));
+		let query = models.encode_text_mean(texts)?;
+		let negative = encode_negative(models, negative)?;
+		self.query_embedding(&query, negative.as_ref(), options)
+	}
+
+	/// Search by reference image, leaving the image itself out unless
+	/// `options.include_ref` is set
+	pub fn query_image(
+		&self,
+		models: &mut Models,
+		image: &QueryImage,
+		negative: Option<&str>,
+		options: &SearchOptions,
+	) -> Result<Vec<Match>> {
+		ui::info(&format!("Searching by image: {}", image.label));
+		let query = models.encode_image(&image.image)?;
+		let negative = encode_negative(models, negative)?;
+		let histogram = options
+			.prefilter
+			.map(|_| crate::processing::image::color_histogram(&image.image));
+		self.query_reference(
+			&query,
+			None,
+			negative.as_ref(),
+			image.path.as_deref(),
+			histogram.as_deref(),
+			options,
+		)
+	}
+
+	/// Search with a text query combined with a reference image.
+	///
+	/// `weight` is the share of the text (0.0-1.0). With
+	/// [`CombineMode::Blend`] the two embeddings are blended into one query;
+	/// the other modes score each candidate against both and combine the
+	/// scores. The blend still picks `--ann` candidates and drives `--diverse`.
+	#[allow(clippy::too_many_arguments)]
+	pub fn query_combined(
+		&self,
+		models: &mut Models,
+		texts: &[&str],
+		image: &QueryImage,
+		weight: f32,
+		combine: CombineMode,
+		negative: Option<&str>,
+		options: &SearchOptions,
+	) -> Result<Vec<Match>> {
+		let filename = image
+			.path
+			.as_deref()
+			.and_then(Path::file_name)
+			.and_then(|n| n.to_str())
+			.unwrap_or(&image.label);
+		ui::info(&format!(
+			"Combined search: {} + {} (weight: {:.2}, {:?})",
+			quote_queries(texts),
+			filename,
+			weight,
+			combine
+		));
+		let text_emb = models.encode_text_mean(texts)?;
+		let img_emb = models.encode_image(&image.image)?;
+		let query = Embedding::blend(&text_emb, &img_emb, weight);
+		let split = (combine != CombineMode::Blend).then_some(SplitQuery {
+			text: text_emb,
+			image: img_emb,
+			mode: combine,
+			weight,
+		});
+		let negative = encode_negative(models, negative)?;
+		// The text part may ask for different colors, so no color prefilter
+		self.query_reference(
+			&query,
+			split.as_ref(),
+			negative.as_ref(),
+			image.path.as_deref(),
+			None,
+			options,
+		)
+	}
+
+	/// Score every indexed file against a precomputed query embedding.
+	///
+	/// Returns matches sorted by descending score and truncated to `options.limit`.
+	pub fn query_embedding(
+		&self,
+		query: &Embedding,
+		negative: Option<&Embedding>,
+		options: &SearchOptions,
+	) -> Result<Vec<Match>> {
+		self.score_candidates(query, None, negative, None, options)
+	}
+
+	/// [`SearchEngine::query_embedding`], skipping images whose color
+	/// histogram is too far from `histogram` when `options.prefilter` is set.
+	/// Videos and sidecars without a histogram are always scored.
+	///
+	/// With `split`, candidates are scored against its text and image instead
+	/// of `query`, which still picks ANN candidates and drives `--diverse`.
+	fn score_candidates(
+		&self,
+		query: &Embedding,
+		split: Option<&SplitQuery>,
+		negative: Option<&Embedding>,
+		histogram: Option<&[f32]>,
+		options: &SearchOptions,
+	) -> Result<Vec<Match>> {
+		if self.is_empty() {
+			ui::warn("No indexed images found. Run 'scout scan' first.");
+			return Ok(Vec::new());
+		}
+
+		if let Some(dim) = self.dimension {
+			if dim != query.dimension() {
+				anyhow::bail!(
+					"Index holds {}D embeddings but the current model produces {}D. \
+					 Rescan with the same model or point --model-dir at the one used for scanning",
+					dim,
+					query.dimension()
+				);
+			}
+		}
+
+		let score = |emb: &Embedding| {
+			let mut score = match split {
+				Some(split) => split.score(emb),
+				None => query.similarity(emb),
+			};
+			if let Some(neg_emb) = negative {
+				score -= neg_emb.similarity(emb) * NEGATIVE_WEIGHT;
+			}
+			score
+		};
+
+		// --diverse re-ranks a wider pool of the best matches, bounded since
+		// re-ranking compares every pick against the whole pool
+		let pool = match options.diverse {
+			Some(_) => options
+				.limit
+				.saturating_mul(MMR_POOL_FACTOR)
+				.min(MAX_MMR_POOL),
+			None => options.limit,
+		};
+
+		let keywords = options.hybrid.as_ref().map(|hybrid| {
+			let paths = self.roots.iter().flat_map(|root| &root.sidecars);
+			KeywordScorer::new(hybrid, paths.map(|(path, _)| path.as_path()))
+		});
+
+		let prefilter = options.prefilter.zip(histogram);
+		if let Some((max_distance, _)) = prefilter {
+			ui::debug(&format!(
+				"Color prefilter: skipping images beyond distance {:.2}",
+				max_distance
+			));
+		}
+
+		// --score auto needs every score, which streaming doesn't keep as matches
+		let (mut matches, all_scores) = match self.streamed.is_empty() {
+			true => (Vec::new(), None),
+			false => self.score_streamed(query, &score, prefilter, options)?,
+		};
+
+		for root in &self.roots {
+			// Narrow down to approximate neighbors, then score those exactly
+			let keep = options
+				.ann
+				.then(|| ann_candidates(&root.dir, &root.sidecars, query, pool));
+
+			let root_matches: Vec<Match> = root
+				.sidecars
+				.par_iter()
+				.filter(|(_, sidecar)| match &keep {
+					Some(keep) => keep.contains(sidecar.hash()),
+					None => true,
+				})
+				.flat_map_iter(|(media_path, sidecar)| {
+					let path = media_path.to_string_lossy().to_string();
+					let keywords = keywords.as_ref();
+					score_sidecar(
+						sidecar,
+						score,
+						prefilter,
+						keywords,
+						options,
+						path,
+						root.tag.as_ref(),
+					)
+				})
+				.collect();
+			matches.extend(root_matches);
+		}
+
+		matches.sort_by(|a, b| {
+			b.score
+				.partial_cmp(&a.score)
+				.unwrap_or(std::cmp::Ordering::Equal)
+		});
+
+		if options.auto_score {
+			let scores: Vec<f32> =
+				all_scores.unwrap_or_else(|| matches.iter().map(|m| m.score).collect());
+			let threshold = auto_threshold(&scores);
+			ui::debug(&format!("Auto score threshold: {:.3}", threshold));
+			matches.retain(|m| m.score >= threshold);
+		}
+
+		if options.dedup {
+			matches = self.dedup(matches, options.limit.max(pool));
+		}
+
+		if let Some(lambda) = options.diverse {
+			// Matches beyond the pool keep their order behind the re-ranked ones
+			let rest = matches.split_off(pool.min(matches.len()));
+			matches = self.diversify(query, matches, lambda, options.limit);
+			matches.extend(rest);
+		}
+		matches.truncate(options.limit);
+
+		Ok(matches)
+	}
+
+	/// Read and score the streamed sidecars one at a time, keeping the best
+	/// `options.limit` matches. Also returns every score for `--score auto`.
+	fn score_streamed(
+		&self,
+		query: &Embedding,
+		score: &(impl Fn(&Embedding) -> f32 + Sync),
+		prefilter: Option<(f32, &[f32])>,
+		options: &SearchOptions,
+	) -> Result<(Vec<Match>, Option<Vec<f32>>)> {
+		let files: Vec<(&StreamedRoot, &(PathBuf, PathBuf))> = self
+			.streamed
+			.iter()
+			.flat_map(|root| root.files.iter().map(move |file| (root, file)))
+			.collect();
+
+		let resolver = MediaResolver::default();
+		let streamed = files
+			.into_par_iter()
+			.fold(
+				|| Streamed::new(options.limit),
+				|mut streamed, (root, (sidecar_path, media_dir))| {
+					let sidecar = match storage::load(sidecar_path) {
+						Ok(sidecar) => sidecar,
+						Err(e) => {
+							ui::debug(&format!(
+								"Unreadable sidecar {}: {:#}",
+								sidecar_path.display(),
+								e
+							));
+							streamed.unreadable += 1;
+							return streamed;
+						}
+					};
+					if sidecar.dimension() > 0 && sidecar.dimension() != query.dimension() {
+						streamed.mismatched.get_or_insert(sidecar.dimension());
+						return streamed;
+					}
+					if let Some(fingerprint) = sidecar.fingerprint() {
+						match streamed.models.get_mut(fingerprint) {
+							Some(count) => *count += 1,
+							None => {
+								streamed.models.insert(fingerprint.to_string(), 1);
+							}
+						}
+					}
+
+					let tag = root.tag.as_ref();
+					let mut matches = score_sidecar(
+						&sidecar,
+						score,
+						prefilter,
+						None,
+						options,
+						String::new(),
+						tag,
+					);
+					if options.auto_score {
+						streamed.scores.extend(matches.iter().map(|m| m.score));
+					}
+					matches.retain(|m| streamed.top.admits(m.score));
+					if matches.is_empty() {
+						return streamed;
+					}
+
+					// Sidecars whose media file is gone drop out before taking a slot
+					let Some(media_path) = resolver.resolve(&sidecar, media_dir) else {
+						return streamed;
+					};
+					let path = media_path.to_string_lossy().to_string();
+					for mut m in matches {
+						m.path.clone_from(&path);
+						streamed.top.push(m);
+					}
+					streamed
+				},
+			)
+			.reduce(|| Streamed::new(options.limit), Streamed::merge);
+
+		if let Some(dim) = streamed.mismatched {
+			anyhow::bail!(
+				"Index holds {}D embeddings but the current model produces {}D. \
+				 Rescan with the same model or point --model-dir at the one used for scanning",
+				dim,
+				query.dimension()
+			);
+		}
+		storage::check_model_counts(streamed.models, self.strict)?;
+		if streamed.unreadable > 0 {
+			ui::warn(&format!(
+				"Skipped {} unreadable sidecars. Run 'scout clean --repair' to remove them",
+				streamed.unreadable
+			));
+		}
+
+		let matches = streamed.top.into_sorted();
+		Ok((matches, options.auto_score.then_some(streamed.scores)))
+	}
+
+	/// Re-rank `matches` with [`mmr_rerank`], using the best-matching frame
+	/// of each video as its embedding
+	fn diversify(
+		&self,
+		query: &Embedding,
+		matches: Vec<Match>,
+		lambda: f32,
+		limit: usize,
+	) -> Vec<Match> {
+		let sidecars = self.sidecars_by_hash();
+		let embeddings: Vec<Embedding> = matches
+			.iter()
+			.map(|m| match_embedding(&sidecars, m))
+			.collect();
+
+		let order = mmr_rerank(query, &embeddings, lambda, limit);
+		let mut matches: Vec<Option<Match>> = matches.into_iter().map(Some).collect();
+		order
+			.into_iter()
+			.filter_map(|i| matches[i].take())
+			.collect()
+	}
+
+	/// Drop matches that repeat a better one: the same file reached through a
+	/// symlink or hardlink, or a near-identical embedding such as a photo that
+	/// is also a video frame. Separate moments of one video are kept.
+	/// Expects `matches` sorted best first and stops once `keep` are left.
+	///
+	/// Near-identical embeddings score almost the same, so they end up next
+	/// to each other: each match is only compared with the last
+	/// [`DEDUP_WINDOW`] kept ones, which keeps `--limit all` linear. Streamed
+	/// sidecars aren't kept in memory, so their matches are only compared by
+	/// path and content hash.
+	fn dedup(&self, matches: Vec<Match>, keep: usize) -> Vec<Match> {
+		let sidecars = self.sidecars_by_hash();
+		let mut kept: Vec<(Match, PathBuf, Embedding)> = Vec::new();
+		// Paths and content hashes kept so far, with the matched frame
+		let mut seen_paths: HashSet<(PathBuf, Option<u64>)> = HashSet::new();
+		let mut seen_hashes: HashSet<(String, Option<u64>)> = HashSet::new();
+		let mut dropped = 0;
+
+		for m in matches {
+			if kept.len() == keep {
+				break;
+			}
+			let canonical = absolute(Path::new(&m.path));
+			let moment = m.timestamp.map(f64::to_bits);
+			let embedding = match_embedding(&sidecars, &m);
+
+			let same_file = seen_paths.contains(&(canonical.clone(), moment))
+				|| m.hash
+					.as_ref()
+					.is_some_and(|hash| seen_hashes.contains(&(hash.clone(), moment)));
+			let window = &kept[kept.len().saturating_sub(DEDUP_WINDOW)..];
+			let duplicate = same_file
+				|| window.iter().any(|(_, other_path, other_embedding)| {
+					// Other moments of the same video are never duplicates
+					*other_path != canonical
+						&& embedding
+							.try_similarity(other_embedding)
+							.is_ok_and(|sim| sim > DEDUP_SIMILARITY)
+				});
+
+			if duplicate {
+				dropped += 1;
+				continue;
+			}
+			seen_paths.insert((canonical.clone(), moment));
+			if let Some(hash) = &m.hash {
+				seen_hashes.insert((hash.clone(), moment));
+			}
+			kept.push((m, canonical, embedding));
+		}
+
+		if dropped > 0 {
+			ui::debug(&format!("Dropped {} duplicate results", dropped));
+		}
+		kept.into_iter().map(|(m, _, _)| m).collect()
+	}
+
+	/// Embedding `m` was scored with: the image's, or the matched video
+	/// frame's. `None` for streamed sidecars, which aren't kept in memory
+	pub fn embedding_of(&self, m: &Match) -> Option<Embedding> {
+		let hash = m.hash.as_deref()?;
+		let (_, sidecar) = self
+			.roots
+			.iter()
+			.flat_map(|root| &root.sidecars)
+			.find(|(_, sidecar)| sidecar.hash() == hash)?;
+		Some(scored_embedding(sidecar, m.timestamp))
+	}
+
+	/// In-memory sidecars by content hash
+	fn sidecars_by_hash(&self) -> HashMap<&str, &storage::Sidecar> {
+		self.roots
+			.iter()
+			.flat_map(|root| &root.sidecars)
+			.map(|(_, sidecar)| (sidecar.hash(), sidecar))
+			.collect()
+	}
+
+	/// Query with an embedding derived from `reference`, dropping the reference
+	/// file from the results unless `options.include_ref` is set (piped and
+	/// downloaded images have no file to drop)
+	fn query_reference(
+		&self,
+		query: &Embedding,
+		split: Option<&SplitQuery>,
+		negative: Option<&Embedding>,
+		reference: Option<&Path>,
+		histogram: Option<&[f32]>,
+		options: &SearchOptions,
+	) -> Result<Vec<Match>> {
+		let canonical_ref = match reference.map(Path::canonicalize) {
+			Some(Ok(path)) if !options.include_ref => path,
+			_ => return self.score_candidates(query, split, negative, histogram, options),
+		};
+
+		// Fetch one extra result in case the reference itself is among them
+		let widened = SearchOptions {
+			limit: options.limit.saturating_add(1),
+			..options.clone()
+		};
+		let mut matches = self.score_candidates(query, split, negative, histogram, &widened)?;
+		matches.retain(|m| {
+			Path::new(&m.path)
+				.canonicalize()
+				.map_or(true, |p| p != canonical_ref)
+		});
+		matches.truncate(options.limit);
+		Ok(matches)
+	}
+}
+
+/// Embedding behind a match, see [`scored_embedding`]. Empty when the
+/// sidecar isn't in `sidecars`
+fn match_embedding(sidecars: &HashMap<&str, &storage::Sidecar>, m: &Match) -> Embedding {
+	match m.hash.as_deref().and_then(|hash| sidecars.get(hash)) {
+		Some(sidecar) => scored_embedding(sidecar, m.timestamp),
+		None => Embedding::raw(Vec::new()),
+	}
+}
+
+/// The image's embedding, or the video frame at `timestamp` (the first
+/// frame if there is none)
+fn scored_embedding(sidecar: &storage::Sidecar, timestamp: Option<f64>) -> Embedding {
+	match sidecar {
+		storage::Sidecar::Image(img) => img.embedding(),
+		storage::Sidecar::Video(vid) => {
+			let frames = vid.frames();
+			let best = frames
+				.iter()
+				.position(|(ts, _)| Some(*ts) == timestamp)
+				.unwrap_or(0);
+			frames
+				.into_iter()
+				.nth(best)
+				.map_or_else(|| Embedding::raw(Vec::new()), |(_, e)| e)
+		}
+	}
+}
+
+/// Matches for one sidecar: none if it's filtered out or below the minimum
+/// score, several for a video with `options.frames_per_video`
+fn score_sidecar(
+	sidecar: &storage::Sidecar,
+	score: impl Fn(&Embedding) -> f32,
+	prefilter: Option<(f32, &[f32])>,
+	keywords: Option<&KeywordScorer>,
+	options: &SearchOptions,
+	path: String,
+	root: Option<&String>,
+) -> Vec<Match> {
+	let taken = match sidecar {
+		storage::Sidecar::Image(img) => img.info().and_then(storage::MediaInfo::taken),
+		storage::Sidecar::Video(_) => None,
+	};
+	if !crate::core::media::taken_within(taken, options.after_date, options.before_date) {
+		return Vec::new();
+	}
+
+	if let (Some((max_distance, reference)), storage::Sidecar::Image(img)) = (prefilter, sidecar) {
+		let far = img.histogram().is_some_and(|candidate| {
+			crate::processing::image::histogram_distance(reference, candidate) > max_distance
+		});
+		if far {
+			return Vec::new();
+		}
+	}
+
+	let hits: Vec<(f32, Option<f64>)> = match sidecar {
+		storage::Sidecar::Image(img) => vec![(score(&img.embedding()), None)],
+		storage::Sidecar::Video(_) if options.exclude_videos => return Vec::new(),
+		storage::Sidecar::Video(vid) => match options.frames_per_video {
+			Some(max) => {
+				let smooth = options.temporal_smooth.unwrap_or(1);
+				let frames = vid.frame_scores(&score, options.after, options.before, smooth);
+				top_moments(frames, max, options.frame_gap)
+					.into_iter()
+					.map(|(timestamp, score)| (score, Some(timestamp)))
+					.collect()
+			}
+			None => {
+				let ranged = options.after.is_some() || options.before.is_some();
+				let (score, timestamp) = match vid.aggregate_between(
+					score,
+					options.video_agg,
+					options.video_topk,
+					options.after,
+					options.before,
+					options.temporal_smooth.unwrap_or(1),
+				) {
+					Some(result) => result,
+					// Nothing in the requested time range
+					None if ranged => return Vec::new(),
+					None => (0.0, 0.0),
+				};
+				vec![(score, Some(timestamp))]
+			}
+		},
+	};
+
+	// Hybrid scores are already on a 0.0-1.0 scale
+	let hits = match keywords {
+		Some(keywords) => {
+			let keyword = keywords.keyword_score(Path::new(&path));
+			hits.into_iter()
+				.map(|(score, timestamp)| (keywords.combine(score, keyword), timestamp))
+				.collect()
+		}
+		None => hits,
+	};
+
+	let passes = |score: f32| {
+		let threshold_score = match options.calibration {
+			Some(temperature) if keywords.is_none() => {
+				Embedding::calibrate_score(score, temperature)
+			}
+			_ => score,
+		};
+		options.auto_score || threshold_score >= options.min_score
+	};
+
+	hits.into_iter()
+		.filter(|(score, _)| passes(*score))
+		.map(|(score, timestamp)| Match {
+			path: path.clone(),
+			score,
+			timestamp,
+			hash: Some(sidecar.hash().to_string()),
+			root: root.cloned(),
+			info: match sidecar {
+				storage::Sidecar::Image(img) => img.info().copied(),
+				storage::Sidecar::Video(_) => None,
+			},
+			thumbnail: None,
+		})
+		.collect()
+}
+
+/// Temporal non-max suppression over `(timestamp, score)` frames sorted best
+/// first: keep a frame only if no better kept frame is within `gap` seconds,
+/// up to `max` frames.
+fn top_moments(frames: Vec<(f64, f32)>, max: usize, gap: f64) -> Vec<(f64, f32)> {
+	let mut kept: Vec<(f64, f32)> = Vec::with_capacity(max);
+	for (timestamp, score) in frames {
+		if kept.len() >= max {
+			break;
+		}
+		if kept.iter().all(|(t, _)| (t - timestamp).abs() > gap) {
+			kept.push((timestamp, score));
+		}
+	}
+	kept
+}
+
+/// What one thread gathered while streaming sidecars
+struct Streamed {
+	top: TopMatches,
+	scores: Vec<f32>,
+	models: HashMap<String, usize>,
+	/// Any embedding size that differs from the query's
+	mismatched: Option<usize>,
+	unreadable: usize,
+}
+
+impl Streamed {
+	fn new(limit: usize) -> Self {
+		Self {
+			top: TopMatches::new(limit),
+			scores: Vec::new(),
+			models: HashMap::new(),
+			mismatched: None,
+			unreadable: 0,
+		}
+	}
+
+	fn merge(mut self, other: Self) -> Self {
+		for Reverse(Ranked(m)) in other.top.heap {
+			self.top.push(m);
+		}
+		self.scores.extend(other.scores);
+		for (fingerprint, count) in other.models {
+			*self.models.entry(fingerprint).or_default() += count;
+		}
+		self.mismatched = self.mismatched.or(other.mismatched);
+		self.unreadable += other.unreadable;
+		self
+	}
+}
+
+/// Bounded min-heap holding the `limit` best matches seen so far
+struct TopMatches {
+	limit: usize,
+	heap: BinaryHeap<Reverse<Ranked>>,
+}
+
+struct Ranked(Match);
+
+impl PartialEq for Ranked {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == std::cmp::Ordering::Equal
+	}
+}
+
+impl Eq for Ranked {}
+
+impl PartialOrd for Ranked {
+	fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Ranked {
+	fn cmp(&self, other: &Self) -> std::cmp::Ordering {
+		self.0.score.total_cmp(&other.0.score)
+	}
+}
+
+impl TopMatches {
+	fn new(limit: usize) -> Self {
+		Self {
+			limit,
+			// Unlimited searches grow the heap as matches come in
+			heap: match limit {
+				usize::MAX => BinaryHeap::new(),
+				_ => BinaryHeap::with_capacity(limit + 1),
+			},
+		}
+	}
+
+	/// Whether a match with this score would be kept
+	fn admits(&self, score: f32) -> bool {
+		self.heap.len() < self.limit
+			|| self
+				.heap
+				.peek()
+				.is_some_and(|worst| score > worst.0 .0.score)
+	}
+
+	fn push(&mut self, m: Match) {
+		if self.heap.len() < self.limit {
+			self.heap.push(Reverse(Ranked(m)));
+		} else if let Some(mut worst) = self.heap.peek_mut() {
+			if m.score > worst.0 .0.score {
+				*worst = Reverse(Ranked(m));
+			}
+		}
+	}
+
+	/// Matches by descending score
+	fn into_sorted(self) -> Vec<Match> {
+		self.heap
+			.into_sorted_vec()
+			.into_iter()
+			.map(|Reverse(Ranked(m))| m)
+			.collect()
+	}
+}
+
+/// Finds the media files of streamed sidecars.
+///
+/// The path recorded at scan time is tried first. Older sidecars without one
+/// fall back to hashing the files of their media directory, which happens
+/// once per directory however many of its sidecars match.
+#[derive(Default)]
+struct MediaResolver<'a> {
+	dirs: Mutex<HashMap<(&'a Path, HashMode), DirHashes>>,
+}
+
+/// Hash to media path for the files of one directory, filled on first use
+type DirHashes = Arc<OnceLock<HashMap<String, PathBuf>>>;
+
+impl<'a> MediaResolver<'a> {
+	/// The media file of `sidecar` in `media_dir`, or `None` if it is gone
+	fn resolve(&self, sidecar: &storage::Sidecar, media_dir: &'a Path) -> Option<PathBuf> {
+		let hash = sidecar.hash();
+		let mode = HashMode::of(hash);
+		let unchanged =
+			|path: &Path| FileHash::compute_with(path, mode).is_ok_and(|h| h.as_str() == hash);
+		if let Some(source) = sidecar.source().filter(|source| unchanged(source)) {
+			return Some(source.to_path_buf());
+		}
+
+		let listing = Arc::clone(
+			self.dirs
+				.lock()
+				.unwrap()
+				.entry((media_dir, mode))
+				.or_default(),
+		);
+		listing
+			.get_or_init(|| storage::index::build_hash_cache_with(media_dir, false, mode))
+			.get(hash)
+			.cloned()
+	}
+}
+
+fn quote_queries(texts: &[&str]) -> String {
+	texts
+		.iter()
+		.map(|t| format!("\"{}\"", t))
+		.collect::<Vec<_>>()
+		.join(" + ")
+}
+
+fn encode_negative(models: &mut Models, negative: Option<&str>) -> Result<Option<Embedding>> {
+	negative
+		.map(|neg| {
+			ui::debug(&format!("Negative prompt: \"{}\"", neg));
+			models.encode_text(neg)
+		})
+		.transpose()
+}
+
+/// Hashes of the files owning the query's approximate nearest neighbors.
+///
+/// The HNSW index is cached in the root's sidecar directory and rebuilt when
+/// the set of indexed files or the model that embedded them changes.
+fn ann_candidates(
+	dir: &Path,
+	sidecars: &[(PathBuf, storage::Sidecar)],
+	query: &Embedding,
+	limit: usize,
+) -> HashSet<String> {
+	// Key on the model fingerprint too, so a rescan with another model invalidates the cache
+	let keys: Vec<String> = sidecars
+		.iter()
+		.map(|(_, sidecar)| {
+			format!(
+				"{}:{}",
+				sidecar.hash(),
+				sidecar.fingerprint().unwrap_or_default()
+			)
+		})
+		.collect();
+
+	let content_hash = compute_content_hash(&keys);
+	let cache_dir = storage::sidecar_dir(dir);
+	let cache_path = cache_dir.join(ANN_FILE);
+
+	let cached = std::fs::read(&cache_path)
+		.ok()
+		.and_then(|bytes| rmp_serde::from_slice::<AnnCache>(&bytes).ok())
+		.filter(|cache| cache.content_hash == content_hash);
+
+	let cache = match cached {
+		Some(cache) => {
+			ui::debug(&format!(
+				"Using cached ANN index ({} vectors)",
+				cache.index.len()
+			));
+			cache
+		}
+		None => {
+			let mut owners = Vec::new();
+			let mut vectors = Vec::new();
+			for (_, sidecar) in sidecars {
+				match sidecar {
+					storage::Sidecar::Image(img) => vectors.push(img.embedding()),
+					storage::Sidecar::Video(vid) => {
+						vectors.extend(vid.frames().into_iter().map(|(_, emb)| emb))
+					}
+				}
+				owners.resize(vectors.len(), sidecar.hash().to_string());
+			}
+
+			ui::info(&format!("Building ANN index ({} vectors)", vectors.len()));
+			let cache = AnnCache {
+				content_hash,
+				owners,
+				index: AnnIndex::build(&vectors, AnnParams::default()),
+			};
+
+			let saved = rmp_serde::to_vec(&cache)
+				.map_err(anyhow::Error::from)
+				.and_then(|bytes| {
+					std::fs::create_dir_all(&cache_dir)?;
+					std::fs::write(&cache_path, bytes)?;
+					Ok(())
+				});
+			if let Err(e) = saved {
+				ui::debug(&format!("Failed to cache ANN index: {}", e));
+			}
+			cache
+		}
+	};
+
+	let keep: HashSet<String> = cache
+		.index
+		.search(
+			query,
+			limit.saturating_add(1).saturating_mul(ANN_OVERSAMPLE),
+		)
+		.into_iter()
+		.map(|(id, _)| cache.owners[id].clone())
+		.collect();
+
+	ui::debug(&format!("ANN narrowed search to {} files", keep.len()));
+	keep
+}
+
+/// Resolve a path against the current directory so it survives being sent to a daemon
+fn absolute(path: &Path) -> PathBuf {
+	path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Write a tiny PNG named `name` into `dir` and save its sidecar. The
+	/// pixels spell out the name, so every file hashes differently.
+	fn index_image(dir: &Path, name: &str, embedding: Vec<f32>) -> FileHash {
+		let path = dir.join(name);
+		let bytes = name.as_bytes();
+		image::RgbImage::from_fn(bytes.len() as u32, 1, |x, _| {
+			image::Rgb([bytes[x as usize], 0, 0])
+		})
+		.save(&path)
+		.unwrap();
+		let hash = FileHash::compute(&path).unwrap();
+		let sidecar = storage::ImageSidecar::new(hash.clone(), Embedding::new(embedding));
+		storage::save_image(&sidecar, dir, &hash).unwrap();
+		hash
+	}
+
+	fn options(limit: usize) -> SearchOptions {
+		SearchOptions {
+			limit,
+			min_score: 0.0,
+			auto_score: false,
+			include_ref: false,
+			exclude_videos: false,
+			ann: false,
+			video_agg: VideoAggregation::default(),
+			video_topk: DEFAULT_VIDEO_TOPK,
+			temporal_smooth: None,
+			after: None,
+			before: None,
+			calibration: None,
+			diverse: None,
+			prefilter: None,
+			frames_per_video: None,
+			frame_gap: DEFAULT_FRAME_GAP,
+			after_date: None,
+			before_date: None,
+			dedup: true,
+			hybrid: None,
+		}
+	}
+
+	#[test]
+	fn test_tiers_split_at_score_boundaries() {
+		let matches: Vec<Match> = [
+			("a", 0.2),
+			("b", SCORE_MED),
+			("c", 0.05),
+			("d", SCORE_HIGH),
+			("e", SCORE_MED - 0.001),
+			("f", 0.12),
+		]
+		.into_iter()
+		.map(|(path, score)| Match {
+			path: path.to_string(),
+			score,
+			timestamp: None,
+			hash: None,
+			root: None,
+			info: None,
+			thumbnail: None,
+		})
+		.collect();
+
+		let rows: Vec<(Tier, &str)> = tiered(&matches)
+			.into_iter()
+			.map(|(tier, m)| (tier, m.path.as_str()))
+			.collect();
+		assert_eq!(
+			rows,
+			[
+				(Tier::Strong, "a"),
+				(Tier::Strong, "d"),
+				(Tier::Possible, "b"),
+				(Tier::Possible, "f"),
+			]
+		);
+	}
+
+	#[test]
+	fn test_auto_threshold_cuts_at_largest_gap() {
+		let scores = [0.12, 0.31, 0.11, 0.29, 0.13, 0.30, 0.10];
+		assert_eq!(auto_threshold(&scores), 0.29);
+	}
+
+	#[test]
+	fn test_auto_threshold_falls_back_to_mean_plus_sigma() {
+		// Evenly spaced below the minimum gap: no natural cutoff
+		let scores: Vec<f32> = (0..20).map(|i| 0.3 - i as f32 * 0.01).collect();
+		let threshold = auto_threshold(&scores);
+		assert!(threshold > 0.2 && threshold < 0.3, "{}", threshold);
+
+		assert_eq!(auto_threshold(&[0.4]), 0.4);
+		assert_eq!(auto_threshold(&[]), 0.0);
+	}
+
+	#[test]
+	fn test_normalized_scores_keep_order_within_bounds() {
+		let scores = [0.21, 0.18, 0.18, 0.09, 0.05, 0.03];
+		let (floor, top) = (0.05, 0.21);
+		let shown: Vec<f32> = scores
+			.iter()
+			.map(|&score| normalized_score(score, floor, top) * 100.0)
+			.collect();
+
+		assert_eq!(shown[0], 100.0);
+		assert_eq!(shown[4], 0.0);
+		assert!(shown.iter().all(|p| (0.0..=100.0).contains(p)));
+		assert!(shown.windows(2).all(|pair| pair[0] >= pair[1]));
+		assert!(shown[1] > shown[3]);
+
+		// A lone result at the cutoff still reads as the best match
+		assert_eq!(normalized_score(0.05, 0.05, 0.05), 1.0);
+	}
+
+	#[test]
+	fn test_mmr_diversity_increases_as_lambda_decreases() {
+		let at = |angle: f32| Embedding::new(vec![angle.cos(), angle.sin()]);
+		let query = at(0.0);
+		// Three near-duplicates closest to the query, then two distinct shots
+		let candidates = vec![at(0.10), at(0.11), at(0.12), at(0.5), at(-0.6)];
+
+		let spread = |lambda: f32| {
+			let picked = mmr_rerank(&query, &candidates, lambda, 3);
+			let mut total = 0.0;
+			for (n, &i) in picked.iter().enumerate() {
+				for &j in &picked[n + 1..] {
+					total += 1.0 - candidates[i].similarity(&candidates[j]);
+				}
+			}
+			(picked, total)
+		};
+
+		let (relevant, relevant_spread) = spread(1.0);
+		let (balanced, balanced_spread) = spread(0.5);
+		let (diverse, diverse_spread) = spread(0.2);
+
+		assert_eq!(relevant, vec![0, 1, 2], "λ = 1 is the plain ranking");
+		assert_eq!(balanced[0], 0, "the best match always comes first");
+		assert_eq!(diverse[0], 0);
+		assert!(balanced_spread > relevant_spread);
+		assert!(diverse_spread >= balanced_spread);
+		assert!(diverse.contains(&3) && diverse.contains(&4));
+	}
+
+	#[test]
+	fn test_piped_image_errors_are_distinct() {
+		let empty = ImageData::checked("stdin", Vec::new()).unwrap_err();
+		assert!(empty.to_string().contains("No image data"));
+
+		let garbage = ImageData::checked("stdin", b"not an image".to_vec()).unwrap_err();
+		assert!(garbage
+			.to_string()
+			.contains("not a recognized image format"));
+
+		let mut png = Vec::new();
+		image::DynamicImage::new_rgb8(4, 4)
+			.write_to(&mut std::io::Cursor::new(&mut png), image::ImageFormat::Png)
+			.unwrap();
+		let data = ImageData::checked("stdin", png).unwrap();
+		let query = QueryImage::from_data(&data).unwrap();
+		assert_eq!(query.image.width(), 4);
+		assert!(query.path.is_none());
+	}
+
+	#[test]
+	fn test_score_threshold_parsing() {
+		assert_eq!("auto".parse(), Ok(ScoreThreshold::Auto));
+		assert_eq!("0.15".parse(), Ok(ScoreThreshold::Fixed(0.15)));
+		assert!("Auto".parse::<ScoreThreshold>().is_err());
+	}
+
+	#[test]
+	fn test_thumbnail_decodes_to_jpeg() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		let path = dir.join("wide.png");
+		image::RgbImage::from_fn(400, 200, |x, _| image::Rgb([(x % 256) as u8, 90, 40]))
+			.save(&path)
+			.unwrap();
+
+		let encoded = thumbnail_base64(&path, None, 128, 80).unwrap();
+
+		let bytes = base64::engine::general_purpose::STANDARD
+			.decode(encoded)
+			.unwrap();
+		assert_eq!(
+			image::guess_format(&bytes).unwrap(),
+			image::ImageFormat::Jpeg
+		);
+		let thumb = image::load_from_memory(&bytes).unwrap();
+		assert_eq!((thumb.width(), thumb.height()), (128, 64));
+
+		assert!(parse_thumbnail_size("0").is_err());
+		assert!(parse_thumbnail_size("4096").is_err());
+		assert_eq!(parse_jpeg_quality("90"), Ok(90));
+		assert!(parse_jpeg_quality("101").is_err());
+	}
+
+	#[test]
+	fn test_streaming_matches_in_memory_top_k() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		for i in 0..40 {
+			let angle = i as f32 * 0.07;
+			index_image(
+				dir,
+				&format!("{}.png", i),
+				vec![angle.cos(), angle.sin(), 0.3],
+			);
+		}
+
+		let query = Embedding::new(vec![0.8f32.cos(), 0.8f32.sin(), 0.3]);
+		let options = options(5);
+		let auto = SearchOptions {
+			auto_score: true,
+			..options.clone()
+		};
+
+		let dirs = [dir.to_path_buf()];
+		let loaded = SearchEngine::load(&dirs, false, false).unwrap();
+		let streamed = SearchEngine::stream(&dirs, false, false);
+		let ranked = |engine: &SearchEngine, options: &SearchOptions| -> Vec<(String, f32)> {
+			let matches = engine.query_embedding(&query, None, options).unwrap();
+			matches.into_iter().map(|m| (m.path, m.score)).collect()
+		};
+		let in_memory = ranked(&loaded, &options);
+		let streaming = ranked(&streamed, &options);
+		let in_memory_auto = ranked(&loaded, &auto);
+		let streaming_auto = ranked(&streamed, &auto);
+
+		assert_eq!(streamed.len(), 40);
+		assert_eq!(in_memory.len(), 5);
+		assert!(in_memory[0].0.ends_with("11.png"), "{:?}", in_memory);
+		assert_eq!(streaming, in_memory);
+		assert_eq!(streaming_auto, in_memory_auto);
+	}
+
+	#[test]
+	fn test_streaming_skips_missing_media() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		for i in 0..8 {
+			let angle = i as f32 * 0.1;
+			index_image(dir, &format!("{}.png", i), vec![angle.cos(), angle.sin()]);
+		}
+		// The best match is deleted but its sidecar stays behind
+		std::fs::remove_file(dir.join("0.png")).unwrap();
+
+		let query = Embedding::new(vec![1.0, 0.0]);
+		let streamed = SearchEngine::stream(&[dir.to_path_buf()], false, false);
+		let matches = streamed.query_embedding(&query, None, &options(3)).unwrap();
+
+		let names: Vec<&str> = matches
+			.iter()
+			.map(|m| Path::new(&m.path).file_name().unwrap().to_str().unwrap())
+			.collect();
+		assert_eq!(names, ["1.png", "2.png", "3.png"]);
+	}
+
+	#[test]
+	fn test_limit_all_returns_every_match() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		for i in 0..25 {
+			let angle = i as f32 * 0.06;
+			index_image(dir, &format!("{}.png", i), vec![angle.cos(), angle.sin()]);
+		}
+
+		let limit = parse_limit("all").unwrap();
+		let query = Embedding::new(vec![1.0, 0.0]);
+		let dirs = [dir.to_path_buf()];
+		let loaded = SearchEngine::load(&dirs, false, false).unwrap();
+		let streamed = SearchEngine::stream(&dirs, false, false);
+		let all = loaded
+			.query_embedding(&query, None, &options(limit))
+			.unwrap();
+		let all_streamed = streamed
+			.query_embedding(&query, None, &options(limit))
+			.unwrap();
+		let cut = SearchOptions {
+			min_score: 0.57f32.cos(),
+			..options(limit)
+		};
+		let above = loaded.query_embedding(&query, None, &cut).unwrap();
+		let diverse = SearchOptions {
+			diverse: Some(0.5),
+			..options(limit)
+		};
+		let diversified = loaded.query_embedding(&query, None, &diverse).unwrap();
+
+		assert_eq!(all.len(), 25);
+		assert_eq!(all_streamed.len(), 25);
+		assert!(all.windows(2).all(|pair| pair[0].score >= pair[1].score));
+		// Still only matches above the score threshold
+		assert_eq!(above.len(), 10);
+		// A bounded pool is re-ranked and the rest still follow
+		assert_eq!(diversified.len(), 25);
+
+		assert_eq!(parse_limit("0"), Ok(usize::MAX));
+		assert_eq!(parse_limit("25"), Ok(25));
+		assert!(parse_limit("every").is_err());
+	}
+
+	#[test]
+	fn test_engine_cache_reloads_after_sidecars_change() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		let add = |i: u8| index_image(dir, &format!("{}.png", i), vec![1.0, i as f32]);
+		add(1);
+
+		let cache = EngineCache::default();
+		let dirs = [dir.to_path_buf()];
+		let get = |streaming: bool| {
+			cache
+				.get(&dirs, false, false, streaming, scan_roots(&dirs, false))
+				.unwrap()
+		};
+		let first = get(false);
+		let again = get(false);
+		let streamed = get(true);
+		let streamed_again = get(true);
+		add(2);
+		let reloaded = get(false);
+		let restreamed = get(true);
+
+		assert!(Arc::ptr_eq(&first, &again));
+		assert!(!Arc::ptr_eq(&first, &streamed));
+		assert!(
+			Arc::ptr_eq(&streamed, &streamed_again),
+			"streamed engines are kept too"
+		);
+		assert!(!Arc::ptr_eq(&first, &reloaded));
+		assert!(!Arc::ptr_eq(&streamed, &restreamed));
+		assert_eq!(reloaded.len(), 2);
+		assert_eq!(restreamed.len(), 2);
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_dedup_drops_symlinked_and_identical_copies() {
+		let temp = tempfile::tempdir().unwrap();
+		let root = temp.path();
+		let (photos, links) = (root.join("photos"), root.join("links"));
+		std::fs::create_dir_all(&photos).unwrap();
+		std::fs::create_dir_all(&links).unwrap();
+
+		index_image(&photos, "beach.png", vec![1.0, 0.0, 0.0]);
+		// A re-encoded copy: different bytes, practically the same embedding
+		index_image(&photos, "beach-copy.png", vec![1.0, 0.0001, 0.0]);
+		index_image(&photos, "forest.png", vec![0.8, 0.6, 0.0]);
+
+		// The same photo indexed a second time through a symlink
+		std::os::unix::fs::symlink(photos.join("beach.png"), links.join("beach.png")).unwrap();
+		let hash = crate::core::FileHash::compute(&links.join("beach.png")).unwrap();
+		let sidecar = storage::ImageSidecar::new(hash.clone(), Embedding::new(vec![1.0, 0.0, 0.0]));
+		storage::save_image(&sidecar, &links, &hash).unwrap();
+
+		let engine = SearchEngine::load(&[photos.clone(), links.clone()], false, false).unwrap();
+		let query = Embedding::new(vec![1.0, 0.1, 0.0]);
+		let names = |dedup: bool| -> Vec<String> {
+			let options = SearchOptions {
+				dedup,
+				..options(10)
+			};
+			let matches = engine.query_embedding(&query, None, &options).unwrap();
+			let name = |m: &Match| {
+				Path::new(&m.path)
+					.file_name()
+					.unwrap()
+					.to_string_lossy()
+					.to_string()
+			};
+			matches.iter().map(name).collect()
+		};
+		let deduped = names(true);
+		let all = names(false);
+
+		assert_eq!(all.len(), 4, "{:?}", all);
+		assert_eq!(deduped.len(), 2, "{:?}", deduped);
+		assert!(deduped[0].starts_with("beach"));
+		assert_eq!(deduped[1], "forest.png");
+	}
+
+	#[test]
+	fn test_combine_modes_rank_an_image_only_match() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		// Only looks like the reference image, and a partial match for both
+		index_image(dir, "image-only.png", vec![0.1, 1.0, 0.0]);
+		index_image(dir, "both.png", vec![1.0, 1.0, 0.3]);
+
+		let engine = SearchEngine::load(&[dir.to_path_buf()], false, false).unwrap();
+		let text = Embedding::new(vec![1.0, 0.0, 0.0]);
+		let image = Embedding::new(vec![0.0, 1.0, 0.0]);
+		let ranked = |mode: CombineMode| -> Vec<String> {
+			let query = Embedding::blend(&text, &image, 0.5);
+			let split = (mode != CombineMode::Blend).then(|| SplitQuery {
+				text: text.clone(),
+				image: image.clone(),
+				mode,
+				weight: 0.5,
+			});
+			let matches = engine
+				.score_candidates(&query, split.as_ref(), None, None, &options(10))
+				.unwrap();
+			let name = |m: Match| {
+				Path::new(&m.path)
+					.file_name()
+					.unwrap()
+					.to_string_lossy()
+					.to_string()
+			};
+			matches.into_iter().map(name).collect()
+		};
+		let blend = ranked(CombineMode::Blend);
+		let max = ranked(CombineMode::Max);
+		let min = ranked(CombineMode::Min);
+
+		assert_eq!(blend, ["both.png", "image-only.png"]);
+		assert_eq!(max, ["image-only.png", "both.png"]);
+		assert_eq!(min, ["both.png", "image-only.png"]);
+	}
+
+	#[test]
+	fn test_hybrid_surfaces_a_filename_only_match() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		// The camera roll name looks closer to the query than the named file
+		index_image(dir, "beach_2019.png", vec![0.2, 1.0, 0.0]);
+		index_image(dir, "IMG_0001.png", vec![0.5, 1.0, 0.0]);
+
+		let engine = SearchEngine::load(&[dir.to_path_buf()], false, false).unwrap();
+		let query = Embedding::new(vec![1.0, 0.0, 0.0]);
+		let ranked = |hybrid: Option<Hybrid>| -> Vec<String> {
+			let options = SearchOptions {
+				hybrid,
+				..options(10)
+			};
+			let matches = engine
+				.score_candidates(&query, None, None, None, &options)
+				.unwrap();
+			let name = |m: Match| {
+				Path::new(&m.path)
+					.file_name()
+					.unwrap()
+					.to_string_lossy()
+					.to_string()
+			};
+			matches.into_iter().map(name).collect()
+		};
+		let semantic = ranked(None);
+		let hybrid = ranked(Some(Hybrid::new(0.5, &["a beach".to_string()])));
+
+		assert_eq!(semantic, ["IMG_0001.png", "beach_2019.png"]);
+		assert_eq!(hybrid, ["beach_2019.png", "IMG_0001.png"]);
+	}
+
+	#[test]
+	fn test_keyword_tokens_split_filenames() {
+		assert_eq!(
+			filename_tokens(Path::new("/photos/Beach_Trip-2019.JPG")),
+			["beach", "trip", "2019"]
+		);
+		assert_eq!(
+			Hybrid::new(0.7, &["a Beach trip, beach".to_string()]).terms,
+			["beach", "trip"]
+		);
+	}
+
+	#[test]
+	fn test_frames_per_video_reports_each_scene() {
+		// One frame per second: a beach scene peaking at 4s, a city scene in
+		// between, then a second beach scene peaking at 34s
+		let at = |angle: f32| Embedding::new(vec![angle.cos(), angle.sin()]);
+		let frames: Vec<(f64, Embedding)> = (0..50)
+			.map(|t| {
+				let angle = match t {
+					0..=9 => (t as f32 - 4.0).abs() * 0.05,
+					30..=39 => 0.1 + (t as f32 - 34.0).abs() * 0.05,
+					_ => 1.4,
+				};
+				(t as f64, at(angle))
+			})
+			.collect();
+		let video = storage::Sidecar::Video(storage::VideoSidecar::new(
+			crate::core::FileHash::from_hex("0".repeat(16)),
+			frames,
+		));
+
+		let query = at(0.0);
+		let score = |emb: &Embedding| query.similarity(emb);
+		let search = |frames_per_video: Option<usize>| -> Vec<Option<f64>> {
+			let options = SearchOptions {
+				min_score: 0.5,
+				frames_per_video,
+				..options(10)
+			};
+			score_sidecar(&video, score, None, None, &options, "clip.mp4".into(), None)
+				.into_iter()
+				.map(|m| m.timestamp)
+				.collect()
+		};
+
+		// Neighbouring frames of the same scene collapse into its peak, and
+		// the city frames are below the minimum score
+		assert_eq!(search(Some(5)), vec![Some(4.0), Some(34.0)]);
+		assert_eq!(search(Some(1)), vec![Some(4.0)]);
+		assert_eq!(search(None), vec![Some(4.0)]);
+	}
+}
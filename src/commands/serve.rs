@@ -0,0 +1,317 @@
+//! # Serve Command
+//!
+//! Expose the loaded models to other languages over a local TCP socket.
+//! Speaks a small versioned protocol of JSON lines documented in
+//! `docs/PROTOCOL.md`: encode text or images, compare embeddings, search.
+//! `scout daemon` is this server on localhost, and `scout search --daemon`
+//! sends it a `search` request.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::mpsc::channel;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use super::search::{self, EngineCache, Match, SearchRequest};
+use crate::config::{
+	DAEMON_QUERY_CACHE_SIZE, SERVE_MAX_REQUEST_BYTES, SERVE_PROTOCOL_VERSION, SERVE_TIMEOUT_SECS,
+	SERVE_WORKERS,
+};
+use crate::core::Embedding;
+use crate::models::Models;
+use crate::ui;
+
+/// One request line. `v` is optional; when present it must match
+/// [`SERVE_PROTOCOL_VERSION`]
+#[derive(Debug, Deserialize)]
+struct Request {
+	#[serde(default)]
+	v: Option<u32>,
+	#[serde(flatten)]
+	op: Op,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+enum Op {
+	EncodeText {
+		text: String,
+	},
+	EncodeImageB64 {
+		/// Base64 of an encoded image file (JPEG, PNG, ...)
+		image: String,
+	},
+	Similarity {
+		a: Vec<f32>,
+		b: Vec<f32>,
+	},
+	/// The same request `scout search --daemon` sends
+	Search(Box<SearchRequest>),
+	Version,
+}
+
+#[derive(Debug, Serialize)]
+struct Response {
+	v: u32,
+	#[serde(flatten)]
+	reply: Reply,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+enum Reply {
+	Embedding { embedding: Vec<f32> },
+	Similarity { similarity: f32 },
+	Results { results: Vec<Match> },
+	Version { version: u32, scout: &'static str },
+	Error { error: String },
+}
+
+/// Shared by all workers
+struct State {
+	models: Mutex<Models>,
+	/// Searched directories stay loaded between requests
+	engines: EngineCache,
+}
+
+pub fn run(addr: SocketAddr) -> Result<()> {
+	let mut models = Models::new()?;
+	models.set_text_cache(DAEMON_QUERY_CACHE_SIZE);
+
+	ui::info("Loading models...");
+	models.warmup()?;
+
+	let listener =
+		TcpListener::bind(addr).with_context(|| format!("Failed to bind server to {}", addr))?;
+
+	// Connections are spread over a few workers; encoding itself is
+	// serialized by the lock since all of them share the loaded models
+	let state = Arc::new(State {
+		models: Mutex::new(models),
+		engines: EngineCache::default(),
+	});
+	let (conn_tx, conn_rx) = channel::<TcpStream>();
+	let conn_rx = Arc::new(Mutex::new(conn_rx));
+
+	for _ in 0..SERVE_WORKERS {
+		let state = Arc::clone(&state);
+		let conn_rx = Arc::clone(&conn_rx);
+		thread::spawn(move || loop {
+			let next = conn_rx.lock().unwrap().recv();
+			let Ok(stream) = next else {
+				break;
+			};
+			if let Err(e) = handle_client(&state, stream) {
+				ui::debug(&format!("Connection closed: {}", e));
+			}
+		});
+	}
+
+	ui::success(&format!(
+		"Serving protocol v{} on {} (Ctrl+C to stop)",
+		SERVE_PROTOCOL_VERSION, addr
+	));
+
+	for stream in listener.incoming() {
+		match stream {
+			Ok(stream) => conn_tx.send(stream)?,
+			Err(e) => ui::error(&format!("Connection failed: {}", e)),
+		}
+	}
+
+	Ok(())
+}
+
+/// Answer request lines until the client disconnects or goes idle
+fn handle_client(state: &State, stream: TcpStream) -> Result<()> {
+	// A stalled client only holds its worker until the timeout
+	let timeout = Some(Duration::from_secs(SERVE_TIMEOUT_SECS));
+	stream.set_read_timeout(timeout)?;
+	stream.set_write_timeout(timeout)?;
+
+	let mut writer = stream.try_clone()?;
+	let mut reader = BufReader::new(stream);
+	loop {
+		let mut line = String::new();
+		let read = (&mut reader)
+			.take(SERVE_MAX_REQUEST_BYTES)
+			.read_line(&mut line)
+			.context("Failed to read request")?;
+		if read == 0 {
+			return Ok(());
+		}
+		if !line.ends_with('\n') && read as u64 == SERVE_MAX_REQUEST_BYTES {
+			// The rest of the line can't be skipped reliably, so hang up
+			send_reply(
+				&mut writer,
+				Reply::Error {
+					error: format!("Request exceeds {} bytes", SERVE_MAX_REQUEST_BYTES),
+				},
+			)?;
+			return Ok(());
+		}
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let reply = match parse(&line) {
+			Ok(op) => respond(state, op).unwrap_or_else(|e| Reply::Error {
+				error: format!("{:#}", e),
+			}),
+			Err(e) => Reply::Error {
+				error: format!("Invalid request: {:#}", e),
+			},
+		};
+		send_reply(&mut writer, reply)?;
+	}
+}
+
+fn send_reply(writer: &mut TcpStream, reply: Reply) -> Result<()> {
+	let response = Response {
+		v: SERVE_PROTOCOL_VERSION,
+		reply,
+	};
+	serde_json::to_writer(&mut *writer, &response)?;
+	writer.write_all(b"\n")?;
+	writer.flush()?;
+	Ok(())
+}
+
+fn parse(line: &str) -> Result<Op> {
+	let request: Request = serde_json::from_str(line)?;
+	match request.v {
+		Some(v) if v != SERVE_PROTOCOL_VERSION => anyhow::bail!(
+			"Unsupported protocol version {}, this server speaks v{}",
+			v,
+			SERVE_PROTOCOL_VERSION
+		),
+		_ => Ok(request.op),
+	}
+}
+
+fn respond(state: &State, op: Op) -> Result<Reply> {
+	let models = &state.models;
+	let reply = match op {
+		Op::EncodeText { text } => {
+			let embedding = models.lock().unwrap().encode_text(&text)?;
+			Reply::Embedding {
+				embedding: embedding.0,
+			}
+		}
+		Op::EncodeImageB64 { image } => {
+			let bytes = base64::engine::general_purpose::STANDARD
+				.decode(image.trim())
+				.context("Image is not valid base64")?;
+			let mut models = models.lock().unwrap();
+			let image =
+				crate::processing::image::load_from_memory_with(&bytes, models.auto_orient())
+					.context("Could not decode image")?;
+			let embedding = models.encode_image(&image)?;
+			Reply::Embedding {
+				embedding: embedding.0,
+			}
+		}
+		Op::Similarity { a, b } => Reply::Similarity {
+			similarity: Embedding::new(a).try_similarity(&Embedding::new(b))?,
+		},
+		Op::Search(request) => {
+			// Loading sidecars can take a while, so only hold the models to query
+			let engine = search::open_engine(&request, Some(&state.engines))?;
+			Reply::Results {
+				results: search::query(&mut models.lock().unwrap(), &engine, &request)?,
+			}
+		}
+		Op::Version => Reply::Version {
+			version: SERVE_PROTOCOL_VERSION,
+			scout: env!("CARGO_PKG_VERSION"),
+		},
+	};
+	Ok(reply)
+}
+
+/// A `search` request line carrying `request`
+fn search_line(request: &SearchRequest) -> Result<serde_json::Value> {
+	let mut line = serde_json::to_value(request)?;
+	line["v"] = SERVE_PROTOCOL_VERSION.into();
+	line["op"] = "search".into();
+	Ok(line)
+}
+
+/// Forward a search request to a running server and return its matches
+pub fn send_search(addr: SocketAddr, request: &SearchRequest) -> Result<Vec<Match>> {
+	#[derive(Deserialize)]
+	struct SearchReply {
+		#[serde(default)]
+		results: Vec<Match>,
+		error: Option<String>,
+	}
+
+	let mut stream = TcpStream::connect(addr).with_context(|| {
+		format!(
+			"Could not connect to a server on {}. Start one with 'scout daemon'",
+			addr
+		)
+	})?;
+
+	serde_json::to_writer(&mut stream, &search_line(request)?)?;
+	stream.write_all(b"\n")?;
+
+	let mut response = String::new();
+	BufReader::new(&stream)
+		.read_line(&mut response)
+		.context("Failed to read server response")?;
+
+	let reply: SearchReply = serde_json::from_str(&response).context("Invalid server response")?;
+	match reply.error {
+		Some(message) => anyhow::bail!("Server error: {}", message),
+		None => Ok(reply.results),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_protocol_requests() {
+		assert!(matches!(
+			parse(r#"{"op":"encode_text","text":"a dog"}"#).unwrap(),
+			Op::EncodeText { text } if text == "a dog"
+		));
+		assert!(matches!(
+			parse(r#"{"v":1,"op":"encode_image_b64","image":"aGk="}"#).unwrap(),
+			Op::EncodeImageB64 { .. }
+		));
+		assert!(matches!(
+			parse(r#"{"op":"similarity","a":[1,0],"b":[0.5,0.5]}"#).unwrap(),
+			Op::Similarity { a, b } if a.len() == 2 && b.len() == 2
+		));
+
+		let search = parse(
+			r#"{"op":"search","queries":["cat"],"weight":0.5,"dirs":["."],"recursive":true,
+			"limit":5,"min_score":0.0,"include_ref":false,"exclude_videos":false}"#,
+		)
+		.unwrap();
+		let Op::Search(request) = search else {
+			panic!("expected a search request");
+		};
+		assert_eq!(request.queries, vec!["cat"]);
+		assert_eq!(request.options.limit, 5);
+
+		// What `scout search --daemon` sends parses back to the same request
+		let line = search_line(&request).unwrap().to_string();
+		let Op::Search(forwarded) = parse(&line).unwrap() else {
+			panic!("expected a search request");
+		};
+		assert_eq!(forwarded.queries, request.queries);
+		assert_eq!(forwarded.dirs, request.dirs);
+
+		assert!(parse(r#"{"v":2,"op":"version"}"#).is_err());
+		assert!(parse(r#"{"op":"train"}"#).is_err());
+	}
+}
@@ -0,0 +1,176 @@
+//! # Verify Command
+//!
+//! Rehash every media file and check it against the sidecars on disk.
+//! Catches files that changed content in place: their old sidecar lingers
+//! next to the new one, or the new content was never indexed at all.
+
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use colored::*;
+use rayon::prelude::*;
+
+use crate::core::{FileHash, HashMode};
+use crate::processing::scan::{discover_files, IgnoreRules};
+use crate::storage::{self, ScanJournal};
+use crate::ui;
+
+/// Files listed per category before the rest is summarized
+const LIST_LIMIT: usize = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+	Current,
+	/// Written by another model or Scout version
+	Outdated,
+	/// No sidecar for the file's current content
+	Unindexed,
+}
+
+struct Checked {
+	path: PathBuf,
+	status: Status,
+	/// Sidecar matching the content, if there is one
+	sidecar: Option<PathBuf>,
+	/// Sidecar of the content the last scan saw, when the file has changed since
+	superseded: Option<PathBuf>,
+}
+
+pub fn run(dir: &Path, recursive: bool, fix: bool) -> Result<()> {
+	let fingerprint = crate::commands::scan::current_fingerprint();
+	ui::info(&format!("Verifying: {}", dir.display()));
+
+	let files = discover_files(dir, recursive, &IgnoreRules::default());
+	let journal = ScanJournal::load(dir);
+	let checked: Vec<Checked> = files
+		.par_iter()
+		.filter_map(|path| check_file(path, &journal, fingerprint.as_deref()))
+		.collect();
+
+	// Every sidecar not backing a media file's current content is stale
+	let live: HashSet<&Path> = checked
+		.iter()
+		.filter_map(|c| c.sidecar.as_deref())
+		.collect();
+	let superseded: HashSet<&Path> = checked
+		.iter()
+		.filter_map(|c| c.superseded.as_deref())
+		.filter(|path| !live.contains(path))
+		.collect();
+	let dangling: Vec<PathBuf> = storage::scan(dir, recursive)
+		.into_iter()
+		.filter_map(|(path, _)| path.canonicalize().ok())
+		.filter(|path| !live.contains(path.as_path()) && !superseded.contains(path.as_path()))
+		.collect();
+
+	let with_status = |status: Status| -> Vec<&Checked> {
+		checked.iter().filter(|c| c.status == status).collect()
+	};
+	let current = with_status(Status::Current);
+	let outdated = with_status(Status::Outdated);
+	let unindexed = with_status(Status::Unindexed);
+	let changed: Vec<&Checked> = checked
+		.iter()
+		.filter(|c| {
+			c.superseded
+				.as_deref()
+				.is_some_and(|p| superseded.contains(p))
+		})
+		.collect();
+
+	ui::success(&format!(
+		"{} of {} media files have a current sidecar",
+		current.len(),
+		checked.len()
+	));
+	list("Changed since indexed", &changed);
+	list("Outdated", &outdated);
+	list("Not indexed", &unindexed);
+	if !dangling.is_empty() {
+		ui::warn(&format!("{} sidecars match no media file", dangling.len()));
+	}
+
+	let stale: Vec<&Path> = superseded
+		.iter()
+		.copied()
+		.chain(dangling.iter().map(PathBuf::as_path))
+		.collect();
+
+	if fix && !stale.is_empty() {
+		for path in &stale {
+			fs::remove_file(path)?;
+			ui::debug(&format!("Deleted: {}", path.display()));
+		}
+		ui::success(&format!("Removed {} stale sidecars", stale.len()));
+	} else if !stale.is_empty() {
+		ui::info(&format!(
+			"Rerun with --fix to remove {} stale sidecars",
+			stale.len()
+		));
+	}
+
+	if !outdated.is_empty() || !unindexed.is_empty() {
+		ui::info("Run 'scout scan' to index new and changed files");
+	}
+
+	Ok(())
+}
+
+/// Hash a media file and find the sidecars for its current and previous content
+fn check_file(path: &Path, journal: &ScanJournal, fingerprint: Option<&str>) -> Option<Checked> {
+	let media_dir = path.parent()?;
+	let hash = match FileHash::compute(path) {
+		Ok(hash) => hash,
+		Err(e) => {
+			ui::debug(&format!("Could not hash {}: {}", path.display(), e));
+			return None;
+		}
+	};
+
+	// Sidecars from `scan --hash full` are keyed by the whole-file hash
+	let sidecar = storage::find(media_dir, &hash).or_else(|| {
+		FileHash::compute_with(path, HashMode::Full)
+			.ok()
+			.and_then(|full| storage::find(media_dir, &full))
+	});
+
+	let status = match &sidecar {
+		None => Status::Unindexed,
+		Some(sidecar_path) => match storage::load(sidecar_path) {
+			Ok(loaded) if loaded.is_current(fingerprint) => Status::Current,
+			_ => Status::Outdated,
+		},
+	};
+
+	let superseded = journal
+		.recorded(path)
+		.filter(|previous| previous.as_str() != hash.as_str())
+		.and_then(|previous| storage::find(media_dir, &previous))
+		.filter(|previous| Some(previous) != sidecar.as_ref());
+
+	Some(Checked {
+		path: path.to_path_buf(),
+		status,
+		sidecar,
+		superseded,
+	})
+}
+
+fn list(label: &str, files: &[&Checked]) {
+	if files.is_empty() {
+		return;
+	}
+
+	ui::warn(&format!("{}: {}", label, files.len()));
+	for file in files.iter().take(LIST_LIMIT) {
+		eprintln!("  {}", ui::path_link(&file.path, 60));
+	}
+	if files.len() > LIST_LIMIT {
+		eprintln!(
+			"  {}",
+			format!("... and {} more", files.len() - LIST_LIMIT).dimmed()
+		);
+	}
+}
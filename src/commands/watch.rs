@@ -2,18 +2,25 @@
 //!
 //! Monitor directory for file changes and auto-index new media.
 //! Uses debounced filesystem events for efficient processing.
+//!
+//! Every newly indexed file is published as an [`IndexedEvent`] to
+//! [`subscribe`]rs, and can optionally be folded into the consolidated index.
 
 use anyhow::{Context, Result};
 use notify_debouncer_mini::{new_debouncer, DebounceEventResult};
 use std::path::{Path, PathBuf};
-use std::sync::mpsc::channel;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{channel, Receiver, RecvTimeoutError, Sender};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
-use crate::core::{FileHash, MediaType};
+use crate::commands::scan::VideoOptions;
+use crate::config::WATCH_INDEX_FLUSH_SECS;
+use crate::core::{Embedding, FileHash, MediaType};
 use crate::models::Models;
 use crate::processing;
+use crate::processing::video::{FrameSampling, SamplingMode};
 use crate::storage;
 use crate::ui;
 
@@ -21,10 +28,90 @@ use crate::ui;
 struct WatchTask {
 	path: PathBuf,
 	media_type: MediaType,
-	max_frames: usize,
-	scene_threshold: f32,
+	video: VideoOptions,
+	format: storage::SidecarFormat,
+}
+
+/// A file the watcher has just indexed
+#[derive(Debug, Clone)]
+pub struct IndexedEvent {
+	pub path: PathBuf,
+	pub hash: String,
+	/// Image embedding, or the first frame embedding for videos
+	pub embedding: Embedding,
+}
+
+static SUBSCRIBERS: Mutex<Vec<Sender<IndexedEvent>>> = Mutex::new(Vec::new());
+
+/// Receive an [`IndexedEvent`] for every file indexed by a running watcher.
+///
+/// Subscribe before calling [`run`]; dropped receivers are pruned on the next event.
+pub fn subscribe() -> Receiver<IndexedEvent> {
+	let (tx, rx) = channel();
+	SUBSCRIBERS.lock().unwrap().push(tx);
+	rx
+}
+
+fn publish(event: &IndexedEvent) {
+	SUBSCRIBERS
+		.lock()
+		.unwrap()
+		.retain(|tx| tx.send(event.clone()).is_ok());
+}
+
+/// Consolidated index kept in memory and written back every few files
+struct LiveIndex {
+	root: PathBuf,
+	index: storage::IndexFile,
+	every: usize,
+	pending: usize,
+}
+
+impl LiveIndex {
+	fn open(root: PathBuf, recursive: bool, every: usize) -> Self {
+		let index = storage::IndexFile::load_fresh(&root, recursive)
+			.unwrap_or_else(|| storage::IndexFile::build(&root, recursive));
+		ui::debug(&format!(
+			"Updating consolidated index every {} files ({} entries)",
+			every,
+			index.len()
+		));
+		Self {
+			root,
+			index,
+			every: every.max(1),
+			pending: 0,
+		}
+	}
+
+	fn add(&mut self, path: &Path, sidecar: storage::Sidecar) {
+		// Files outside the watched root (unlikely) cannot be stored relative to it
+		if !path.starts_with(&self.root) {
+			return;
+		}
+
+		self.index.upsert(&self.root, path, sidecar);
+		self.pending += 1;
+		if self.pending >= self.every {
+			self.flush();
+		}
+	}
+
+	fn flush(&mut self) {
+		if self.pending == 0 {
+			return;
+		}
+		match self.index.save(&self.root) {
+			Ok(()) => {
+				ui::debug(&format!("Index updated ({} entries)", self.index.len()));
+				self.pending = 0;
+			}
+			Err(e) => ui::error(&format!("Failed to update index: {}", e)),
+		}
+	}
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn run(
 	dir: &Path,
 	recursive: bool,
@@ -33,11 +120,30 @@ pub fn run(
 	exclude_videos: bool,
 	max_frames: Option<usize>,
 	scene_threshold: Option<f32>,
+	sampling: SamplingMode,
+	video_stream: usize,
+	frame_dedup: Option<f32>,
+	strict_frames: Option<f32>,
+	max_video_embeddings: Option<usize>,
+	quantize: bool,
+	half: bool,
+	index_every: Option<usize>,
 ) -> Result<()> {
 	ui::info(&format!("Watching: {}", dir.display()));
-
-	let max_frames = max_frames.unwrap_or(crate::config::MAX_VIDEO_FRAMES);
-	let scene_threshold = scene_threshold.unwrap_or(crate::config::SCENE_THRESHOLD);
+	let format = storage::SidecarFormat { quantize, half };
+	let fingerprint = crate::commands::scan::current_fingerprint();
+
+	let video = VideoOptions {
+		sampling: FrameSampling::new(
+			sampling,
+			max_frames.unwrap_or(crate::config::MAX_VIDEO_FRAMES),
+			scene_threshold.unwrap_or(crate::config::SCENE_THRESHOLD),
+		),
+		stream: video_stream,
+		frame_dedup,
+		strict_frames,
+		max_embeddings: max_video_embeddings,
+	};
 
 	// 1. Check FFmpeg availability
 	let video_supported = if exclude_videos {
@@ -54,31 +160,92 @@ pub fn run(
 
 	// 2. Load models safely (Shared ownership)
 	// We wrap Models in a Mutex so the worker thread can lock it briefly when needed
-	let models = Arc::new(Mutex::new(Models::new()?));
+	let mut models = Models::new()?;
+	models.require_vision()?;
+	models.set_fingerprint(fingerprint.clone());
+	let models = Arc::new(Mutex::new(models));
 
 	// 3. Setup the Worker Thread (The Queue)
 	// We use a channel to decouple "detection" from "processing"
 	let (task_tx, task_rx) = channel::<WatchTask>();
 	let worker_models = Arc::clone(&models);
 
-	// Spawn the background worker
-	thread::spawn(move || {
+	let mut live_index = match index_every {
+		Some(every) => Some(LiveIndex::open(dir.canonicalize()?, recursive, every)),
+		None => None,
+	};
+
+	// Files sent to the worker that it hasn't started on yet
+	let queued = Arc::new(AtomicUsize::new(0));
+	let worker_queued = Arc::clone(&queued);
+
+	// Spawn the background worker, which reports (indexed, failed) when the queue closes
+	let worker = thread::spawn(move || {
+		let flush_interval = Duration::from_secs(WATCH_INDEX_FLUSH_SECS);
+		let (mut indexed, mut failed) = (0usize, 0usize);
+
 		// This loop runs forever (or until the main program closes the channel)
-		while let Ok(task) = task_rx.recv() {
+		loop {
+			let task = match task_rx.recv_timeout(flush_interval) {
+				Ok(task) => task,
+				Err(RecvTimeoutError::Timeout) => {
+					// Quiet period: write out whatever the index is still holding
+					if let Some(index) = live_index.as_mut() {
+						index.flush();
+					}
+					continue;
+				}
+				Err(RecvTimeoutError::Disconnected) => break,
+			};
+			worker_queued.fetch_sub(1, Ordering::SeqCst);
+
 			// Process files one by one to avoid CPU spikes
-			if let Err(e) = process_task(&worker_models, &task) {
+			match process_task(&worker_models, &task, fingerprint.as_deref()) {
+				Ok(Some((path, sidecar))) => {
+					let event = IndexedEvent {
+						path: path.clone(),
+						hash: sidecar.hash().to_string(),
+						embedding: sidecar.primary_embedding(),
+					};
+					if let Some(index) = live_index.as_mut() {
+						index.add(&path, sidecar);
+					}
+					publish(&event);
+					indexed += 1;
+				}
+				Ok(None) => {}
 				// Log errors but don't crash the worker
-				ui::error(&format!("Error processing {}: {}", task.path.display(), e));
+				Err(e) => {
+					ui::error(&format!("Error processing {}: {}", task.path.display(), e));
+					failed += 1;
+				}
 			}
 		}
+
+		if let Some(index) = live_index.as_mut() {
+			index.flush();
+		}
+		(indexed, failed)
 	});
 
+	// Ctrl+C asks for a clean stop; a second one exits immediately
+	let (stop_tx, stop_rx) = channel::<()>();
+	let stopping = AtomicBool::new(false);
+	ctrlc::set_handler(move || {
+		if stopping.swap(true, Ordering::SeqCst) {
+			std::process::exit(130);
+		}
+		let _ = stop_tx.send(());
+	})
+	.context("Failed to install Ctrl+C handler")?;
+
 	ui::success("Ready - watching for file changes (Ctrl+C to stop)");
 	println!();
 
 	// 4. Helper closure to filter and queue files
 	// This removes duplicate logic for handling direct files vs folder contents
 	let tx = task_tx.clone();
+	let queue_queued = Arc::clone(&queued);
 	let queue_file = move |path: PathBuf| {
 		// Check filtering options
 		if let Some(media_type) = MediaType::detect(&path) {
@@ -110,12 +277,16 @@ pub fn run(
 			}
 
 			// Send to worker
-			let _ = tx.send(WatchTask {
+			queue_queued.fetch_add(1, Ordering::SeqCst);
+			let task = WatchTask {
 				path,
 				media_type,
-				max_frames,
-				scene_threshold,
-			});
+				video,
+				format,
+			};
+			if tx.send(task).is_err() {
+				queue_queued.fetch_sub(1, Ordering::SeqCst);
+			}
 		}
 	};
 
@@ -166,14 +337,37 @@ pub fn run(
 		.watch(dir, watch_mode)
 		.context("Failed to watch directory")?;
 
-	// Keep the main thread alive indefinitely
-	loop {
-		thread::sleep(Duration::from_secs(3600));
+	// Keep the main thread alive until Ctrl+C
+	let _ = stop_rx.recv();
+
+	// Stop accepting new files, then let the worker drain what's already queued
+	drop(debouncer);
+	drop(task_tx);
+	println!();
+	ui::info(&format!(
+		"Shutting down, finishing {} queued files...",
+		queued.load(Ordering::SeqCst)
+	));
+
+	let (indexed, failed) = worker
+		.join()
+		.map_err(|_| anyhow::anyhow!("Watch worker panicked"))?;
+
+	ui::success(&format!("Indexed {} files this session", indexed));
+	if failed > 0 {
+		ui::warn(&format!("{} files failed", failed));
 	}
+	Ok(())
 }
 
-/// The main logic run by the background worker
-fn process_task(models: &Arc<Mutex<Models>>, task: &WatchTask) -> Result<()> {
+/// The main logic run by the background worker.
+///
+/// Returns the indexed file and its new sidecar, or `None` if it was already up to date.
+fn process_task(
+	models: &Arc<Mutex<Models>>,
+	task: &WatchTask,
+	fingerprint: Option<&str>,
+) -> Result<Option<(PathBuf, storage::Sidecar)>> {
 	let file_start = Instant::now();
 
 	// 1. Wait for file to be safe (unlocked and fully written)
@@ -186,9 +380,9 @@ fn process_task(models: &Arc<Mutex<Models>>, task: &WatchTask) -> Result<()> {
 	// 3. Check DB (Avoid locking models if we don't need to)
 	if let Some(sidecar_path) = storage::find(media_dir, &hash) {
 		if let Ok(sidecar) = storage::load(&sidecar_path) {
-			if sidecar.is_current_version() {
+			if sidecar.is_current(fingerprint) {
 				ui::debug(&format!("Already indexed: {}", task.path.display()));
-				return Ok(());
+				return Ok(None);
 			}
 		}
 	}
@@ -202,7 +396,7 @@ fn process_task(models: &Arc<Mutex<Models>>, task: &WatchTask) -> Result<()> {
 	let file = processing::scan::MediaFile {
 		path: canonical.clone(),
 		filename,
-		hash,
+		hash: hash.clone(),
 		media_type: task.media_type,
 	};
 
@@ -211,23 +405,27 @@ fn process_task(models: &Arc<Mutex<Models>>, task: &WatchTask) -> Result<()> {
 	{
 		let mut models_guard = models.lock().unwrap(); // Wait for lock
 		match task.media_type {
-			MediaType::Image => {
-				crate::commands::scan::process_image(&mut models_guard, &file, media_dir)?
-			}
+			MediaType::Image => crate::commands::scan::process_image(
+				&mut models_guard,
+				&file,
+				media_dir,
+				task.format,
+			)?,
 			MediaType::Video => crate::commands::scan::process_video(
 				&mut models_guard,
 				&file,
 				media_dir,
-				task.max_frames,
-				task.scene_threshold,
+				&task.video,
+				task.format,
 			)?,
-		}
+		};
 	} // Lock is automatically released here
 
 	let duration_ms = file_start.elapsed().as_millis();
 	ui::log::file_processed(&canonical, duration_ms);
 
-	Ok(())
+	let sidecar_path = storage::find(media_dir, &hash).context("Sidecar missing after indexing")?;
+	Ok(Some((canonical, storage::load(&sidecar_path)?)))
 }
 
 /// Smart wait that handles both "File Busy" (Windows) and "Slow Copy" (Linux/Network)
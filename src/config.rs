@@ -3,27 +3,49 @@
 //! Global constants, model paths, and file type definitions.
 //! Configurable via environment variables and CLI flags.
 
-use std::path::PathBuf;
+use anyhow::Context;
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::OnceLock;
 
 static CUSTOM_MODEL_DIR: OnceLock<PathBuf> = OnceLock::new();
+static CUSTOM_PREPROCESS_MODE: OnceLock<crate::models::PreprocessMode> = OnceLock::new();
+static CUSTOM_AUTO_ORIENT: OnceLock<bool> = OnceLock::new();
 static MODEL_DIR_LOGGED: AtomicBool = AtomicBool::new(false);
 
 // === Model Files ===
 pub const VISION_MODEL: &str = "vision_model_q4f16.onnx";
 pub const TEXT_MODEL: &str = "text_model_q4f16.onnx";
 pub const TOKENIZER: &str = "tokenizer.json";
+pub const MODELS_URL: &str =
+	"https://huggingface.co/onnx-community/siglip2-large-patch16-512-ONNX/resolve/main"; // Default source for 'scout models download'
 
 // === Model Parameters ===
 pub const INPUT_SIZE: u32 = 512;
-pub const EMBEDDING_DIM: usize = 1024; // SigLIP2
+pub const EMBEDDING_DIM: usize = 1024; // SigLIP2, used when a model doesn't declare its output size
 pub const MAX_QUERY_TOKENS: usize = 64; // SigLIP2 text encoder max sequence length
+pub const DEFAULT_BATCH_SIZE: usize = 8; // Images per vision forward pass
+pub const DEFAULT_SCAN_WORKERS: usize = 1; // Model instances encoding in parallel; each holds its own session
+pub const DEFAULT_PREPROCESS_MODE: crate::models::PreprocessMode =
+	crate::models::PreprocessMode::Stretch; // Letterbox changes embeddings, so it is opt-in
+pub const DEFAULT_AUTO_ORIENT: bool = false; // EXIF rotation changes embeddings, so it is opt-in
+pub const LETTERBOX_FILL: u8 = 128; // Gray padding, matches SigLIP2's 0.5 channel mean
+pub const PIXEL_MEAN: [f32; 3] = [0.5, 0.5, 0.5]; // SigLIP2 per-channel mean (RGB, after /255)
+pub const PIXEL_STD: [f32; 3] = [0.5, 0.5, 0.5]; // SigLIP2 per-channel std, maps pixels to [-1, 1]
 
 // === Storage ===
 pub const SIDECAR_DIR: &str = ".scout";
 pub const SIDECAR_EXT: &str = "msgpack";
 pub const CLUSTERS_FILE: &str = "clusters.msgpack";
+pub const INDEX_FILE: &str = "index.msgpack";
+pub const ANN_FILE: &str = "ann.msgpack";
+pub const SCAN_JOURNAL_FILE: &str = "scan_journal.msgpack"; // Hashes of already scanned files by path, size and mtime
+pub const STORE_DIR_FILE: &str = "dir.txt"; // Media directory of a folder in a central store
+pub const SIDECAR_ESTIMATE_BYTES: u64 = 4 * 1024; // Typical image sidecar, used by scan --dry-run
+pub const SIDECAR_LOAD_RETRIES: u32 = 2; // Extra attempts for sidecars that fail to load
+pub const SIDECAR_RETRY_BACKOFF_MS: u64 = 50; // Delay before the first retry, doubled each attempt
+pub const DIR_CONFIG_FILE: &str = ".scoutconfig"; // Per-directory defaults for scan, search and watch
 
 // === File Extensions ===
 pub const IMAGE_EXTENSIONS: &[&str] = &[
@@ -37,29 +59,161 @@ pub const VIDEO_EXTENSIONS: &[&str] = &[
 /// Maximum number of frames to extract from videos (used with scene detection)
 pub const MAX_VIDEO_FRAMES: usize = 15;
 
+/// Frames sampled from animated GIF/WebP files, which are indexed like short videos
+pub const ANIMATION_MAX_FRAMES: usize = 8;
+
 /// Scene detection threshold (0.0-1.0). Lower = more sensitive
 pub const SCENE_THRESHOLD: f32 = 0.3;
 
 // === Search Defaults ===
 pub const DEFAULT_LIMIT: usize = 10;
+pub const MAX_LISTED_RESULTS: usize = 100; // Matches listed in the terminal with --limit all; --paths and --export get every one
 pub const DEFAULT_MIN_SCORE: f32 = 0.05;
 pub const NEGATIVE_WEIGHT: f32 = 0.7;
+pub const DEFAULT_VIDEO_TOPK: usize = 3; // Frames averaged by --video-agg topk
+pub const DEFAULT_FRAME_GAP: f64 = 5.0; // Seconds within which --frames-per-video keeps only the best frame
+pub const AUTO_SCORE_WINDOW: usize = 50; // Top scores inspected by --score auto
+pub const AUTO_SCORE_MIN_GAP: f32 = 0.02; // Smallest drop --score auto treats as a natural cutoff
+pub const SCORE_STATS_BINS: usize = 20; // Histogram bins in Scout::search_stats
+pub const SCORE_HIGH: f32 = 0.15; // Raw score from which --tiers lists a result as a strong match
+pub const SCORE_MED: f32 = 0.10; // Raw score from which --tiers lists a possible match; lower ones are hidden
+pub const MMR_POOL_FACTOR: usize = 4; // --diverse re-ranks the top limit x this many matches
+pub const MAX_MMR_POOL: usize = 1000; // --diverse never re-ranks more than this, even with --limit all
+pub const DEDUP_SIMILARITY: f32 = 0.999; // Results closer than this to a better one are dropped as duplicates
+pub const DEDUP_WINDOW: usize = 256; // Most recently kept results a match's embedding is compared against
+pub const MAX_IMAGE_DOWNLOAD_BYTES: u64 = 50 * 1024 * 1024; // Cap for --image-url
+pub const HISTOGRAM_BINS: usize = 4; // Bins per RGB channel of the color histogram stored in sidecars
+pub const HISTOGRAM_SAMPLE_SIZE: u32 = 64; // Thumbnail edge the color histogram is computed from
+pub const DEFAULT_PREFILTER_DISTANCE: f32 = 0.9; // --prefilter skips images whose colors differ more than this
+pub const EXPORT_THUMBNAIL_SIZE: u32 = 128; // Max edge of --export-thumbnails JPEGs
+pub const MAX_EXPORT_THUMBNAIL_SIZE: u32 = 512; // Largest --thumbnail-size accepted
+pub const STREAMING_THRESHOLD: usize = 250_000; // Sidecars above which search reads them one at a time
+pub const EXPORT_THUMBNAIL_QUALITY: u8 = 75; // JPEG quality of --export-thumbnails
+
+/// Default steepness of `--calibrate`. SigLIP2 scores pairs as
+/// `sigmoid(scale * cosine + bias)` with a learned scale of ~112 and bias of
+/// ~-16. The full scale pushes nearly every result to 0% or 100%, so a quarter
+/// of it is used, spreading the usual 0.05-0.25 cosine band over ~5-95%.
+pub const SCORE_CALIBRATION_TEMPERATURE: f32 = 28.0;
+/// Raw cosine mapped to 50% by `--calibrate` (SigLIP2's -bias / scale)
+pub const SCORE_CALIBRATION_MIDPOINT: f32 = 0.145;
+
+// === ANN (HNSW) Defaults ===
+pub const DEFAULT_ANN_M: usize = 16;
+pub const DEFAULT_ANN_EF_CONSTRUCTION: usize = 200;
+pub const DEFAULT_ANN_EF_SEARCH: usize = 64;
+pub const ANN_OVERSAMPLE: usize = 4; // Neighbors fetched per requested result before exact rescoring
+
+// === Daemon Defaults ===
+pub const DEFAULT_DAEMON_PORT: u16 = 7860;
+pub const DAEMON_QUERY_CACHE_SIZE: usize = 64; // Text embeddings kept for repeated queries
+pub const DAEMON_ENGINE_CACHE_SIZE: usize = 4; // Searched directory sets kept loaded between requests
+
+// === Serve Defaults ===
+pub const DEFAULT_SERVE_ADDR: &str = "127.0.0.1:7861";
+pub const SERVE_PROTOCOL_VERSION: u32 = 1; // Bump on incompatible changes to docs/PROTOCOL.md
+pub const SERVE_WORKERS: usize = 4; // Connections handled at once
+pub const SERVE_TIMEOUT_SECS: u64 = 30; // Idle or stalled connections are closed after this
+pub const SERVE_MAX_REQUEST_BYTES: u64 = 256 * 1024 * 1024; // Longest request line (piped images are inlined)
+
+// === Watch Defaults ===
+pub const WATCH_INDEX_FLUSH_SECS: u64 = 30; // Idle time before pending index updates are written
 
 // === Cluster Defaults ===
 pub const DEFAULT_MIN_CLUSTER_SIZE: usize = 5;
 pub const DEFAULT_COHESION_THRESHOLD: f32 = 0.70;
 pub const DEFAULT_UMAP_NEIGHBORS: usize = 50;
 pub const DEFAULT_UMAP_COMPONENTS: usize = 64;
+pub const CLUSTER_PRECOMPUTED_LIMIT: usize = 20_000; // Max points for a precomputed (n²) distance matrix
 pub const DEFAULT_CLUSTER_PREVIEW: i32 = 5;
+pub const CLUSTER_LABEL_COUNT: usize = 3; // Vocabulary terms shown per cluster with --labels
+pub const DEFAULT_JOIN_SIMILARITY: f32 = 0.75; // Centroid similarity a new file needs to join a cluster with --incremental
+pub const CONTACT_SHEET_THUMB_SIZE: u32 = 256; // Max edge of each tile in --thumbnails sheets
+pub const CONTACT_SHEET_GAP: u32 = 4; // Padding between tiles in pixels
 
 // === Outliers Defaults ===
 pub const DEFAULT_OUTLIER_PREVIEW: usize = 10;
 pub const DEFAULT_OUTLIER_NEIGHBORS: usize = 5;
 
+// === Dedup Defaults ===
+pub const DEFAULT_DEDUP_THRESHOLD: f32 = 0.95;
+pub const DEDUP_ANN_MIN_ITEMS: usize = 2000; // Switch from pairwise to ANN comparison above this
+pub const DEDUP_ANN_NEIGHBORS: usize = 16; // Neighbors checked per item in ANN mode
+
+// === Matrix Defaults ===
+pub const MATRIX_FULL_WARN_ITEMS: usize = 5000; // Suggest --top-k above this many files
+
+// === Bench Defaults ===
+pub const DEFAULT_BENCH_IMAGES: usize = 32;
+pub const DEFAULT_BENCH_TEXTS: usize = 64;
+
+/// Defaults from a [`DIR_CONFIG_FILE`] (TOML) at the root of a directory.
+///
+/// Flags given on the command line win over these values, which win over the
+/// built-in defaults. See [`crate::cli::Cli::apply_dir_config`].
+#[derive(Debug, Default, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct DirConfig {
+	pub recursive: Option<bool>,
+	/// Skip images whose shortest side is smaller (`--min-resolution`)
+	pub min_resolution: Option<u32>,
+	/// Skip files larger than this many MB (`--max-size`)
+	pub max_size_mb: Option<u64>,
+	/// Gitignore-style globs skipped by scan (`--exclude-dir`)
+	pub exclude: Vec<String>,
+	pub provider: Option<crate::cli::Provider>,
+	pub max_frames: Option<usize>,
+}
+
+impl DirConfig {
+	/// Read `dir/.scoutconfig`, or `None` if the directory has none
+	pub fn load(dir: &Path) -> anyhow::Result<Option<Self>> {
+		let path = dir.join(DIR_CONFIG_FILE);
+		let text = match std::fs::read_to_string(&path) {
+			Ok(text) => text,
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+			Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+		};
+
+		let config: Self =
+			toml::from_str(&text).with_context(|| format!("Invalid {}", path.display()))?;
+		if config.max_frames == Some(0) {
+			anyhow::bail!("Invalid {}: max_frames must be at least 1", path.display());
+		}
+		Ok(Some(config))
+	}
+}
+
 pub fn set_model_dir(path: PathBuf) {
 	let _ = CUSTOM_MODEL_DIR.set(path);
 }
 
+/// Set the `--preprocess` mode used by [`crate::models::Models::new`]
+pub fn set_preprocess_mode(mode: crate::models::PreprocessMode) {
+	let _ = CUSTOM_PREPROCESS_MODE.set(mode);
+}
+
+/// Preprocessing mode from [`set_preprocess_mode`], or the default
+pub fn preprocess_mode() -> crate::models::PreprocessMode {
+	CUSTOM_PREPROCESS_MODE
+		.get()
+		.copied()
+		.unwrap_or(DEFAULT_PREPROCESS_MODE)
+}
+
+/// Set the `--auto-orient` flag used by [`crate::models::Models::new`]
+pub fn set_auto_orient(enabled: bool) {
+	let _ = CUSTOM_AUTO_ORIENT.set(enabled);
+}
+
+/// EXIF auto-orientation from [`set_auto_orient`], or the default
+pub fn auto_orient() -> bool {
+	CUSTOM_AUTO_ORIENT
+		.get()
+		.copied()
+		.unwrap_or(DEFAULT_AUTO_ORIENT)
+}
+
 /// Get models directory (same dir as executable, or SCOUT_MODELS_DIR env var)
 pub fn models_dir() -> Option<PathBuf> {
 	// Check custom model dir
@@ -97,6 +251,21 @@ pub fn models_dir() -> Option<PathBuf> {
 	None
 }
 
+/// Where `scout models download` puts the model files: the custom model
+/// dir, then `SCOUT_MODELS_DIR`, then `models/` next to the executable.
+/// Unlike [`models_dir`], the directory doesn't have to exist yet.
+pub fn models_download_dir() -> Option<PathBuf> {
+	if let Some(custom) = CUSTOM_MODEL_DIR.get() {
+		return Some(custom.clone());
+	}
+	if let Ok(env_path) = std::env::var("SCOUT_MODELS_DIR") {
+		return Some(PathBuf::from(env_path));
+	}
+	std::env::current_exe()
+		.ok()
+		.and_then(|exe| exe.parent().map(|dir| dir.join("models")))
+}
+
 pub fn get_vision_model_path() -> Option<PathBuf> {
 	models_dir().map(|d| d.join(VISION_MODEL))
 }
@@ -108,3 +277,41 @@ pub fn get_text_model_path() -> Option<PathBuf> {
 pub fn get_tokenizer_path() -> Option<PathBuf> {
 	models_dir().map(|d| d.join(TOKENIZER))
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_dir_config_loads_sample() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		assert!(DirConfig::load(dir).unwrap().is_none());
+
+		std::fs::write(
+			dir.join(DIR_CONFIG_FILE),
+			r#"
+			# Photo library defaults
+			recursive = true
+			min_resolution = 512
+			max_size_mb = 40
+			exclude = ["**/thumbnails", "raw/"]
+			provider = "cpu"
+			max_frames = 30
+			"#,
+		)
+		.unwrap();
+		let config = DirConfig::load(dir).unwrap().unwrap();
+		assert_eq!(config.recursive, Some(true));
+		assert_eq!(config.min_resolution, Some(512));
+		assert_eq!(config.max_size_mb, Some(40));
+		assert_eq!(config.exclude, vec!["**/thumbnails", "raw/"]);
+		assert!(matches!(config.provider, Some(crate::cli::Provider::Cpu)));
+		assert_eq!(config.max_frames, Some(30));
+
+		// Typos are reported instead of silently ignored
+		std::fs::write(dir.join(DIR_CONFIG_FILE), "recursiv = true").unwrap();
+		let error = DirConfig::load(dir).unwrap_err();
+		assert!(format!("{:#}", error).contains("recursiv"));
+	}
+}
@@ -0,0 +1,357 @@
+//! # Approximate Nearest Neighbors
+//!
+//! HNSW (Hierarchical Navigable Small World) graph over normalized embeddings.
+//! Trades a small amount of recall for sub-linear cosine search on large libraries.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashSet};
+use std::path::Path;
+use xxhash_rust::xxh3::xxh3_64;
+
+use super::Embedding;
+use crate::config::{DEFAULT_ANN_EF_CONSTRUCTION, DEFAULT_ANN_EF_SEARCH, DEFAULT_ANN_M};
+
+/// Recall/speed tradeoff parameters for [`AnnIndex`]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct AnnParams {
+	/// Links per node on upper layers (layer 0 uses `2 * m`)
+	pub m: usize,
+	/// Candidate list size while inserting. Higher = better graph, slower build
+	pub ef_construction: usize,
+	/// Candidate list size while searching. Higher = better recall, slower queries
+	pub ef_search: usize,
+}
+
+impl Default for AnnParams {
+	fn default() -> Self {
+		Self {
+			m: DEFAULT_ANN_M,
+			ef_construction: DEFAULT_ANN_EF_CONSTRUCTION,
+			ef_search: DEFAULT_ANN_EF_SEARCH,
+		}
+	}
+}
+
+/// HNSW index using cosine distance
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnnIndex {
+	params: AnnParams,
+	vectors: Vec<Embedding>,
+	/// `links[node][layer]` lists the neighbor ids of `node` on `layer`
+	links: Vec<Vec<Vec<u32>>>,
+	entry: Option<u32>,
+	max_layer: usize,
+}
+
+/// Search candidate ordered by distance
+#[derive(Clone, Copy, PartialEq)]
+struct Candidate {
+	distance: f32,
+	id: u32,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+	fn cmp(&self, other: &Self) -> Ordering {
+		self.distance
+			.total_cmp(&other.distance)
+			.then(self.id.cmp(&other.id))
+	}
+}
+
+impl PartialOrd for Candidate {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl AnnIndex {
+	pub fn new(params: AnnParams) -> Self {
+		Self {
+			params: AnnParams {
+				m: params.m.max(2),
+				ef_construction: params.ef_construction.max(1),
+				ef_search: params.ef_search.max(1),
+			},
+			vectors: Vec::new(),
+			links: Vec::new(),
+			entry: None,
+			max_layer: 0,
+		}
+	}
+
+	/// Build an index from a set of embeddings (ids follow slice order)
+	pub fn build(embeddings: &[Embedding], params: AnnParams) -> Self {
+		let mut index = Self::new(params);
+		for embedding in embeddings {
+			index.add(embedding.clone());
+		}
+		index
+	}
+
+	pub fn len(&self) -> usize {
+		self.vectors.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.vectors.is_empty()
+	}
+
+	pub fn params(&self) -> AnnParams {
+		self.params
+	}
+
+	/// Override the query-time candidate list size
+	pub fn set_ef_search(&mut self, ef_search: usize) {
+		self.params.ef_search = ef_search.max(1);
+	}
+
+	/// Insert an embedding and return its id
+	pub fn add(&mut self, embedding: Embedding) -> usize {
+		let id = self.vectors.len() as u32;
+		let level = self.random_level(id);
+
+		self.vectors.push(embedding);
+		self.links.push(vec![Vec::new(); level + 1]);
+
+		let Some(mut entry) = self.entry else {
+			self.entry = Some(id);
+			self.max_layer = level;
+			return id as usize;
+		};
+
+		let query = self.vectors[id as usize].clone();
+
+		// Greedy descent through layers above the new node's level
+		for layer in (level + 1..=self.max_layer).rev() {
+			entry = self.search_layer(&query, &[entry], 1, layer)[0].id;
+		}
+
+		let mut entry_points = vec![entry];
+		for layer in (0..=level.min(self.max_layer)).rev() {
+			let candidates =
+				self.search_layer(&query, &entry_points, self.params.ef_construction, layer);
+			let neighbors = self.select_neighbors(&candidates, self.params.m);
+
+			self.links[id as usize][layer] = neighbors.clone();
+
+			let max_links = self.max_links(layer);
+			for neighbor in neighbors {
+				let links = &mut self.links[neighbor as usize][layer];
+				links.push(id);
+				if links.len() > max_links {
+					self.prune(neighbor, layer, max_links);
+				}
+			}
+
+			entry_points = candidates.iter().map(|c| c.id).collect();
+		}
+
+		if level > self.max_layer {
+			self.entry = Some(id);
+			self.max_layer = level;
+		}
+
+		id as usize
+	}
+
+	/// Find the `k` nearest embeddings to `query`.
+	///
+	/// Returns `(id, cosine similarity)` pairs sorted by descending similarity.
+	pub fn search(&self, query: &Embedding, k: usize) -> Vec<(usize, f32)> {
+		let Some(mut entry) = self.entry else {
+			return Vec::new();
+		};
+
+		for layer in (1..=self.max_layer).rev() {
+			entry = self.search_layer(query, &[entry], 1, layer)[0].id;
+		}
+
+		let ef = self.params.ef_search.max(k);
+		self.search_layer(query, &[entry], ef, 0)
+			.into_iter()
+			.take(k)
+			.map(|c| (c.id as usize, 1.0 - c.distance))
+			.collect()
+	}
+
+	pub fn save(&self, path: &Path) -> Result<()> {
+		let bytes = rmp_serde::to_vec(self).context("Failed to serialize ANN index")?;
+		std::fs::write(path, bytes).context("Failed to write ANN index")
+	}
+
+	pub fn load(path: &Path) -> Result<Self> {
+		let bytes = std::fs::read(path).context("Failed to read ANN index")?;
+		rmp_serde::from_slice(&bytes).context("Failed to deserialize ANN index")
+	}
+
+	fn distance(&self, query: &Embedding, id: u32) -> f32 {
+		1.0 - query.similarity(&self.vectors[id as usize])
+	}
+
+	fn max_links(&self, layer: usize) -> usize {
+		if layer == 0 {
+			self.params.m * 2
+		} else {
+			self.params.m
+		}
+	}
+
+	/// Deterministic exponentially-distributed level derived from the node id
+	fn random_level(&self, id: u32) -> usize {
+		let uniform = (xxh3_64(&id.to_le_bytes()) >> 11) as f64 / (1u64 << 53) as f64;
+		let level_mult = 1.0 / (self.params.m as f64).ln();
+		(-(1.0 - uniform).ln() * level_mult).floor() as usize
+	}
+
+	/// Best-first search on a single layer, returning up to `ef` candidates sorted by distance
+	fn search_layer(
+		&self,
+		query: &Embedding,
+		entry_points: &[u32],
+		ef: usize,
+		layer: usize,
+	) -> Vec<Candidate> {
+		let mut visited: HashSet<u32> = entry_points.iter().copied().collect();
+		// Min-heap of nodes to expand, max-heap of current best results
+		let mut to_visit: BinaryHeap<std::cmp::Reverse<Candidate>> = BinaryHeap::new();
+		let mut best: BinaryHeap<Candidate> = BinaryHeap::new();
+
+		for &id in entry_points {
+			let candidate = Candidate {
+				distance: self.distance(query, id),
+				id,
+			};
+			to_visit.push(std::cmp::Reverse(candidate));
+			best.push(candidate);
+		}
+
+		while let Some(std::cmp::Reverse(current)) = to_visit.pop() {
+			let worst = best.peek().map(|c| c.distance).unwrap_or(f32::INFINITY);
+			if current.distance > worst && best.len() >= ef {
+				break;
+			}
+
+			let Some(neighbors) = self.links[current.id as usize].get(layer) else {
+				continue;
+			};
+
+			for &neighbor in neighbors {
+				if !visited.insert(neighbor) {
+					continue;
+				}
+
+				let distance = self.distance(query, neighbor);
+				let worst = best.peek().map(|c| c.distance).unwrap_or(f32::INFINITY);
+				if best.len() < ef || distance < worst {
+					let candidate = Candidate {
+						distance,
+						id: neighbor,
+					};
+					to_visit.push(std::cmp::Reverse(candidate));
+					best.push(candidate);
+					if best.len() > ef {
+						best.pop();
+					}
+				}
+			}
+		}
+
+		best.into_sorted_vec()
+	}
+
+	/// Neighbor selection heuristic: prefer candidates that are not already
+	/// covered by a closer selected neighbor, keeping the graph well connected
+	fn select_neighbors(&self, candidates: &[Candidate], m: usize) -> Vec<u32> {
+		let mut selected: Vec<u32> = Vec::with_capacity(m);
+		let mut skipped: Vec<u32> = Vec::new();
+
+		for candidate in candidates {
+			if selected.len() >= m {
+				break;
+			}
+			let vector = &self.vectors[candidate.id as usize];
+			let dominated = selected
+				.iter()
+				.any(|&s| self.distance(vector, s) < candidate.distance);
+			if dominated {
+				skipped.push(candidate.id);
+			} else {
+				selected.push(candidate.id);
+			}
+		}
+
+		// Fill remaining slots with the closest skipped candidates
+		for id in skipped {
+			if selected.len() >= m {
+				break;
+			}
+			selected.push(id);
+		}
+
+		selected
+	}
+
+	fn prune(&mut self, node: u32, layer: usize, max_links: usize) {
+		let vector = self.vectors[node as usize].clone();
+		let mut candidates: Vec<Candidate> = self.links[node as usize][layer]
+			.iter()
+			.map(|&id| Candidate {
+				distance: self.distance(&vector, id),
+				id,
+			})
+			.collect();
+		candidates.sort();
+		self.links[node as usize][layer] = self.select_neighbors(&candidates, max_links);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use rand::{Rng, SeedableRng};
+
+	#[test]
+	fn test_recall_against_brute_force() {
+		let mut rng = rand::rngs::StdRng::seed_from_u64(42);
+		let random_embedding = |rng: &mut rand::rngs::StdRng| {
+			Embedding::new((0..32).map(|_| rng.random_range(-1.0f32..1.0)).collect())
+		};
+
+		let embeddings: Vec<Embedding> = (0..5000).map(|_| random_embedding(&mut rng)).collect();
+		// A smaller construction beam keeps the test fast in debug builds
+		let params = AnnParams {
+			ef_construction: 64,
+			..AnnParams::default()
+		};
+		let index = AnnIndex::build(&embeddings, params);
+
+		let k = 10;
+		let queries = 50;
+		let mut hits = 0;
+
+		for _ in 0..queries {
+			let query = random_embedding(&mut rng);
+
+			let mut exact: Vec<(usize, f32)> = embeddings
+				.iter()
+				.enumerate()
+				.map(|(i, e)| (i, query.similarity(e)))
+				.collect();
+			exact.sort_by(|a, b| b.1.total_cmp(&a.1));
+			let truth: HashSet<usize> = exact.iter().take(k).map(|(i, _)| *i).collect();
+
+			hits += index
+				.search(&query, k)
+				.iter()
+				.filter(|(i, _)| truth.contains(i))
+				.count();
+		}
+
+		let recall = hits as f32 / (queries * k) as f32;
+		assert!(recall > 0.95, "recall {:.3} below 0.95", recall);
+	}
+}
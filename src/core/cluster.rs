@@ -6,6 +6,8 @@
 use serde::{Deserialize, Serialize};
 use xxhash_rust::xxh3::xxh3_64;
 
+use super::Metric;
+
 /// Represents a single cluster of visually similar media
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Cluster {
@@ -47,6 +49,12 @@ pub struct ClusterParams {
 	pub use_umap: bool,
 	pub umap_neighbors: usize,
 	pub umap_components: usize,
+	/// Distance HDBSCAN and cohesion use (absent in older caches)
+	#[serde(default)]
+	pub metric: Metric,
+	/// Seed for UMAP's initial layout, random when absent
+	#[serde(default)]
+	pub seed: Option<u64>,
 }
 
 impl ClusterDatabase {
@@ -3,13 +3,78 @@
 //! Normalized vector representations for semantic similarity.
 //! Supports cosine similarity, blending, and distance metrics.
 
+use anyhow::{bail, Result};
+use half::f16;
+use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
+/// Format byte for [`Embedding::serialize_compact`]: little-endian f16 values
+pub const COMPACT_F16: u8 = 1;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Embedding(pub Vec<f32>);
 
+/// int8 scalar-quantized embedding (~4x smaller on disk)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuantizedEmbedding {
+	/// Multiplier mapping int8 values back to floats
+	pub scale: f32,
+	pub data: Vec<i8>,
+}
+
+impl QuantizedEmbedding {
+	/// Restore a normalized float embedding
+	pub fn dequantize(&self) -> Embedding {
+		Embedding::new(self.data.iter().map(|&v| v as f32 * self.scale).collect())
+	}
+}
+
+/// Vector comparison metric
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+pub enum Metric {
+	/// Angle between vectors, ignoring magnitude
+	#[default]
+	Cosine,
+	/// Straight-line (L2) distance
+	Euclidean,
+	/// Raw dot product, sensitive to magnitude
+	Dot,
+}
+
+impl Metric {
+	/// Distance under this metric (lower = closer)
+	pub fn distance(self, a: &Embedding, b: &Embedding) -> f32 {
+		match self {
+			Metric::Cosine => 1.0 - a.cosine(b),
+			Metric::Euclidean => a.distance(b),
+			Metric::Dot => 1.0 - a.dot(b),
+		}
+	}
+}
+
+/// An embedding that is empty, all zeros or holds NaN or infinite values.
+///
+/// Models produce these for corrupt input. Such a vector scores the same
+/// against everything, so it must not end up in a sidecar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DegenerateEmbedding;
+
+impl std::fmt::Display for DegenerateEmbedding {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+		write!(
+			f,
+			"Model returned a degenerate embedding (all zeros or NaN)"
+		)
+	}
+}
+
+impl std::error::Error for DegenerateEmbedding {}
+
 impl Embedding {
-	/// Create normalized embedding from raw data
+	/// Create normalized embedding from raw data.
+	///
+	/// Zero and NaN vectors can't be normalized and are kept as they are;
+	/// check [`Embedding::is_degenerate`] before storing or comparing them.
 	pub fn new(data: Vec<f32>) -> Self {
 		Self(normalize(&data))
 	}
@@ -24,15 +89,139 @@ impl Embedding {
 		&self.0
 	}
 
+	/// Number of components in the vector
+	pub fn dimension(&self) -> usize {
+		self.0.len()
+	}
+
+	/// Whether the vector is empty, all zeros, or holds NaN or infinite values
+	pub fn is_degenerate(&self) -> bool {
+		self.0.iter().any(|v| !v.is_finite()) || self.0.iter().all(|&v| v == 0.0)
+	}
+
+	/// The embedding itself, or a [`DegenerateEmbedding`] error
+	pub fn non_degenerate(self) -> Result<Self> {
+		if self.is_degenerate() {
+			return Err(DegenerateEmbedding.into());
+		}
+		Ok(self)
+	}
+
 	/// Cosine similarity [0.0, 1.0]
+	///
+	/// Embeddings of different sizes come from different models and can't be
+	/// compared; that case scores 0.0 and warns once. Use
+	/// [`Embedding::try_similarity`] where a mismatch means a corrupt index.
 	pub fn similarity(&self, other: &Self) -> f32 {
-		self.0.iter().zip(other.0.iter()).map(|(a, b)| a * b).sum()
+		if self.dimension() != other.dimension() {
+			static WARNED: std::sync::Once = std::sync::Once::new();
+			WARNED.call_once(|| {
+				crate::ui::warn(&format!(
+					"Comparing {}D and {}D embeddings; scoring them as unrelated",
+					self.dimension(),
+					other.dimension()
+				))
+			});
+			return 0.0;
+		}
+		dot_product(&self.0, &other.0)
+	}
+
+	/// Cosine similarity that fails instead of guessing on a dimension mismatch
+	pub fn try_similarity(&self, other: &Self) -> Result<f32> {
+		if self.dimension() != other.dimension() {
+			bail!(
+				"Embedding dimension mismatch: {}D vs {}D",
+				self.dimension(),
+				other.dimension()
+			);
+		}
+		Ok(dot_product(&self.0, &other.0))
+	}
+
+	/// Map a raw similarity onto `0.0..1.0` with a sigmoid centered on
+	/// [`SCORE_CALIBRATION_MIDPOINT`](crate::config::SCORE_CALIBRATION_MIDPOINT).
+	///
+	/// Higher `temperature` spreads scores near the midpoint further apart. The
+	/// mapping is strictly increasing, so it never reorders results. Meant for
+	/// display and threshold comparison only; stored scores stay raw.
+	pub fn calibrate_score(raw: f32, temperature: f32) -> f32 {
+		let logit = temperature * (raw - crate::config::SCORE_CALIBRATION_MIDPOINT);
+		1.0 / (1.0 + (-logit).exp())
 	}
 
-	/// Cosine distance for clustering algorithms [0.0, 2.0]
-	#[allow(dead_code)]
+	/// Euclidean (L2) distance
 	pub fn distance(&self, other: &Self) -> f32 {
-		1.0 - self.similarity(other)
+		self.0
+			.iter()
+			.zip(other.0.iter())
+			.map(|(a, b)| (a - b) * (a - b))
+			.sum::<f32>()
+			.sqrt()
+	}
+
+	/// Raw dot product (equals cosine similarity for normalized vectors)
+	pub fn dot(&self, other: &Self) -> f32 {
+		dot_product(&self.0, &other.0)
+	}
+
+	/// Cosine similarity that does not assume normalized inputs
+	pub fn cosine(&self, other: &Self) -> f32 {
+		let norms = self.dot(self).sqrt() * other.dot(other).sqrt();
+		if norms > 0.0 {
+			self.dot(other) / norms
+		} else {
+			0.0
+		}
+	}
+
+	/// Similarity under the given metric (higher = more similar).
+	///
+	/// Euclidean distance is mapped to `1 / (1 + d)` so it stays in (0.0, 1.0].
+	pub fn similarity_with(&self, other: &Self, metric: Metric) -> f32 {
+		match metric {
+			Metric::Cosine => self.cosine(other),
+			Metric::Euclidean => 1.0 / (1.0 + self.distance(other)),
+			Metric::Dot => self.dot(other),
+		}
+	}
+
+	/// Symmetric int8 quantization scaled to the largest absolute component
+	pub fn quantize(&self) -> QuantizedEmbedding {
+		let max = self.0.iter().fold(0.0f32, |m, v| m.max(v.abs()));
+		let scale = if max > 0.0 { max / 127.0 } else { 1.0 };
+		QuantizedEmbedding {
+			scale,
+			data: self
+				.0
+				.iter()
+				.map(|v| (v / scale).round().clamp(-127.0, 127.0) as i8)
+				.collect(),
+		}
+	}
+
+	/// Lossy half-precision encoding (~2x smaller than f32), led by a format byte
+	pub fn serialize_compact(&self) -> Vec<u8> {
+		let mut bytes = Vec::with_capacity(1 + self.0.len() * 2);
+		bytes.push(COMPACT_F16);
+		for v in &self.0 {
+			bytes.extend_from_slice(&f16::from_f32(*v).to_le_bytes());
+		}
+		bytes
+	}
+
+	/// Restore an embedding written by [`Embedding::serialize_compact`]
+	pub fn deserialize_compact(bytes: &[u8]) -> Result<Self> {
+		match bytes.split_first() {
+			Some((&COMPACT_F16, data)) if data.len() % 2 == 0 => Ok(Self(
+				data.chunks_exact(2)
+					.map(|pair| f16::from_le_bytes([pair[0], pair[1]]).to_f32())
+					.collect(),
+			)),
+			Some((&COMPACT_F16, _)) => bail!("Truncated f16 embedding"),
+			Some((format, _)) => bail!("Unknown embedding format {}", format),
+			None => bail!("Empty embedding data"),
+		}
 	}
 
 	/// Re-normalize this embedding (returns new instance)
@@ -40,7 +229,51 @@ impl Embedding {
 		Self(normalize(&self.0))
 	}
 
-	/// Weighted combination of two embeddings
+	/// Re-normalize an owned embedding, e.g. the result of `a - b + c`.
+	///
+	/// The arithmetic operators leave their result unnormalized so that
+	/// `(a + b) - b == a` holds; call this before comparing the result.
+	pub fn normalized(self) -> Self {
+		Self(normalize(&self.0))
+	}
+
+	/// Normalized mean of several embeddings, or `None` if there are none
+	pub fn mean(embeddings: &[Self]) -> Option<Self> {
+		let first = embeddings.first()?;
+		let mut sum = vec![0.0f32; first.0.len()];
+		for embedding in embeddings {
+			for (acc, v) in sum.iter_mut().zip(embedding.0.iter()) {
+				*acc += v;
+			}
+		}
+		Some(Self::new(sum))
+	}
+
+	/// Similarity of every pair: `matrix[i][j]` compares `a[i]` with `b[j]`.
+	///
+	/// Rows are computed in parallel.
+	pub fn similarity_matrix(a: &[Self], b: &[Self]) -> Vec<Vec<f32>> {
+		a.par_iter()
+			.map(|x| b.iter().map(|y| x.similarity(y)).collect())
+			.collect()
+	}
+
+	/// Normalized weighted sum `weight_a * a + (1 - weight_a) * b`.
+	///
+	/// `1.0` returns `a`, `0.0` returns `b`. Weights outside `0.0..=1.0` are
+	/// allowed and extrapolate: `1.5` pushes past `a`, away from `b`. Used for
+	/// hybrid text and image queries, e.g. `blend(&text, &image, 0.3)`.
+	/// Both embeddings must have the same dimension; extra values are ignored.
+	///
+	/// # Example
+	/// ```
+	/// use scout::Embedding;
+	///
+	/// let text = Embedding::new(vec![1.0, 0.0]);
+	/// let image = Embedding::new(vec![0.0, 1.0]);
+	/// let hybrid = Embedding::blend(&text, &image, 0.5);
+	/// assert!((hybrid.similarity(&text) - hybrid.similarity(&image)).abs() < 1e-6);
+	/// ```
 	pub fn blend(a: &Self, b: &Self, weight_a: f32) -> Self {
 		let weight_b = 1.0 - weight_a;
 		let combined: Vec<f32> =
@@ -52,6 +285,121 @@ impl Embedding {
 	}
 }
 
+/// Componentwise sum, unnormalized. Panics if the dimensions differ.
+///
+/// Together with `-` and scalar `*` this supports CLIP-style analogies:
+///
+/// ```
+/// use scout::Embedding;
+///
+/// let sunset = Embedding::new(vec![1.0, 1.0, 0.0]);
+/// let beach = Embedding::new(vec![0.0, 1.0, 0.0]);
+/// let mountain = Embedding::new(vec![0.0, 0.0, 1.0]);
+/// let query = (&sunset - &beach + &mountain).normalized();
+/// assert!(query.similarity(&mountain) > query.similarity(&beach));
+/// ```
+impl std::ops::Add<&Embedding> for &Embedding {
+	type Output = Embedding;
+
+	fn add(self, other: &Embedding) -> Embedding {
+		Embedding(zip_with(&self.0, &other.0, |a, b| a + b))
+	}
+}
+
+/// Componentwise difference, unnormalized. Panics if the dimensions differ.
+impl std::ops::Sub<&Embedding> for &Embedding {
+	type Output = Embedding;
+
+	fn sub(self, other: &Embedding) -> Embedding {
+		Embedding(zip_with(&self.0, &other.0, |a, b| a - b))
+	}
+}
+
+/// Every component scaled by a factor, unnormalized
+impl std::ops::Mul<f32> for &Embedding {
+	type Output = Embedding;
+
+	fn mul(self, factor: f32) -> Embedding {
+		Embedding(self.0.iter().map(|v| v * factor).collect())
+	}
+}
+
+impl std::ops::Add<&Embedding> for Embedding {
+	type Output = Embedding;
+
+	fn add(self, other: &Embedding) -> Embedding {
+		&self + other
+	}
+}
+
+impl std::ops::Sub<&Embedding> for Embedding {
+	type Output = Embedding;
+
+	fn sub(self, other: &Embedding) -> Embedding {
+		&self - other
+	}
+}
+
+impl std::ops::Mul<f32> for Embedding {
+	type Output = Embedding;
+
+	fn mul(self, factor: f32) -> Embedding {
+		&self * factor
+	}
+}
+
+fn zip_with(a: &[f32], b: &[f32], op: impl Fn(f32, f32) -> f32) -> Vec<f32> {
+	assert_eq!(
+		a.len(),
+		b.len(),
+		"Embedding arithmetic needs equal dimensions"
+	);
+	a.iter().zip(b).map(|(&x, &y)| op(x, y)).collect()
+}
+
+/// Number of lanes accumulated in parallel by [`dot_lanes`]
+#[cfg(feature = "simd")]
+const LANES: usize = 8;
+
+#[cfg(feature = "simd")]
+fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+	dot_lanes(a, b)
+}
+
+#[cfg(not(feature = "simd"))]
+fn dot_product(a: &[f32], b: &[f32]) -> f32 {
+	dot_scalar(a, b)
+}
+
+/// Plain element-by-element dot product
+#[doc(hidden)]
+pub fn dot_scalar(a: &[f32], b: &[f32]) -> f32 {
+	a.iter().zip(b.iter()).map(|(x, y)| x * y).sum()
+}
+
+/// Dot product over 8 independent accumulators, which the compiler lowers to
+/// SIMD on any target with vector registers. The tail that doesn't fill a full
+/// set of lanes goes through [`dot_scalar`].
+#[cfg(feature = "simd")]
+#[doc(hidden)]
+pub fn dot_lanes(a: &[f32], b: &[f32]) -> f32 {
+	let len = a.len().min(b.len());
+	let (a, b) = (&a[..len], &b[..len]);
+
+	let mut acc = [0.0f32; LANES];
+	let chunks_a = a.chunks_exact(LANES);
+	let chunks_b = b.chunks_exact(LANES);
+	let tail = dot_scalar(chunks_a.remainder(), chunks_b.remainder());
+
+	for (ca, cb) in chunks_a.zip(chunks_b) {
+		for lane in 0..LANES {
+			acc[lane] += ca[lane] * cb[lane];
+		}
+	}
+
+	acc.iter().sum::<f32>() + tail
+}
+
 fn normalize(v: &[f32]) -> Vec<f32> {
 	let norm: f32 = v.iter().map(|x| x * x).sum::<f32>().sqrt();
 	if norm > 0.0 {
@@ -60,3 +408,245 @@ fn normalize(v: &[f32]) -> Vec<f32> {
 		v.to_vec()
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_arithmetic_operators() {
+		let a = Embedding::raw(vec![1.0, 2.0, 3.0]);
+		let b = Embedding::raw(vec![0.5, -1.0, 2.0]);
+
+		assert_eq!((&a + &b).0, [1.5, 1.0, 5.0]);
+		assert_eq!((&a - &b).0, [0.5, 3.0, 1.0]);
+		assert_eq!((&a * 2.0).0, [2.0, 4.0, 6.0]);
+		assert_eq!((a.clone() + &b - &b).0, a.0);
+		assert_eq!((&a - &a).0, [0.0; 3]);
+		assert_eq!((&a + &a).0, (a.clone() * 2.0).0);
+
+		let sum = (&a + &b).normalized();
+		let norm: f32 = sum.0.iter().map(|v| v * v).sum::<f32>().sqrt();
+		assert!((norm - 1.0).abs() < 1e-6);
+		assert!((sum.similarity(&(&a + &b).normalize()) - 1.0).abs() < 1e-6);
+	}
+
+	#[test]
+	#[should_panic(expected = "equal dimensions")]
+	fn test_arithmetic_dimension_mismatch_panics() {
+		let _ = &Embedding::new(vec![1.0, 0.0]) + &Embedding::new(vec![1.0, 0.0, 0.0]);
+	}
+
+	#[test]
+	fn test_dimension_mismatch() {
+		let a = Embedding::new(vec![1.0, 0.0, 0.0]);
+		let b = Embedding::new(vec![1.0, 0.0]);
+
+		assert_eq!(a.dimension(), 3);
+		assert_eq!(a.similarity(&b), 0.0);
+		assert!(a.try_similarity(&b).is_err());
+		assert!((a.try_similarity(&a).unwrap() - 1.0).abs() < 1e-6);
+	}
+
+	#[test]
+	fn test_zero_and_nan_vectors_are_degenerate() {
+		let zero = Embedding::new(vec![0.0; 4]);
+		assert_eq!(zero.as_slice(), &[0.0; 4]);
+		assert!(zero.is_degenerate());
+
+		let nan = Embedding::new(vec![0.5, f32::NAN, 0.1]);
+		assert!(nan.is_degenerate());
+		assert!(Embedding::new(vec![f32::INFINITY, 0.0]).is_degenerate());
+		assert!(Embedding::raw(Vec::new()).is_degenerate());
+
+		let error = zero.non_degenerate().unwrap_err();
+		assert!(error.downcast_ref::<DegenerateEmbedding>().is_some());
+
+		let valid = Embedding::new(vec![0.0, 3.0, 4.0]);
+		assert!(!valid.is_degenerate());
+		assert!(valid.non_degenerate().is_ok());
+	}
+
+	#[test]
+	fn test_metrics_on_unnormalized_vectors() {
+		let a = Embedding::raw(vec![3.0, 0.0]);
+		let b = Embedding::raw(vec![0.0, 4.0]);
+		let c = Embedding::raw(vec![6.0, 0.0]);
+
+		assert!((a.distance(&b) - 5.0).abs() < 1e-6);
+		assert!((a.dot(&c) - 18.0).abs() < 1e-6);
+		assert!(a.similarity_with(&b, Metric::Cosine).abs() < 1e-6);
+		assert!((a.similarity_with(&c, Metric::Cosine) - 1.0).abs() < 1e-6);
+		assert!((a.similarity_with(&c, Metric::Euclidean) - 0.25).abs() < 1e-6);
+	}
+
+	#[test]
+	fn test_quantization_error_is_bounded() {
+		use rand::{Rng, SeedableRng};
+
+		// SigLIP2-like: 1024 dims, roughly Gaussian components, normalized
+		let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+		let mut random_embedding = || {
+			Embedding::new(
+				(0..1024)
+					.map(|_| (0..4).map(|_| rng.random_range(-1.0f32..1.0)).sum())
+					.collect(),
+			)
+		};
+
+		for _ in 0..20 {
+			let a = random_embedding();
+			let b = random_embedding();
+			let restored = a.quantize().dequantize();
+
+			assert!((1.0 - a.similarity(&restored)) < 0.005);
+			assert!((a.similarity(&b) - restored.similarity(&b)).abs() < 0.005);
+		}
+	}
+
+	#[test]
+	fn test_euclidean_preserves_cosine_order_when_normalized() {
+		let query = Embedding::new(vec![1.0, 0.0, 0.0]);
+		let near = Embedding::new(vec![0.9, 0.1, 0.0]);
+		let far = Embedding::new(vec![0.1, 0.9, 0.2]);
+
+		for metric in [Metric::Cosine, Metric::Euclidean, Metric::Dot] {
+			assert!(
+				metric.distance(&query, &near) < metric.distance(&query, &far),
+				"{:?} ordered neighbors incorrectly",
+				metric
+			);
+		}
+	}
+
+	#[cfg(feature = "simd")]
+	#[test]
+	fn test_simd_matches_scalar() {
+		use rand::{Rng, SeedableRng};
+
+		let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+		// Cover full lanes only, a ragged tail, and vectors shorter than one lane
+		for len in [1024, 1021, 768, 5] {
+			let a: Vec<f32> = (0..len).map(|_| rng.random_range(-1.0f32..1.0)).collect();
+			let b: Vec<f32> = (0..len).map(|_| rng.random_range(-1.0f32..1.0)).collect();
+
+			let scalar = dot_scalar(&a, &b);
+			let lanes = dot_lanes(&a, &b);
+			assert!(
+				(scalar - lanes).abs() <= 1e-5 * scalar.abs().max(1.0),
+				"len {}: scalar {} vs simd {}",
+				len,
+				scalar,
+				lanes
+			);
+		}
+	}
+
+	#[test]
+	fn test_compact_similarity_error_is_tiny() {
+		use rand::{Rng, SeedableRng};
+
+		let mut rng = rand::rngs::StdRng::seed_from_u64(11);
+		let mut random_embedding = || {
+			Embedding::new(
+				(0..1024)
+					.map(|_| (0..4).map(|_| rng.random_range(-1.0f32..1.0)).sum())
+					.collect(),
+			)
+		};
+
+		for _ in 0..20 {
+			let a = random_embedding();
+			let b = random_embedding();
+			let bytes = a.serialize_compact();
+			assert_eq!(bytes.len(), 1 + 1024 * 2);
+
+			let restored = Embedding::deserialize_compact(&bytes).unwrap();
+			assert!((1.0 - a.similarity(&restored)) < 0.001);
+			assert!((a.similarity(&b) - restored.similarity(&b)).abs() < 0.001);
+		}
+
+		assert!(Embedding::deserialize_compact(&[]).is_err());
+		assert!(Embedding::deserialize_compact(&[COMPACT_F16, 0]).is_err());
+		assert!(Embedding::deserialize_compact(&[9, 0, 0]).is_err());
+	}
+
+	#[test]
+	fn test_calibration_preserves_order() {
+		let temperature = crate::config::SCORE_CALIBRATION_TEMPERATURE;
+		let raw: Vec<f32> = (-20..=60).map(|i| i as f32 * 0.01).collect();
+		let calibrated: Vec<f32> = raw
+			.iter()
+			.map(|&score| Embedding::calibrate_score(score, temperature))
+			.collect();
+
+		assert!(calibrated.windows(2).all(|pair| pair[0] < pair[1]));
+		assert!(calibrated.iter().all(|score| (0.0..=1.0).contains(score)));
+
+		let midpoint = Embedding::calibrate_score(crate::config::SCORE_CALIBRATION_MIDPOINT, 5.0);
+		assert!((midpoint - 0.5).abs() < 1e-6);
+		// The typical raw band is spread out instead of bunched below 25%
+		assert!(Embedding::calibrate_score(0.05, temperature) < 0.1);
+		assert!(Embedding::calibrate_score(0.25, temperature) > 0.9);
+	}
+
+	#[test]
+	fn test_similarity_matrix_layout() {
+		let a = [
+			Embedding::raw(vec![1.0, 0.0]),
+			Embedding::raw(vec![0.6, 0.8]),
+		];
+		let b = [
+			Embedding::raw(vec![1.0, 0.0]),
+			Embedding::raw(vec![0.0, 1.0]),
+			Embedding::raw(vec![0.8, 0.6]),
+		];
+
+		let expected = [[1.0, 0.0, 0.8], [0.6, 0.8, 0.96]];
+		let matrix = Embedding::similarity_matrix(&a, &b);
+		assert_eq!(matrix.len(), 2);
+		for (row, expected_row) in matrix.iter().zip(expected) {
+			assert_eq!(row.len(), 3);
+			for (value, want) in row.iter().zip(expected_row) {
+				assert!((value - want).abs() < 1e-6);
+			}
+		}
+	}
+
+	#[test]
+	fn test_similarity_matrix_is_symmetric_for_one_set() {
+		use rand::{Rng, SeedableRng};
+
+		let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+		let set: Vec<Embedding> = (0..64)
+			.map(|_| Embedding::new((0..256).map(|_| rng.random_range(-1.0f32..1.0)).collect()))
+			.collect();
+
+		let matrix = Embedding::similarity_matrix(&set, &set);
+		assert_eq!(matrix.len(), set.len());
+		for (i, row) in matrix.iter().enumerate() {
+			assert!((row[i] - 1.0).abs() < 1e-5);
+			for (j, value) in row.iter().enumerate() {
+				assert!((value - matrix[j][i]).abs() < 1e-6);
+			}
+		}
+	}
+
+	#[test]
+	fn test_blend_weights() {
+		let a = Embedding::new(vec![1.0, 0.2, 0.0]);
+		let b = Embedding::new(vec![0.0, 0.3, 1.0]);
+
+		assert!((Embedding::blend(&a, &b, 1.0).similarity(&a) - 1.0).abs() < 1e-6);
+		assert!((Embedding::blend(&a, &b, 0.0).similarity(&b) - 1.0).abs() < 1e-6);
+
+		let half = Embedding::blend(&a, &b, 0.5);
+		assert!((half.similarity(&a) - half.similarity(&b)).abs() < 1e-6);
+		assert!(half.similarity(&a) < 1.0);
+
+		// Extrapolating past `a` moves further away from `b`
+		let beyond = Embedding::blend(&a, &b, 1.5);
+		assert!(beyond.similarity(&b) < a.similarity(&b));
+		assert!((beyond.as_slice().iter().map(|v| v * v).sum::<f32>() - 1.0).abs() < 1e-5);
+	}
+}
@@ -1,33 +1,88 @@
 //! # Content-Based Hashing
 //!
-//! Fast file identification using xxHash on the first 64KB.
-//! Used to detect file changes and avoid reprocessing.
+//! Fast file identification using xxHash on the first 64KB, or optionally
+//! on the whole file. Used to detect file changes and avoid reprocessing.
 
+use serde::{Deserialize, Serialize};
 use std::fs::File;
 use std::io::Read;
 use std::path::Path;
-use xxhash_rust::xxh3::xxh3_64;
+use xxhash_rust::xxh3::{xxh3_64, Xxh3};
 
 const HASH_BUFFER_SIZE: usize = 65536; // 64KB
 
+/// How much of a file goes into its [`FileHash`]
+#[derive(
+	Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, clap::ValueEnum,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum HashMode {
+	/// 64-bit xxh3 of the first 64KB. Files sharing a 64KB prefix collide
+	#[default]
+	Fast,
+	/// 128-bit xxh3 of the entire file. Slower on large files, no prefix collisions
+	Full,
+}
+
+impl HashMode {
+	/// Mode that produced `hash`, told apart by its length
+	pub fn of(hash: &str) -> Self {
+		if hash.len() == 32 {
+			Self::Full
+		} else {
+			Self::Fast
+		}
+	}
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct FileHash(String);
 
 impl FileHash {
 	/// Compute hash from file's first 64KB
 	pub fn compute(path: &Path) -> std::io::Result<Self> {
+		Self::compute_with(path, HashMode::Fast)
+	}
+
+	/// Compute hash using the given [`HashMode`]
+	pub fn compute_with(path: &Path, mode: HashMode) -> std::io::Result<Self> {
 		let mut file = File::open(path)?;
 		let mut buffer = vec![0u8; HASH_BUFFER_SIZE];
-		let n = file.read(&mut buffer)?;
-		buffer.truncate(n);
 
-		let hash = xxh3_64(&buffer);
-		Ok(Self(format!("{:016x}", hash)))
+		match mode {
+			HashMode::Fast => {
+				let n = file.read(&mut buffer)?;
+				buffer.truncate(n);
+
+				let hash = xxh3_64(&buffer);
+				Ok(Self(format!("{:016x}", hash)))
+			}
+			HashMode::Full => {
+				let mut hasher = Xxh3::new();
+				loop {
+					let n = file.read(&mut buffer)?;
+					if n == 0 {
+						break;
+					}
+					hasher.update(&buffer[..n]);
+				}
+				Ok(Self(format!("{:032x}", hasher.digest128())))
+			}
+		}
+	}
+
+	/// Wrap a hash computed earlier, e.g. one stored in the scan journal
+	pub fn from_hex(hash: String) -> Self {
+		Self(hash)
 	}
 
 	pub fn as_str(&self) -> &str {
 		&self.0
 	}
+
+	pub fn mode(&self) -> HashMode {
+		HashMode::of(&self.0)
+	}
 }
 
 impl std::fmt::Display for FileHash {
@@ -35,3 +90,30 @@ impl std::fmt::Display for FileHash {
 		write!(f, "{}", self.0)
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_full_hash_separates_shared_prefix() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+
+		let prefix = vec![7u8; HASH_BUFFER_SIZE];
+		let a = dir.join("a.bin");
+		let b = dir.join("b.bin");
+		std::fs::write(&a, [prefix.as_slice(), b"tail one"].concat()).unwrap();
+		std::fs::write(&b, [prefix.as_slice(), b"tail two"].concat()).unwrap();
+
+		let fast_a = FileHash::compute(&a).unwrap();
+		let fast_b = FileHash::compute(&b).unwrap();
+		assert_eq!(fast_a, fast_b, "fast mode only sees the shared prefix");
+
+		let full_a = FileHash::compute_with(&a, HashMode::Full).unwrap();
+		let full_b = FileHash::compute_with(&b, HashMode::Full).unwrap();
+		assert_ne!(full_a, full_b);
+		assert_eq!(full_a.mode(), HashMode::Full);
+		assert_eq!(fast_a.mode(), HashMode::Fast);
+	}
+}
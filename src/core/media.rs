@@ -1,10 +1,22 @@
 //! # Media Type Detection
 //!
-//! Identify images and videos by file extension.
+//! Identify images and videos by file extension, or by their first bytes when
+//! the extension is missing or unknown, and read the capture date photos
+//! record in their EXIF metadata.
 
 use crate::config::{IMAGE_EXTENSIONS, VIDEO_EXTENSIONS};
+use anyhow::Result;
+use chrono::{NaiveDate, NaiveDateTime};
+use image::ImageDecoder;
+use std::io::Read;
 use std::path::Path;
 
+/// Bytes read from files whose extension doesn't say what they are
+const SNIFF_LEN: u64 = 32;
+
+/// ISO base media brands of still images, which share the `ftyp` box with MP4
+const IMAGE_BRANDS: &[&[u8; 4]] = &[b"avif", b"avis", b"heic", b"heix", b"mif1", b"msf1"];
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MediaType {
 	Image,
@@ -24,6 +36,304 @@ impl MediaType {
 			}
 		}
 
-		None
+		// Downloads often come without an extension, or with a made-up one
+		let mut header = Vec::new();
+		std::fs::File::open(path)
+			.ok()?
+			.take(SNIFF_LEN)
+			.read_to_end(&mut header)
+			.ok()?;
+		Self::sniff(&header)
+	}
+
+	/// Media type from a file's first bytes, for formats Scout can index
+	pub fn sniff(header: &[u8]) -> Option<Self> {
+		if let Ok(format) = image::guess_format(header) {
+			let indexed = format
+				.extensions_str()
+				.iter()
+				.any(|ext| IMAGE_EXTENSIONS.contains(ext));
+			return indexed.then_some(MediaType::Image);
+		}
+
+		let video = match header {
+			// MP4, MOV and M4V, unless the brand is a still image format
+			[_, _, _, _, b'f', b't', b'y', b'p', brand @ ..] => {
+				brand.len() >= 4 && !IMAGE_BRANDS.iter().any(|b| brand.starts_with(*b))
+			}
+			// Matroska and WebM
+			[0x1A, 0x45, 0xDF, 0xA3, ..] => true,
+			[b'R', b'I', b'F', b'F', _, _, _, _, b'A', b'V', b'I', b' ', ..] => true,
+			// ASF, the container of WMV
+			[0x30, 0x26, 0xB2, 0x75, 0x8E, 0x66, 0xCF, 0x11, ..] => true,
+			[b'F', b'L', b'V', 0x01, ..] => true,
+			// MPEG program stream
+			[0x00, 0x00, 0x01, 0xBA, ..] => true,
+			_ => false,
+		};
+		video.then_some(MediaType::Video)
+	}
+}
+
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+
+/// When a photo was taken, from its EXIF `DateTimeOriginal` tag or else the
+/// `DateTime` tag. Only reads the file's headers.
+pub fn exif_date(path: &Path) -> Option<NaiveDateTime> {
+	let mut decoder = image::ImageReader::open(path)
+		.ok()?
+		.with_guessed_format()
+		.ok()?
+		.into_decoder()
+		.ok()?;
+	let exif = decoder.exif_metadata().ok()??;
+	parse_exif_date(&exif)
+}
+
+/// Capture date from a raw EXIF block (a TIFF structure, optionally behind
+/// the `Exif\0\0` marker JPEG files put in front of it)
+pub fn parse_exif_date(exif: &[u8]) -> Option<NaiveDateTime> {
+	let tiff = Tiff::new(exif.strip_prefix(b"Exif\0\0").unwrap_or(exif))?;
+	let ifd0 = tiff.u32(4)? as usize;
+
+	let original = tiff
+		.entry(ifd0, TAG_EXIF_IFD)
+		.and_then(|entry| tiff.u32(entry + 8))
+		.and_then(|exif_ifd| tiff.entry(exif_ifd as usize, TAG_DATE_TIME_ORIGINAL))
+		.and_then(|entry| tiff.date(entry));
+	original.or_else(|| {
+		tiff.entry(ifd0, TAG_DATE_TIME)
+			.and_then(|entry| tiff.date(entry))
+	})
+}
+
+/// Parse a `--after-date`/`--before-date` value
+pub fn parse_date(text: &str) -> Result<NaiveDate> {
+	NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+		.map_err(|_| anyhow::anyhow!("Invalid date '{}', expected YYYY-MM-DD", text))
+}
+
+/// Whether a capture time falls within the inclusive date range. Without a
+/// range everything passes; with one, files without a known date don't.
+pub fn taken_within(
+	taken: Option<NaiveDateTime>,
+	after: Option<NaiveDate>,
+	before: Option<NaiveDate>,
+) -> bool {
+	if after.is_none() && before.is_none() {
+		return true;
+	}
+	let Some(date) = taken.map(|taken| taken.date()) else {
+		return false;
+	};
+	after.map_or(true, |after| date >= after) && before.map_or(true, |before| date <= before)
+}
+
+/// Just enough of a TIFF reader to find tags in image file directories
+struct Tiff<'a> {
+	data: &'a [u8],
+	little_endian: bool,
+}
+
+impl<'a> Tiff<'a> {
+	fn new(data: &'a [u8]) -> Option<Self> {
+		let little_endian = match data.get(..4)? {
+			b"II*\0" => true,
+			b"MM\0*" => false,
+			_ => return None,
+		};
+		Some(Self {
+			data,
+			little_endian,
+		})
+	}
+
+	fn u16(&self, at: usize) -> Option<u16> {
+		let bytes = self.data.get(at..at.checked_add(2)?)?.try_into().ok()?;
+		Some(if self.little_endian {
+			u16::from_le_bytes(bytes)
+		} else {
+			u16::from_be_bytes(bytes)
+		})
+	}
+
+	fn u32(&self, at: usize) -> Option<u32> {
+		let bytes = self.data.get(at..at.checked_add(4)?)?.try_into().ok()?;
+		Some(if self.little_endian {
+			u32::from_le_bytes(bytes)
+		} else {
+			u32::from_be_bytes(bytes)
+		})
+	}
+
+	/// Offset of the 12-byte entry for `tag` in the directory at `ifd`
+	fn entry(&self, ifd: usize, tag: u16) -> Option<usize> {
+		let count = self.u16(ifd)? as usize;
+		(0..count)
+			.map(|i| ifd + 2 + i * 12)
+			.find(|&entry| self.u16(entry) == Some(tag))
+	}
+
+	/// An ASCII entry holding `YYYY:MM:DD HH:MM:SS`. Cameras without a clock
+	/// write zeros, which don't parse and count as no date.
+	fn date(&self, entry: usize) -> Option<NaiveDateTime> {
+		let count = self.u32(entry + 4)? as usize;
+		// Values of up to 4 bytes are stored in the entry itself
+		let start = match count {
+			0..=4 => entry + 8,
+			_ => self.u32(entry + 8)? as usize,
+		};
+		let bytes = self.data.get(start..start.checked_add(count)?)?;
+		let text = std::str::from_utf8(bytes).ok()?.trim_end_matches('\0');
+		NaiveDateTime::parse_from_str(text.trim(), "%Y:%m:%d %H:%M:%S").ok()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Big-endian EXIF block with `DateTime` in IFD0 and `DateTimeOriginal`
+	/// in the Exif sub-directory, like most cameras write it
+	fn exif_block(original: &str, modified: &str) -> Vec<u8> {
+		let entry = |tag: u16, kind: u16, count: u32, value: u32| {
+			[
+				tag.to_be_bytes().as_slice(),
+				&kind.to_be_bytes(),
+				&count.to_be_bytes(),
+				&value.to_be_bytes(),
+			]
+			.concat()
+		};
+		// Header (8) + IFD0 with 2 entries (2 + 24 + 4) + Exif IFD with 1 (2 + 12 + 4)
+		let exif_ifd = 38;
+		let strings = 56;
+		[
+			b"MM\0*".as_slice(),
+			&8u32.to_be_bytes(),
+			&2u16.to_be_bytes(),
+			&entry(TAG_DATE_TIME, 2, 20, strings + 20),
+			&entry(TAG_EXIF_IFD, 4, 1, exif_ifd),
+			&0u32.to_be_bytes(),
+			&1u16.to_be_bytes(),
+			&entry(TAG_DATE_TIME_ORIGINAL, 2, 20, strings),
+			&0u32.to_be_bytes(),
+			original.as_bytes(),
+			b"\0",
+			modified.as_bytes(),
+			b"\0",
+		]
+		.concat()
+	}
+
+	fn at(date: &str) -> NaiveDateTime {
+		parse_date(date).unwrap().and_hms_opt(12, 0, 0).unwrap()
+	}
+
+	#[test]
+	fn test_exif_date_prefers_original() {
+		let exif = exif_block("2023:06:14 09:30:00", "2024:01:02 10:00:00");
+		let taken = parse_exif_date(&exif).unwrap();
+		assert_eq!(taken.to_string(), "2023-06-14 09:30:00");
+
+		// Unset camera clock: fall back to the file's DateTime
+		let exif = exif_block("0000:00:00 00:00:00", "2024:01:02 10:00:00");
+		assert_eq!(
+			parse_exif_date(&exif).unwrap().date(),
+			at("2024-01-02").date()
+		);
+
+		assert!(parse_exif_date(b"not exif").is_none());
+		assert!(parse_exif_date(&exif[..20]).is_none());
+	}
+
+	#[test]
+	fn test_exif_date_from_jpeg() {
+		use image::ImageEncoder;
+
+		let temp = tempfile::tempdir().unwrap();
+		let path = temp.path().join("exif.jpg");
+		let mut jpeg = Vec::new();
+		let mut encoder = image::codecs::jpeg::JpegEncoder::new(&mut jpeg);
+		encoder
+			.set_exif_metadata(exif_block("2021:08:01 18:45:10", "2021:08:02 08:00:00"))
+			.unwrap();
+		encoder
+			.write_image(&[128; 8 * 8 * 3], 8, 8, image::ExtendedColorType::Rgb8)
+			.unwrap();
+		std::fs::write(&path, jpeg).unwrap();
+
+		let taken = exif_date(&path);
+		assert_eq!(taken.unwrap().to_string(), "2021-08-01 18:45:10");
+	}
+
+	#[test]
+	fn test_content_sniffing_without_extension() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		let pixel = image::RgbImage::from_pixel(2, 2, image::Rgb([10, 20, 30]));
+		pixel
+			.save_with_format(dir.join("download"), image::ImageFormat::Jpeg)
+			.unwrap();
+		pixel
+			.save_with_format(dir.join("export.dat"), image::ImageFormat::Png)
+			.unwrap();
+		std::fs::write(dir.join("clip"), b"\0\0\0\x18ftypmp42\0\0\0\0mp42isom").unwrap();
+		std::fs::write(dir.join("notes"), b"just some text").unwrap();
+
+		let detected = |name: &str| MediaType::detect(&dir.join(name));
+		let results = [
+			detected("download"),
+			detected("export.dat"),
+			detected("clip"),
+			detected("notes"),
+			detected("missing"),
+		];
+
+		assert_eq!(
+			results,
+			[
+				Some(MediaType::Image),
+				Some(MediaType::Image),
+				Some(MediaType::Video),
+				None,
+				None
+			]
+		);
+		// HEIC shares the MP4 container but isn't a video
+		assert_eq!(
+			MediaType::sniff(b"\0\0\0\x18ftypheic\0\0\0\0mif1heic"),
+			None
+		);
+		assert_eq!(
+			MediaType::sniff(b"\x1A\x45\xDF\xA3\x9F\x42\x86\x81"),
+			Some(MediaType::Video)
+		);
+	}
+
+	#[test]
+	fn test_taken_within_date_range() {
+		let (start, end) = (parse_date("2023-01-01").ok(), parse_date("2023-12-31").ok());
+
+		assert!(taken_within(Some(at("2023-01-01")), start, end));
+		assert!(taken_within(Some(at("2023-12-31")), start, end));
+		assert!(!taken_within(Some(at("2022-12-31")), start, end));
+		assert!(taken_within(Some(at("2024-01-01")), start, None));
+		assert!(!taken_within(Some(at("2024-01-01")), None, end));
+		assert!(taken_within(
+			Some(at("2024-01-01")),
+			None,
+			parse_date("2024-06-01").ok()
+		));
+
+		// Undated files only pass without a filter
+		assert!(taken_within(None, None, None));
+		assert!(!taken_within(None, start, None));
+		assert!(!taken_within(None, None, end));
+
+		assert!(parse_date("2023-13-01").is_err());
+		assert!(parse_date("01/02/2023").is_err());
 	}
 }
@@ -1,14 +1,17 @@
 //! # Core Domain Types
 //!
-//! Fundamental data structures: embeddings, hashes, clusters, and media types.
+//! Fundamental data structures: embeddings, hashes, clusters, media types,
+//! and the approximate nearest-neighbor index.
 //! These types are used throughout the application.
 
+pub mod ann;
 pub mod cluster;
 pub mod embedding;
 pub mod hash;
 pub mod media;
 
+pub use ann::{AnnIndex, AnnParams};
 pub use cluster::{compute_content_hash, Cluster, ClusterDatabase, ClusterParams};
-pub use embedding::Embedding;
-pub use hash::FileHash;
+pub use embedding::{DegenerateEmbedding, Embedding, Metric, QuantizedEmbedding};
+pub use hash::{FileHash, HashMode};
 pub use media::MediaType;
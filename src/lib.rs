@@ -11,6 +11,7 @@
 //! ```no_run
 //! use scout::{Scout, Embedding};
 //!
+//! # let image_bytes: Vec<u8> = Vec::new();
 //! let mut scout = Scout::builder()
 //!     .model_dir("path/to/models")
 //!     .build()
@@ -35,6 +36,7 @@
 //! them however you like (JSON, MessagePack, a database, etc.):
 //!
 //! ```no_run
+//! # let embedding = scout::Embedding::new(vec![1.0, 0.0]);
 //! let json = serde_json::to_string(&embedding).unwrap();
 //! let restored: scout::Embedding = serde_json::from_str(&json).unwrap();
 //! ```
@@ -51,15 +53,18 @@ pub mod ui;
 
 // === Public Library API ===
 
-pub use crate::core::Embedding;
+pub use crate::core::{AnnIndex, AnnParams, Embedding, Metric};
 pub use crate::cli::Provider;
+pub use crate::models::{LongTextMode, Normalization, PreprocessMode};
+pub use crate::processing::image::Region;
 
 /// Re-export the `image` crate so library consumers can use `scout::image::DynamicImage`
 /// without adding `image` as a separate dependency.
 pub use image;
 
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 
 /// The main entry point for using Scout as a library.
 ///
@@ -67,17 +72,179 @@ use std::path::PathBuf;
 /// Construct one via [`Scout::builder()`].
 pub struct Scout {
 	models: models::Models,
+	ann: AnnParams,
+	calibration: Option<f32>,
+	/// Text embeddings of vocabulary terms seen by [`Scout::label_cluster`]
+	label_cache: std::collections::HashMap<String, Embedding>,
+}
+
+/// Outcome of one file during [`Scout::scan_dir`]
+#[derive(Debug)]
+pub struct ScanProgress {
+	pub path: PathBuf,
+	/// The image's embedding, or the first frame's for videos and animations.
+	/// `None` when the file failed.
+	pub embedding: Option<Embedding>,
+	/// Time spent on this file (images encoded in a batch share the batch time)
+	pub elapsed: Duration,
+	/// Whether the sidecar was written
+	pub result: Result<()>,
+	/// Files handled so far, including this one
+	pub done: usize,
+	/// Files that needed indexing in this scan
+	pub total: usize,
+}
+
+/// Sorted search results that can be served page by page.
+///
+/// Returned by [`Scout::prepare_search`]. Candidates are scored and sorted
+/// once, with `min_score` already applied, so each [`ScoredResults::page`] is
+/// only a slice copy.
+#[derive(Debug, Clone, Default)]
+pub struct ScoredResults {
+	results: Vec<(usize, f32)>,
+}
+
+impl ScoredResults {
+	/// Number of candidates that passed `min_score`
+	pub fn len(&self) -> usize {
+		self.results.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.results.is_empty()
+	}
+
+	/// Up to `size` results starting at `offset` (empty past the end)
+	pub fn page(&self, offset: usize, size: usize) -> &[(usize, f32)] {
+		let start = offset.min(self.results.len());
+		let end = offset.saturating_add(size).min(self.results.len());
+		&self.results[start..end]
+	}
+
+	/// All results, best first
+	pub fn as_slice(&self) -> &[(usize, f32)] {
+		&self.results
+	}
+}
+
+/// Distribution of a query's scores over all candidates.
+///
+/// Returned by [`Scout::search_stats`]. All fields are zero and the histogram
+/// is empty when there are no candidates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchStats {
+	pub min: f32,
+	pub max: f32,
+	pub mean: f32,
+	/// Population standard deviation
+	pub stddev: f32,
+	/// Equal-width bins from `min` to `max` as `(lower edge, count)`; the
+	/// maximum is counted in the last bin
+	pub histogram: Vec<(f32, usize)>,
+}
+
+impl SearchStats {
+	fn from_scores(scores: &[f32], bins: usize) -> Self {
+		if scores.is_empty() {
+			return Self::default();
+		}
+
+		let min = scores.iter().copied().fold(f32::MAX, f32::min);
+		let max = scores.iter().copied().fold(f32::MIN, f32::max);
+		let n = scores.len() as f64;
+		let mean = scores.iter().map(|&s| s as f64).sum::<f64>() / n;
+		let variance = scores
+			.iter()
+			.map(|&s| (s as f64 - mean).powi(2))
+			.sum::<f64>()
+			/ n;
+
+		let width = (max - min) / bins as f32;
+		let mut histogram: Vec<(f32, usize)> =
+			(0..bins).map(|i| (min + i as f32 * width, 0)).collect();
+		for &score in scores {
+			let bin = if width > 0.0 {
+				(((score - min) / width) as usize).min(bins - 1)
+			} else {
+				0
+			};
+			histogram[bin].1 += 1;
+		}
+
+		Self {
+			min,
+			max,
+			mean: mean as f32,
+			stddev: variance.sqrt() as f32,
+			histogram,
+		}
+	}
+}
+
+/// Sidecars of a directory loaded by [`Scout::open_index`]
+#[derive(Debug, Default)]
+pub struct Index {
+	sidecars: Vec<(PathBuf, storage::Sidecar)>,
+}
+
+impl Index {
+	/// Number of indexed images and videos
+	pub fn len(&self) -> usize {
+		self.sidecars.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.sidecars.is_empty()
+	}
+
+	/// Rank every indexed file against `query`.
+	///
+	/// Returns `(media path, raw score, timestamp)` sorted by descending score,
+	/// filtered like [`Scout::search`]. Videos score by their best frame, whose
+	/// timestamp in seconds is included; images have no timestamp.
+	pub fn search(
+		&self,
+		scout: &Scout,
+		query: &Embedding,
+		limit: usize,
+		min_score: f32,
+	) -> Vec<(PathBuf, f32, Option<f64>)> {
+		let mut results: Vec<(PathBuf, f32, Option<f64>)> = self
+			.sidecars
+			.iter()
+			.filter_map(|(path, sidecar)| {
+				let (score, timestamp) = match sidecar {
+					storage::Sidecar::Image(img) => (query.similarity(&img.embedding()), None),
+					storage::Sidecar::Video(vid) => {
+						let (score, timestamp) =
+							vid.aggregate_score(query, storage::VideoAggregation::Best, 1)?;
+						(score, Some(timestamp))
+					}
+				};
+				Some((path.clone(), score, timestamp))
+			})
+			.filter(|(_, score, _)| scout.calibrated_score(*score) >= min_score)
+			.collect();
+
+		results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+		results.truncate(limit);
+		results
+	}
 }
 
 /// Builder for configuring and constructing a [`Scout`] instance.
 ///
 /// # Example
 /// ```no_run
+/// # fn main() -> anyhow::Result<()> {
 /// let scout = scout::Scout::builder()
 ///     .model_dir("./models")
 ///     .provider(scout::Provider::Cuda)
 ///     .verbose(true)
 ///     .build()?;
+/// # Ok(())
+/// # }
 /// ```
 pub struct ScoutBuilder {
 	model_dir: Option<PathBuf>,
@@ -85,7 +252,16 @@ pub struct ScoutBuilder {
 	text_path: Option<PathBuf>,
 	tokenizer_path: Option<PathBuf>,
 	provider: Option<Provider>,
+	threads: Option<(usize, usize)>,
 	verbose: bool,
+	batch_size: Option<usize>,
+	ann_params: AnnParams,
+	warmup: bool,
+	preprocess_mode: Option<PreprocessMode>,
+	normalization: Option<Normalization>,
+	auto_orient: Option<bool>,
+	calibration: Option<f32>,
+	long_text: LongTextMode,
 }
 
 impl Scout {
@@ -97,24 +273,290 @@ impl Scout {
 			text_path: None,
 			tokenizer_path: None,
 			provider: None,
+			threads: None,
 			verbose: false,
+			batch_size: None,
+			ann_params: AnnParams::default(),
+			warmup: false,
+			preprocess_mode: None,
+			normalization: None,
+			auto_orient: None,
+			calibration: None,
+			long_text: LongTextMode::default(),
 		}
 	}
 
+	/// Score as it should be shown or thresholded: calibrated when
+	/// [`ScoutBuilder::score_calibration`] is set, otherwise `raw` unchanged.
+	pub fn calibrated_score(&self, raw: f32) -> f32 {
+		match self.calibration {
+			Some(temperature) => Embedding::calibrate_score(raw, temperature),
+			None => raw,
+		}
+	}
+
+	/// Load both models and run a dummy forward pass through each.
+	///
+	/// Models are otherwise loaded on the first `encode_*` call, which then takes
+	/// several seconds. Call this up front in interactive applications.
+	pub fn warmup(&mut self) -> Result<()> {
+		self.models.warmup()
+	}
+
 	/// Encode an [`image::DynamicImage`] into an [`Embedding`].
 	pub fn encode_image(&mut self, image: &image::DynamicImage) -> Result<Embedding> {
 		self.models.encode_image(image)
 	}
 
+	/// Encode only `region` of an image, e.g. one object in a busy photo.
+	///
+	/// Fails when the region is empty or reaches past the image's edges.
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let mut scout = scout::Scout::builder().build()?;
+	/// let photo = scout::image::open("street.jpg")?;
+	/// let car = scout.encode_image_region(&photo, scout::Region::new(120, 340, 400, 220))?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn encode_image_region(
+		&mut self,
+		image: &image::DynamicImage,
+		region: Region,
+	) -> Result<Embedding> {
+		self.models.encode_image(&region.crop(image)?)
+	}
+
+	/// Encode several images, batching them into as few forward passes as possible.
+	///
+	/// Returns one [`Embedding`] per input image, in the same order. The number of
+	/// images per forward pass is capped by [`ScoutBuilder::batch_size`].
+	pub fn encode_images(&mut self, images: &[image::DynamicImage]) -> Result<Vec<Embedding>> {
+		self.models.encode_images(images)
+	}
+
 	/// Load an image from raw bytes (JPEG, PNG, WebP, etc.) and encode it.
 	///
 	/// This is typically what you want when downloading images from URLs.
 	pub fn encode_image_bytes(&mut self, bytes: &[u8]) -> Result<Embedding> {
-		let image = image::load_from_memory(bytes)
+		let image = processing::image::load_from_memory_with(bytes, self.models.auto_orient())
 			.context("Failed to decode image from bytes")?;
 		self.models.encode_image(&image)
 	}
 
+	/// Load an image file and encode it.
+	///
+	/// The format is detected from the file's contents when the extension is
+	/// wrong, so a PNG saved as `.jpg` still decodes. `image::open` only goes
+	/// by the extension.
+	pub fn encode_image_path(&mut self, path: &Path) -> Result<Embedding> {
+		let image = processing::image::load_with(path, self.models.auto_orient())?;
+		self.models.encode_image(&image)
+	}
+
+	/// Extract key frames from a video with scene detection and encode each one.
+	///
+	/// Keeps at most `max_frames` frames, taken where the scene changes by more
+	/// than `scene_threshold` (0.0-1.0). The CLI defaults are
+	/// [`config::MAX_VIDEO_FRAMES`] and [`config::SCENE_THRESHOLD`].
+	///
+	/// Returns `(timestamp in seconds, embedding)` pairs in playback order.
+	/// Requires FFmpeg.
+	pub fn encode_video(
+		&mut self,
+		path: &Path,
+		max_frames: usize,
+		scene_threshold: f32,
+	) -> Result<Vec<(f64, Embedding)>> {
+		if !processing::video::is_available() {
+			anyhow::bail!(
+				"FFmpeg not found. Install it or set its location with processing::video::set_ffmpeg_path"
+			);
+		}
+
+		let sampling = processing::video::FrameSampling::Scene {
+			max: max_frames,
+			threshold: scene_threshold,
+		};
+		let extracted = processing::video::extract_frames(path, sampling, 0)
+			.with_context(|| format!("Failed to extract frames from {}", path.display()))?;
+
+		let (timestamps, images): (Vec<f64>, Vec<image::DynamicImage>) = extracted
+			.frames
+			.into_iter()
+			.map(|(timestamp, frame)| (timestamp, image::DynamicImage::ImageRgb8(frame)))
+			.unzip();
+
+		let embeddings = self.models.encode_images(&images)?;
+		Ok(timestamps.into_iter().zip(embeddings).collect())
+	}
+
+	/// Index a directory like `scout scan`, reporting each file to `on_file`.
+	///
+	/// Discovers media (honoring `.scoutignore`), skips files whose sidecars
+	/// are current unless `force` is set, encodes the rest and writes their
+	/// sidecars. Nothing is printed; a file that fails is reported through
+	/// the callback with an error `result` and the scan carries on. Videos use
+	/// the CLI's default frame settings and fail individually without FFmpeg.
+	///
+	/// Returns the number of files indexed successfully.
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// let dir = std::env::temp_dir().join("scout-library");
+	/// std::fs::create_dir_all(&dir)?;
+	/// scout::image::RgbImage::new(64, 64).save(dir.join("black.png"))?;
+	///
+	/// let mut scout = scout::Scout::builder().model_dir("./models").build()?;
+	/// let indexed = scout.scan_dir(&dir, true, false, &mut |progress| {
+	///     match &progress.result {
+	///         Ok(()) => println!("[{}/{}] {}", progress.done, progress.total, progress.path.display()),
+	///         Err(e) => eprintln!("{}: {:#}", progress.path.display(), e),
+	///     }
+	/// })?;
+	/// assert_eq!(indexed, 1);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn scan_dir(
+		&mut self,
+		dir: &Path,
+		recursive: bool,
+		force: bool,
+		on_file: &mut dyn FnMut(ScanProgress),
+	) -> Result<usize> {
+		if !dir.is_dir() {
+			anyhow::bail!("Not a directory: {}", dir.display());
+		}
+		let scan = processing::scan_directory(
+			dir,
+			recursive,
+			force,
+			None,
+			None,
+			&processing::scan::IgnoreRules::default(),
+			core::HashMode::Fast,
+			self.models.fingerprint(),
+		);
+		let (images, videos): (Vec<_>, Vec<_>) = scan
+			.to_process
+			.into_iter()
+			.partition(|file| file.media_type == core::MediaType::Image);
+
+		let total = images.len() + videos.len();
+		let mut done = 0;
+		let mut indexed = 0;
+		let mut report = |path: &Path, result: Result<Embedding>, elapsed: Duration| {
+			done += 1;
+			let (embedding, result) = match result {
+				Ok(embedding) => {
+					indexed += 1;
+					(Some(embedding), Ok(()))
+				}
+				Err(e) => (None, Err(e)),
+			};
+			on_file(ScanProgress {
+				path: path.to_path_buf(),
+				embedding,
+				elapsed,
+				result,
+				done,
+				total,
+			});
+		};
+
+		for batch in images.chunks(self.models.batch_size()) {
+			let start = Instant::now();
+			let results =
+				commands::scan::process_image_batch(&mut self.models, batch, Default::default());
+			let elapsed = start.elapsed() / batch.len() as u32;
+			for (file, result) in batch.iter().zip(results) {
+				report(&file.path, result, elapsed);
+			}
+		}
+
+		let video_supported = processing::video::is_available();
+		for file in &videos {
+			let start = Instant::now();
+			let result = if video_supported {
+				let media_dir = file.path.parent().unwrap_or(dir);
+				commands::scan::process_video(
+					&mut self.models,
+					file,
+					media_dir,
+					&commands::scan::VideoOptions::default(),
+					Default::default(),
+				)
+			} else {
+				Err(anyhow::anyhow!("FFmpeg not found, video skipped"))
+			};
+			report(&file.path, result, start.elapsed());
+		}
+
+		Ok(indexed)
+	}
+
+	/// Load every sidecar under `dir` for searching with [`Index::search`].
+	///
+	/// Reads the `.scout` directories written by `scout scan` or
+	/// [`Scout::scan_dir`]. Sidecars whose media file no longer exists are
+	/// left out, and unreadable ones are skipped with a warning.
+	///
+	/// # Example
+	/// ```
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let temp = tempfile::tempdir()?;
+	/// # let dir = temp.path();
+	/// # use scout::config::{TEXT_MODEL, TOKENIZER, VISION_MODEL};
+	/// # for file in [VISION_MODEL, TEXT_MODEL, TOKENIZER] {
+	/// #     std::fs::write(dir.join(file), b"")?;
+	/// # }
+	/// # let scout = scout::Scout::builder().model_dir(&dir).build()?;
+	/// // Index a photo by hand (normally done by `scout scan`)
+	/// let photo = dir.join("beach.png");
+	/// scout::image::RgbImage::new(8, 8).save(&photo)?;
+	/// let hash = scout::core::FileHash::compute(&photo)?;
+	/// let sidecar = scout::storage::ImageSidecar::new(hash.clone(), scout::Embedding::new(vec![1.0, 0.0]));
+	/// scout::storage::save_image(&sidecar, &dir, &hash)?;
+	///
+	/// let index = scout::Scout::open_index(&dir, true)?;
+	/// let query = scout::Embedding::new(vec![0.8, 0.6]);
+	/// let results = index.search(&scout, &query, 10, 0.5);
+	/// assert_eq!(results.len(), 1);
+	/// assert!(results[0].0.ends_with("beach.png"));
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn open_index(dir: &Path, recursive: bool) -> Result<Index> {
+		if !dir.is_dir() {
+			anyhow::bail!("Not a directory: {}", dir.display());
+		}
+		let (sidecars, _) = storage::load_all_sidecars(dir, recursive);
+		Ok(Index { sidecars })
+	}
+
+	/// Make sure `dir` holds the model files, downloading the missing ones.
+	///
+	/// Fetches the default SigLIP2 models from [`config::MODELS_URL`], checking
+	/// each file's size and SHA-256 against what the server reports before
+	/// moving it into place. Does nothing
+	/// when every file is present. Downloading needs the `download` feature;
+	/// without it, missing files are an error.
+	///
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// scout::Scout::ensure_models("models".as_ref())?;
+	/// let scout = scout::Scout::builder().model_dir("models").build()?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn ensure_models(dir: &Path) -> Result<()> {
+		models::download::download(dir, config::MODELS_URL, false)
+	}
+
 	/// Encode a text query into an [`Embedding`].
 	///
 	/// The text is tokenized and run through the SigLIP2 text encoder.
@@ -123,18 +565,123 @@ impl Scout {
 		self.models.encode_text(text)
 	}
 
+	/// Encode several text queries in a single forward pass.
+	///
+	/// Useful for scoring an image against many candidate labels. Each query is
+	/// truncated to the model's token limit on its own, and embeddings are
+	/// returned in input order.
+	pub fn encode_text_batch(&mut self, texts: &[&str]) -> Result<Vec<Embedding>> {
+		self.models.encode_text_batch(texts)
+	}
+
+	/// Encode several phrasings of the same query and average them.
+	///
+	/// A few paraphrases ("a red sports car", "a ferrari") often retrieve
+	/// better than any single one. Empty queries are rejected.
+	pub fn encode_texts_mean(&mut self, texts: &[&str]) -> Result<Embedding> {
+		self.models.encode_text_mean(texts)
+	}
+
+	/// Encode a query expression such as `"sunset" - "beach" + "mountain"`.
+	///
+	/// Each `+`/`-` separated phrase is encoded on its own, the embeddings are
+	/// added or subtracted, and the result is re-normalized so it compares like
+	/// any other query. Quotes are optional; `+` and `-` only count as operators
+	/// after whitespace, so `black-and-white` stays one phrase. An expression
+	/// that cancels out to a zero vector is an error.
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let mut scout = scout::Scout::builder().build()?;
+	/// let query = scout.encode_expression(r#""sunset" - "beach" + "mountain""#)?;
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn encode_expression(&mut self, expr: &str) -> Result<Embedding> {
+		self.models.encode_expression(expr)
+	}
+
+	/// Name a group of images: rank `vocabulary` terms by similarity to the
+	/// centroid of `members`, best first.
+	///
+	/// Terms are encoded once and remembered, so labeling many clusters with the
+	/// same vocabulary only pays for the text encoder on the first call.
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let mut scout = scout::Scout::builder().build()?;
+	/// # let members: Vec<scout::Embedding> = Vec::new();
+	/// let labels = scout.label_cluster(&members, &["beach", "forest", "city"])?;
+	/// for (term, score) in labels.iter().take(3) {
+	///     println!("{} ({:.1}%)", term, score * 100.0);
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn label_cluster(
+		&mut self,
+		members: &[Embedding],
+		vocabulary: &[&str],
+	) -> Result<Vec<(String, f32)>> {
+		let missing: Vec<&str> = vocabulary
+			.iter()
+			.copied()
+			.filter(|term| !self.label_cache.contains_key(*term))
+			.collect();
+		let encoded = processing::cluster::encode_vocabulary(&mut self.models, &missing)?;
+		self.label_cache.extend(encoded);
+
+		let terms: Vec<(String, Embedding)> = vocabulary
+			.iter()
+			.map(|term| (term.to_string(), self.label_cache[*term].clone()))
+			.collect();
+		Ok(processing::cluster::rank_labels(members, &terms))
+	}
+
+	/// Similarity of every embedding in `a` against every embedding in `b`.
+	///
+	/// The result has one row per `a` embedding and one column per `b`
+	/// embedding: `matrix[i][j]` is the cosine similarity of `a[i]` and `b[j]`.
+	/// Rows are computed in parallel. Scores are raw, like [`Scout::search`].
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let mut scout = scout::Scout::builder().build()?;
+	/// # let queries: Vec<scout::Embedding> = Vec::new();
+	/// # let catalog: Vec<scout::Embedding> = Vec::new();
+	/// let matrix = scout.compare_many(&queries, &catalog);
+	/// for (i, row) in matrix.iter().enumerate() {
+	///     let best = row.iter().copied().fold(f32::MIN, f32::max);
+	///     println!("query {}: best score {:.1}%", i, best * 100.0);
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn compare_many(&self, a: &[Embedding], b: &[Embedding]) -> Vec<Vec<f32>> {
+		Embedding::similarity_matrix(a, b)
+	}
+
 	/// Find the best matches from a list of candidate embeddings.
 	///
-	/// Returns indices and scores sorted by descending similarity,
-	/// filtered to those above `min_score`.
+	/// Returns indices and raw scores sorted by descending similarity,
+	/// filtered to those above `min_score` (compared against the calibrated
+	/// score when [`ScoutBuilder::score_calibration`] is set).
 	///
 	/// # Example
 	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let mut scout = scout::Scout::builder().build()?;
+	/// # let stored_embeddings: Vec<scout::Embedding> = Vec::new();
 	/// let query = scout.encode_text("sunset over water")?;
 	/// let matches = scout.search(&query, &stored_embeddings, 10, 0.05);
 	/// for (index, score) in matches {
 	///     println!("#{}: {:.1}%", index, score * 100.0);
 	/// }
+	/// # Ok(())
+	/// # }
 	/// ```
 	pub fn search(
 		&self,
@@ -143,17 +690,176 @@ impl Scout {
 		limit: usize,
 		min_score: f32,
 	) -> Vec<(usize, f32)> {
+		let mut results = self.prepare_search(query, candidates, min_score).results;
+		results.truncate(limit);
+		results
+	}
+
+	/// Score and sort every candidate once, for serving results in pages.
+	///
+	/// Keep the returned [`ScoredResults`] (e.g. per session in a web backend)
+	/// and call [`ScoredResults::page`] for each "load more" instead of
+	/// searching again. Always exact; [`Scout::search_ann`] is the approximate alternative.
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let mut scout = scout::Scout::builder().build()?;
+	/// # let stored_embeddings: Vec<scout::Embedding> = Vec::new();
+	/// let query = scout.encode_text("sunset over water")?;
+	/// let results = scout.prepare_search(&query, &stored_embeddings, 0.05);
+	/// let first = results.page(0, 10);
+	/// let second = results.page(10, 10);
+	/// println!("{} of {} matches shown", first.len() + second.len(), results.len());
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn prepare_search(
+		&self,
+		query: &Embedding,
+		candidates: &[Embedding],
+		min_score: f32,
+	) -> ScoredResults {
 		let mut results: Vec<(usize, f32)> = candidates
 			.iter()
 			.enumerate()
 			.map(|(i, emb)| (i, query.similarity(emb)))
-			.filter(|(_, score)| *score >= min_score)
+			.filter(|(_, score)| self.calibrated_score(*score) >= min_score)
+			.collect();
+
+		results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+		ScoredResults { results }
+	}
+
+	/// Score distribution of `query` over every candidate, for rendering a
+	/// confidence histogram or picking a data-driven `min_score`.
+	///
+	/// Uses raw similarities before any limit or threshold, in
+	/// [`config::SCORE_STATS_BINS`] bins. Separate from [`Scout::search`], so
+	/// only callers that want it pay for the extra pass.
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let mut scout = scout::Scout::builder().build()?;
+	/// # let stored_embeddings: Vec<scout::Embedding> = Vec::new();
+	/// let query = scout.encode_text("sunset over water")?;
+	/// let stats = scout.search_stats(&query, &stored_embeddings);
+	/// let threshold = stats.mean + 2.0 * stats.stddev;
+	/// let matches = scout.search(&query, &stored_embeddings, 10, threshold);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn search_stats(&self, query: &Embedding, candidates: &[Embedding]) -> SearchStats {
+		let scores: Vec<f32> = candidates.iter().map(|emb| query.similarity(emb)).collect();
+		SearchStats::from_scores(&scores, config::SCORE_STATS_BINS)
+	}
+
+	/// One page of [`Scout::prepare_search`] results.
+	///
+	/// Scores every candidate on each call; prefer `prepare_search` when
+	/// serving several pages of the same query.
+	pub fn search_paged(
+		&self,
+		query: &Embedding,
+		candidates: &[Embedding],
+		page_size: usize,
+		offset: usize,
+		min_score: f32,
+	) -> Vec<(usize, f32)> {
+		self.prepare_search(query, candidates, min_score)
+			.page(offset, page_size)
+			.to_vec()
+	}
+
+	/// Like [`Scout::search`], but penalizes candidates that match a negative prompt.
+	///
+	/// Each candidate is scored as `sim(query) - neg_weight * sim(negative)`,
+	/// the same formula the CLI uses for `--not`. Negative final scores are
+	/// clamped to `0.0` before filtering by `min_score`.
+	///
+	/// `neg_weight` is clamped to `0.0..=1.0`: `0.0` ignores the negative prompt
+	/// and `1.0` subtracts it fully. The CLI uses `0.7`.
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let mut scout = scout::Scout::builder().build()?;
+	/// # let stored_embeddings: Vec<scout::Embedding> = Vec::new();
+	/// let query = scout.encode_text("a dog at the beach")?;
+	/// let negative = scout.encode_text("people")?;
+	/// let matches = scout.search_with_negative(&query, &negative, &stored_embeddings, 10, 0.05, 0.7);
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn search_with_negative(
+		&self,
+		query: &Embedding,
+		negative: &Embedding,
+		candidates: &[Embedding],
+		limit: usize,
+		min_score: f32,
+		neg_weight: f32,
+	) -> Vec<(usize, f32)> {
+		let neg_weight = neg_weight.clamp(0.0, 1.0);
+
+		let mut results: Vec<(usize, f32)> = candidates
+			.iter()
+			.enumerate()
+			.map(|(i, emb)| {
+				let score = query.similarity(emb) - neg_weight * negative.similarity(emb);
+				(i, score.max(0.0))
+			})
+			.filter(|(_, score)| self.calibrated_score(*score) >= min_score)
 			.collect();
 
 		results.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
 		results.truncate(limit);
 		results
 	}
+
+	/// Build an approximate nearest-neighbor (HNSW) index over `candidates`
+	/// for [`Scout::search_ann`], tuned by [`ScoutBuilder::ann_params`].
+	///
+	/// Much faster to query than [`Scout::search`] on large candidate sets
+	/// (100k+), at the cost of occasionally missing a true top match. Build it
+	/// once and keep it while the candidates stay the same; new embeddings can
+	/// be appended with [`AnnIndex::add`].
+	///
+	/// # Example
+	/// ```no_run
+	/// # fn main() -> anyhow::Result<()> {
+	/// # let mut scout = scout::Scout::builder().build()?;
+	/// # let stored_embeddings: Vec<scout::Embedding> = Vec::new();
+	/// let index = scout.build_ann(&stored_embeddings);
+	/// for text in ["sunset over water", "a red car"] {
+	///     let query = scout.encode_text(text)?;
+	///     let matches = scout.search_ann(&index, &query, 10, 0.05);
+	///     println!("{}: {} matches", text, matches.len());
+	/// }
+	/// # Ok(())
+	/// # }
+	/// ```
+	pub fn build_ann(&self, candidates: &[Embedding]) -> AnnIndex {
+		AnnIndex::build(candidates, self.ann)
+	}
+
+	/// Like [`Scout::search`], through an index from [`Scout::build_ann`].
+	///
+	/// Indices refer to the embeddings in the order they were added to `index`.
+	pub fn search_ann(
+		&self,
+		index: &AnnIndex,
+		query: &Embedding,
+		limit: usize,
+		min_score: f32,
+	) -> Vec<(usize, f32)> {
+		index
+			.search(query, limit)
+			.into_iter()
+			.filter(|(_, score)| self.calibrated_score(*score) >= min_score)
+			.collect()
+	}
 }
 
 impl ScoutBuilder {
@@ -194,6 +900,16 @@ impl ScoutBuilder {
 		self
 	}
 
+	/// Set the ONNX Runtime thread pools: `intra` threads split the work of
+	/// one operator, `inter` threads run independent operators concurrently.
+	///
+	/// Both must be at least 1, or [`ScoutBuilder::build`] fails. When unset,
+	/// intra-op uses one thread per logical CPU. Applies process-wide.
+	pub fn threads(mut self, intra: usize, inter: usize) -> Self {
+		self.threads = Some((intra, inter));
+		self
+	}
+
 	/// Enable or disable verbose logging to stderr.
 	///
 	/// Defaults to `false` (quiet) for library use.
@@ -202,9 +918,87 @@ impl ScoutBuilder {
 		self
 	}
 
+	/// Set the maximum number of images encoded per forward pass.
+	///
+	/// Larger batches improve GPU throughput but use more memory.
+	/// Defaults to 8.
+	pub fn batch_size(mut self, batch_size: usize) -> Self {
+		self.batch_size = Some(batch_size);
+		self
+	}
+
+	/// Tune the HNSW index built by [`Scout::build_ann`].
+	pub fn ann_params(mut self, params: AnnParams) -> Self {
+		self.ann_params = params;
+		self
+	}
+
+	/// Choose how images are fitted to the square model input.
+	///
+	/// [`PreprocessMode::Letterbox`] keeps the aspect ratio of wide and tall
+	/// images, which usually matches them better, but produces different
+	/// embeddings than the default [`PreprocessMode::Stretch`]. Rescan existing
+	/// libraries after switching.
+	pub fn preprocess_mode(mut self, mode: PreprocessMode) -> Self {
+		self.preprocess_mode = Some(mode);
+		self
+	}
+
+	/// Rotate images upright according to their EXIF orientation tag before
+	/// encoding (default off).
+	///
+	/// Affects [`Scout::encode_image_bytes`] and files loaded by Scout; images
+	/// you decode yourself are encoded as given.
+	pub fn auto_orient(mut self, enabled: bool) -> Self {
+		self.auto_orient = Some(enabled);
+		self
+	}
+
+	/// Override the per-channel pixel mean and std used before encoding.
+	///
+	/// Defaults to SigLIP2's 0.5/0.5. Only change this for vision models trained
+	/// with different statistics.
+	pub fn normalization(mut self, mean: [f32; 3], std: [f32; 3]) -> Self {
+		self.normalization = Some(Normalization { mean, std });
+		self
+	}
+
+	/// Choose what happens to text queries longer than the encoder's 64-token
+	/// limit.
+	///
+	/// [`LongTextMode::Truncate`] (default) drops the tail with a warning.
+	/// [`LongTextMode::Chunk`] encodes the whole text in token-limited chunks
+	/// and averages them, which suits pasted paragraphs. Affects
+	/// [`Scout::encode_text`] and [`Scout::encode_texts_mean`];
+	/// [`Scout::encode_text_batch`] always truncates.
+	pub fn long_text_mode(mut self, mode: LongTextMode) -> Self {
+		self.long_text = mode;
+		self
+	}
+
+	/// Compare `min_score` in [`Scout::search`] against calibrated scores.
+	///
+	/// Raw SigLIP2 similarities bunch up between roughly 0.05 and 0.25. With
+	/// calibration, a threshold like `0.5` means "better than the midpoint" of
+	/// [`Embedding::calibrate_score`]. Returned scores stay raw; use
+	/// [`Scout::calibrated_score`] to display them. Pass
+	/// [`config::SCORE_CALIBRATION_TEMPERATURE`] for the CLI's default.
+	pub fn score_calibration(mut self, temperature: f32) -> Self {
+		self.calibration = Some(temperature);
+		self
+	}
+
+	/// Load and warm up both models in [`ScoutBuilder::build`] instead of on
+	/// first use. See [`Scout::warmup`]. Defaults to `false`.
+	pub fn warmup(mut self, enabled: bool) -> Self {
+		self.warmup = enabled;
+		self
+	}
+
 	/// Build the [`Scout`] instance, loading model metadata and validating paths.
 	///
-	/// The actual ONNX models are lazy-loaded on first use (first `encode_*` call).
+	/// The actual ONNX models are lazy-loaded on first use (first `encode_*` call)
+	/// unless [`ScoutBuilder::warmup`] is enabled.
 	pub fn build(self) -> Result<Scout> {
 		// Configure verbose logging
 		ui::Log::set_verbose(self.verbose);
@@ -213,9 +1007,12 @@ impl ScoutBuilder {
 		if let Some(provider) = self.provider {
 			runtime::set_provider(provider);
 		}
+		if let Some((intra, inter)) = self.threads {
+			runtime::set_threads(Some(intra), Some(inter))?;
+		}
 
 		// Build models from explicit paths or model_dir
-		let models = if self.vision_path.is_some() || self.text_path.is_some() || self.tokenizer_path.is_some() {
+		let mut models = if self.vision_path.is_some() || self.text_path.is_some() || self.tokenizer_path.is_some() {
 			// Use individual paths (all three must be set)
 			let vision = self.vision_path.context(
 				"vision_model path required when using individual model paths",
@@ -241,6 +1038,278 @@ impl ScoutBuilder {
 			m
 		};
 
-		Ok(Scout { models })
+		if let Some(batch_size) = self.batch_size {
+			models.set_batch_size(batch_size);
+		}
+		models.set_long_text_mode(self.long_text);
+		if let Some(mode) = self.preprocess_mode {
+			models.set_preprocess_mode(mode);
+		}
+		if let Some(normalization) = self.normalization {
+			models.set_normalization(normalization);
+		}
+		if let Some(enabled) = self.auto_orient {
+			models.set_auto_orient(enabled);
+		}
+
+		if self.warmup {
+			models.warmup()?;
+		}
+
+		Ok(Scout {
+			models,
+			ann: self.ann_params,
+			calibration: self.calibration,
+			label_cache: Default::default(),
+		})
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Build a Scout over an empty model directory (models load lazily, so
+	/// tests that never encode don't need model files)
+	fn test_scout() -> Scout {
+		let temp = tempfile::tempdir().unwrap();
+		Scout::builder().model_dir(temp.path()).build().unwrap()
+	}
+
+	#[test]
+	#[ignore = "requires model files (set SCOUT_MODELS_DIR)"]
+	fn test_text_batch_matches_single_encodes() {
+		let mut scout = Scout::builder().build().unwrap();
+		let texts = [
+			"a dog",
+			"a red car parked on a rainy street at night",
+			"sunset",
+		];
+
+		let batch = scout.encode_text_batch(&texts).unwrap();
+		assert_eq!(batch.len(), texts.len());
+
+		for (text, batched) in texts.iter().zip(&batch) {
+			let single = scout.encode_text(text).unwrap();
+			assert!(
+				single.similarity(batched) > 0.999,
+				"mismatch for {:?}",
+				text
+			);
+		}
+	}
+
+	#[test]
+	#[ignore = "requires model files (set SCOUT_MODELS_DIR)"]
+	fn test_letterbox_only_changes_non_square_images() {
+		let mut scout = Scout::builder().build().unwrap();
+		let gradient = |w: u32, h: u32| {
+			image::DynamicImage::ImageRgb8(image::RgbImage::from_fn(w, h, |x, y| {
+				image::Rgb([(x * 255 / w) as u8, (y * 255 / h) as u8, 96])
+			}))
+		};
+		let wide = gradient(1536, 384);
+		let square = gradient(512, 512);
+
+		let mut encode_both = |image: &image::DynamicImage| {
+			scout.models.set_preprocess_mode(PreprocessMode::Stretch);
+			let stretched = scout.encode_image(image).unwrap();
+			scout.models.set_preprocess_mode(PreprocessMode::Letterbox);
+			let letterboxed = scout.encode_image(image).unwrap();
+			stretched.similarity(&letterboxed)
+		};
+
+		let wide_similarity = encode_both(&wide);
+		assert!(
+			wide_similarity < 0.99 && wide_similarity > 0.5,
+			"wide image similarity {:.4}",
+			wide_similarity
+		);
+		assert!(encode_both(&square) > 0.999);
+	}
+
+	#[test]
+	#[ignore = "requires model files (set SCOUT_MODELS_DIR)"]
+	fn test_region_embedding_differs_from_full_image() {
+		let mut scout = Scout::builder().build().unwrap();
+		// Sky over grass, with a red square in the lower left
+		let photo = image::RgbImage::from_fn(512, 512, |x, y| match (x, y) {
+			(32..=223, 288..=479) => image::Rgb([220, 30, 30]),
+			(_, 0..=255) => image::Rgb([110, 170, 235]),
+			_ => image::Rgb([60, 150, 50]),
+		});
+		let photo = image::DynamicImage::ImageRgb8(photo);
+		let region = Region::new(32, 288, 192, 192);
+
+		let full = scout.encode_image(&photo).unwrap();
+		let cropped = scout.encode_image_region(&photo, region).unwrap();
+		let manual = scout
+			.encode_image(&photo.crop_imm(32, 288, 192, 192))
+			.unwrap();
+
+		assert!(cropped.similarity(&manual) > 0.999);
+		let similarity = cropped.similarity(&full);
+		assert!(
+			similarity < 0.95,
+			"region vs full similarity {:.4}",
+			similarity
+		);
+		assert!(scout
+			.encode_image_region(&photo, Region::new(400, 400, 200, 200))
+			.is_err());
+	}
+
+	#[test]
+	#[ignore = "requires model files (set SCOUT_MODELS_DIR)"]
+	fn test_chunked_long_query_keeps_its_tail() {
+		let opening = "a quiet harbor at dawn with small fishing boats tied to a wooden pier, \
+			gulls circling over calm gray water and fog hanging low over the hills behind the town";
+		let tail = "later that night the same street fills with neon signs, crowded food \
+			stalls, red paper lanterns and people with umbrellas walking through heavy rain";
+		let paragraph = format!("{} {} {}", opening, opening, tail);
+
+		let mut scout = Scout::builder().build().unwrap();
+		let truncated = scout.encode_text(&paragraph).unwrap();
+		let night = scout.encode_text(tail).unwrap();
+
+		let mut scout = Scout::builder()
+			.long_text_mode(LongTextMode::Chunk)
+			.build()
+			.unwrap();
+		let chunked = scout.encode_text(&paragraph).unwrap();
+		// Short queries are encoded the same way in both modes
+		assert!(scout.encode_text(tail).unwrap().similarity(&night) > 0.999);
+
+		let drift = truncated.similarity(&chunked);
+		assert!(drift < 0.97, "chunked vs truncated similarity {:.4}", drift);
+		// Only the chunked embedding saw the night scene at the end
+		assert!(chunked.similarity(&night) > truncated.similarity(&night) + 0.05);
+	}
+
+	#[test]
+	#[ignore = "requires model files (set SCOUT_MODELS_DIR)"]
+	fn test_parallel_scan_indexes_every_file() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		for i in 0..7u8 {
+			image::RgbImage::from_pixel(64, 48, image::Rgb([i * 30, 255 - i * 30, 128]))
+				.save(dir.join(format!("{}.png", i)))
+				.unwrap();
+		}
+
+		// Two workers with a batch size that leaves a partial last batch
+		let options = commands::scan::ScanOptions {
+			force: true,
+			exclude_videos: true,
+			batch_size: 3,
+			workers: 2,
+			..Default::default()
+		};
+		commands::scan::run(dir, false, &options).unwrap();
+
+		let (sidecars, _) = storage::load_all_sidecars(dir, false);
+		assert_eq!(sidecars.len(), 7);
+	}
+
+	#[test]
+	fn test_negative_prompt_drops_matching_item() {
+		let scout = test_scout();
+		let query = Embedding::new(vec![1.0, 0.0, 0.0]);
+		let negative = Embedding::new(vec![0.0, 1.0, 0.0]);
+		let candidates = vec![
+			// Matches the query only
+			Embedding::new(vec![1.0, 0.0, 0.2]),
+			// Matches the query, but matches the negative prompt more strongly
+			Embedding::new(vec![0.6, 0.8, 0.0]),
+		];
+
+		let plain = scout.search(&query, &candidates, 10, 0.3);
+		assert_eq!(plain.len(), 2);
+
+		let filtered = scout.search_with_negative(&query, &negative, &candidates, 10, 0.3, 0.7);
+		assert_eq!(filtered.len(), 1);
+		assert_eq!(filtered[0].0, 0);
+	}
+
+	#[test]
+	fn test_pages_concatenate_to_full_sort() {
+		let scout = test_scout();
+		let query = Embedding::new(vec![1.0, 0.0]);
+		let candidates: Vec<Embedding> = (0..23)
+			.map(|i| {
+				let angle = (i * 7 % 23) as f32 * 0.06;
+				Embedding::new(vec![angle.cos(), angle.sin()])
+			})
+			.collect();
+
+		let results = scout.prepare_search(&query, &candidates, 0.5);
+		let full = scout.search(&query, &candidates, usize::MAX, 0.5);
+		assert_eq!(results.len(), full.len());
+		assert!(
+			results.len() < candidates.len(),
+			"min_score applied up front"
+		);
+
+		let pages: Vec<(usize, f32)> = (0..results.len())
+			.step_by(5)
+			.flat_map(|offset| results.page(offset, 5).to_vec())
+			.collect();
+		assert_eq!(pages, full);
+
+		// Boundaries: the last page is partial, pages past the end are empty
+		assert_eq!(results.page(0, 5).len(), 5);
+		assert_eq!(results.page(results.len() - 2, 5).len(), 2);
+		assert!(results.page(results.len(), 5).is_empty());
+		assert!(results.page(usize::MAX, usize::MAX).is_empty());
+		assert_eq!(
+			scout.search_paged(&query, &candidates, 5, 5, 0.5),
+			results.page(5, 5)
+		);
+	}
+
+	#[test]
+	fn test_search_stats_over_known_scores() {
+		let scout = test_scout();
+		let query = Embedding::new(vec![1.0, 0.0]);
+		let at = |score: f32| Embedding::new(vec![score, (1.0 - score * score).sqrt()]);
+		let candidates: Vec<Embedding> = [0.2, 0.4, 0.6, 0.8].into_iter().map(at).collect();
+
+		let stats = scout.search_stats(&query, &candidates);
+		assert!((stats.min - 0.2).abs() < 1e-5);
+		assert!((stats.max - 0.8).abs() < 1e-5);
+		assert!((stats.mean - 0.5).abs() < 1e-5);
+		assert!((stats.stddev - 0.05f32.sqrt()).abs() < 1e-5);
+
+		let histogram = &stats.histogram;
+		assert_eq!(histogram.len(), config::SCORE_STATS_BINS);
+		assert_eq!(histogram.iter().map(|(_, count)| count).sum::<usize>(), 4);
+		assert_eq!(histogram[0], (stats.min, 1));
+		assert_eq!(
+			histogram[config::SCORE_STATS_BINS - 1].1,
+			1,
+			"max lands in the last bin"
+		);
+
+		assert_eq!(scout.search_stats(&query, &[]), SearchStats::default());
+	}
+
+	#[test]
+	fn test_ann_index_finds_exact_top_match() {
+		let scout = test_scout();
+		let candidates: Vec<Embedding> = (0..200)
+			.map(|i| {
+				let angle = i as f32 * 0.03;
+				Embedding::new(vec![angle.cos(), angle.sin(), 0.2])
+			})
+			.collect();
+		let index = scout.build_ann(&candidates);
+
+		for angle in [0.4f32, 2.5, 5.1] {
+			let query = Embedding::new(vec![angle.cos(), angle.sin(), 0.2]);
+			let exact = scout.search(&query, &candidates, 3, 0.0);
+			let approximate = scout.search_ann(&index, &query, 3, 0.0);
+			assert_eq!(approximate[0].0, exact[0].0);
+		}
+		assert!(scout.search_ann(&index, &candidates[0], 5, 1.1).is_empty());
 	}
 }
@@ -3,26 +3,23 @@
 //! Entry point for the Scout command-line application.
 //! Parses arguments and dispatches to the appropriate command handler.
 
-mod cli;
-mod commands;
-mod config;
-mod core;
-mod models;
-mod processing;
-mod runtime;
-mod storage;
-mod ui;
-
 use clap::Parser;
+use scout::{cli, commands, config, processing, runtime, storage, ui};
 
 fn main() {
-	let cli = cli::Cli::parse();
+	let mut cli = cli::Cli::parse();
 
 	ui::log::print_logo();
 	eprintln!();
 
 	ui::Log::set_verbose(cli.verbose);
 
+	// Defaults from .scoutconfig apply only where no flag was given
+	if let Err(e) = cli.apply_dir_config() {
+		ui::error(&format!("{:#}", e));
+		std::process::exit(1);
+	}
+
 	// Set custom model directory if provided
 	if let Some(dir) = cli.model_dir {
 		config::set_model_dir(dir);
@@ -33,10 +30,30 @@ fn main() {
 		processing::video::set_ffmpeg_path(path);
 	}
 
+	// Keep sidecars in a central store instead of beside the media
+	if let Some(store) = cli.store {
+		storage::set_store(Some(store));
+	}
+
+	// Symlinked directories are followed unless asked not to
+	processing::scan::set_follow_symlinks(!cli.no_follow_symlinks);
+
 	// Set provider
 	if let Some(provider) = cli.provider {
 		runtime::set_provider(provider);
 	}
+	if let Err(e) = runtime::set_threads(cli.threads, cli.inter_threads) {
+		ui::error(&format!("{}", e));
+		std::process::exit(1);
+	}
+
+	// Set image preprocessing mode
+	if let Some(mode) = cli.preprocess {
+		config::set_preprocess_mode(mode);
+	}
+	if cli.auto_orient {
+		config::set_auto_orient(true);
+	}
 
 	let result = match cli.command {
 		cli::Command::Scan {
@@ -47,20 +64,52 @@ fn main() {
 			exclude_videos,
 			max_frames,
 			scene_threshold,
+			sampling,
+			video_stream,
+			frame_dedup,
+			strict_frames,
+			max_video_embeddings,
+			batch_size,
+			workers,
+			quantize,
+			half,
+			dry_run,
+			exclude_dir,
+			hash,
 		} => commands::scan::run(
 			&dir,
 			cli.recursive,
-			force,
-			min_resolution,
-			max_size,
-			exclude_videos,
-			max_frames,
-			scene_threshold,
+			&commands::scan::ScanOptions {
+				force,
+				min_resolution,
+				max_size,
+				exclude_videos,
+				video: commands::scan::VideoOptions {
+					sampling: processing::video::FrameSampling::new(
+						sampling,
+						max_frames.unwrap_or(config::MAX_VIDEO_FRAMES),
+						scene_threshold.unwrap_or(config::SCENE_THRESHOLD),
+					),
+					stream: video_stream,
+					frame_dedup,
+					strict_frames,
+					max_embeddings: max_video_embeddings,
+				},
+				batch_size,
+				workers,
+				format: storage::SidecarFormat { quantize, half },
+				dry_run,
+				exclude_dir,
+				hash_mode: hash,
+			},
 		),
 		cli::Command::Search {
 			query,
 			image,
+			image_url,
+			crop,
 			weight,
+			combine_mode,
 			not,
 			dir,
 			limit,
@@ -70,9 +119,38 @@ fn main() {
 			exclude_videos,
 			paths,
 			export,
+			format,
+			daemon,
+			port,
+			ann,
+			video_agg,
+			video_topk,
+			temporal_smooth,
+			after,
+			before,
+			after_date,
+			before_date,
+			frames_per_video,
+			frame_gap,
+			calibrate,
+			temperature,
+			normalize_scores,
+			tiers,
+			diverse,
+			prefilter,
+			prefilter_distance,
+			export_thumbnails,
+			thumbnail_size,
+			thumbnail_quality,
+			strict,
+			streaming,
+			no_dedup,
+			long_text,
+			hybrid,
 		} => commands::search::run(
-			query.as_deref(),
+			&query,
 			image.as_deref(),
+			image_url.as_deref(),
 			weight,
 			not.as_deref(),
 			&dir,
@@ -84,32 +162,91 @@ fn main() {
 			exclude_videos,
 			paths,
 			export.as_deref(),
+			format,
+			daemon,
+			port,
+			ann,
+			video_agg,
+			video_topk,
+			temporal_smooth,
+			after,
+			before,
+			after_date,
+			before_date,
+			calibrate.then_some(temperature),
+			normalize_scores,
+			tiers,
+			diverse,
+			prefilter.then_some(prefilter_distance),
+			frames_per_video,
+			frame_gap,
+			export_thumbnails.then_some((thumbnail_size, thumbnail_quality)),
+			strict,
+			streaming,
+			!no_dedup,
+			long_text,
+			combine_mode,
+			hybrid,
+			crop,
 		),
+		cli::Command::Explore {
+			image,
+			dir,
+			limit,
+			exclude_videos,
+		} => commands::explore::run(&image, &dir, cli.recursive, limit, exclude_videos),
 		cli::Command::Cluster {
 			dir,
 			force,
+			incremental,
+			join_similarity,
 			min_cluster_size,
 			min_samples,
-			threshold,
+			min_cohesion,
 			use_umap,
 			umap_neighbors,
 			umap_components,
+			metric,
+			seed,
 			preview_count,
 			export,
+			format,
+			include_centroids,
+			thumbnails,
+			labels,
+			strict,
 		} => commands::cluster::run(
 			&dir,
 			cli.recursive,
 			force,
+			incremental,
+			join_similarity,
 			min_cluster_size,
 			min_samples,
-			threshold,
+			min_cohesion,
 			use_umap,
 			umap_neighbors,
 			umap_components,
+			metric,
+			seed,
 			preview_count,
 			export.as_deref(),
+			format,
+			include_centroids,
+			thumbnails.as_deref(),
+			labels.as_deref(),
+			strict,
 		),
-		cli::Command::Clean { dir } => commands::clean::run(&dir, cli.recursive),
+		cli::Command::Reindex { dir } => commands::reindex::run(&dir, cli.recursive),
+		cli::Command::Export { dir, output } => commands::export::run(&dir, cli.recursive, &output),
+		cli::Command::Import { input, dir, rebase } => {
+			commands::import::run(&input, dir.as_deref(), rebase.as_deref())
+		}
+		cli::Command::Info { dir, export } => {
+			commands::info::run(&dir, cli.recursive, export.as_deref())
+		}
+		cli::Command::Verify { dir, fix } => commands::verify::run(&dir, cli.recursive, fix),
+		cli::Command::Clean { dir, repair } => commands::clean::run(&dir, cli.recursive, repair),
 		cli::Command::Watch {
 			dir,
 			min_resolution,
@@ -117,6 +254,14 @@ fn main() {
 			exclude_videos,
 			max_frames,
 			scene_threshold,
+			sampling,
+			video_stream,
+			frame_dedup,
+			strict_frames,
+			max_video_embeddings,
+			quantize,
+			half,
+			index_every,
 		} => commands::watch::run(
 			&dir,
 			cli.recursive,
@@ -125,13 +270,48 @@ fn main() {
 			exclude_videos,
 			max_frames,
 			scene_threshold,
+			sampling,
+			video_stream,
+			frame_dedup,
+			strict_frames,
+			max_video_embeddings,
+			quantize,
+			half,
+			index_every,
 		),
+		cli::Command::Probe { file } => commands::probe::run(&file),
+		cli::Command::Bench { images, text } => commands::bench::run(images, text),
+		cli::Command::Providers { export } => commands::providers::run(export.as_deref()),
+		cli::Command::Models {
+			action: cli::ModelsAction::Download { url, force },
+		} => commands::models::download(&url, force),
+		cli::Command::Daemon { port } => {
+			commands::serve::run((std::net::Ipv4Addr::LOCALHOST, port).into())
+		}
+		cli::Command::Serve { addr } => commands::serve::run(addr),
+		cli::Command::Dedup {
+			dir,
+			threshold,
+			export,
+			delete,
+		} => commands::dedup::run(&dir, cli.recursive, threshold, export.as_deref(), delete),
+		cli::Command::Matrix { dir, export, top_k } => {
+			commands::matrix::run(&dir, cli.recursive, &export, top_k)
+		}
 		cli::Command::Outliers {
 			dir,
 			limit,
 			neighbors,
 			export,
-		} => commands::outliers::run(&dir, cli.recursive, limit, neighbors, export.as_deref()),
+			within_cluster,
+		} => commands::outliers::run(
+			&dir,
+			cli.recursive,
+			limit,
+			neighbors,
+			export.as_deref(),
+			within_cluster,
+		),
 	};
 
 	if let Err(e) = result {
@@ -142,11 +322,9 @@ fn main() {
 
 #[cfg(test)]
 mod tests {
-	use super::*;
-
 	#[test]
 	fn test_embedding_similarity() {
-		use crate::core::Embedding;
+		use scout::core::Embedding;
 		let e1 = Embedding::new(vec![1.0, 0.0, 0.0]);
 		let e2 = Embedding::new(vec![1.0, 0.0, 0.0]);
 		assert!((e1.similarity(&e2) - 1.0).abs() < 0.001);
@@ -0,0 +1,188 @@
+//! # Model Download
+//!
+//! Fetch the default SigLIP2 model files into a models directory, for
+//! `scout models download` and [`crate::Scout::ensure_models`].
+//! Needs the `download` feature.
+
+use std::path::Path;
+
+use anyhow::Result;
+
+use crate::config::{TEXT_MODEL, TOKENIZER, VISION_MODEL};
+
+/// Model files Scout loads, with their path under the download URL
+pub const MODEL_FILES: [(&str, &str); 3] = [
+	(VISION_MODEL, "onnx/vision_model_q4f16.onnx"),
+	(TEXT_MODEL, "onnx/text_model_q4f16.onnx"),
+	(TOKENIZER, "tokenizer.json"),
+];
+
+/// Model files not yet in `dir`
+pub fn missing(dir: &Path) -> Vec<&'static str> {
+	MODEL_FILES
+		.iter()
+		.map(|(name, _)| *name)
+		.filter(|name| !dir.join(name).is_file())
+		.collect()
+}
+
+/// Download the model files missing from `dir` (all of them if `force`)
+/// from `base_url`, creating `dir` if needed.
+///
+/// Each file is written to a `.part` file and renamed once its size and,
+/// when the server publishes one, its SHA-256 check out, so an interrupted
+/// download never leaves a truncated model behind. Both come from the same
+/// server as the file, so this guards the transfer, not the source.
+#[cfg(feature = "download")]
+pub fn download(dir: &Path, base_url: &str, force: bool) -> Result<()> {
+	use anyhow::Context;
+
+	std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+
+	let missing = missing(dir);
+	for (name, remote) in MODEL_FILES {
+		if !force && !missing.contains(&name) {
+			crate::ui::debug(&format!("{} already present", name));
+			continue;
+		}
+		let url = format!("{}/{}", base_url.trim_end_matches('/'), remote);
+		fetch(&url, &dir.join(name))?;
+	}
+	Ok(())
+}
+
+/// Download the model files missing from `dir`
+#[cfg(not(feature = "download"))]
+pub fn download(dir: &Path, _base_url: &str, force: bool) -> Result<()> {
+	if !force && missing(dir).is_empty() {
+		return Ok(());
+	}
+	anyhow::bail!(
+		"Downloading models requires Scout to be built with the 'download' feature. \
+		 See docs/MODELS.md to place them in {} by hand",
+		dir.display()
+	)
+}
+
+/// Size and SHA-256 the server publishes for a file
+#[cfg(feature = "download")]
+#[derive(Debug, Default)]
+struct Expected {
+	size: Option<u64>,
+	sha256: Option<String>,
+}
+
+/// Hugging Face answers with a redirect to its CDN that carries the size and
+/// SHA-256 of large files; the CDN's own response doesn't.
+#[cfg(feature = "download")]
+fn expected(url: &str) -> Expected {
+	let agent: ureq::Agent = ureq::Agent::config_builder()
+		.max_redirects(0)
+		.build()
+		.into();
+	let Ok(response) = agent.head(url).call() else {
+		return Expected::default();
+	};
+
+	let header = |name: &str| {
+		response
+			.headers()
+			.get(name)
+			.and_then(|value| value.to_str().ok())
+			.map(|value| value.trim_matches('"').to_string())
+	};
+	Expected {
+		size: header("x-linked-size").and_then(|size| size.parse().ok()),
+		sha256: header("x-linked-etag").filter(|etag| etag.len() == 64),
+	}
+}
+
+#[cfg(feature = "download")]
+fn fetch(url: &str, dest: &Path) -> Result<()> {
+	use anyhow::{anyhow, Context};
+	use sha2::{Digest, Sha256};
+	use std::io::{Read, Write};
+
+	let name = dest.file_name().unwrap_or_default().to_string_lossy();
+	let expected = expected(url);
+	crate::ui::debug(&format!("Downloading {}", url));
+
+	let mut response = ureq::get(url)
+		.call()
+		.map_err(|e| anyhow!("Failed to download {}: {}", url, e))?;
+	let size = expected.size.or(response.body().content_length());
+
+	let mut part = dest.as_os_str().to_owned();
+	part.push(".part");
+	let part = std::path::PathBuf::from(part);
+	let mut file = std::fs::File::create(&part)
+		.with_context(|| format!("Failed to create {}", part.display()))?;
+
+	let progress = crate::ui::Progress::bytes(size.unwrap_or(0), &name);
+	let mut reader = response.body_mut().as_reader();
+	let mut hasher = Sha256::new();
+	let mut buffer = vec![0u8; 1 << 16];
+	let mut written = 0u64;
+	loop {
+		let n = reader
+			.read(&mut buffer)
+			.map_err(|e| anyhow!("Failed to download {}: {}", url, e))?;
+		if n == 0 {
+			break;
+		}
+		file.write_all(&buffer[..n])?;
+		hasher.update(&buffer[..n]);
+		written += n as u64;
+		progress.inc_by(n as u64);
+	}
+	file.sync_all()?;
+	progress.finish();
+
+	let digest: String = hasher
+		.finalize()
+		.iter()
+		.map(|byte| format!("{:02x}", byte))
+		.collect();
+	let mismatch = match (size, &expected.sha256) {
+		(Some(size), _) if size != written => {
+			Some(format!("expected {} bytes, got {}", size, written))
+		}
+		(_, Some(sha256)) if *sha256 != digest => {
+			Some(format!("SHA-256 is {}, expected {}", digest, sha256))
+		}
+		_ => None,
+	};
+	if let Some(reason) = mismatch {
+		let _ = std::fs::remove_file(&part);
+		anyhow::bail!("Download of {} is corrupt: {}", name, reason);
+	}
+	if expected.sha256.is_none() {
+		crate::ui::debug(&format!("No checksum published for {}", name));
+	}
+
+	std::fs::rename(&part, dest)
+		.with_context(|| format!("Failed to move {} into place", part.display()))?;
+	crate::ui::success(&format!(
+		"Downloaded {} ({:.1} MB)",
+		name,
+		written as f64 / 1e6
+	));
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_missing_model_files() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		let before = missing(dir);
+		std::fs::write(dir.join(TOKENIZER), "{}").unwrap();
+		let after = missing(dir);
+
+		assert_eq!(before, vec![VISION_MODEL, TEXT_MODEL, TOKENIZER]);
+		assert_eq!(after, vec![VISION_MODEL, TEXT_MODEL]);
+	}
+}
@@ -1,11 +1,15 @@
 //! # Model Manager
 //!
 //! Lazy-loads vision and text models on first use.
-//! Validates model paths and provides unified encoding interface.
+//! Checks a model's files only when it is loaded, so text search works
+//! without the vision model, and provides a unified encoding interface.
 
 use anyhow::{Context, Result};
-use std::path::PathBuf;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
 
+use super::{Normalization, PreprocessMode};
 use crate::config;
 use crate::core::Embedding;
 
@@ -17,24 +21,41 @@ pub struct Models {
 	tokenizer_path: PathBuf,
 	/// If true, suppress UI output (for library use)
 	pub(crate) quiet: bool,
+	/// Maximum images per vision forward pass in `encode_images`
+	batch_size: usize,
+	/// Recently encoded queries, most recent last (disabled when capacity is 0)
+	text_cache: VecDeque<(String, Embedding)>,
+	text_cache_capacity: usize,
+	/// How queries beyond the text encoder's token limit are handled
+	long_text: super::text::LongTextMode,
+	/// How images are fitted to the vision model input
+	preprocess: PreprocessMode,
+	normalization: Normalization,
+	/// Rotate images upright by their EXIF orientation when loading them
+	auto_orient: bool,
+	/// Model fingerprint for new sidecars, hashed on first use
+	fingerprint: OnceLock<Option<String>>,
 }
 
 impl Models {
 	pub fn new() -> Result<Self> {
 		let vision_path = config::get_vision_model_path().context(format!(
-			"Vision model not found. Ensure {} exists",
+			"Vision model not found. Ensure {} exists or run 'scout models download'",
 			config::VISION_MODEL
 		))?;
 		let text_path = config::get_text_model_path().context(format!(
-			"Text model not found. Ensure {} exists",
+			"Text model not found. Ensure {} exists or run 'scout models download'",
 			config::TEXT_MODEL
 		))?;
 		let tokenizer_path = config::get_tokenizer_path().context(format!(
-			"Tokenizer not found. Ensure {} exists",
+			"Tokenizer not found. Ensure {} exists or run 'scout models download'",
 			config::TOKENIZER
 		))?;
 
-		Self::validate_and_build(vision_path, text_path, tokenizer_path, false)
+		let mut models = Self::build(vision_path, text_path, tokenizer_path, false);
+		models.preprocess = config::preprocess_mode();
+		models.auto_orient = config::auto_orient();
+		Ok(models)
 	}
 
 	/// Create Models with explicit file paths (for library use).
@@ -42,61 +63,215 @@ impl Models {
 	/// - `vision_path`: path to the vision ONNX model
 	/// - `text_path`: path to the text ONNX model
 	/// - `tokenizer_path`: path to the tokenizer JSON
+	///
+	/// Files are checked when their model is first used, so text-only use
+	/// doesn't need the vision model on disk.
 	pub fn with_paths(
 		vision_path: PathBuf,
 		text_path: PathBuf,
 		tokenizer_path: PathBuf,
 	) -> Result<Self> {
-		Self::validate_and_build(vision_path, text_path, tokenizer_path, false)
+		Ok(Self::build(vision_path, text_path, tokenizer_path, false))
 	}
 
-	/// Create Models from a directory containing all three model files.
+	/// Create Models from a directory with the model files.
 	///
 	/// Expects the directory to contain:
-	/// - `vision_model_q4f16.onnx`
-	/// - `text_model_q4f16.onnx`
-	/// - `tokenizer.json`
+	/// - `vision_model_q4f16.onnx` (only needed to encode images)
+	/// - `text_model_q4f16.onnx` and `tokenizer.json` (only needed to encode text)
 	pub fn from_dir(model_dir: PathBuf) -> Result<Self> {
 		let vision_path = model_dir.join(config::VISION_MODEL);
 		let text_path = model_dir.join(config::TEXT_MODEL);
 		let tokenizer_path = model_dir.join(config::TOKENIZER);
-		Self::validate_and_build(vision_path, text_path, tokenizer_path, false)
+		Ok(Self::build(vision_path, text_path, tokenizer_path, false))
 	}
 
-	fn validate_and_build(
+	fn build(
 		vision_path: PathBuf,
 		text_path: PathBuf,
 		tokenizer_path: PathBuf,
 		quiet: bool,
-	) -> Result<Self> {
-		if !vision_path.exists() {
-			anyhow::bail!(
-				"Vision model file does not exist: {}",
-				vision_path.display()
-			);
-		}
-		if !text_path.exists() {
-			anyhow::bail!("Text model file does not exist: {}", text_path.display());
-		}
-		if !tokenizer_path.exists() {
-			anyhow::bail!(
-				"Tokenizer file does not exist: {}",
-				tokenizer_path.display()
-			);
-		}
-
-		Ok(Self {
+	) -> Self {
+		Self {
 			vision: None,
 			text: None,
 			vision_path,
 			text_path,
 			tokenizer_path,
 			quiet,
-		})
+			batch_size: config::DEFAULT_BATCH_SIZE,
+			text_cache: VecDeque::new(),
+			text_cache_capacity: 0,
+			long_text: super::text::LongTextMode::default(),
+			preprocess: PreprocessMode::default(),
+			normalization: Normalization::default(),
+			auto_orient: config::DEFAULT_AUTO_ORIENT,
+			fingerprint: OnceLock::new(),
+		}
 	}
 
+	/// Fail early, before any work, when the vision model file is missing
+	pub fn require_vision(&self) -> Result<()> {
+		require_file(&self.vision_path, "Vision model")
+	}
+
+	/// Load both models and run one dummy forward pass through each.
+	///
+	/// Sessions are lazy-loaded and ONNX Runtime finishes graph optimization and
+	/// allocator setup on the first run, so this moves that stall out of the first query.
+	pub fn warmup(&mut self) -> Result<()> {
+		let start = std::time::Instant::now();
+
+		let size = config::INPUT_SIZE;
+		self.encode_image(&image::DynamicImage::new_rgb8(size, size))?;
+		self.encode_text_uncached("warmup")?;
+
+		if !self.quiet {
+			crate::ui::debug(&format!(
+				"Models warmed up in {:.1}s",
+				start.elapsed().as_secs_f32()
+			));
+		}
+		Ok(())
+	}
+
+	/// Cap how many images are stacked into one forward pass (minimum 1)
+	pub fn set_batch_size(&mut self, batch_size: usize) {
+		self.batch_size = batch_size.max(1);
+	}
+
+	pub fn batch_size(&self) -> usize {
+		self.batch_size
+	}
+
+	pub fn vision_path(&self) -> &std::path::Path {
+		&self.vision_path
+	}
+
+	/// Choose how images are fitted to the model input. Embeddings from
+	/// different modes are not directly comparable, so rescan after changing it.
+	pub fn set_preprocess_mode(&mut self, mode: PreprocessMode) {
+		self.preprocess = mode;
+		self.fingerprint = OnceLock::new();
+	}
+
+	pub fn preprocess_mode(&self) -> PreprocessMode {
+		self.preprocess
+	}
+
+	/// Override the pixel normalization for models trained with different statistics
+	pub fn set_normalization(&mut self, normalization: Normalization) {
+		self.normalization = normalization;
+		self.fingerprint = OnceLock::new();
+	}
+
+	pub fn normalization(&self) -> Normalization {
+		self.normalization
+	}
+
+	/// Rotate images upright according to their EXIF orientation tag when
+	/// Scout loads them. Changes embeddings of rotated photos, so rescan after
+	/// changing it.
+	pub fn set_auto_orient(&mut self, enabled: bool) {
+		self.auto_orient = enabled;
+		self.fingerprint = OnceLock::new();
+	}
+
+	pub fn auto_orient(&self) -> bool {
+		self.auto_orient
+	}
+
+	/// [`model_fingerprint`](super::model_fingerprint) of the vision model
+	/// with these preprocessing settings, or `None` when the model can't be read
+	pub fn fingerprint(&self) -> Option<&str> {
+		self.fingerprint
+			.get_or_init(|| {
+				match super::model_fingerprint(
+					&self.vision_path,
+					self.preprocess,
+					self.normalization,
+					self.auto_orient,
+				) {
+					Ok(fingerprint) => Some(fingerprint),
+					Err(e) => {
+						if !self.quiet {
+							crate::ui::debug(&format!("Model fingerprint unavailable: {:#}", e));
+						}
+						None
+					}
+				}
+			})
+			.as_deref()
+	}
+
+	/// Use an already computed fingerprint instead of hashing the model again
+	pub(crate) fn set_fingerprint(&mut self, fingerprint: Option<String>) {
+		self.fingerprint = OnceLock::from(fingerprint);
+	}
+
+	/// Keep up to `capacity` text embeddings in an LRU cache keyed by the exact
+	/// query. Text encoding is deterministic, so entries never need invalidating.
+	pub fn set_text_cache(&mut self, capacity: usize) {
+		self.text_cache_capacity = capacity;
+		self.text_cache.truncate(capacity);
+	}
+
+	/// Truncate long queries or encode them in chunks. Cached query embeddings
+	/// are dropped when the mode changes.
+	pub fn set_long_text_mode(&mut self, mode: super::text::LongTextMode) {
+		if mode != self.long_text {
+			self.text_cache.clear();
+		}
+		self.long_text = mode;
+	}
+
+	pub fn long_text_mode(&self) -> super::text::LongTextMode {
+		self.long_text
+	}
+
+	/// Encode one image, failing with a
+	/// [`DegenerateEmbedding`](crate::core::DegenerateEmbedding) error if the
+	/// model returns a zero or NaN vector
 	pub fn encode_image(&mut self, image: &image::DynamicImage) -> Result<Embedding> {
+		let (mode, norm) = (self.preprocess, self.normalization);
+		self.vision()?.encode(image, mode, norm)?.non_degenerate()
+	}
+
+	/// Encode many images, running at most `batch_size` per forward pass.
+	///
+	/// A batch that runs out of memory is halved and retried, down to single
+	/// images, and later calls keep the smaller batch size.
+	///
+	/// Degenerate embeddings are returned as they are, so one bad image
+	/// doesn't fail the batch; check [`Embedding::is_degenerate`].
+	pub fn encode_images(&mut self, images: &[image::DynamicImage]) -> Result<Vec<Embedding>> {
+		let batch_size = self.batch_size;
+		let (mode, norm) = (self.preprocess, self.normalization);
+		let vision = self.vision()?;
+
+		let (embeddings, fitted) = encode_in_batches(images, batch_size, |batch| {
+			vision.encode_batch(batch, mode, norm)
+		})?;
+		if fitted < batch_size {
+			if !self.quiet {
+				crate::ui::debug(&format!(
+					"Out of memory at batch size {}, lowered it to {}",
+					batch_size, fitted
+				));
+			}
+			self.batch_size = fitted;
+		}
+		Ok(embeddings)
+	}
+
+	/// Embedding size produced by the vision model (loads it if needed)
+	pub fn dimension(&mut self) -> Result<usize> {
+		Ok(self.vision()?.dimension())
+	}
+
+	fn vision(&mut self) -> Result<&mut super::vision::VisionModel> {
 		if self.vision.is_none() {
+			self.require_vision()?;
 			if !self.quiet {
 				crate::ui::debug(&format!(
 					"Loading vision model: {}",
@@ -109,11 +284,86 @@ impl Models {
 			}
 		}
 
-		self.vision.as_mut().unwrap().encode(image)
+		Ok(self.vision.as_mut().unwrap())
 	}
 
 	pub fn encode_text(&mut self, text: &str) -> Result<Embedding> {
+		if let Some(pos) = self.text_cache.iter().position(|(query, _)| query == text) {
+			let entry = self.text_cache.remove(pos).unwrap();
+			let embedding = entry.1.clone();
+			self.text_cache.push_back(entry);
+			if !self.quiet {
+				crate::ui::debug(&format!("Query cache hit: \"{}\"", text));
+			}
+			return Ok(embedding);
+		}
+
+		let embedding = self.encode_text_uncached(text)?;
+
+		if self.text_cache_capacity > 0 {
+			if self.text_cache.len() >= self.text_cache_capacity {
+				self.text_cache.pop_front();
+			}
+			self.text_cache
+				.push_back((text.to_string(), embedding.clone()));
+		}
+
+		Ok(embedding)
+	}
+
+	/// Encode several phrasings of one query and mean-pool them
+	pub fn encode_text_mean(&mut self, texts: &[&str]) -> Result<Embedding> {
+		if texts.is_empty() || texts.iter().any(|t| t.trim().is_empty()) {
+			anyhow::bail!("Query cannot be empty");
+		}
+
+		let embeddings = texts
+			.iter()
+			.map(|text| self.encode_text(text))
+			.collect::<Result<Vec<_>>>()?;
+		Ok(Embedding::mean(&embeddings).expect("at least one query"))
+	}
+
+	/// Encode a `+`/`-` expression of phrases, see [`super::text::parse_expression`]
+	pub fn encode_expression(&mut self, expr: &str) -> Result<Embedding> {
+		let terms = super::text::parse_expression(expr)?;
+		let mut sum: Option<Embedding> = None;
+		for (sign, phrase) in &terms {
+			let term = self.encode_text(phrase)? * *sign;
+			sum = Some(match sum {
+				Some(sum) => sum + &term,
+				None => term,
+			});
+		}
+		sum.expect("at least one term")
+			.normalized()
+			.non_degenerate()
+			.with_context(|| format!("Expression \"{}\" cancels out", expr))
+	}
+
+	/// Encode several queries in one forward pass, returned in input order
+	pub fn encode_text_batch(&mut self, texts: &[&str]) -> Result<Vec<Embedding>> {
+		let embeddings = self.text()?.encode_batch(texts)?;
+		if let Some(first) = embeddings.first() {
+			self.check_text_dimension(first.0.len())?;
+		}
+		Ok(embeddings)
+	}
+
+	fn encode_text_uncached(&mut self, text: &str) -> Result<Embedding> {
+		let embedding = match self.long_text {
+			super::text::LongTextMode::Truncate => self.text()?.encode(text)?,
+			super::text::LongTextMode::Chunk => self.text()?.encode_chunked(text)?,
+		}
+		.non_degenerate()?;
+		self.check_text_dimension(embedding.0.len())?;
+		Ok(embedding)
+	}
+
+	fn text(&mut self) -> Result<&mut super::text::TextModel> {
 		if self.text.is_none() {
+			require_file(&self.text_path, "Text model")?;
+			require_file(&self.tokenizer_path, "Tokenizer")?;
 			if !self.quiet {
 				crate::ui::debug(&format!("Loading text model: {}", self.text_path.display()));
 			}
@@ -126,6 +376,137 @@ impl Models {
 			}
 		}
 
-		self.text.as_mut().unwrap().encode(text)
+		Ok(self.text.as_mut().unwrap())
+	}
+
+	/// Text and vision towers must share one embedding space
+	fn check_text_dimension(&self, text_dim: usize) -> Result<()> {
+		if let Some(vision) = &self.vision {
+			if vision.dimension() != text_dim {
+				anyhow::bail!(
+					"Text model produces {}D embeddings but vision model produces {}D. Use models from the same family",
+					text_dim,
+					vision.dimension()
+				);
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Run `encode` over `items` in batches of up to `batch_size`, halving a batch
+/// that runs out of memory until it fits or is down to one item.
+///
+/// Returns the embeddings in order and the batch size that last succeeded.
+fn encode_in_batches<T>(
+	items: &[T],
+	batch_size: usize,
+	mut encode: impl FnMut(&[T]) -> Result<Vec<Embedding>>,
+) -> Result<(Vec<Embedding>, usize)> {
+	let mut batch_size = batch_size.max(1);
+	let mut embeddings = Vec::with_capacity(items.len());
+	let mut start = 0;
+
+	while start < items.len() {
+		let end = (start + batch_size).min(items.len());
+		match encode(&items[start..end]) {
+			Ok(batch) => {
+				embeddings.extend(batch);
+				start = end;
+			}
+			Err(e) if end - start > 1 && is_out_of_memory(&e) => batch_size = (end - start) / 2,
+			Err(e) => return Err(e),
+		}
+	}
+
+	Ok((embeddings, batch_size))
+}
+
+/// Whether a forward pass failed for lack of memory, going by the messages of
+/// ONNX Runtime's allocators and the CUDA provider
+fn is_out_of_memory(error: &anyhow::Error) -> bool {
+	let message = format!("{:#}", error).to_lowercase();
+	OUT_OF_MEMORY_MESSAGES
+		.iter()
+		.any(|marker| message.contains(marker))
+}
+
+const OUT_OF_MEMORY_MESSAGES: &[&str] = &[
+	"out of memory",
+	"failed to allocate memory",
+	"bad_alloc",
+	"cudaerrormemoryallocation",
+	"cublas_status_alloc_failed",
+];
+
+fn require_file(path: &Path, what: &str) -> Result<()> {
+	if !path.is_file() {
+		anyhow::bail!(
+			"{} not found at {}. Run 'scout models download' to fetch it",
+			what,
+			path.display()
+		);
+	}
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_missing_vision_model_only_fails_image_encoding() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		for file in [config::TEXT_MODEL, config::TOKENIZER] {
+			std::fs::write(dir.join(file), b"").unwrap();
+		}
+
+		let mut models = Models::from_dir(dir.to_path_buf()).unwrap();
+		let required = models.require_vision();
+		let image = models.encode_image(&image::DynamicImage::new_rgb8(8, 8));
+		// The text files are placeholders, so loading fails, just not for a missing file
+		let text = models.encode_text("a dog");
+
+		for err in [required.unwrap_err(), image.unwrap_err()] {
+			let message = err.to_string();
+			assert!(message.starts_with("Vision model not found"), "{}", message);
+			assert!(message.contains(config::VISION_MODEL), "{}", message);
+		}
+		let message = format!("{:#}", text.unwrap_err());
+		assert!(!message.contains("not found at"), "{}", message);
+	}
+
+	#[test]
+	fn test_batches_shrink_after_out_of_memory() {
+		let items: Vec<usize> = (0..11).collect();
+		let mut attempts = Vec::new();
+		let (embeddings, fitted) = encode_in_batches(&items, 8, |batch| {
+			attempts.push(batch.len());
+			if batch.len() > 3 {
+				anyhow::bail!("CUDA failure 2: out of memory");
+			}
+			Ok(batch
+				.iter()
+				.map(|&i| Embedding::raw(vec![i as f32]))
+				.collect())
+		})
+		.unwrap();
+
+		assert_eq!(fitted, 2);
+		assert_eq!(attempts, [8, 4, 2, 2, 2, 2, 2, 1]);
+		let order: Vec<f32> = embeddings.iter().map(|e| e.as_slice()[0]).collect();
+		assert_eq!(order, (0..11).map(|i| i as f32).collect::<Vec<_>>());
+
+		// Other errors, and running out of memory on a single item, are surfaced
+		let failed = encode_in_batches(&items, 8, |_| anyhow::bail!("Invalid input shape"));
+		assert!(failed.unwrap_err().to_string().contains("shape"));
+		let attempts = std::cell::Cell::new(0);
+		let failed = encode_in_batches(&items, 4, |_| {
+			attempts.set(attempts.get() + 1);
+			anyhow::bail!("Failed to allocate memory for requested buffer of size 4096")
+		});
+		assert!(failed.is_err());
+		assert_eq!(attempts.get(), 3);
 	}
 }
@@ -2,8 +2,28 @@
 //!
 //! Lazy-loading model coordinator for vision and text encoders.
 
+pub mod download;
 pub mod manager;
 pub mod text;
 pub mod vision;
 
 pub use manager::Models;
+pub use text::LongTextMode;
+pub use vision::{model_fingerprint, Normalization, PreprocessMode};
+
+/// Embedding size declared by a model's `pooler_output`, if it is static
+pub(crate) fn pooler_dimension(session: &ort::session::Session) -> Option<usize> {
+	output_dimension(session, "pooler_output")
+}
+
+/// Last dimension declared by the named output, if it is static
+pub(crate) fn output_dimension(session: &ort::session::Session, name: &str) -> Option<usize> {
+	session
+		.outputs()
+		.iter()
+		.find(|output| output.name() == name)
+		.and_then(|output| output.dtype().tensor_shape())
+		.and_then(|shape| shape.last().copied())
+		.filter(|&dim| dim > 0)
+		.map(|dim| dim as usize)
+}
@@ -1,19 +1,100 @@
 //! # Text Encoder
 //!
 //! SigLIP2 text model for encoding search queries into embeddings.
-//! Handles tokenization and warns about query length limits.
+//! Handles tokenization and either truncates queries beyond the length limit
+//! or splits them into chunks that are encoded separately and averaged.
+//! CLIP/OpenCLIP exports are supported by detecting their input and output names.
 
 use anyhow::{Context, Result};
 use ort::session::Session;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
-use tokenizers::Tokenizer;
+use tokenizers::{Encoding, Tokenizer};
 
-use crate::config::EMBEDDING_DIM;
+use crate::config::{EMBEDDING_DIM, MAX_QUERY_TOKENS};
 use crate::core::Embedding;
 
+/// What to do with queries longer than the text encoder's token limit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LongTextMode {
+	/// Keep the first tokens and drop the rest, with a warning
+	#[default]
+	Truncate,
+	/// Encode every token-limited chunk and average their embeddings
+	Chunk,
+}
+
 pub struct TextModel {
 	session: Session,
 	tokenizer: Tokenizer,
+	signature: TextSignature,
+	dim: usize,
+}
+
+/// Where a text encoder export puts its embedding
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextModelKind {
+	/// SigLIP2: pooled and projected embedding in `pooler_output`
+	SigLip,
+	/// CLIP/OpenCLIP with a projection head: `text_embeds`
+	Clip,
+}
+
+impl TextModelKind {
+	/// Output tensor holding the embeddings
+	pub fn output_name(self) -> &'static str {
+		match self {
+			TextModelKind::SigLip => "pooler_output",
+			TextModelKind::Clip => "text_embeds",
+		}
+	}
+}
+
+/// Inputs and output of a text encoder, detected from the model's tensor names
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextSignature {
+	pub kind: TextModelKind,
+	/// Model takes an `attention_mask` (1 for tokens, 0 for padding)
+	pub attention_mask: bool,
+	/// Model takes `token_type_ids` (all zeros for a single sentence)
+	pub token_type_ids: bool,
+}
+
+impl TextSignature {
+	/// Detect the signature from input and output names.
+	///
+	/// Projected outputs are preferred: CLIP exports also have an unprojected
+	/// `pooler_output`, so `text_embeds` wins over it. Exports with only a
+	/// `last_hidden_state` are rejected, since without the projection head
+	/// their embeddings don't share a space with the vision model's.
+	pub fn detect(inputs: &[&str], outputs: &[&str]) -> Result<Self> {
+		if !inputs.contains(&"input_ids") {
+			anyhow::bail!("Text model has no 'input_ids' input (found {:?})", inputs);
+		}
+
+		let kind = [TextModelKind::Clip, TextModelKind::SigLip]
+			.into_iter()
+			.find(|kind| outputs.contains(&kind.output_name()))
+			.with_context(|| {
+				format!(
+					"Text model export is missing a 'pooler_output' or 'text_embeds' output (found {:?}); re-export it with the projection head included",
+					outputs
+				)
+			})?;
+
+		Ok(Self {
+			kind,
+			attention_mask: inputs.contains(&"attention_mask"),
+			token_type_ids: inputs.contains(&"token_type_ids"),
+		})
+	}
+
+	fn of_session(session: &Session) -> Result<Self> {
+		let inputs: Vec<&str> = session.inputs().iter().map(|i| i.name()).collect();
+		let outputs: Vec<&str> = session.outputs().iter().map(|o| o.name()).collect();
+		Self::detect(&inputs, &outputs)
+	}
 }
 
 impl TextModel {
@@ -24,53 +105,327 @@ impl TextModel {
 		let tokenizer = Tokenizer::from_file(tokenizer_path)
 			.map_err(|e| anyhow::anyhow!("Failed to load tokenizer: {}", e))?;
 
-		Ok(Self { session, tokenizer })
+		let signature = TextSignature::of_session(&session)?;
+		crate::ui::debug(&format!("Text model signature: {:?}", signature));
+
+		let dim = super::output_dimension(&session, signature.kind.output_name())
+			.unwrap_or(EMBEDDING_DIM);
+
+		Ok(Self {
+			session,
+			tokenizer,
+			signature,
+			dim,
+		})
+	}
+
+	pub fn dimension(&self) -> usize {
+		self.dim
 	}
 
 	pub fn encode(&mut self, text: &str) -> Result<Embedding> {
-		// Validate input
-		let trimmed = text.trim();
-		if trimmed.is_empty() {
-			anyhow::bail!("Query cannot be empty");
+		self.encode_batch(&[text])?
+			.pop()
+			.context("Text model returned no embedding")
+	}
+
+	/// Encode several queries in a single forward pass.
+	///
+	/// Shorter queries are padded to the longest one, with an attention mask
+	/// when the model accepts it. Embeddings are returned in input order.
+	pub fn encode_batch(&mut self, texts: &[&str]) -> Result<Vec<Embedding>> {
+		let tokenized: Vec<Vec<i64>> = texts
+			.iter()
+			.map(|text| self.tokenize(text))
+			.collect::<Result<_>>()?;
+		self.run(&tokenized)
+	}
+
+	/// Encode a query of any length: split it into chunks that fit the token
+	/// limit, encode them in one forward pass and mean-pool the embeddings.
+	///
+	/// Short queries give the same embedding as [`TextModel::encode`].
+	pub fn encode_chunked(&mut self, text: &str) -> Result<Embedding> {
+		let encoding = self.encoding(text)?;
+		let ids: Vec<i64> = encoding.get_ids().iter().map(|&x| x as i64).collect();
+		let chunks = chunk_ids(&ids, encoding.get_special_tokens_mask(), MAX_QUERY_TOKENS);
+		if chunks.len() > 1 {
+			crate::ui::debug(&format!(
+				"Query of {} tokens split into {} chunks",
+				ids.len(),
+				chunks.len()
+			));
 		}
 
-		let encoding = self
-			.tokenizer
-			.encode(trimmed, true)
-			.map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))?;
+		let embeddings = self.run(&chunks)?;
+		Embedding::mean(&embeddings).context("Text model returned no embedding")
+	}
+
+	/// Run the encoder on tokenized queries, padding them to the longest one
+	fn run(&mut self, tokenized: &[Vec<i64>]) -> Result<Vec<Embedding>> {
+		if tokenized.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		let max_len = tokenized.iter().map(Vec::len).max().unwrap_or(0);
+		let pad_id = self.tokenizer.get_padding().map_or(0, |p| p.pad_id) as i64;
+
+		let mut input_ids = Vec::with_capacity(tokenized.len() * max_len);
+		let mut attention_mask = Vec::with_capacity(tokenized.len() * max_len);
+		for ids in tokenized {
+			let row_end = input_ids.len() + max_len;
+			input_ids.extend(ids);
+			input_ids.resize(row_end, pad_id);
+			attention_mask.resize(attention_mask.len() + ids.len(), 1i64);
+			attention_mask.resize(row_end, 0i64);
+		}
+
+		let shape = vec![tokenized.len(), max_len];
+		let mut inputs = vec![(
+			"input_ids",
+			ort::value::Value::from_array((shape.clone(), input_ids))?,
+		)];
+		if self.signature.attention_mask {
+			inputs.push((
+				"attention_mask",
+				ort::value::Value::from_array((shape.clone(), attention_mask))?,
+			));
+		}
+		if self.signature.token_type_ids {
+			let token_types = vec![0i64; tokenized.len() * max_len];
+			inputs.push((
+				"token_type_ids",
+				ort::value::Value::from_array((shape, token_types))?,
+			));
+		}
+
+		let output_name = self.signature.kind.output_name();
+		let outputs = self.session.run(inputs)?;
+		let output = outputs
+			.get(output_name)
+			.with_context(|| format!("No {} output found", output_name))?;
 
+		let (shape, data) = output.try_extract_tensor::<f32>()?;
+		let dims: Vec<usize> = shape.iter().map(|&x| x as usize).collect();
+		let embeddings = extract_embeddings(&dims, data, tokenized.len(), self.dim)?;
+
+		if let Some(first) = embeddings.first() {
+			self.dim = first.len();
+		}
+
+		Ok(embeddings.into_iter().map(Embedding::new).collect())
+	}
+
+	/// Tokenize a single query, truncating it to `MAX_QUERY_TOKENS`
+	fn tokenize(&self, text: &str) -> Result<Vec<i64>> {
+		let encoding = self.encoding(text)?;
 		let mut input_ids: Vec<i64> = encoding.get_ids().iter().map(|&x| x as i64).collect();
 
 		// Warn if query is too long and will be truncated
-		if input_ids.len() > crate::config::MAX_QUERY_TOKENS {
+		if input_ids.len() > MAX_QUERY_TOKENS {
 			crate::ui::warn(&format!(
-				"Query too long ({} tokens, max {}). Truncating - results may be less accurate.",
+				"Query too long ({} tokens, max {}). Truncating - results may be less accurate. \
+				 Use --long-text chunk to encode all of it",
 				input_ids.len(),
-				crate::config::MAX_QUERY_TOKENS
+				MAX_QUERY_TOKENS
 			));
-			input_ids.truncate(crate::config::MAX_QUERY_TOKENS);
+			input_ids.truncate(MAX_QUERY_TOKENS);
+		}
+
+		Ok(input_ids)
+	}
+
+	/// Full tokenization of a trimmed, non-empty query, special tokens included
+	fn encoding(&self, text: &str) -> Result<Encoding> {
+		let trimmed = text.trim();
+		if trimmed.is_empty() {
+			anyhow::bail!("Query cannot be empty");
 		}
 
-		let shape = vec![1, input_ids.len()];
-		let input = ort::value::Value::from_array((shape, input_ids))?;
+		self.tokenizer
+			.encode(trimmed, true)
+			.map_err(|e| anyhow::anyhow!("Tokenization failed: {}", e))
+	}
+}
+
+/// Split a query expression like `"sunset" - "beach" + mountain` into signed
+/// phrases: `[(1.0, "sunset"), (-1.0, "beach"), (1.0, "mountain")]`.
+///
+/// `+` and `-` are operators only at the start of a term or after whitespace,
+/// so hyphenated words like `black-and-white` stay one phrase. Quotes are
+/// optional and keep operators inside a phrase literal.
+pub fn parse_expression(expr: &str) -> Result<Vec<(f32, String)>> {
+	let mut terms = Vec::new();
+	let mut sign = 1.0;
+	let mut phrase = String::new();
+	let mut quoted = false;
+	let mut boundary = true;
+	let mut leading = true;
 
-		let outputs = self.session.run(ort::inputs!["input_ids" => input])?;
-		let embedding = extract_embedding(&outputs)?;
+	for c in expr.chars() {
+		let operator = matches!(c, '+' | '-') && !quoted && boundary;
+		if operator && !leading {
+			terms.push((sign, expression_term(&phrase, expr)?));
+			phrase.clear();
+		}
+		match c {
+			_ if operator => sign = if c == '+' { 1.0 } else { -1.0 },
+			'"' => quoted = !quoted,
+			_ => phrase.push(c),
+		}
+		// A leading operator only sets the sign of the first term
+		leading &= c.is_whitespace() && !quoted;
+		boundary = operator || (!quoted && (c.is_whitespace() || c == '"'));
+	}
+	if quoted {
+		anyhow::bail!("Unclosed quote in expression \"{}\"", expr);
+	}
+	terms.push((sign, expression_term(&phrase, expr)?));
+
+	Ok(terms)
+}
 
-		Ok(Embedding::new(embedding))
+fn expression_term(phrase: &str, expr: &str) -> Result<String> {
+	let phrase = phrase.trim();
+	if phrase.is_empty() {
+		anyhow::bail!("Empty term in expression \"{}\"", expr);
 	}
+	Ok(phrase.to_string())
 }
 
-fn extract_embedding(outputs: &ort::session::SessionOutputs) -> Result<Vec<f32>> {
-	let pooler = outputs
-		.get("pooler_output")
-		.context("No pooler output found")?;
+/// Split token ids into pieces of at most `max_len`, on token boundaries.
+///
+/// Special tokens the tokenizer adds at the start and end (marked in
+/// `special_mask`) are repeated around every piece, so each one is a
+/// well-formed query on its own.
+fn chunk_ids(ids: &[i64], special_mask: &[u32], max_len: usize) -> Vec<Vec<i64>> {
+	if ids.len() <= max_len {
+		return vec![ids.to_vec()];
+	}
+
+	let prefix = special_mask.iter().take_while(|&&m| m == 1).count();
+	let suffix = special_mask[prefix..]
+		.iter()
+		.rev()
+		.take_while(|&&m| m == 1)
+		.count();
+	let (head, rest) = ids.split_at(prefix);
+	let (body, tail) = rest.split_at(rest.len() - suffix);
+
+	let size = max_len.saturating_sub(prefix + suffix).max(1);
+	body.chunks(size)
+		.map(|chunk| [head, chunk, tail].concat())
+		.collect()
+}
+
+/// Split a model output of shape `dims` into one embedding per query
+fn extract_embeddings(
+	dims: &[usize],
+	data: &[f32],
+	batch: usize,
+	expected_dim: usize,
+) -> Result<Vec<Vec<f32>>> {
+	match dims {
+		[n, dim] if *n == batch => Ok(data.chunks(*dim).map(|chunk| chunk.to_vec()).collect()),
+		_ if batch == 1 => Ok(vec![data.iter().take(expected_dim).copied().collect()]),
+		_ => anyhow::bail!(
+			"Unexpected text output shape {:?} for batch of {}",
+			dims,
+			batch
+		),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_multi_term_expression() {
+		let terms = parse_expression(r#""sunset" - "beach" + mountain lake"#).unwrap();
+		assert_eq!(
+			terms,
+			[
+				(1.0, "sunset".to_string()),
+				(-1.0, "beach".to_string()),
+				(1.0, "mountain lake".to_string()),
+			]
+		);
+
+		let terms = parse_expression(r#"-black-and-white photo + "a + b""#).unwrap();
+		assert_eq!(
+			terms,
+			[
+				(-1.0, "black-and-white photo".to_string()),
+				(1.0, "a + b".to_string()),
+			]
+		);
+
+		for invalid in ["", "sunset +", "sunset + - beach", r#""sunset"#] {
+			assert!(parse_expression(invalid).is_err(), "{:?} parsed", invalid);
+		}
+	}
+
+	#[test]
+	fn test_detect_siglip_and_clip_signatures() {
+		let siglip = TextSignature::detect(&["input_ids"], &["last_hidden_state", "pooler_output"]);
+		assert_eq!(
+			siglip.unwrap(),
+			TextSignature {
+				kind: TextModelKind::SigLip,
+				attention_mask: false,
+				token_type_ids: false,
+			}
+		);
+
+		// HuggingFace CLIP exports have an unprojected pooler_output as well
+		let clip = TextSignature::detect(
+			&["input_ids", "attention_mask"],
+			&["text_embeds", "last_hidden_state", "pooler_output"],
+		)
+		.unwrap();
+		assert_eq!(clip.kind, TextModelKind::Clip);
+		assert!(clip.attention_mask);
+
+		// Unprojected hidden states aren't comparable with image embeddings
+		let bare = TextSignature::detect(
+			&["input_ids", "attention_mask", "token_type_ids"],
+			&["last_hidden_state"],
+		);
+		assert!(bare.unwrap_err().to_string().contains("pooler_output"));
+
+		assert!(TextSignature::detect(&["pixel_values"], &["pooler_output"]).is_err());
+		assert!(TextSignature::detect(&["input_ids"], &["logits"]).is_err());
+	}
+
+	#[test]
+	fn test_extract_pooled_embeddings() {
+		let data = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+		let rows = extract_embeddings(&[2, 3], &data, 2, 3).unwrap();
+		assert_eq!(rows, vec![vec![1.0, 2.0, 3.0], vec![4.0, 5.0, 6.0]]);
+		assert!(extract_embeddings(&[3, 2], &data, 2, 2).is_err());
+	}
+
+	#[test]
+	fn test_chunks_split_on_tokens_and_keep_special_tokens() {
+		// <bos> 10..=19 <eos>
+		let ids: Vec<i64> = [1].into_iter().chain(10..20).chain([2]).collect();
+		let mut mask = vec![0u32; ids.len()];
+		mask[0] = 1;
+		mask[11] = 1;
 
-	let (shape, data) = pooler.try_extract_tensor::<f32>()?;
-	let dims: Vec<usize> = shape.iter().map(|&x| x as usize).collect();
+		let chunks = chunk_ids(&ids, &mask, 6);
+		assert_eq!(
+			chunks,
+			vec![
+				vec![1, 10, 11, 12, 13, 2],
+				vec![1, 14, 15, 16, 17, 2],
+				vec![1, 18, 19, 2],
+			]
+		);
+		assert!(chunks.iter().all(|chunk| chunk.len() <= 6));
 
-	match dims.as_slice() {
-		[1, dim] if *dim == EMBEDDING_DIM => Ok(data.to_vec()),
-		_ => Ok(data.iter().take(EMBEDDING_DIM).copied().collect()),
+		// Queries within the limit stay whole
+		assert_eq!(chunk_ids(&ids, &mask, 12), vec![ids.clone()]);
 	}
 }
@@ -5,38 +5,160 @@
 
 use anyhow::{Context, Result};
 use ort::session::Session;
+use serde::{Deserialize, Serialize};
 use std::path::Path;
 
-use crate::config::{EMBEDDING_DIM, INPUT_SIZE};
-use crate::core::Embedding;
+use crate::config::{
+	DEFAULT_PREPROCESS_MODE, EMBEDDING_DIM, INPUT_SIZE, LETTERBOX_FILL, PIXEL_MEAN, PIXEL_STD,
+};
+use crate::core::{Embedding, FileHash, HashMode};
+
+/// How images are fitted to the square model input
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum PreprocessMode {
+	/// Resize to 512x512, distorting non-square images
+	Stretch,
+	/// Resize keeping the aspect ratio and pad to 512x512 with gray
+	Letterbox,
+}
+
+impl Default for PreprocessMode {
+	fn default() -> Self {
+		DEFAULT_PREPROCESS_MODE
+	}
+}
+
+/// Per-channel (RGB) normalization applied after scaling pixels to 0.0-1.0
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Normalization {
+	pub mean: [f32; 3],
+	pub std: [f32; 3],
+}
+
+impl Default for Normalization {
+	fn default() -> Self {
+		Self {
+			mean: PIXEL_MEAN,
+			std: PIXEL_STD,
+		}
+	}
+}
+
+/// Identify everything that determines an image embedding.
+///
+/// Hashes the whole vision model file, then combines that with the input
+/// size, preprocessing `mode`, normalization and EXIF auto-orientation into
+/// a 64-bit xxh3.
+/// Sidecars with a matching fingerprint are still valid, whichever Scout
+/// version wrote them.
+pub fn model_fingerprint(
+	model_path: &Path,
+	mode: PreprocessMode,
+	norm: Normalization,
+	auto_orient: bool,
+) -> Result<String> {
+	let model_hash = FileHash::compute_with(model_path, HashMode::Full)
+		.with_context(|| format!("Failed to hash {}", model_path.display()))?;
+	let params = format!(
+		"{}|{}|{:?}|{:?}|{:?}|{}",
+		model_hash, INPUT_SIZE, mode, norm.mean, norm.std, auto_orient
+	);
+	Ok(format!(
+		"{:016x}",
+		xxhash_rust::xxh3::xxh3_64(params.as_bytes())
+	))
+}
 
 pub struct VisionModel {
 	session: Session,
+	/// Embedding size, read from the model and confirmed by the first output
+	dim: usize,
 }
 
 impl VisionModel {
 	pub fn load(model_path: &Path) -> Result<Self> {
 		let session =
 			crate::runtime::create_session(model_path).context("Failed to load vision model")?;
-		Ok(Self { session })
+		let dim = super::pooler_dimension(&session).unwrap_or(EMBEDDING_DIM);
+		Ok(Self { session, dim })
 	}
 
-	pub fn encode(&mut self, image: &image::DynamicImage) -> Result<Embedding> {
-		let pixels = preprocess(image)?;
-		let input = ort::value::Value::from_array(pixels)?;
+	pub fn dimension(&self) -> usize {
+		self.dim
+	}
+
+	pub fn encode(
+		&mut self,
+		image: &image::DynamicImage,
+		mode: PreprocessMode,
+		norm: Normalization,
+	) -> Result<Embedding> {
+		self.encode_batch(std::slice::from_ref(image), mode, norm)?
+			.pop()
+			.context("Vision model returned no embedding")
+	}
+
+	/// Encode several images in a single forward pass
+	pub fn encode_batch(
+		&mut self,
+		images: &[image::DynamicImage],
+		mode: PreprocessMode,
+		norm: Normalization,
+	) -> Result<Vec<Embedding>> {
+		if images.is_empty() {
+			return Ok(Vec::new());
+		}
+
+		// Stack preprocessed tensors along the batch axis: [N, 3, H, W]
+		let size = INPUT_SIZE as usize;
+		let mut data = Vec::with_capacity(images.len() * 3 * size * size);
+		for image in images {
+			let (_, pixels) = preprocess(image, mode, norm)?;
+			data.extend(pixels);
+		}
+
+		let shape = vec![images.len(), 3, size, size];
+		let input = ort::value::Value::from_array((shape, data))?;
 
 		let outputs = self.session.run(ort::inputs!["pixel_values" => input])?;
-		let embedding = extract_embedding(&outputs)?;
+		let embeddings = extract_embeddings(&outputs, images.len(), self.dim)?;
 
-		Ok(Embedding::new(embedding))
+		// Models with a dynamic output shape only reveal their size at runtime
+		if let Some(first) = embeddings.first() {
+			self.dim = first.len();
+		}
+
+		Ok(embeddings.into_iter().map(Embedding::new).collect())
 	}
 }
 
-fn preprocess(img: &image::DynamicImage) -> Result<(Vec<usize>, Vec<f32>)> {
+fn preprocess(
+	img: &image::DynamicImage,
+	mode: PreprocessMode,
+	norm: Normalization,
+) -> Result<(Vec<usize>, Vec<f32>)> {
 	use image::imageops::FilterType;
 
-	let resized = img.resize_exact(INPUT_SIZE, INPUT_SIZE, FilterType::CatmullRom);
-	let rgb = resized.to_rgb8();
+	let rgb = match mode {
+		PreprocessMode::Stretch => img
+			.resize_exact(INPUT_SIZE, INPUT_SIZE, FilterType::CatmullRom)
+			.to_rgb8(),
+		PreprocessMode::Letterbox => {
+			let resized = img
+				.resize(INPUT_SIZE, INPUT_SIZE, FilterType::CatmullRom)
+				.to_rgb8();
+			let mut canvas = image::RgbImage::from_pixel(
+				INPUT_SIZE,
+				INPUT_SIZE,
+				image::Rgb([LETTERBOX_FILL; 3]),
+			);
+			let x = (INPUT_SIZE - resized.width()) / 2;
+			let y = (INPUT_SIZE - resized.height()) / 2;
+			image::imageops::replace(&mut canvas, &resized, x as i64, y as i64);
+			canvas
+		}
+	};
 	let size = INPUT_SIZE as usize;
 
 	let shape = vec![1, 3, size, size];
@@ -46,16 +168,21 @@ fn preprocess(img: &image::DynamicImage) -> Result<(Vec<usize>, Vec<f32>)> {
 		for x in 0..size {
 			let px = rgb.get_pixel(x as u32, y as u32);
 			let idx = y * size + x;
-			data[idx] = px[0] as f32 / 255.0; // R
-			data[size * size + idx] = px[1] as f32 / 255.0; // G
-			data[2 * size * size + idx] = px[2] as f32 / 255.0; // B
+			for c in 0..3 {
+				// Channel planes are stored R, G, B
+				data[c * size * size + idx] = (px[c] as f32 / 255.0 - norm.mean[c]) / norm.std[c];
+			}
 		}
 	}
 
 	Ok((shape, data))
 }
 
-fn extract_embedding(outputs: &ort::session::SessionOutputs) -> Result<Vec<f32>> {
+fn extract_embeddings(
+	outputs: &ort::session::SessionOutputs,
+	batch: usize,
+	expected_dim: usize,
+) -> Result<Vec<Vec<f32>>> {
 	let pooler = outputs
 		.get("pooler_output")
 		.context("No pooler output found")?;
@@ -65,18 +192,83 @@ fn extract_embedding(outputs: &ort::session::SessionOutputs) -> Result<Vec<f32>>
 
 	// Handle different output shapes
 	match dims.as_slice() {
-		[1, dim] if *dim == EMBEDDING_DIM => Ok(data.to_vec()),
-		[1, n, dim] if *dim == EMBEDDING_DIM => {
-			// Mean pooling
-			let mut pooled = vec![0.0; *dim];
-			for i in 0..*n {
-				for j in 0..*dim {
-					pooled[j] += data[i * dim + j];
-				}
-			}
-			pooled.iter_mut().for_each(|v| *v /= *n as f32);
-			Ok(pooled)
+		[n, dim] if *n == batch => Ok(data.chunks(*dim).map(|chunk| chunk.to_vec()).collect()),
+		[n, tokens, dim] if *n == batch => {
+			// Mean pooling per image
+			Ok(data
+				.chunks(tokens * dim)
+				.map(|image| {
+					let mut pooled = vec![0.0; *dim];
+					for token in image.chunks(*dim) {
+						for (p, v) in pooled.iter_mut().zip(token) {
+							*p += v;
+						}
+					}
+					pooled.iter_mut().for_each(|v| *v /= *tokens as f32);
+					pooled
+				})
+				.collect())
 		}
-		_ => Ok(data.iter().take(EMBEDDING_DIM).copied().collect()),
+		_ if batch == 1 => Ok(vec![data.iter().take(expected_dim).copied().collect()]),
+		_ => anyhow::bail!(
+			"Unexpected vision output shape {:?} for batch of {}",
+			dims,
+			batch
+		),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_letterbox_pads_wide_images() {
+		let size = INPUT_SIZE as usize;
+		let wide = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+			1024,
+			256,
+			image::Rgb([255, 255, 255]),
+		));
+
+		let norm = Normalization::default();
+		let (_, stretched) = preprocess(&wide, PreprocessMode::Stretch, norm).unwrap();
+		let (_, letterboxed) = preprocess(&wide, PreprocessMode::Letterbox, norm).unwrap();
+
+		let fill = (LETTERBOX_FILL as f32 / 255.0 - norm.mean[0]) / norm.std[0];
+		let red = |data: &[f32], y: usize| data[y * size + size / 2];
+
+		// Content ends up 128px tall in the middle, with gray bars above and below
+		assert!((red(&letterboxed, 0) - fill).abs() < 1e-6);
+		assert!((red(&letterboxed, size - 1) - fill).abs() < 1e-6);
+		assert!((red(&letterboxed, size / 2) - 1.0).abs() < 1e-6);
+		assert!((red(&stretched, 0) - 1.0).abs() < 1e-6);
+	}
+
+	#[test]
+	fn test_pixels_are_normalized_per_channel() {
+		let size = INPUT_SIZE as usize;
+		let image = image::DynamicImage::ImageRgb8(image::RgbImage::from_pixel(
+			8,
+			8,
+			image::Rgb([0, 255, 51]),
+		));
+
+		// SigLIP2 defaults map 0..255 to -1..1
+		let (_, data) =
+			preprocess(&image, PreprocessMode::Stretch, Normalization::default()).unwrap();
+		assert!((data[0] + 1.0).abs() < 1e-6);
+		assert!((data[size * size] - 1.0).abs() < 1e-6);
+		assert!((data[2 * size * size] + 0.6).abs() < 1e-6);
+
+		// ImageNet-style statistics are applied per channel
+		let imagenet = Normalization {
+			mean: [0.485, 0.456, 0.406],
+			std: [0.229, 0.224, 0.225],
+		};
+		let (_, data) = preprocess(&image, PreprocessMode::Stretch, imagenet).unwrap();
+		assert!((data[0] - (-0.485 / 0.229)).abs() < 1e-5);
+		assert!((data[size * size] - (1.0 - 0.456) / 0.224).abs() < 1e-5);
+		assert!((data[2 * size * size] - (0.2 - 0.406) / 0.225).abs() < 1e-5);
 	}
 }
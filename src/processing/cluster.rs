@@ -3,24 +3,33 @@
 //! Cluster embeddings using HDBSCAN with optional UMAP reduction.
 //! Computes cohesion scores and identifies representative files.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::path::PathBuf;
 
 use anyhow::{Context, Result};
-use hdbscan::{Hdbscan, HdbscanHyperParams};
+use hdbscan::{DistanceMetric, Hdbscan, HdbscanHyperParams};
 use rayon::prelude::*;
 
-use crate::core::{compute_content_hash, Cluster, ClusterDatabase, ClusterParams, Embedding};
+use crate::config::CLUSTER_PRECOMPUTED_LIMIT;
+use crate::core::{
+	compute_content_hash, Cluster, ClusterDatabase, ClusterParams, Embedding, Metric,
+};
 use crate::storage::Sidecar;
 use crate::ui;
 
 /// Clusters embeddings using HDBSCAN algorithm
+///
+/// `metric` is the distance between the points HDBSCAN sees (UMAP output when
+/// `use_umap` is set) and the similarity cohesion is measured with. `seed`
+/// fixes UMAP's initial layout; without UMAP, HDBSCAN is deterministic anyway.
 pub fn cluster_embeddings(
 	sidecars: Vec<(PathBuf, Sidecar)>,
 	params: ClusterParams,
 	use_umap: bool,
 	umap_neighbors: usize,
 	umap_components: usize,
+	metric: Metric,
+	seed: Option<u64>,
 ) -> Result<ClusterDatabase> {
 	if sidecars.is_empty() {
 		anyhow::bail!("No embeddings found to cluster");
@@ -49,7 +58,13 @@ pub fn cluster_embeddings(
 			"Dataset size ({}) > 50, applying UMAP",
 			sidecars.len()
 		));
-		crate::processing::umap::reduce_embeddings(&embeddings, umap_components, umap_neighbors)?
+		crate::processing::umap::reduce_embeddings(
+			&embeddings,
+			umap_components,
+			umap_neighbors,
+			Metric::Cosine,
+			seed,
+		)?
 	} else {
 		if use_umap {
 			ui::debug("Dataset too small for UMAP (<50), using raw embeddings");
@@ -57,20 +72,24 @@ pub fn cluster_embeddings(
 		embeddings.iter().map(|e| e.0.clone()).collect()
 	};
 
+	let (points, dist_metric) = hdbscan_input(embeddings_2d, metric);
+
 	// Configure HDBSCAN
 	let hyper_params = match params.min_samples {
 		Some(min_samples) => HdbscanHyperParams::builder()
 			.min_cluster_size(params.min_cluster_size)
 			.min_samples(min_samples)
+			.dist_metric(dist_metric)
 			.build(),
 		None => HdbscanHyperParams::builder()
 			.min_cluster_size(params.min_cluster_size)
+			.dist_metric(dist_metric)
 			.build(),
 	};
 
 	// Run clustering
 	ui::debug("Running HDBSCAN...");
-	let clusterer = Hdbscan::new(&embeddings_2d, hyper_params);
+	let clusterer = Hdbscan::new(&points, hyper_params);
 	let labels = clusterer.cluster().context("HDBSCAN clustering failed")?;
 
 	// Process results
@@ -93,27 +112,209 @@ pub fn cluster_embeddings(
 		.into_par_iter()
 		.map(|(cluster_id, hashes)| {
 			let representative = find_representative(&hashes, &sidecars, &hash_to_idx);
-			let cohesion = compute_cohesion(&hashes, &sidecars, &hash_to_idx);
+			let cohesion = compute_cohesion(&hashes, &sidecars, &hash_to_idx, metric)?;
 
-			Cluster {
+			Ok(Cluster {
 				id: cluster_id as usize,
 				image_hashes: hashes,
 				representative_hash: representative,
 				cohesion,
-			}
+			})
 		})
-		.collect();
+		.collect::<Result<_>>()?;
 
 	// Sort clusters by size (largest first) and re-assign IDs
 	let mut clusters = clusters;
-	clusters.sort_by(|a, b| b.image_hashes.len().cmp(&a.image_hashes.len()));
+	clusters.sort_by_key(|c| std::cmp::Reverse(c.image_hashes.len()));
 
 	for (new_id, cluster) in clusters.iter_mut().enumerate() {
 		cluster.id = new_id;
 	}
 
 	// Filter clusters by cohesion threshold
-	let threshold = params.cohesion_threshold;
+	let filtered_clusters =
+		filter_by_cohesion(clusters, &mut noise_hashes, params.cohesion_threshold);
+
+	// Compute content hash for cache invalidation
+	let all_hashes: Vec<String> = idx_to_hash.clone();
+	let content_hash = compute_content_hash(&all_hashes);
+
+	let db = ClusterDatabase {
+		version: env!("CARGO_PKG_VERSION").to_string(),
+		timestamp: chrono::Utc::now().to_rfc3339(),
+		params,
+		clusters: filtered_clusters,
+		noise: noise_hashes,
+		total_images: sidecars.len(),
+		content_hash,
+	};
+
+	Ok(db)
+}
+
+/// Update a cached clustering with the files added or removed since, without
+/// running HDBSCAN again.
+///
+/// Each new file joins the cluster whose centroid (mean of the original
+/// embeddings, also with UMAP) it is most similar to, if that similarity
+/// reaches `min_similarity`; the rest become noise. Removed files are dropped.
+/// Clusters that changed get a fresh representative and cohesion, and the
+/// cached cohesion threshold is applied again.
+pub fn update_clusters(
+	cached: ClusterDatabase,
+	sidecars: Vec<(PathBuf, Sidecar)>,
+	min_similarity: f32,
+) -> Result<ClusterDatabase> {
+	let hash_to_idx: HashMap<String, usize> = sidecars
+		.iter()
+		.enumerate()
+		.map(|(idx, (_, sidecar))| (sidecar.hash().to_string(), idx))
+		.collect();
+	let embedding_of = |hash: &String| sidecars[hash_to_idx[hash]].1.primary_embedding();
+
+	let known: HashSet<&String> = cached
+		.clusters
+		.iter()
+		.flat_map(|c| &c.image_hashes)
+		.chain(&cached.noise)
+		.collect();
+	let added: Vec<String> = sidecars
+		.iter()
+		.map(|(_, sidecar)| sidecar.hash().to_string())
+		.filter(|hash| !known.contains(hash))
+		.collect();
+	let removed = known
+		.iter()
+		.filter(|hash| !hash_to_idx.contains_key(**hash))
+		.count();
+
+	let params = cached.params;
+	let mut clusters = cached.clusters;
+	let mut noise = cached.noise;
+	noise.retain(|hash| hash_to_idx.contains_key(hash));
+	let mut changed: Vec<bool> = clusters
+		.iter_mut()
+		.map(|cluster| {
+			let before = cluster.image_hashes.len();
+			cluster
+				.image_hashes
+				.retain(|hash| hash_to_idx.contains_key(hash));
+			cluster.image_hashes.len() < before
+		})
+		.collect();
+
+	let centroids: Vec<Embedding> = clusters
+		.iter()
+		.map(|cluster| {
+			let members: Vec<Embedding> = cluster.image_hashes.iter().map(embedding_of).collect();
+			compute_centroid(&members)
+		})
+		.collect();
+
+	let (mut joined, mut unassigned) = (0, 0);
+	for hash in added {
+		let embedding = embedding_of(&hash);
+		let nearest = centroids
+			.iter()
+			.enumerate()
+			.map(|(i, centroid)| (i, centroid.similarity(&embedding)))
+			.max_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+		match nearest {
+			Some((i, similarity)) if similarity >= min_similarity => {
+				clusters[i].image_hashes.push(hash);
+				changed[i] = true;
+				joined += 1;
+			}
+			_ => {
+				noise.push(hash);
+				unassigned += 1;
+			}
+		}
+	}
+
+	ui::debug(&format!(
+		"Incremental update: {} new files joined clusters, {} became noise, {} removed",
+		joined, unassigned, removed
+	));
+
+	let mut updated = Vec::with_capacity(clusters.len());
+	for (mut cluster, changed) in clusters.into_iter().zip(changed) {
+		if cluster.image_hashes.is_empty() {
+			continue;
+		}
+		if changed {
+			cluster.representative_hash =
+				find_representative(&cluster.image_hashes, &sidecars, &hash_to_idx);
+			cluster.cohesion = compute_cohesion(
+				&cluster.image_hashes,
+				&sidecars,
+				&hash_to_idx,
+				params.metric,
+			)?;
+		}
+		updated.push(cluster);
+	}
+	let clusters = filter_by_cohesion(updated, &mut noise, params.cohesion_threshold);
+
+	let all_hashes: Vec<String> = hash_to_idx.into_keys().collect();
+	Ok(ClusterDatabase {
+		version: env!("CARGO_PKG_VERSION").to_string(),
+		timestamp: chrono::Utc::now().to_rfc3339(),
+		params,
+		clusters,
+		noise,
+		total_images: sidecars.len(),
+		content_hash: compute_content_hash(&all_hashes),
+	})
+}
+
+/// Points and distance metric to hand HDBSCAN for `metric`.
+///
+/// HDBSCAN measures Euclidean distance itself. Other metrics become a
+/// precomputed distance matrix, which grows with the square of the point
+/// count, so past [`CLUSTER_PRECOMPUTED_LIMIT`] they fall back to Euclidean
+/// distance between the normalized points, which orders neighbors the same
+/// way cosine distance does.
+fn hdbscan_input(points: Vec<Vec<f32>>, metric: Metric) -> (Vec<Vec<f32>>, DistanceMetric) {
+	if metric == Metric::Euclidean {
+		return (points, DistanceMetric::Euclidean);
+	}
+
+	let points: Vec<Embedding> = points.into_iter().map(Embedding::new).collect();
+	if points.len() > CLUSTER_PRECOMPUTED_LIMIT {
+		ui::debug(&format!(
+			"{} points are too many for a {:?} distance matrix, using Euclidean distance of normalized points",
+			points.len(),
+			metric
+		));
+		let unit = points.into_iter().map(|p| p.0).collect();
+		return (unit, DistanceMetric::Euclidean);
+	}
+
+	ui::debug(&format!("Precomputing {:?} distances", metric));
+	// Always measure from the lower index so the matrix is exactly symmetric
+	let matrix = (0..points.len())
+		.into_par_iter()
+		.map(|i| {
+			(0..points.len())
+				.map(|j| match i.cmp(&j) {
+					std::cmp::Ordering::Equal => 0.0,
+					std::cmp::Ordering::Less => metric.distance(&points[i], &points[j]),
+					std::cmp::Ordering::Greater => metric.distance(&points[j], &points[i]),
+				})
+				.map(|distance| distance.max(0.0))
+				.collect()
+		})
+		.collect();
+	(matrix, DistanceMetric::Precalculated)
+}
+
+/// Move clusters below `threshold` cohesion into `noise` and renumber the rest
+fn filter_by_cohesion(
+	clusters: Vec<Cluster>,
+	noise: &mut Vec<String>,
+	threshold: f32,
+) -> Vec<Cluster> {
 	let mut filtered_clusters = Vec::new();
 	let mut low_cohesion_count = 0;
 
@@ -128,7 +329,7 @@ pub fn cluster_embeddings(
 				threshold * 100.0,
 				cluster.image_hashes.len()
 			));
-			noise_hashes.extend(cluster.image_hashes);
+			noise.extend(cluster.image_hashes);
 			low_cohesion_count += 1;
 		}
 	}
@@ -145,21 +346,7 @@ pub fn cluster_embeddings(
 		cluster.id = new_id;
 	}
 
-	// Compute content hash for cache invalidation
-	let all_hashes: Vec<String> = idx_to_hash.clone();
-	let content_hash = compute_content_hash(&all_hashes);
-
-	let db = ClusterDatabase {
-		version: env!("CARGO_PKG_VERSION").to_string(),
-		timestamp: chrono::Utc::now().to_rfc3339(),
-		params,
-		clusters: filtered_clusters,
-		noise: noise_hashes,
-		total_images: sidecars.len(),
-		content_hash,
-	};
-
-	Ok(db)
+	filtered_clusters
 }
 
 /// Find the most representative file in a cluster (closest to centroid)
@@ -202,14 +389,19 @@ fn find_representative(
 		.unwrap_or_else(|| hashes[0].clone())
 }
 
-/// Compute average pairwise similarity within cluster
+/// Compute average pairwise similarity within cluster under `metric`, see
+/// [`Embedding::similarity_with`].
+///
+/// Fails if members disagree on embedding size, which means the index mixes
+/// sidecars from different models.
 fn compute_cohesion(
 	hashes: &[String],
 	sidecars: &[(PathBuf, Sidecar)],
 	hash_to_idx: &HashMap<String, usize>,
-) -> f32 {
+	metric: Metric,
+) -> Result<f32> {
 	if hashes.len() < 2 {
-		return 1.0;
+		return Ok(1.0);
 	}
 
 	let embeddings: Vec<Embedding> = hashes
@@ -226,22 +418,109 @@ fn compute_cohesion(
 
 	for i in 0..embeddings.len() {
 		for j in (i + 1)..embeddings.len() {
-			total_similarity += embeddings[i].similarity(&embeddings[j]);
+			let (a, b) = (&embeddings[i], &embeddings[j]);
+			if a.dimension() != b.dimension() {
+				anyhow::bail!(
+					"Cluster members come from different models ({}D and {}D); rescan with a single model",
+					a.dimension(),
+					b.dimension()
+				);
+			}
+			total_similarity += a.similarity_with(b, metric);
 			count += 1;
 		}
 	}
 
 	if count > 0 {
-		total_similarity / count as f32
+		Ok(total_similarity / count as f32)
 	} else {
-		1.0
+		Ok(1.0)
 	}
 }
 
+/// Encode vocabulary terms for [`rank_labels`], one batch at a time
+pub fn encode_vocabulary(
+	models: &mut crate::models::Models,
+	vocabulary: &[&str],
+) -> Result<Vec<(String, Embedding)>> {
+	let mut terms = Vec::with_capacity(vocabulary.len());
+	for chunk in vocabulary.chunks(models.batch_size()) {
+		let embeddings = models.encode_text_batch(chunk)?;
+		terms.extend(chunk.iter().map(|t| t.to_string()).zip(embeddings));
+	}
+	Ok(terms)
+}
+
+/// Rank vocabulary terms by similarity to the centroid of `members`, best first
+pub fn rank_labels(members: &[Embedding], terms: &[(String, Embedding)]) -> Vec<(String, f32)> {
+	let Some(centroid) = Embedding::mean(members) else {
+		return Vec::new();
+	};
+
+	let mut ranked: Vec<(String, f32)> = terms
+		.iter()
+		.map(|(term, embedding)| (term.clone(), centroid.similarity(embedding)))
+		.collect();
+	ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+	ranked
+}
+
+/// Group `embeddings` into at most `max_groups` clusters by repeatedly merging
+/// the two clusters whose centroids are most similar.
+///
+/// Returns member indices per cluster, the member closest to the centroid
+/// first, so `group[0]` is its representative.
+pub fn merge_closest(embeddings: &[Embedding], max_groups: usize) -> Vec<Vec<usize>> {
+	let mut groups: Vec<Vec<usize>> = (0..embeddings.len()).map(|i| vec![i]).collect();
+	let mut centroids = embeddings.to_vec();
+	let mut similarity = Embedding::similarity_matrix(&centroids, &centroids);
+
+	while groups.len() > max_groups.max(1) {
+		let mut best = (0, 1, f32::MIN);
+		for (i, row) in similarity.iter().enumerate() {
+			for (j, &sim) in row.iter().enumerate().skip(i + 1) {
+				if sim > best.2 {
+					best = (i, j, sim);
+				}
+			}
+		}
+
+		// j > i, so removing j leaves i in place
+		let (i, j, _) = best;
+		let merged = groups.swap_remove(j);
+		centroids.swap_remove(j);
+		similarity.swap_remove(j);
+		for row in &mut similarity {
+			row.swap_remove(j);
+		}
+
+		groups[i].extend(merged);
+		let members: Vec<Embedding> = groups[i].iter().map(|&m| embeddings[m].clone()).collect();
+		centroids[i] = compute_centroid(&members);
+		for k in 0..groups.len() {
+			let sim = centroids[i].similarity(&centroids[k]);
+			similarity[i][k] = sim;
+			similarity[k][i] = sim;
+		}
+	}
+
+	for (group, centroid) in groups.iter_mut().zip(&centroids) {
+		let closeness = |&m: &usize| centroid.similarity(&embeddings[m]);
+		if let Some(pos) = (0..group.len()).max_by(|&a, &b| {
+			closeness(&group[a])
+				.partial_cmp(&closeness(&group[b]))
+				.unwrap_or(std::cmp::Ordering::Equal)
+		}) {
+			group.swap(0, pos);
+		}
+	}
+	groups
+}
+
 /// Compute centroid (mean) of embeddings
 fn compute_centroid(embeddings: &[Embedding]) -> Embedding {
 	if embeddings.is_empty() {
-		return Embedding::raw(vec![0.0; 1024]);
+		return Embedding::raw(Vec::new());
 	}
 
 	let dim = embeddings[0].0.len();
@@ -260,3 +539,199 @@ fn compute_centroid(embeddings: &[Embedding]) -> Embedding {
 
 	Embedding::raw(centroid).normalize()
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn cluster(id: usize, hashes: &[&str], cohesion: f32) -> Cluster {
+		Cluster {
+			id,
+			image_hashes: hashes.iter().map(|h| h.to_string()).collect(),
+			representative_hash: hashes[0].to_string(),
+			cohesion,
+		}
+	}
+
+	#[test]
+	fn test_labels_rank_by_centroid() {
+		let term = |name: &str, v: Vec<f32>| (name.to_string(), Embedding::new(v));
+		let vocabulary = vec![
+			term("beach", vec![1.0, 0.0, 0.0]),
+			term("forest", vec![0.0, 1.0, 0.0]),
+			term("city", vec![0.0, 0.0, 1.0]),
+			term("coast", vec![0.8, 0.0, 0.6]),
+		];
+		let members = vec![
+			Embedding::new(vec![0.9, 0.1, 0.3]),
+			Embedding::new(vec![1.0, 0.0, 0.1]),
+		];
+
+		let ranked = rank_labels(&members, &vocabulary);
+		let names: Vec<&str> = ranked.iter().map(|(name, _)| name.as_str()).collect();
+		assert_eq!(names, ["beach", "coast", "city", "forest"]);
+		assert!(rank_labels(&[], &vocabulary).is_empty());
+	}
+
+	/// Unnormalized 2-D points, like UMAP output: two directions at two
+	/// distances from the origin
+	fn rays() -> Vec<(PathBuf, Sidecar)> {
+		let mut sidecars = Vec::new();
+		for (direction, base) in [(0.0f32, 0usize), (90.0, 100)] {
+			for (k, radius) in [1.0f32, 10.0].iter().flat_map(|r| [*r; 6]).enumerate() {
+				let angle = (direction + k as f32 * 0.3).to_radians();
+				let radius = radius + (k % 6) as f32 * 0.05;
+				let embedding = Embedding::raw(vec![radius * angle.cos(), radius * angle.sin()]);
+				let hash = crate::core::FileHash::from_hex(format!("{:016x}", base + k));
+				sidecars.push((
+					PathBuf::from(format!("{}.jpg", base + k)),
+					Sidecar::Image(crate::storage::ImageSidecar::new(hash, embedding)),
+				));
+			}
+		}
+		sidecars
+	}
+
+	#[test]
+	fn test_metric_changes_clustering() {
+		let run = |metric: Metric| {
+			let params = ClusterParams {
+				min_cluster_size: 4,
+				min_samples: None,
+				cohesion_threshold: 0.0,
+				use_umap: false,
+				umap_neighbors: 0,
+				umap_components: 0,
+				metric,
+				seed: None,
+			};
+			cluster_embeddings(rays(), params, false, 0, 0, metric, None).unwrap()
+		};
+		let cosine = run(Metric::Cosine);
+		let euclidean = run(Metric::Euclidean);
+
+		for db in [&cosine, &euclidean] {
+			let mut seen: Vec<&String> = db
+				.clusters
+				.iter()
+				.flat_map(|c| &c.image_hashes)
+				.chain(&db.noise)
+				.collect();
+			seen.sort();
+			seen.dedup();
+			assert_eq!(seen.len(), 24, "every file is labeled exactly once");
+			assert!(db
+				.clusters
+				.iter()
+				.all(|c| (0.0..=1.0).contains(&c.cohesion)));
+		}
+
+		// Cosine ignores the distance from the origin, Euclidean separates it
+		assert_eq!(cosine.clusters.len(), 2);
+		assert_eq!(euclidean.clusters.len(), 4);
+	}
+
+	#[test]
+	fn test_loose_cluster_moves_to_noise() {
+		let clusters = vec![
+			cluster(0, &["a", "b", "c"], 0.35),
+			cluster(1, &["d", "e"], 0.92),
+		];
+		let mut noise = vec!["f".to_string()];
+
+		let kept = filter_by_cohesion(clusters, &mut noise, 0.6);
+
+		assert_eq!(kept.len(), 1);
+		assert_eq!(kept[0].id, 0, "remaining clusters are renumbered");
+		assert_eq!(kept[0].image_hashes, vec!["d", "e"]);
+		assert_eq!(noise, vec!["f", "a", "b", "c"]);
+
+		let db = ClusterDatabase {
+			version: String::new(),
+			timestamp: String::new(),
+			params: ClusterParams {
+				min_cluster_size: 2,
+				min_samples: None,
+				cohesion_threshold: 0.6,
+				use_umap: false,
+				umap_neighbors: 0,
+				umap_components: 0,
+				metric: Metric::Cosine,
+				seed: None,
+			},
+			clusters: kept,
+			noise,
+			total_images: 6,
+			content_hash: String::new(),
+		};
+		assert!((db.noise_percent() - 400.0 / 6.0).abs() < 1e-4);
+	}
+
+	#[test]
+	fn test_incremental_update_joins_nearest_cluster() {
+		let file = |n: usize, v: [f32; 3]| {
+			let hash = crate::core::FileHash::from_hex(format!("{:016x}", n));
+			let sidecar = crate::storage::ImageSidecar::new(hash, Embedding::new(v.to_vec()));
+			(PathBuf::from(format!("{}.jpg", n)), Sidecar::Image(sidecar))
+		};
+		let hash = |n: usize| format!("{:016x}", n);
+		let mut sidecars = vec![
+			file(1, [1.0, 0.1, 0.0]),
+			file(2, [1.0, 0.0, 0.1]),
+			file(3, [1.0, 0.05, 0.05]),
+			file(4, [0.1, 1.0, 0.0]),
+			file(5, [0.0, 1.0, 0.1]),
+			file(6, [0.05, 1.0, 0.05]),
+		];
+		let cached = ClusterDatabase {
+			version: String::new(),
+			timestamp: String::new(),
+			params: ClusterParams {
+				min_cluster_size: 2,
+				min_samples: None,
+				cohesion_threshold: 0.5,
+				use_umap: false,
+				umap_neighbors: 0,
+				umap_components: 0,
+				metric: Metric::Cosine,
+				seed: None,
+			},
+			clusters: vec![
+				Cluster {
+					id: 0,
+					image_hashes: vec![hash(1), hash(2), hash(3)],
+					representative_hash: hash(3),
+					cohesion: 0.5,
+				},
+				Cluster {
+					id: 1,
+					image_hashes: vec![hash(4), hash(5), hash(6)],
+					representative_hash: hash(6),
+					cohesion: 0.99,
+				},
+			],
+			noise: Vec::new(),
+			total_images: 6,
+			content_hash: String::new(),
+		};
+
+		// One photo like the first cluster, one like neither; one file deleted
+		sidecars.push(file(7, [1.0, 0.08, 0.02]));
+		sidecars.push(file(8, [0.1, 0.1, 1.0]));
+		sidecars.retain(|(path, _)| path != std::path::Path::new("5.jpg"));
+
+		let db = update_clusters(cached, sidecars, 0.9).unwrap();
+
+		assert_eq!(db.clusters.len(), 2);
+		assert_eq!(
+			db.clusters[0].image_hashes,
+			vec![hash(1), hash(2), hash(3), hash(7)]
+		);
+		assert!(db.clusters[0].cohesion > 0.9, "cohesion is recomputed");
+		assert_eq!(db.clusters[1].image_hashes, vec![hash(4), hash(6)]);
+		assert_eq!(db.noise, vec![hash(8)]);
+		assert_eq!(db.total_images, 7);
+		let current: Vec<String> = [1, 2, 3, 4, 6, 7, 8].map(hash).to_vec();
+		assert_eq!(db.content_hash, compute_content_hash(&current));
+	}
+}
@@ -1,21 +1,81 @@
 //! # Image Processing
 //!
 //! Load and encode images, with format detection fallback.
-//! Handles mismatched extensions and corrupted files gracefully.
+//! Handles mismatched extensions and corrupted files gracefully, and can
+//! rotate photos upright according to their EXIF orientation.
+//! Animated GIF and WebP files can be sampled into several frames.
 
 use anyhow::{Context, Result};
+use image::codecs::gif::GifDecoder;
+use image::codecs::webp::WebPDecoder;
+use image::{
+	AnimationDecoder, DynamicImage, Frames, ImageDecoder, ImageFormat, ImageReader, ImageResult,
+};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Cursor, Seek};
 use std::path::Path;
 
+use crate::config::{HISTOGRAM_BINS, HISTOGRAM_SAMPLE_SIZE};
 use crate::core::Embedding;
 use crate::models::Models;
 
+/// Decode an image as stored, ignoring its EXIF orientation
+pub fn decode<R: BufRead + Seek>(reader: ImageReader<R>) -> ImageResult<DynamicImage> {
+	decode_with(reader, false)
+}
+
+/// Decode an image, rotating and flipping it according to its EXIF
+/// orientation tag when `auto_orient` is set.
+///
+/// Orienting changes embeddings of photos with a non-default orientation and
+/// is part of the model fingerprint, see [`Models::auto_orient`].
+pub fn decode_with<R: BufRead + Seek>(
+	reader: ImageReader<R>,
+	auto_orient: bool,
+) -> ImageResult<DynamicImage> {
+	if !auto_orient {
+		return reader.decode();
+	}
+
+	let mut decoder = reader.into_decoder()?;
+	let orientation = decoder.orientation()?;
+	let mut img = DynamicImage::from_decoder(decoder)?;
+	img.apply_orientation(orientation);
+	Ok(img)
+}
+
+/// Decode an in-memory image as stored, see [`decode`]
+pub fn load_from_memory(bytes: &[u8]) -> ImageResult<DynamicImage> {
+	load_from_memory_with(bytes, false)
+}
+
+/// Decode an in-memory image, see [`decode_with`]
+pub fn load_from_memory_with(bytes: &[u8], auto_orient: bool) -> ImageResult<DynamicImage> {
+	decode_with(
+		ImageReader::new(Cursor::new(bytes)).with_guessed_format()?,
+		auto_orient,
+	)
+}
+
 /// Load and encode image file
 pub fn encode(models: &mut Models, path: &Path) -> Result<Embedding> {
 	crate::ui::debug(&format!("Encoding image: {}", path.display()));
+	let img = load_with(path, models.auto_orient())?;
+	models.encode_image(&img)
+}
 
+/// Load image file as stored, see [`load_with`]
+pub fn load(path: &Path) -> Result<image::DynamicImage> {
+	load_with(path, false)
+}
+
+/// Load image file, falling back to content sniffing when the extension lies.
+/// Applies the EXIF orientation when `auto_orient` is set, see [`decode_with`].
+pub fn load_with(path: &Path, auto_orient: bool) -> Result<image::DynamicImage> {
 	// Try to open with default extension-based detection first
-	if let Ok(img) = image::open(path) {
-		return models.encode_image(&img);
+	if let Ok(img) = open(path, auto_orient) {
+		return Ok(img);
 	}
 
 	// If that fails, check actual file format and try again
@@ -24,37 +84,390 @@ pub fn encode(models: &mut Models, path: &Path) -> Result<Embedding> {
 			let extension = path
 				.extension()
 				.and_then(|ext| ext.to_str())
-				.unwrap_or("unknown")
-				.to_lowercase();
+				.map(str::to_lowercase);
 
 			let detected_ext = format!("{:?}", detected_format).to_lowercase();
 
-			if !extension.is_empty() && !detected_ext.contains(&extension) {
-				let link = crate::ui::path_link(path, 60);
-				crate::ui::warn(&format!(
-					"File extension does not match content: {} (expected {:?}, detected {:?})",
-					link, extension, detected_format
-				));
+			// Files without an extension can only go by their content
+			let mismatched = match &extension {
+				Some(extension) => !detected_ext.contains(extension.as_str()),
+				None => true,
+			};
+			if mismatched {
+				if let Some(extension) = &extension {
+					let link = crate::ui::path_link(path, 60);
+					crate::ui::warn(&format!(
+						"File extension does not match content: {} (expected {:?}, detected {:?})",
+						link, extension, detected_format
+					));
+				}
 
 				// Try to decode with the detected format
-				if let Ok(img) = image::load_from_memory_with_format(&bytes, detected_format) {
-					return models.encode_image(&img);
+				let reader = ImageReader::with_format(Cursor::new(&bytes), detected_format);
+				if let Ok(img) = decode_with(reader, auto_orient) {
+					return Ok(img);
 				}
 			}
 		}
 	}
 
 	// If all else fails, return the original error
-	let img = image::open(path).with_context(|| {
+	open(path, auto_orient).with_context(|| {
 		format!(
 			"Failed to open image. File may be corrupted or in an unsupported format: {}",
 			path.display()
 		)
-	})?;
-	models.encode_image(&img)
+	})
+}
+
+fn open(path: &Path, auto_orient: bool) -> ImageResult<DynamicImage> {
+	decode_with(ImageReader::open(path)?, auto_orient)
 }
 
 /// Encode a DynamicImage (for video frames)
 pub fn encode_image(models: &mut Models, img: &image::DynamicImage) -> Result<Embedding> {
 	models.encode_image(img)
 }
+
+/// Rectangle of an image in pixels, parsed from `x,y,w,h` (`--crop`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Region {
+	pub x: u32,
+	pub y: u32,
+	pub width: u32,
+	pub height: u32,
+}
+
+impl Region {
+	pub fn new(x: u32, y: u32, width: u32, height: u32) -> Self {
+		Self {
+			x,
+			y,
+			width,
+			height,
+		}
+	}
+
+	/// Cut the region out of `img`, failing when it is empty or reaches past
+	/// the image's edges
+	pub fn crop(&self, img: &DynamicImage) -> Result<DynamicImage> {
+		if self.width == 0 || self.height == 0 {
+			anyhow::bail!("Crop region {} is empty", self);
+		}
+		let (right, bottom) = (
+			self.x as u64 + self.width as u64,
+			self.y as u64 + self.height as u64,
+		);
+		if right > img.width() as u64 || bottom > img.height() as u64 {
+			anyhow::bail!(
+				"Crop region {} reaches past the {}x{} image",
+				self,
+				img.width(),
+				img.height()
+			);
+		}
+		Ok(img.crop_imm(self.x, self.y, self.width, self.height))
+	}
+}
+
+impl std::str::FromStr for Region {
+	type Err = String;
+
+	fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+		let parts: Vec<u32> = s
+			.split(',')
+			.map(|part| part.trim().parse())
+			.collect::<std::result::Result<_, _>>()
+			.map_err(|_| format!("expected x,y,w,h in pixels, got '{}'", s))?;
+		match parts.as_slice() {
+			&[x, y, width, height] => Ok(Self::new(x, y, width, height)),
+			_ => Err(format!("expected x,y,w,h in pixels, got '{}'", s)),
+		}
+	}
+}
+
+impl std::fmt::Display for Region {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		write!(f, "{},{},{},{}", self.x, self.y, self.width, self.height)
+	}
+}
+
+/// Coarse RGB color distribution with [`HISTOGRAM_BINS`] bins per channel,
+/// normalized to sum to 1.
+///
+/// Computed from a small thumbnail, so it costs a fraction of an encode.
+pub fn color_histogram(img: &DynamicImage) -> Vec<f32> {
+	let bins = HISTOGRAM_BINS;
+	let thumb = img
+		.thumbnail(HISTOGRAM_SAMPLE_SIZE, HISTOGRAM_SAMPLE_SIZE)
+		.to_rgb8();
+
+	let mut histogram = vec![0.0f32; bins * bins * bins];
+	for pixel in thumb.pixels() {
+		let [r, g, b] = pixel.0.map(|c| c as usize * bins / 256);
+		histogram[(r * bins + g) * bins + b] += 1.0;
+	}
+
+	let total = thumb.pixels().len().max(1) as f32;
+	for bin in &mut histogram {
+		*bin /= total;
+	}
+	histogram
+}
+
+/// Share of color mass that differs between two histograms: 0.0 for the same
+/// distribution, 1.0 when no bin overlaps. Histograms of different sizes
+/// compare as 0.0 so they are never pruned.
+pub fn histogram_distance(a: &[f32], b: &[f32]) -> f32 {
+	if a.len() != b.len() {
+		return 0.0;
+	}
+	a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum::<f32>() / 2.0
+}
+
+/// Sample up to `max_frames` evenly spaced frames from an animated GIF or WebP.
+///
+/// Returns `None` for static images and other formats (animated AVIF and APNG
+/// are decoded as a single frame). Timestamps are seconds from the start,
+/// summed from the frame delays.
+pub fn load_animation(path: &Path, max_frames: usize) -> Result<Option<Vec<(f64, DynamicImage)>>> {
+	let Some(format) = animated_format(path) else {
+		return Ok(None);
+	};
+
+	// Frames must be decoded in order, so count them first and keep only the
+	// sampled ones on the second pass instead of holding every frame in memory
+	let Some(frames) = decode_frames(path, format)? else {
+		return Ok(None);
+	};
+	let count = frames.take_while(Result::is_ok).count();
+	if count < 2 {
+		return Ok(None);
+	}
+
+	let keep = sample_indices(count, max_frames);
+	let frames = decode_frames(path, format)?.context("Animation disappeared")?;
+
+	let mut sampled = Vec::with_capacity(keep.len());
+	let mut elapsed = 0.0;
+	for (i, frame) in frames.take(count).enumerate() {
+		let frame = frame.context("Failed to decode animation frame")?;
+		let (numer, denom) = frame.delay().numer_denom_ms();
+		let delay = numer as f64 / denom.max(1) as f64 / 1000.0;
+
+		if keep.contains(&i) {
+			sampled.push((elapsed, DynamicImage::ImageRgba8(frame.into_buffer())));
+		}
+		elapsed += delay;
+	}
+
+	Ok(Some(sampled))
+}
+
+/// Container format of files that may hold several frames, by content
+fn animated_format(path: &Path) -> Option<ImageFormat> {
+	let format = ImageReader::open(path)
+		.ok()?
+		.with_guessed_format()
+		.ok()?
+		.format()?;
+	matches!(format, ImageFormat::Gif | ImageFormat::WebP).then_some(format)
+}
+
+/// Frame iterator for an animated file (`None` for static WebP)
+fn decode_frames(path: &Path, format: ImageFormat) -> Result<Option<Frames<'static>>> {
+	let reader = BufReader::new(File::open(path)?);
+	let frames = match format {
+		ImageFormat::Gif => GifDecoder::new(reader)?.into_frames(),
+		ImageFormat::WebP => {
+			let decoder = WebPDecoder::new(reader)?;
+			if !decoder.has_animation() {
+				return Ok(None);
+			}
+			decoder.into_frames()
+		}
+		_ => return Ok(None),
+	};
+	Ok(Some(frames))
+}
+
+/// `count` evenly spaced indices out of `total`, always including the first
+fn sample_indices(total: usize, count: usize) -> Vec<usize> {
+	if total <= count {
+		return (0..total).collect();
+	}
+	let step = total as f64 / count as f64;
+	(0..count)
+		.map(|i| (i as f64 * step).floor() as usize)
+		.collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use image::codecs::gif::GifEncoder;
+	use image::codecs::jpeg::JpegEncoder;
+	use image::{Delay, Frame, ImageEncoder, Rgb, RgbImage, Rgba, RgbaImage};
+
+	#[test]
+	fn test_animated_gif_frames_are_sampled() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+
+		// Three 100ms frames: red, green, blue
+		let gif = dir.join("sticker.gif");
+		let mut encoder = GifEncoder::new(File::create(&gif).unwrap());
+		let colors = [[255, 0, 0, 255], [0, 255, 0, 255], [0, 0, 255, 255]];
+		encoder
+			.encode_frames(colors.into_iter().map(|color| {
+				let buffer = RgbaImage::from_pixel(8, 8, Rgba(color));
+				Frame::from_parts(buffer, 0, 0, Delay::from_numer_denom_ms(100, 1))
+			}))
+			.unwrap();
+		drop(encoder);
+
+		let frames = load_animation(&gif, 8).unwrap().expect("animated");
+		let timestamps: Vec<f64> = frames.iter().map(|(ts, _)| *ts).collect();
+		assert_eq!(timestamps.len(), 3);
+		assert!((timestamps[2] - 0.2).abs() < 1e-9);
+		assert_eq!(frames[1].1.to_rgb8().get_pixel(4, 4).0, [0, 255, 0]);
+
+		let sampled = load_animation(&gif, 2).unwrap().unwrap();
+		assert_eq!(sampled.len(), 2);
+		assert_eq!(sampled[0].0, 0.0);
+
+		// A single-frame image stays a regular image
+		let still = dir.join("still.png");
+		RgbaImage::from_pixel(8, 8, Rgba([9, 9, 9, 255]))
+			.save(&still)
+			.unwrap();
+		assert!(load_animation(&still, 8).unwrap().is_none());
+	}
+
+	#[test]
+	fn test_mislabeled_file_decodes_by_content() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+
+		// PNG bytes under a JPEG name
+		let path = dir.join("photo.jpg");
+		RgbImage::from_pixel(6, 4, Rgb([20, 140, 60]))
+			.save_with_format(&path, ImageFormat::Png)
+			.unwrap();
+
+		let by_extension = image::open(&path);
+		let by_content = load(&path);
+
+		assert!(by_extension.is_err());
+		let img = by_content.unwrap().to_rgb8();
+		assert_eq!((img.width(), img.height()), (6, 4));
+		assert_eq!(img.get_pixel(0, 0).0, [20, 140, 60]);
+	}
+
+	#[test]
+	fn test_exif_orientation_is_applied() {
+		// Little-endian TIFF header with one IFD entry: Orientation (0x0112) = 6,
+		// meaning the stored pixels must be rotated 90° clockwise
+		let exif = [
+			b"II*\0".as_slice(),
+			&8u32.to_le_bytes(),
+			&1u16.to_le_bytes(),
+			&0x0112u16.to_le_bytes(),
+			&3u16.to_le_bytes(),
+			&1u32.to_le_bytes(),
+			&6u32.to_le_bytes(),
+			&0u32.to_le_bytes(),
+		]
+		.concat();
+
+		// Sideways photo: 32x16 with a bright left half
+		let sideways = RgbImage::from_fn(32, 16, |x, _| {
+			if x < 16 {
+				Rgb([250, 250, 250])
+			} else {
+				Rgb([5, 5, 5])
+			}
+		});
+		let mut jpeg = Vec::new();
+		let mut encoder = JpegEncoder::new_with_quality(&mut jpeg, 95);
+		encoder.set_exif_metadata(exif).unwrap();
+		encoder
+			.write_image(&sideways, 32, 16, image::ExtendedColorType::Rgb8)
+			.unwrap();
+
+		let raw = load_from_memory(&jpeg).unwrap();
+		let upright = load_from_memory_with(&jpeg, true).unwrap().to_rgb8();
+
+		assert_eq!((raw.width(), raw.height()), (32, 16));
+		assert_eq!((upright.width(), upright.height()), (16, 32));
+		// The bright left half ends up on top after rotating clockwise
+		assert!(upright.get_pixel(8, 4).0[0] > 200);
+		assert!(upright.get_pixel(8, 28).0[0] < 50);
+	}
+
+	#[test]
+	fn test_prefilter_keeps_edited_copies() {
+		use crate::config::DEFAULT_PREFILTER_DISTANCE;
+
+		let beach = RgbImage::from_fn(320, 240, |x, y| {
+			if y < 120 {
+				Rgb([90 + (x / 8) as u8, 160, 235])
+			} else {
+				Rgb([225, 200 - (y / 6) as u8, 140])
+			}
+		});
+		let reference = color_histogram(&DynamicImage::ImageRgb8(beach.clone()));
+
+		// Copies a user would expect to find: resized, brightened, recompressed
+		let resized = DynamicImage::ImageRgb8(beach.clone()).resize_exact(
+			160,
+			120,
+			image::imageops::FilterType::Triangle,
+		);
+		let brighter = DynamicImage::ImageRgb8(beach.clone()).brighten(20);
+		let mut jpeg = Vec::new();
+		JpegEncoder::new_with_quality(&mut jpeg, 60)
+			.write_image(&beach, 320, 240, image::ExtendedColorType::Rgb8)
+			.unwrap();
+		let recompressed = load_from_memory(&jpeg).unwrap();
+
+		for copy in [resized, brighter, recompressed] {
+			let distance = histogram_distance(&reference, &color_histogram(&copy));
+			assert!(
+				distance < DEFAULT_PREFILTER_DISTANCE,
+				"pruned at {}",
+				distance
+			);
+		}
+
+		let night = DynamicImage::ImageRgb8(RgbImage::from_pixel(320, 240, Rgb([10, 12, 30])));
+		let distance = histogram_distance(&reference, &color_histogram(&night));
+		assert!(
+			distance > DEFAULT_PREFILTER_DISTANCE,
+			"kept at {}",
+			distance
+		);
+	}
+
+	#[test]
+	fn test_crop_region_is_validated() {
+		let region: Region = "2, 1,3,2".parse().unwrap();
+		assert_eq!(region, Region::new(2, 1, 3, 2));
+		for invalid in ["", "1,2,3", "1,2,3,4,5", "-1,0,2,2", "a,b,c,d"] {
+			assert!(invalid.parse::<Region>().is_err(), "{:?} parsed", invalid);
+		}
+
+		let img = DynamicImage::ImageRgb8(RgbImage::from_fn(6, 4, |x, y| {
+			Rgb([x as u8 * 40, y as u8 * 60, 0])
+		}));
+		let cropped = region.crop(&img).unwrap();
+		assert_eq!((cropped.width(), cropped.height()), (3, 2));
+		assert_eq!(cropped.to_rgb8().get_pixel(0, 0).0, [80, 60, 0]);
+
+		// Flush with the bottom-right corner is fine, one pixel further is not
+		assert!(Region::new(3, 2, 3, 2).crop(&img).is_ok());
+		let error = Region::new(4, 2, 3, 2).crop(&img).unwrap_err();
+		assert!(error.to_string().contains("6x4"), "{}", error);
+		assert!(Region::new(0, 0, 0, 2).crop(&img).is_err());
+	}
+}
@@ -1,19 +1,44 @@
 //! # Directory Scanning
 //!
 //! Discover and filter media files with parallel hashing.
-//! Respects .scoutignore and handles resolution/size limits.
+//! Respects .scoutignore and --exclude-dir globs, and handles resolution/size limits.
+//! Symlinked directories are followed unless `--no-follow-symlinks` is set.
 
+use anyhow::{Context, Result};
+use globset::{GlobBuilder, GlobMatcher};
 use std::collections::HashSet;
 use std::fs::{self, File};
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
 
 use rayon::prelude::*;
 
 use crate::config::SIDECAR_DIR;
-use crate::core::{FileHash, MediaType};
+use crate::core::{FileHash, HashMode, MediaType};
+use crate::storage::journal::{JournalEntry, ScanJournal};
 use crate::ui;
 
+static FOLLOW_SYMLINKS: AtomicBool = AtomicBool::new(true);
+
+/// Descend into symlinked directories when discovering media and sidecars
+/// (on by default).
+///
+/// Each directory is visited once, so links that loop back are harmless.
+pub fn set_follow_symlinks(enabled: bool) {
+	FOLLOW_SYMLINKS.store(enabled, Ordering::Relaxed);
+}
+
+pub fn follow_symlinks() -> bool {
+	FOLLOW_SYMLINKS.load(Ordering::Relaxed)
+}
+
+/// Whether a directory entry is itself a symlink to a directory, not a real one
+pub(crate) fn is_linked_dir(entry: &fs::DirEntry) -> bool {
+	entry.file_type().is_ok_and(|t| t.is_symlink()) && entry.path().is_dir()
+}
+
 fn load_scoutignore(dir: &Path) -> Vec<String> {
 	let ignore_path = dir.join(".scoutignore");
 	if !ignore_path.exists() {
@@ -26,16 +51,117 @@ fn load_scoutignore(dir: &Path) -> Vec<String> {
 
 	BufReader::new(file)
 		.lines()
-		.map_while(Result::ok)
-		.filter(|line| !line.trim().is_empty() && !line.starts_with('#'))
+		.map_while(std::result::Result::ok)
+		.map(|line| line.trim().to_string())
+		.filter(|line| !line.is_empty() && !line.starts_with('#'))
 		.collect()
 }
 
-fn is_ignored(path: &Path, patterns: &[String]) -> bool {
-	let path_str = path.to_string_lossy().to_lowercase();
-	patterns
-		.iter()
-		.any(|pattern| path_str.contains(&pattern.to_lowercase()))
+/// Compiled exclude patterns from `--exclude-dir` and `.scoutignore` files.
+///
+/// Patterns use gitignore syntax, matched case-insensitively:
+/// - `*` stays within one path component, `**` spans any number of them
+/// - a trailing `/` only matches directories (`thumbnails/`)
+/// - a `/` at the start, or in the middle of a glob, anchors the pattern to the
+///   directory it comes from (`/raw`, `cache/*`); otherwise it matches at any depth
+/// - a leading `!` re-includes paths excluded by an earlier pattern
+///
+/// Plain lines without glob characters or a leading or trailing `/` keep the
+/// old substring meaning, so `backup` and `photos/raw` match anywhere in the path.
+#[derive(Clone, Default)]
+pub struct IgnoreRules {
+	rules: Vec<IgnoreRule>,
+}
+
+#[derive(Clone)]
+struct IgnoreRule {
+	/// Directory the pattern is relative to
+	base: PathBuf,
+	matcher: GlobMatcher,
+	dir_only: bool,
+	negated: bool,
+}
+
+impl IgnoreRules {
+	/// Compile `patterns` relative to `base` (the scan root for `--exclude-dir`)
+	pub fn new(base: &Path, patterns: &[String]) -> Result<Self> {
+		let mut rules = Self::default();
+		for pattern in patterns {
+			rules.add(base, pattern)?;
+		}
+		Ok(rules)
+	}
+
+	/// Add one pattern relative to `base`
+	pub fn add(&mut self, base: &Path, pattern: &str) -> Result<()> {
+		let (negated, pattern) = match pattern.strip_prefix('!') {
+			Some(rest) => (true, rest),
+			None => (false, pattern),
+		};
+		let dir_only = pattern.ends_with('/');
+		let pattern = pattern.trim_end_matches('/');
+
+		let is_glob = pattern.contains(['*', '?', '[', '{']);
+		let glob = if !is_glob && !dir_only && !pattern.starts_with('/') {
+			format!("**/*{}*", pattern)
+		} else if pattern.contains('/') {
+			pattern.trim_start_matches('/').to_string()
+		} else {
+			format!("**/{}", pattern)
+		};
+
+		let matcher = GlobBuilder::new(&glob)
+			.literal_separator(true)
+			.case_insensitive(true)
+			.build()
+			.with_context(|| format!("Invalid exclude pattern '{}'", pattern))?
+			.compile_matcher();
+
+		self.rules.push(IgnoreRule {
+			base: base.to_path_buf(),
+			matcher,
+			dir_only,
+			negated,
+		});
+		Ok(())
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.rules.is_empty()
+	}
+
+	/// Whether `path` is excluded. The last matching pattern wins.
+	pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+		let mut ignored = false;
+		for rule in &self.rules {
+			if rule.dir_only && !is_dir {
+				continue;
+			}
+			let Ok(relative) = path.strip_prefix(&rule.base) else {
+				continue;
+			};
+			if rule.matcher.is_match(relative) {
+				ignored = !rule.negated;
+			}
+		}
+		ignored
+	}
+
+	/// These rules plus the `.scoutignore` of `dir`, if it has one
+	fn with_scoutignore(&self, dir: &Path) -> Option<Self> {
+		let patterns = load_scoutignore(dir);
+		if patterns.is_empty() {
+			return None;
+		}
+
+		let mut rules = self.clone();
+		for pattern in &patterns {
+			if let Err(e) = rules.add(dir, pattern) {
+				ui::warn(&format!("{}: {:#}", dir.join(".scoutignore").display(), e));
+			}
+		}
+		Some(rules)
+	}
 }
 
 #[derive(Clone)]
@@ -54,37 +180,56 @@ pub struct ScanResult {
 	pub filtered: usize,
 }
 
-/// Scan directory for media files
+/// Scan directory for media files. Existing sidecars count as indexed when
+/// they are current for the model `fingerprint`.
+#[allow(clippy::too_many_arguments)]
 pub fn scan_directory(
 	root: &Path,
 	recursive: bool,
 	force: bool,
 	min_resolution: Option<u32>,
 	max_size_mb: Option<u64>,
+	exclude: &IgnoreRules,
+	hash_mode: HashMode,
+	fingerprint: Option<&str>,
 ) -> ScanResult {
 	// 1. Discovery Phase (Sequential, fast IO)
 	ui::debug("Scanning directory structure...");
-	let candidates = discover_files(root, recursive);
+	let candidates = discover_files(root, recursive, exclude);
 	ui::debug(&format!("Found {} candidate files", candidates.len()));
 
 	// 2. Processing Phase (Parallel, CPU intensive)
 	ui::debug("Processing files (metadata & hashing)...");
 
+	let mut journal = ScanJournal::load(root);
+	if !journal.is_empty() {
+		ui::debug(&format!("Loaded scan journal ({} entries)", journal.len()));
+	}
+	let new_entries: Mutex<Vec<(PathBuf, JournalEntry)>> = Mutex::new(Vec::new());
+
 	let already_indexed = std::sync::atomic::AtomicUsize::new(0);
 	let outdated = std::sync::atomic::AtomicUsize::new(0);
 	let filtered = std::sync::atomic::AtomicUsize::new(0);
+	let reused = std::sync::atomic::AtomicUsize::new(0);
 
 	let to_process: Vec<MediaFile> = candidates
-		.into_par_iter()
+		.par_iter()
 		.filter_map(|path| {
+			let path = path.clone();
+			let metadata = fs::metadata(&path).ok();
+
 			// Filters (Size/Resolution)
-			if let Some(max_mb) = max_size_mb {
-				if let Ok(metadata) = fs::metadata(&path) {
-					let size_mb = metadata.len() / 1024 / 1024;
-					if size_mb > max_mb {
-						filtered.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-						return None;
-					}
+			if let (Some(max_mb), Some(metadata)) = (max_size_mb, &metadata) {
+				let size_mb = metadata.len() / 1024 / 1024;
+				if size_mb > max_mb {
+					ui::debug(&format!(
+						"Filtered {}: {} MB exceeds --max-size {} MB",
+						path.display(),
+						size_mb,
+						max_mb
+					));
+					filtered.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+					return None;
 				}
 			}
 
@@ -92,16 +237,42 @@ pub fn scan_directory(
 				if let Ok(img) = image::image_dimensions(&path) {
 					let (width, height) = img;
 					if width.min(height) < min_res {
+						ui::debug(&format!(
+							"Filtered {}: {}x{} is below --min-resolution {}",
+							path.display(),
+							width,
+							height,
+							min_res
+						));
 						filtered.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 						return None;
 					}
 				}
 			}
 
-			// Hashing
-			let Ok(hash) = FileHash::compute(&path) else {
-				ui::warn(&format!("Failed to hash: {}", path.display()));
-				return None;
+			// Hashing, skipped for files unchanged since the journal saw them
+			let journaled = metadata
+				.as_ref()
+				.and_then(|metadata| journal.lookup(&path, metadata))
+				.filter(|hash| hash.mode() == hash_mode);
+			let hash = match journaled {
+				Some(hash) => {
+					reused.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+					hash
+				}
+				None => {
+					let Ok(hash) = FileHash::compute_with(&path, hash_mode) else {
+						ui::warn(&format!("Failed to hash: {}", path.display()));
+						return None;
+					};
+					if let Some(entry) = metadata
+						.as_ref()
+						.and_then(|metadata| JournalEntry::new(metadata, &hash))
+					{
+						new_entries.lock().unwrap().push((path.clone(), entry));
+					}
+					hash
+				}
 			};
 
 			// Existence Check
@@ -109,7 +280,7 @@ pub fn scan_directory(
 				let media_dir = path.parent().unwrap_or(&path);
 				if let Some(sidecar_path) = crate::storage::find(media_dir, &hash) {
 					if let Ok(sidecar) = crate::storage::load(&sidecar_path) {
-						if sidecar.is_current_version() {
+						if sidecar.is_current(fingerprint) {
 							already_indexed.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
 							return None;
 						} else {
@@ -136,6 +307,23 @@ pub fn scan_directory(
 		})
 		.collect();
 
+	// Record new hashes and forget files that disappeared
+	let new_entries = new_entries.into_inner().unwrap();
+	let reused = reused.into_inner();
+	if reused > 0 {
+		ui::debug(&format!("Reused {} hashes from scan journal", reused));
+	}
+	if !new_entries.is_empty() || journal.len() != reused {
+		let present: HashSet<&Path> = candidates.iter().map(PathBuf::as_path).collect();
+		journal.retain_paths(&present);
+		for (path, entry) in new_entries {
+			journal.record(path, entry);
+		}
+		if let Err(e) = journal.save(root) {
+			ui::debug(&format!("Failed to save scan journal: {}", e));
+		}
+	}
+
 	ScanResult {
 		to_process,
 		already_indexed: already_indexed.load(std::sync::atomic::Ordering::Relaxed),
@@ -144,20 +332,42 @@ pub fn scan_directory(
 	}
 }
 
-fn discover_files(root: &Path, recursive: bool) -> Vec<PathBuf> {
-	let mut files = Vec::new();
-	let mut seen = HashSet::new();
-	discover_recursive(root, recursive, &mut files, &mut seen);
-	files
+pub(crate) fn discover_files(root: &Path, recursive: bool, exclude: &IgnoreRules) -> Vec<PathBuf> {
+	discover(root, recursive, exclude, follow_symlinks())
 }
 
-fn discover_recursive(
-	current: &Path,
-	recursive: bool,
-	files: &mut Vec<PathBuf>,
-	seen: &mut HashSet<PathBuf>,
-) {
-	let ignore_patterns = load_scoutignore(current);
+fn discover(root: &Path, recursive: bool, exclude: &IgnoreRules, follow: bool) -> Vec<PathBuf> {
+	let mut walk = Walk {
+		follow,
+		files: Vec::new(),
+		seen: HashSet::new(),
+		visited: HashSet::new(),
+	};
+	discover_recursive(root, recursive, exclude, &mut walk);
+	walk.files
+}
+
+/// State of one [`discover`] walk
+struct Walk {
+	follow: bool,
+	/// Canonical media paths found so far
+	files: Vec<PathBuf>,
+	seen: HashSet<PathBuf>,
+	/// Canonical directories entered, so symlink cycles end
+	visited: HashSet<PathBuf>,
+}
+
+fn discover_recursive(current: &Path, recursive: bool, rules: &IgnoreRules, walk: &mut Walk) {
+	let Ok(canonical) = current.canonicalize() else {
+		return;
+	};
+	if !walk.visited.insert(canonical) {
+		return;
+	}
+
+	// A .scoutignore applies to its directory and everything below it
+	let local = rules.with_scoutignore(current);
+	let rules = local.as_ref().unwrap_or(rules);
 
 	let Ok(entries) = fs::read_dir(current) else {
 		return;
@@ -171,20 +381,95 @@ fn discover_recursive(
 			continue;
 		}
 
-		if !ignore_patterns.is_empty() && is_ignored(&path, &ignore_patterns) {
+		let is_dir = path.is_dir();
+		if !rules.is_empty() && rules.is_ignored(&path, is_dir) {
+			ui::debug(&format!("Excluded: {}", path.display()));
 			continue;
 		}
 
-		if path.is_dir() {
-			if recursive {
-				discover_recursive(&path, recursive, files, seen);
+		if is_dir {
+			if is_linked_dir(&entry) && !walk.follow {
+				ui::debug(&format!("Skipped symlinked directory: {}", path.display()));
+			} else if recursive {
+				discover_recursive(&path, recursive, rules, walk);
 			}
 		} else if MediaType::detect(&path).is_some() {
 			if let Ok(canonical) = path.canonicalize() {
-				if seen.insert(canonical.clone()) {
-					files.push(canonical);
+				if walk.seen.insert(canonical.clone()) {
+					walk.files.push(canonical);
 				}
 			}
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn rules(patterns: &[&str]) -> IgnoreRules {
+		let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+		IgnoreRules::new(Path::new("/lib"), &patterns).unwrap()
+	}
+
+	#[test]
+	fn test_directory_excludes() {
+		let rules = rules(&["thumbnails/", "/raw"]);
+		assert!(rules.is_ignored(Path::new("/lib/a/thumbnails"), true));
+		assert!(!rules.is_ignored(Path::new("/lib/a/thumbnails"), false));
+		assert!(rules.is_ignored(Path::new("/lib/raw"), true));
+		// Anchored patterns only match at the base
+		assert!(!rules.is_ignored(Path::new("/lib/a/raw"), true));
+	}
+
+	#[test]
+	fn test_extension_excludes() {
+		let rules = rules(&["*.tmp.*"]);
+		assert!(rules.is_ignored(Path::new("/lib/a/photo.tmp.jpg"), false));
+		assert!(rules.is_ignored(Path::new("/lib/PHOTO.TMP.JPG"), false));
+		assert!(!rules.is_ignored(Path::new("/lib/a/photo.jpg"), false));
+	}
+
+	#[test]
+	fn test_negation_and_bare_words() {
+		let rules = rules(&["*.png", "!keep*.png", "cache", "photos/raw"]);
+		assert!(rules.is_ignored(Path::new("/lib/x.png"), false));
+		assert!(!rules.is_ignored(Path::new("/lib/keep-me.png"), false));
+		// Bare words keep the old substring behaviour
+		assert!(rules.is_ignored(Path::new("/lib/my_cache_dir"), true));
+		assert!(rules.is_ignored(Path::new("/lib/a/old_photos/raw"), true));
+		assert!(!rules.is_ignored(Path::new("/outside/x.png"), false));
+	}
+
+	#[test]
+	fn test_invalid_pattern_is_an_error() {
+		assert!(IgnoreRules::new(Path::new("/lib"), &["[".to_string()]).is_err());
+	}
+
+	#[cfg(unix)]
+	#[test]
+	fn test_symlinked_directories_need_follow() {
+		use std::os::unix::fs::symlink;
+
+		let temp = tempfile::tempdir().unwrap();
+		let base = temp.path();
+		let root = base.join("library");
+		let elsewhere = base.join("elsewhere");
+		fs::create_dir_all(&root).unwrap();
+		fs::create_dir_all(&elsewhere).unwrap();
+		image::RgbImage::from_pixel(2, 2, image::Rgb([0, 0, 255]))
+			.save(elsewhere.join("linked.png"))
+			.unwrap();
+		symlink(&elsewhere, root.join("trips")).unwrap();
+		// Leads back to the library itself
+		symlink(&root, elsewhere.join("loop")).unwrap();
+
+		let none = IgnoreRules::default();
+		let skipped = discover(&root, true, &none, false);
+		let followed = discover(&root, true, &none, true);
+
+		assert!(skipped.is_empty(), "{:?}", skipped);
+		assert_eq!(followed.len(), 1);
+		assert!(followed[0].ends_with("elsewhere/linked.png"));
+	}
+}
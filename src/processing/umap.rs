@@ -5,16 +5,25 @@
 
 use anyhow::Result;
 use ndarray::Array2;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
 use rayon::prelude::*;
 
-use crate::core::Embedding;
+use crate::core::{Embedding, Metric};
 use crate::ui;
 
 /// Reduce embeddings from 1024D to lower dimensions using UMAP
+///
+/// `metric` controls how nearest neighbors are found before optimization.
+/// `seed` fixes the random initial layout; umap-rs still draws its negative
+/// samples from an unseeded generator, so the optimized coordinates can vary
+/// slightly between runs even with a seed.
 pub fn reduce_embeddings(
 	embeddings: &[Embedding],
 	n_components: usize,
 	n_neighbors: usize,
+	metric: Metric,
+	seed: Option<u64>,
 ) -> Result<Vec<Vec<f32>>> {
 	let n_samples = embeddings.len();
 	let n_features = embeddings[0].0.len();
@@ -23,7 +32,10 @@ pub fn reduce_embeddings(
 		"Reducing {}D to {}D using UMAP",
 		n_features, n_components
 	));
-	ui::debug(&format!("UMAP neighbors: {}", n_neighbors));
+	ui::debug(&format!(
+		"UMAP neighbors: {}, metric: {:?}, seed: {:?}",
+		n_neighbors, metric, seed
+	));
 
 	// Convert embeddings to ndarray format (umap-rs uses f32)
 	let mut data = Array2::<f32>::zeros((n_samples, n_features));
@@ -35,7 +47,7 @@ pub fn reduce_embeddings(
 
 	// Compute K-nearest neighbors
 	ui::debug("Computing K-nearest neighbors...");
-	let (knn_indices, knn_distances) = compute_knn(embeddings, n_neighbors)?;
+	let (knn_indices, knn_distances) = compute_knn(embeddings, n_neighbors, metric)?;
 
 	// Convert to ndarray format (umap-rs uses u32 for indices)
 	let mut knn_indices_array = Array2::<u32>::zeros((n_samples, n_neighbors));
@@ -49,7 +61,12 @@ pub fn reduce_embeddings(
 	}
 
 	// Initialize embedding with random values
-	let init = initialize_embedding(n_samples, n_components);
+	let init = match seed {
+		Some(seed) => {
+			initialize_embedding(n_samples, n_components, &mut StdRng::seed_from_u64(seed))
+		}
+		None => initialize_embedding(n_samples, n_components, &mut rand::rng()),
+	};
 
 	// Configure and run UMAP
 	ui::debug("Running UMAP optimization...");
@@ -87,7 +104,7 @@ pub fn reduce_embeddings(
 type KnnResult = (Vec<Vec<usize>>, Vec<Vec<f32>>);
 
 /// Compute K-nearest neighbors using brute force (accurate for high dimensions)
-fn compute_knn(embeddings: &[Embedding], k: usize) -> Result<KnnResult> {
+fn compute_knn(embeddings: &[Embedding], k: usize, metric: Metric) -> Result<KnnResult> {
 	let n_samples = embeddings.len();
 
 	ui::debug(&format!(
@@ -102,7 +119,7 @@ fn compute_knn(embeddings: &[Embedding], k: usize) -> Result<KnnResult> {
 			// Compute distances to all other points
 			let mut distances: Vec<(usize, f32)> = (0..n_samples)
 				.filter(|&j| i != j)
-				.map(|j| (j, embeddings[i].distance(&embeddings[j])))
+				.map(|j| (j, metric.distance(&embeddings[i], &embeddings[j])))
 				.collect();
 
 			// Sort by distance and take k nearest
@@ -123,10 +140,7 @@ fn compute_knn(embeddings: &[Embedding], k: usize) -> Result<KnnResult> {
 }
 
 /// Initialize embedding with random values in range [-10, 10]
-fn initialize_embedding(n_samples: usize, n_components: usize) -> Array2<f32> {
-	use rand::Rng;
-	let mut rng = rand::rng();
-
+fn initialize_embedding(n_samples: usize, n_components: usize, rng: &mut impl Rng) -> Array2<f32> {
 	let mut init = Array2::<f32>::zeros((n_samples, n_components));
 	for i in 0..n_samples {
 		for j in 0..n_components {
@@ -136,3 +150,17 @@ fn initialize_embedding(n_samples: usize, n_components: usize) -> Array2<f32> {
 
 	init
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_seeded_initialization_is_reproducible() {
+		let init = |seed: u64| initialize_embedding(60, 4, &mut StdRng::seed_from_u64(seed));
+
+		assert_eq!(init(42), init(42));
+		assert_ne!(init(42), init(43));
+		assert!(init(7).iter().all(|v| (-10.0..10.0).contains(v)));
+	}
+}
@@ -6,7 +6,6 @@
 use anyhow::{Context, Result};
 use image::RgbImage;
 use serde::Deserialize;
-use std::io::Read;
 use std::path::{Path, PathBuf};
 use std::process::{Command, Stdio};
 use std::sync::OnceLock;
@@ -83,10 +82,20 @@ struct ProbeFormat {
 
 #[derive(Deserialize)]
 struct ProbeStream {
+	index: usize,
 	codec_type: String,
+	codec_name: Option<String>,
 	width: Option<u32>,
 	height: Option<u32>,
 	r_frame_rate: Option<String>,
+	#[serde(default)]
+	tags: ProbeTags,
+}
+
+#[derive(Deserialize, Default)]
+struct ProbeTags {
+	language: Option<String>,
+	title: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -95,8 +104,69 @@ struct ProbeOutput {
 	format: ProbeFormat,
 }
 
-/// Get video metadata (duration, dimensions, fps)
-fn probe_video(path: &Path) -> Result<(f64, u32, u32, f64)> {
+#[derive(Deserialize)]
+struct ProbeFrame {
+	width: u32,
+	height: u32,
+}
+
+#[derive(Deserialize)]
+struct ProbeFrames {
+	#[serde(default)]
+	frames: Vec<ProbeFrame>,
+}
+
+/// Description of one video stream in a container
+#[derive(Debug, Clone)]
+pub struct StreamInfo {
+	/// Position among the file's video streams (what `--video-stream` selects)
+	pub video_index: usize,
+	/// Absolute stream index in the container
+	pub index: usize,
+	pub codec: String,
+	pub width: u32,
+	pub height: u32,
+	pub fps: f64,
+	pub language: Option<String>,
+	pub title: Option<String>,
+}
+
+/// List all video streams in a file
+pub fn list_streams(path: &Path) -> Result<(f64, Vec<StreamInfo>)> {
+	let probe = run_ffprobe(path)?;
+
+	let duration = probe
+		.format
+		.duration
+		.as_deref()
+		.and_then(|d| d.parse().ok())
+		.unwrap_or(0.0);
+
+	let streams = probe
+		.streams
+		.into_iter()
+		.filter(|s| s.codec_type == "video")
+		.enumerate()
+		.map(|(video_index, s)| StreamInfo {
+			video_index,
+			index: s.index,
+			codec: s.codec_name.unwrap_or_else(|| "unknown".to_string()),
+			width: s.width.unwrap_or(0),
+			height: s.height.unwrap_or(0),
+			fps: s
+				.r_frame_rate
+				.as_deref()
+				.and_then(parse_fraction)
+				.unwrap_or(30.0),
+			language: s.tags.language,
+			title: s.tags.title,
+		})
+		.collect();
+
+	Ok((duration, streams))
+}
+
+fn run_ffprobe(path: &Path) -> Result<ProbeOutput> {
 	if !is_ffprobe_available() {
 		anyhow::bail!("ffprobe not found in PATH");
 	}
@@ -116,14 +186,30 @@ fn probe_video(path: &Path) -> Result<(f64, u32, u32, f64)> {
 		anyhow::bail!("ffprobe failed");
 	}
 
-	let probe: ProbeOutput =
-		serde_json::from_slice(&output.stdout).context("Failed to parse ffprobe output")?;
+	serde_json::from_slice(&output.stdout).context("Failed to parse ffprobe output")
+}
+
+/// Get video metadata (duration, dimensions, fps) for the selected video stream
+fn probe_video(path: &Path, stream: usize) -> Result<(f64, u32, u32, f64)> {
+	let probe = run_ffprobe(path)?;
 
-	let video_stream = probe
+	let video_streams: Vec<&ProbeStream> = probe
 		.streams
 		.iter()
-		.find(|s| s.codec_type == "video")
-		.context("No video stream found")?;
+		.filter(|s| s.codec_type == "video")
+		.collect();
+
+	if video_streams.is_empty() {
+		anyhow::bail!("No video stream found");
+	}
+
+	let video_stream = video_streams.get(stream).with_context(|| {
+		format!(
+			"Video stream {} not found ({} available)",
+			stream,
+			video_streams.len()
+		)
+	})?;
 
 	let width = video_stream.width.context("Missing width")?;
 	let height = video_stream.height.context("Missing height")?;
@@ -156,59 +242,201 @@ fn parse_fraction(s: &str) -> Option<f64> {
 	}
 }
 
-/// Extract frames using scene detection
-pub fn extract_frames_scene(
+/// How `scan` and `watch` pick frames from a video
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SamplingMode {
+	/// Evenly spaced frames; fastest, may miss short scenes
+	Uniform,
+	/// Frames at scene changes; best coverage, decodes the video once to find cuts
+	#[default]
+	Scene,
+	/// The encoder's keyframes (I-frames); quick to find, spacing set by the encoder
+	Keyframes,
+}
+
+/// Which frames [`extract_frames`] takes from a video
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FrameSampling {
+	/// This many frames spread evenly over the video. Fastest: no analysis
+	/// pass, but cuts shorter than the spacing can be missed.
+	Uniform(usize),
+	/// Frames where the picture changes by more than `threshold` (0.0-1.0),
+	/// thinned evenly to at most `max`. Decodes the whole video once.
+	Scene { max: usize, threshold: f32 },
+	/// Keyframes as placed by the encoder, thinned evenly to at most `max`.
+	/// Cheap to find and seek to, but their spacing depends on the encoder.
+	Keyframes { max: usize },
+}
+
+impl FrameSampling {
+	pub fn new(mode: SamplingMode, max_frames: usize, scene_threshold: f32) -> Self {
+		match mode {
+			SamplingMode::Uniform => Self::Uniform(max_frames),
+			SamplingMode::Scene => Self::Scene {
+				max: max_frames,
+				threshold: scene_threshold,
+			},
+			SamplingMode::Keyframes => Self::Keyframes { max: max_frames },
+		}
+	}
+
+	/// Upper bound on extracted frames
+	pub fn max_frames(&self) -> usize {
+		match *self {
+			Self::Uniform(count) => count,
+			Self::Scene { max, .. } | Self::Keyframes { max } => max,
+		}
+	}
+}
+
+impl Default for FrameSampling {
+	fn default() -> Self {
+		Self::new(
+			SamplingMode::default(),
+			crate::config::MAX_VIDEO_FRAMES,
+			crate::config::SCENE_THRESHOLD,
+		)
+	}
+}
+
+/// Where [`plan_timestamps`] looks up a video's candidate frames
+trait FrameTimes {
+	fn duration(&self) -> f64;
+	fn scene_changes(&self, threshold: f32) -> Result<Vec<f64>>;
+	fn keyframes(&self) -> Result<Vec<f64>>;
+}
+
+/// A video stream on disk, analyzed with FFmpeg and ffprobe
+struct ProbedVideo<'a> {
+	path: &'a Path,
+	stream: usize,
+	duration: f64,
+}
+
+impl FrameTimes for ProbedVideo<'_> {
+	fn duration(&self) -> f64 {
+		self.duration
+	}
+
+	fn scene_changes(&self, threshold: f32) -> Result<Vec<f64>> {
+		detect_scenes(self.path, threshold, self.stream)
+	}
+
+	fn keyframes(&self) -> Result<Vec<f64>> {
+		detect_keyframes(self.path, self.stream)
+	}
+}
+
+/// Timestamps to extract, in playback order
+fn plan_timestamps(sampling: FrameSampling, video: &impl FrameTimes) -> Result<Vec<f64>> {
+	let timestamps = match sampling {
+		FrameSampling::Uniform(count) => uniform_timestamps(video.duration(), count),
+		FrameSampling::Scene { max, threshold } => {
+			sample_timestamps(&video.scene_changes(threshold)?, max)
+		}
+		FrameSampling::Keyframes { max } => sample_timestamps(&video.keyframes()?, max),
+	};
+
+	if timestamps.is_empty() {
+		anyhow::bail!("No frames selected");
+	}
+	Ok(timestamps)
+}
+
+/// Frames read from a video, and the requested timestamps that gave none
+#[derive(Debug, Default)]
+pub struct ExtractedFrames {
+	/// `(timestamp, frame)` in playback order
+	pub frames: Vec<(f64, RgbImage)>,
+	/// `(timestamp, reason)` for each frame that couldn't be read
+	pub failed: Vec<(f64, String)>,
+}
+
+impl ExtractedFrames {
+	/// Number of frames asked for
+	pub fn requested(&self) -> usize {
+		self.frames.len() + self.failed.len()
+	}
+
+	/// Fail unless at least `min_fraction` (0.0-1.0) of the requested frames
+	/// were read
+	pub fn require(&self, min_fraction: f32) -> Result<()> {
+		let fraction = self.frames.len() as f32 / self.requested().max(1) as f32;
+		if fraction < min_fraction {
+			anyhow::bail!(
+				"Extracted only {}/{} requested frames, below the required {:.0}%",
+				self.frames.len(),
+				self.requested(),
+				min_fraction * 100.0
+			);
+		}
+		Ok(())
+	}
+}
+
+/// Extract frames chosen by `sampling`
+///
+/// `stream` selects which video stream to read (0 = first video stream).
+/// Frames FFmpeg can't produce are listed in [`ExtractedFrames::failed`];
+/// it's an error only when none can be read.
+pub fn extract_frames(
 	path: &Path,
-	max_frames: usize,
-	threshold: f32,
-) -> Result<Vec<(f64, RgbImage)>> {
+	sampling: FrameSampling,
+	stream: usize,
+) -> Result<ExtractedFrames> {
 	if !is_available() {
 		anyhow::bail!("FFmpeg not found in PATH");
 	}
 
-	if max_frames == 0 {
+	if sampling.max_frames() == 0 {
 		anyhow::bail!("Max frames must be at least 1");
 	}
 
-	let (duration, width, height, fps) = probe_video(path)?;
+	let (duration, width, height, fps) = probe_video(path, stream)?;
 
 	if duration <= 0.0 {
 		anyhow::bail!("Invalid video duration: {:.2}s", duration);
 	}
 
-	// First pass: detect scene changes
-	let scene_times = detect_scenes(path, threshold)?;
-
-	let frame_count = scene_times.len();
-	let timestamps = if frame_count <= max_frames {
-		// Use all detected scenes
-		scene_times
-	} else {
-		// Too many scenes - sample evenly from detected scenes
-		sample_timestamps(&scene_times, max_frames)
+	let video = ProbedVideo {
+		path,
+		stream,
+		duration,
 	};
-
-	let actual_count = timestamps.len();
+	let timestamps = plan_timestamps(sampling, &video)?;
 
 	ui::debug(&format!(
-		"Video: {:.1}s, {}x{} @ {:.1}fps | Scenes: {} → Frames: {}",
-		duration, width, height, fps, frame_count, actual_count
+		"Video: {:.1}s, {}x{} @ {:.1}fps | {:?} → Frames: {}",
+		duration,
+		width,
+		height,
+		fps,
+		sampling,
+		timestamps.len()
 	));
 
-	if timestamps.is_empty() {
-		anyhow::bail!("No scene changes detected");
+	extract_frames_at_timestamps(path, &timestamps, width, height, stream)
+}
+
+/// Extract the frame at `timestamp` seconds from the first video stream
+pub fn extract_frame(path: &Path, timestamp: f64) -> Result<RgbImage> {
+	if !is_available() {
+		anyhow::bail!("FFmpeg not found in PATH");
 	}
 
-	// Extract frames at detected timestamps
-	extract_frames_at_timestamps(path, &timestamps, width, height)
+	let (_, width, height, _) = probe_video(path, 0)?;
+	let mut extracted = extract_frames_at_timestamps(path, &[timestamp], width, height, 0)?;
+	Ok(extracted.frames.swap_remove(0).1)
 }
 
 /// Detect scene changes in video and return timestamps
-fn detect_scenes(path: &Path, threshold: f32) -> Result<Vec<f64>> {
+fn detect_scenes(path: &Path, threshold: f32, stream: usize) -> Result<Vec<f64>> {
 	// Use FFmpeg's scene detection filter
 	let output = Command::new(get_ffmpeg_binary())
 		.arg("-i")
 		.arg(path)
+		.arg("-map")
+		.arg(format!("0:v:{}", stream))
 		.arg("-vf")
 		.arg(format!("select='gt(scene,{})',showinfo", threshold))
 		.arg("-f")
@@ -247,6 +475,48 @@ fn detect_scenes(path: &Path, threshold: f32) -> Result<Vec<f64>> {
 	Ok(timestamps)
 }
 
+/// Find keyframe timestamps with ffprobe, without decoding other frames
+fn detect_keyframes(path: &Path, stream: usize) -> Result<Vec<f64>> {
+	if !is_ffprobe_available() {
+		anyhow::bail!("ffprobe not found, needed for keyframe sampling");
+	}
+
+	let output = Command::new(get_ffprobe_binary())
+		.arg("-v")
+		.arg("error")
+		.arg("-select_streams")
+		.arg(format!("v:{}", stream))
+		.arg("-skip_frame")
+		.arg("nokey")
+		.arg("-show_entries")
+		.arg("frame=pts_time")
+		.arg("-of")
+		.arg("csv=p=0")
+		.arg(path)
+		.output()
+		.context("Failed to run ffprobe")?;
+
+	if !output.status.success() {
+		anyhow::bail!("ffprobe keyframe detection failed");
+	}
+
+	let timestamps: Vec<f64> = String::from_utf8_lossy(&output.stdout)
+		.lines()
+		.filter_map(|line| line.trim().trim_end_matches(',').parse().ok())
+		.collect();
+
+	if timestamps.is_empty() {
+		anyhow::bail!("No keyframes found");
+	}
+	Ok(timestamps)
+}
+
+/// `count` timestamps at the centers of equal slices of the video
+fn uniform_timestamps(duration: f64, count: usize) -> Vec<f64> {
+	let step = duration / count as f64;
+	(0..count).map(|i| (i as f64 + 0.5) * step).collect()
+}
+
 /// Sample timestamps evenly from a larger set
 fn sample_timestamps(timestamps: &[f64], count: usize) -> Vec<f64> {
 	if timestamps.len() <= count {
@@ -262,22 +532,39 @@ fn sample_timestamps(timestamps: &[f64], count: usize) -> Vec<f64> {
 		.collect()
 }
 
-/// Extract frames at specific timestamps
-fn extract_frames_at_timestamps(
-	path: &Path,
-	timestamps: &[f64],
-	width: u32,
-	height: u32,
-) -> Result<Vec<(f64, RgbImage)>> {
-	let mut frames = Vec::new();
+/// Source of raw rgb24 frames, so extraction can be tested without FFmpeg
+trait FrameReader {
+	/// Raw pixels of the frame at `timestamp`
+	fn read(&self, timestamp: f64) -> Result<Vec<u8>>;
+	/// Dimensions of the frame at `timestamp`, for streams whose size
+	/// changes partway through
+	fn frame_size(&self, timestamp: f64) -> Result<(u32, u32)>;
+}
 
-	for &timestamp in timestamps {
-		// Extract single frame at timestamp
-		let mut child = Command::new(get_ffmpeg_binary())
-			.arg("-ss")
-			.arg(format!("{:.3}", timestamp))
-			.arg("-i")
-			.arg(path)
+/// One video stream read through FFmpeg
+struct FfmpegFrames<'a> {
+	path: &'a Path,
+	stream: usize,
+}
+
+impl FfmpegFrames<'_> {
+	/// Run FFmpeg for one frame. A fast seek jumps to the nearest keyframe
+	/// before decoding; otherwise FFmpeg decodes from the start, which is slow
+	/// but survives broken indexes.
+	fn run(&self, timestamp: f64, fast_seek: bool) -> Result<Vec<u8>> {
+		let seek = ["-ss".to_string(), format!("{:.3}", timestamp)];
+		let mut command = Command::new(get_ffmpeg_binary());
+		if fast_seek {
+			command.args(&seek);
+		}
+		command.arg("-i").arg(self.path);
+		if !fast_seek {
+			command.args(&seek);
+		}
+
+		let output = command
+			.arg("-map")
+			.arg(format!("0:v:{}", self.stream))
 			.arg("-frames:v")
 			.arg("1")
 			.arg("-f")
@@ -288,39 +575,148 @@ fn extract_frames_at_timestamps(
 			.arg("-loglevel")
 			.arg("error")
 			.arg("pipe:1")
-			.stdout(Stdio::piped())
-			.stderr(Stdio::piped())
-			.spawn()
-			.context("Failed to spawn FFmpeg")?;
-
-		let mut frame_data = Vec::new();
-		if let Some(mut stdout) = child.stdout.take() {
-			stdout
-				.read_to_end(&mut frame_data)
-				.context("Failed to read frame from FFmpeg")?;
+			.stdin(Stdio::null())
+			.output()
+			.context("Failed to run FFmpeg")?;
+
+		if !output.status.success() {
+			let stderr = String::from_utf8_lossy(&output.stderr);
+			anyhow::bail!(
+				"FFmpeg failed: {}",
+				stderr.lines().last().unwrap_or("no error output")
+			);
+		}
+		if output.stdout.is_empty() {
+			anyhow::bail!("FFmpeg returned no frame");
 		}
+		Ok(output.stdout)
+	}
+}
 
-		let status = child.wait().context("FFmpeg process failed")?;
+impl FrameReader for FfmpegFrames<'_> {
+	fn read(&self, timestamp: f64) -> Result<Vec<u8>> {
+		self.run(timestamp, true).or_else(|e| {
+			ui::debug(&format!(
+				"Frame at {:.3}s: {:#}, retrying with an accurate seek",
+				timestamp, e
+			));
+			self.run(timestamp, false)
+		})
+	}
 
-		if !status.success() {
-			continue; // Skip failed frames
+	fn frame_size(&self, timestamp: f64) -> Result<(u32, u32)> {
+		if !is_ffprobe_available() {
+			anyhow::bail!("ffprobe not found in PATH");
 		}
 
-		let frame_size = (width * height * 3) as usize;
-		if frame_data.len() >= frame_size {
-			if let Some(image) =
-				RgbImage::from_raw(width, height, frame_data[..frame_size].to_vec())
-			{
-				frames.push((timestamp, image));
-			}
+		let output = Command::new(get_ffprobe_binary())
+			.arg("-v")
+			.arg("error")
+			.arg("-select_streams")
+			.arg(format!("v:{}", self.stream))
+			.arg("-read_intervals")
+			.arg(format!("{:.3}%+#1", timestamp))
+			.arg("-show_entries")
+			.arg("frame=width,height")
+			.arg("-print_format")
+			.arg("json")
+			.arg(self.path)
+			.output()
+			.context("Failed to run ffprobe")?;
+
+		if !output.status.success() {
+			anyhow::bail!("ffprobe failed");
 		}
+		let probe: ProbeFrames =
+			serde_json::from_slice(&output.stdout).context("Failed to parse ffprobe output")?;
+		let frame = probe.frames.first().context("ffprobe found no frame")?;
+		Ok((frame.width, frame.height))
 	}
+}
 
-	if frames.is_empty() {
-		anyhow::bail!("Failed to extract any frames");
+/// Extract frames at specific timestamps
+fn extract_frames_at_timestamps(
+	path: &Path,
+	timestamps: &[f64],
+	width: u32,
+	height: u32,
+	stream: usize,
+) -> Result<ExtractedFrames> {
+	let reader = FfmpegFrames { path, stream };
+	let extracted = read_frames(&reader, timestamps, width, height);
+
+	if extracted.frames.is_empty() {
+		let reason = extracted
+			.failed
+			.first()
+			.map_or("no frames requested", |(_, reason)| reason.as_str());
+		anyhow::bail!("Failed to extract any frames ({})", reason);
+	}
+	if !extracted.failed.is_empty() {
+		ui::warn(&format!(
+			"{}: extracted {}/{} requested frames",
+			path.display(),
+			extracted.frames.len(),
+			extracted.requested()
+		));
 	}
 
-	Ok(frames)
+	Ok(extracted)
+}
+
+/// Read and decode the frame at each timestamp, expecting `width`x`height`.
+///
+/// When the data doesn't match that size, the frame's own dimensions are
+/// probed, since some containers change resolution mid-stream.
+fn read_frames(
+	reader: &impl FrameReader,
+	timestamps: &[f64],
+	width: u32,
+	height: u32,
+) -> ExtractedFrames {
+	let mut extracted = ExtractedFrames::default();
+
+	for &timestamp in timestamps {
+		let frame = reader.read(timestamp).and_then(|data| {
+			let (w, h) = match data.len() == frame_bytes(width, height) {
+				true => (width, height),
+				false => {
+					let (w, h) = reader.frame_size(timestamp).with_context(|| {
+						format!(
+							"Got {} bytes, not a {}x{} frame, and the frame size can't be probed",
+							data.len(),
+							width,
+							height
+						)
+					})?;
+					if data.len() != frame_bytes(w, h) {
+						anyhow::bail!("Got {} bytes, not a {}x{} frame", data.len(), w, h);
+					}
+					ui::debug(&format!(
+						"Frame at {:.3}s is {}x{}, not {}x{}",
+						timestamp, w, h, width, height
+					));
+					(w, h)
+				}
+			};
+			RgbImage::from_raw(w, h, data).context("Invalid frame data")
+		});
+
+		match frame {
+			Ok(image) => extracted.frames.push((timestamp, image)),
+			Err(e) => {
+				ui::debug(&format!("Frame at {:.3}s skipped: {:#}", timestamp, e));
+				extracted.failed.push((timestamp, format!("{:#}", e)));
+			}
+		}
+	}
+
+	extracted
+}
+
+/// Size of a rgb24 frame in bytes
+fn frame_bytes(width: u32, height: u32) -> usize {
+	width as usize * height as usize * 3
 }
 
 /// Format timestamp as MM:SS
@@ -330,3 +726,320 @@ pub fn format_timestamp(seconds: f64) -> String {
 	let secs = total % 60;
 	format!("{:02}:{:02}", minutes, secs)
 }
+
+/// Parse a timestamp like `90`, `01:30`, `1:02:03` or `00:12.5` into seconds.
+///
+/// Inverse of [`format_timestamp`], also accepting hours and fractional seconds.
+pub fn parse_timestamp(text: &str) -> Result<f64> {
+	let invalid = || {
+		anyhow::anyhow!(
+			"Invalid timestamp '{}', expected [HH:]MM:SS or seconds",
+			text
+		)
+	};
+
+	let parts: Vec<&str> = text.trim().split(':').collect();
+	if parts.len() > 3 {
+		return Err(invalid());
+	}
+
+	let (last, leading) = parts.split_last().ok_or_else(invalid)?;
+	let seconds: f64 = last.parse().map_err(|_| invalid())?;
+	if !seconds.is_finite() || seconds < 0.0 || (!leading.is_empty() && seconds >= 60.0) {
+		return Err(invalid());
+	}
+
+	let mut total = 0u64;
+	for (i, part) in leading.iter().enumerate() {
+		let value: u64 = part.parse().map_err(|_| invalid())?;
+		// Minutes must stay below 60 when hours are given
+		if i > 0 && value >= 60 {
+			return Err(invalid());
+		}
+		total = total * 60 + value;
+	}
+
+	Ok(total as f64 * 60.0 + seconds)
+}
+
+/// Drop frames whose embedding is more similar than `threshold` to a frame
+/// already kept, so slow scenes don't fill a sidecar with near-duplicates.
+///
+/// Frames are considered in order, so the first frame is always kept.
+pub fn drop_similar_frames(
+	frames: Vec<(f64, crate::core::Embedding)>,
+	threshold: f32,
+) -> Vec<(f64, crate::core::Embedding)> {
+	let mut kept: Vec<(f64, crate::core::Embedding)> = Vec::with_capacity(frames.len());
+	for (timestamp, embedding) in frames {
+		if kept
+			.iter()
+			.all(|(_, other)| embedding.similarity(other) <= threshold)
+		{
+			kept.push((timestamp, embedding));
+		}
+	}
+	kept
+}
+
+/// Keep at most `max` frames: cluster the frame embeddings into `max` groups
+/// and keep one representative per group, so a long video with many cuts
+/// still covers every distinct scene. Kept frames stay in timestamp order.
+pub fn cap_frames(
+	frames: Vec<(f64, crate::core::Embedding)>,
+	max: usize,
+) -> Vec<(f64, crate::core::Embedding)> {
+	if frames.len() <= max {
+		return frames;
+	}
+
+	let embeddings: Vec<crate::core::Embedding> = frames
+		.iter()
+		.map(|(_, embedding)| embedding.clone())
+		.collect();
+	let mut keep: Vec<usize> = crate::processing::cluster::merge_closest(&embeddings, max)
+		.iter()
+		.map(|group| group[0])
+		.collect();
+	keep.sort_unstable();
+
+	let mut keep = keep.into_iter().peekable();
+	frames
+		.into_iter()
+		.enumerate()
+		.filter(|(i, _)| keep.next_if_eq(i).is_some())
+		.map(|(_, frame)| frame)
+		.collect()
+}
+
+/// Parse a `--max-video-embeddings` count, rejecting 0
+pub fn parse_max_embeddings(text: &str) -> Result<usize, String> {
+	match text.trim().parse::<usize>() {
+		Ok(0) => Err("at least one embedding per video is needed".to_string()),
+		Ok(n) => Ok(n),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
+/// Parse a `--frame-dedup` similarity, which must lie in 0.0-1.0
+pub fn parse_frame_dedup(text: &str) -> Result<f32, String> {
+	parse_unit(text, "similarity")
+}
+
+/// Parse a `--scene-threshold`, which must lie in 0.0-1.0
+pub fn parse_scene_threshold(text: &str) -> Result<f32, String> {
+	parse_unit(text, "threshold")
+}
+
+/// Parse a `--strict-frames` fraction, which must lie in 0.0-1.0
+pub fn parse_strict_frames(text: &str) -> Result<f32, String> {
+	parse_unit(text, "fraction")
+}
+
+/// Parse a `--max-frames` count, rejecting 0
+pub fn parse_max_frames(text: &str) -> Result<usize, String> {
+	match text.trim().parse::<usize>() {
+		Ok(0) => Err("at least one frame per video is needed".to_string()),
+		Ok(n) => Ok(n),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
+fn parse_unit(text: &str, what: &str) -> Result<f32, String> {
+	match text.trim().parse::<f32>() {
+		Ok(value) if (0.0..=1.0).contains(&value) => Ok(value),
+		_ => Err(format!("'{}' is not a {} between 0.0 and 1.0", text, what)),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_parse_timestamp() {
+		assert_eq!(parse_timestamp("01:30").unwrap(), 90.0);
+		assert_eq!(parse_timestamp("90").unwrap(), 90.0);
+		assert_eq!(parse_timestamp("1:02:03").unwrap(), 3723.0);
+		assert_eq!(parse_timestamp("00:12.5").unwrap(), 12.5);
+		assert_eq!(parse_timestamp(" 75:00 ").unwrap(), 4500.0);
+
+		for invalid in ["", "aa:10", "01:60", "1:60:00", "-5", "1:2:3:4", "01:"] {
+			assert!(parse_timestamp(invalid).is_err(), "{:?} parsed", invalid);
+		}
+	}
+
+	#[test]
+	fn test_frame_options_are_validated() {
+		assert_eq!(parse_max_frames("20"), Ok(20));
+		assert!(parse_max_frames("0").is_err());
+		assert_eq!(parse_scene_threshold("0.25"), Ok(0.25));
+		for invalid in ["-0.1", "1.5", "high", "NaN"] {
+			assert!(
+				parse_scene_threshold(invalid).is_err(),
+				"{:?} parsed",
+				invalid
+			);
+		}
+	}
+
+	#[test]
+	fn test_similar_frames_collapse() {
+		use crate::core::Embedding;
+
+		let frame = |x: f32, y: f32| Embedding::new(vec![x, y, 0.0]);
+		let frames = vec![
+			(0.0, frame(1.0, 0.0)),
+			(1.0, frame(1.0, 0.01)),
+			(2.0, frame(0.0, 1.0)),
+			(3.0, frame(1.0, 0.02)),
+			(4.0, frame(0.01, 1.0)),
+		];
+
+		let kept = drop_similar_frames(frames.clone(), 0.98);
+		let timestamps: Vec<f64> = kept.iter().map(|(ts, _)| *ts).collect();
+		assert_eq!(timestamps, vec![0.0, 2.0]);
+
+		// Identical frames still leave one behind
+		let still = vec![(0.0, frame(1.0, 0.0)), (5.0, frame(1.0, 0.0))];
+		assert_eq!(drop_similar_frames(still, 0.0).len(), 1);
+	}
+
+	#[test]
+	fn test_many_scenes_cap_to_max_embeddings() {
+		use crate::core::Embedding;
+
+		// 100 scene frames from five distinct shots, 20 frames each
+		let frames: Vec<(f64, Embedding)> = (0..100)
+			.map(|i| {
+				let mut v = vec![0.0; 5];
+				v[i / 20] = 1.0;
+				v[(i / 20 + 1) % 5] = (i % 20) as f32 * 0.005;
+				(i as f64 * 30.0, Embedding::new(v))
+			})
+			.collect();
+
+		let kept = cap_frames(frames.clone(), 5);
+		let shots: Vec<usize> = kept.iter().map(|(ts, _)| *ts as usize / 600).collect();
+		assert_eq!(shots, vec![0, 1, 2, 3, 4], "one frame per shot, in order");
+
+		assert_eq!(cap_frames(frames.clone(), 12).len(), 12);
+		assert_eq!(cap_frames(frames, 100).len(), 100);
+		assert!(parse_max_embeddings("0").is_err());
+	}
+
+	/// Fixed timings instead of FFmpeg, recording which lookups ran
+	struct StubVideo {
+		scenes: Vec<f64>,
+		keyframes: Vec<f64>,
+		calls: std::cell::RefCell<Vec<String>>,
+	}
+
+	impl FrameTimes for StubVideo {
+		fn duration(&self) -> f64 {
+			100.0
+		}
+
+		fn scene_changes(&self, threshold: f32) -> Result<Vec<f64>> {
+			self.calls.borrow_mut().push(format!("scene {}", threshold));
+			Ok(self.scenes.clone())
+		}
+
+		fn keyframes(&self) -> Result<Vec<f64>> {
+			self.calls.borrow_mut().push("keyframes".to_string());
+			Ok(self.keyframes.clone())
+		}
+	}
+
+	#[test]
+	fn test_sampling_strategy_dispatch() {
+		let video = StubVideo {
+			scenes: vec![3.0, 40.0, 41.0, 90.0],
+			keyframes: (0..50).map(|i| i as f64 * 2.0).collect(),
+			calls: Default::default(),
+		};
+
+		let uniform = plan_timestamps(FrameSampling::Uniform(4), &video).unwrap();
+		assert_eq!(uniform, vec![12.5, 37.5, 62.5, 87.5]);
+		assert!(video.calls.borrow().is_empty());
+
+		let scene = FrameSampling::new(SamplingMode::Scene, 15, 0.2);
+		assert_eq!(plan_timestamps(scene, &video).unwrap(), video.scenes);
+
+		let keyframes = FrameSampling::new(SamplingMode::Keyframes, 5, 0.2);
+		assert_eq!(
+			plan_timestamps(keyframes, &video).unwrap(),
+			vec![0.0, 20.0, 40.0, 60.0, 80.0]
+		);
+		assert_eq!(*video.calls.borrow(), vec!["scene 0.2", "keyframes"]);
+	}
+
+	/// Canned FFmpeg output per timestamp (`None` = FFmpeg fails) and the
+	/// size ffprobe reports for every frame (`None` = probe fails)
+	struct StubFrames {
+		data: Vec<(f64, Option<Vec<u8>>)>,
+		size: Option<(u32, u32)>,
+	}
+
+	impl FrameReader for StubFrames {
+		fn read(&self, timestamp: f64) -> Result<Vec<u8>> {
+			match self.data.iter().find(|(ts, _)| *ts == timestamp) {
+				Some((_, Some(data))) => Ok(data.clone()),
+				_ => anyhow::bail!("FFmpeg failed: Invalid data found when processing input"),
+			}
+		}
+
+		fn frame_size(&self, _timestamp: f64) -> Result<(u32, u32)> {
+			self.size.context("ffprobe failed")
+		}
+	}
+
+	#[test]
+	fn test_frame_failures_are_reported() {
+		// A 4x2 stream that switches to 2x2 at 3s, with a frame FFmpeg can't
+		// decode at 2s and a truncated one at 4s
+		let pixels = |w: u32, h: u32| vec![7u8; frame_bytes(w, h)];
+		let mut reader = StubFrames {
+			data: vec![
+				(0.0, Some(pixels(4, 2))),
+				(1.0, Some(pixels(4, 2))),
+				(2.0, None),
+				(3.0, Some(pixels(2, 2))),
+				(4.0, Some(vec![1, 2, 3])),
+			],
+			size: Some((2, 2)),
+		};
+		let timestamps = [0.0, 1.0, 2.0, 3.0, 4.0];
+
+		let extracted = read_frames(&reader, &timestamps, 4, 2);
+		let read: Vec<f64> = extracted.frames.iter().map(|(ts, _)| *ts).collect();
+		assert_eq!(read, vec![0.0, 1.0, 3.0]);
+		assert_eq!(extracted.frames[2].1.dimensions(), (2, 2));
+
+		assert_eq!(extracted.requested(), 5);
+		let failed: Vec<f64> = extracted.failed.iter().map(|(ts, _)| *ts).collect();
+		assert_eq!(failed, vec![2.0, 4.0]);
+		assert!(extracted.failed[0].1.contains("Invalid data"));
+		assert!(extracted.failed[1].1.contains("Got 3 bytes"));
+
+		assert!(extracted.require(0.6).is_ok());
+		let strict = extracted.require(0.8).unwrap_err();
+		assert!(strict.to_string().contains("3/5"), "{}", strict);
+
+		// Without a probe, a resized frame is skipped instead of misread
+		reader.size = None;
+		let extracted = read_frames(&reader, &[3.0], 4, 2);
+		assert!(extracted.frames.is_empty());
+		assert!(extracted.failed[0].1.contains("can't be probed"));
+	}
+
+	#[test]
+	fn test_parse_timestamp_inverts_format() {
+		for seconds in [0.0, 59.0, 61.0, 3599.0] {
+			assert_eq!(
+				parse_timestamp(&format_timestamp(seconds)).unwrap(),
+				seconds
+			);
+		}
+	}
+}
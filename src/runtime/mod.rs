@@ -4,4 +4,6 @@
 
 pub mod providers;
 
-pub use providers::{create_session, set_provider};
+pub use providers::{
+	create_session, parse_threads, probe_all, set_provider, set_threads, ProviderStatus,
+};
@@ -5,7 +5,9 @@
 
 use anyhow::{Context, Result};
 use ort::session::{builder::GraphOptimizationLevel, Session};
+use serde::Serialize;
 use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Mutex, OnceLock};
 
 use crate::ui;
@@ -14,6 +16,10 @@ pub use crate::cli::Provider;
 
 static SELECTED_PROVIDER: OnceLock<Provider> = OnceLock::new();
 static PROVIDER_LOGGED: Mutex<bool> = Mutex::new(false);
+/// Thread counts for new sessions; 0 means the default
+static INTRA_THREADS: AtomicUsize = AtomicUsize::new(0);
+static INTER_THREADS: AtomicUsize = AtomicUsize::new(0);
+static THREADS_LOGGED: Mutex<bool> = Mutex::new(false);
 
 pub fn set_provider(p: Provider) {
 	let _ = SELECTED_PROVIDER.set(p);
@@ -23,6 +29,39 @@ fn get_provider() -> Provider {
 	*SELECTED_PROVIDER.get().unwrap_or(&Provider::Auto)
 }
 
+/// Set the ONNX Runtime thread pools used by sessions created afterwards.
+///
+/// `intra` threads parallelize work inside an operator; `None` uses one per
+/// logical CPU. `inter` threads run independent operators concurrently;
+/// `None` keeps ONNX Runtime's default. Values must be at least 1.
+pub fn set_threads(intra: Option<usize>, inter: Option<usize>) -> Result<()> {
+	for (name, value) in [("intra-op", intra), ("inter-op", inter)] {
+		if value == Some(0) {
+			anyhow::bail!("{} thread count must be at least 1", name);
+		}
+	}
+	INTRA_THREADS.store(intra.unwrap_or(0), Ordering::Relaxed);
+	INTER_THREADS.store(inter.unwrap_or(0), Ordering::Relaxed);
+	Ok(())
+}
+
+/// Parse a `--threads` value, rejecting 0
+pub fn parse_threads(s: &str) -> std::result::Result<usize, String> {
+	match s.parse::<usize>() {
+		Ok(0) => Err("thread count must be at least 1".to_string()),
+		Ok(n) => Ok(n),
+		Err(e) => Err(e.to_string()),
+	}
+}
+
+/// Intra-op threads: the configured count, or one per logical CPU
+fn intra_threads() -> usize {
+	match INTRA_THREADS.load(Ordering::Relaxed) {
+		0 => std::thread::available_parallelism().map_or(4, |n| n.get()),
+		n => n,
+	}
+}
+
 pub fn create_session(model_path: &Path) -> Result<Session> {
 	let mut builder = Session::builder().context("Failed to create session builder")?;
 
@@ -60,9 +99,30 @@ pub fn create_session(model_path: &Path) -> Result<Session> {
 		}
 	}
 
-	builder
+	let intra = intra_threads();
+	let inter = INTER_THREADS.load(Ordering::Relaxed);
+	{
+		let mut logged = THREADS_LOGGED.lock().unwrap();
+		if !*logged {
+			let inter = match inter {
+				0 => "default".to_string(),
+				n => n.to_string(),
+			};
+			ui::debug(&format!(
+				"ONNX threads: {} intra-op, {} inter-op",
+				intra, inter
+			));
+			*logged = true;
+		}
+	}
+
+	let mut builder = builder
 		.with_optimization_level(GraphOptimizationLevel::Level3)?
-		.with_intra_threads(4)?
+		.with_intra_threads(intra)?;
+	if inter > 0 {
+		builder = builder.with_inter_threads(inter)?;
+	}
+	builder
 		.commit_from_file(model_path)
 		.context("Failed to load model")
 }
@@ -140,3 +200,72 @@ fn try_xnnpack(builder: &mut ort::session::builder::SessionBuilder) -> bool {
 	use ort::ep::XNNPACK;
 	try_provider!(builder, XNNPACK, "XNNPACK")
 }
+
+/// Whether an execution provider can be used on this machine
+#[derive(Debug, Clone, Serialize)]
+pub struct ProviderStatus {
+	pub name: &'static str,
+	pub available: bool,
+	/// Why the provider could not be used, if it was rejected
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub error: Option<String>,
+}
+
+/// Check every execution provider Scout can use, in auto-detection order.
+///
+/// Each provider is registered on a throwaway session builder, so this
+/// reports the same failures `create_session` would hit without loading a model.
+pub fn probe_all() -> Vec<ProviderStatus> {
+	use ort::ep::{TensorRT, CUDA, XNNPACK};
+
+	let mut statuses = vec![
+		probe(TensorRT::default(), "TensorRT"),
+		probe(CUDA::default(), "CUDA"),
+	];
+
+	#[cfg(target_os = "macos")]
+	statuses.push(probe(ort::ep::CoreML::default(), "CoreML"));
+	#[cfg(not(target_os = "macos"))]
+	statuses.push(ProviderStatus {
+		name: "CoreML",
+		available: false,
+		error: Some("Only available on macOS".to_string()),
+	});
+
+	statuses.push(probe(XNNPACK::default(), "XNNPACK"));
+	statuses.push(ProviderStatus {
+		name: "CPU",
+		available: true,
+		error: None,
+	});
+
+	statuses
+}
+
+fn probe(provider: impl ort::ep::ExecutionProvider, name: &'static str) -> ProviderStatus {
+	let unavailable = |error: String| ProviderStatus {
+		name,
+		available: false,
+		error: Some(error),
+	};
+
+	match provider.is_available() {
+		Ok(true) => {}
+		Ok(false) => return unavailable("Not included in this ONNX Runtime build".to_string()),
+		Err(e) => return unavailable(e.to_string()),
+	}
+
+	let mut builder = match Session::builder() {
+		Ok(builder) => builder,
+		Err(e) => return unavailable(e.to_string()),
+	};
+
+	match provider.register(&mut builder) {
+		Ok(_) => ProviderStatus {
+			name,
+			available: true,
+			error: None,
+		},
+		Err(e) => unavailable(e.to_string()),
+	}
+}
@@ -0,0 +1,100 @@
+//! # Sidecar Archive
+//!
+//! Every sidecar under a directory in one file, for backups, moving an index
+//! to another machine, or sharing precomputed embeddings with others.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::storage::Sidecar;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Container layout, bumped when older Scout versions can no longer read it
+const FORMAT: u32 = 1;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Archive {
+	format: u32,
+	/// Scout version that wrote the archive
+	version: String,
+	timestamp: String,
+	/// Absolute directory the archive was exported from
+	root: PathBuf,
+	entries: Vec<ArchiveEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ArchiveEntry {
+	/// Media path relative to the archive's root
+	pub path: PathBuf,
+	pub sidecar: Sidecar,
+}
+
+impl Archive {
+	/// Collect all sidecars under `dir` whose media file still exists
+	pub fn build(dir: &Path, recursive: bool) -> Self {
+		let (sidecars, _) = super::load_all_sidecars(dir, recursive);
+
+		let entries = sidecars
+			.into_iter()
+			.map(|(path, sidecar)| ArchiveEntry {
+				path: path.strip_prefix(dir).unwrap_or(&path).to_path_buf(),
+				sidecar,
+			})
+			.collect();
+
+		Self {
+			format: FORMAT,
+			version: VERSION.to_string(),
+			timestamp: chrono::Utc::now().to_rfc3339(),
+			root: dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf()),
+			entries,
+		}
+	}
+
+	/// Directory the archive was exported from
+	pub fn root(&self) -> &Path {
+		&self.root
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	pub fn save(&self, path: &Path) -> Result<()> {
+		let bytes = rmp_serde::to_vec(self).context("Serialize failed")?;
+		fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+	}
+
+	/// Load an archive, rejecting ones written in a newer format
+	pub fn load(path: &Path) -> Result<Self> {
+		let bytes = fs::read(path).with_context(|| format!("Failed to read {}", path.display()))?;
+		let archive: Self = rmp_serde::from_slice(&bytes).with_context(|| {
+			format!(
+				"{} is not a Scout archive, or one from a newer version",
+				path.display()
+			)
+		})?;
+
+		if archive.format > FORMAT {
+			anyhow::bail!(
+				"{} was exported by Scout {} in a newer format; upgrade to import it",
+				path.display(),
+				archive.version
+			);
+		}
+
+		Ok(archive)
+	}
+
+	pub fn into_entries(self) -> Vec<ArchiveEntry> {
+		self.entries
+	}
+}
@@ -3,15 +3,19 @@
 //! Discover sidecars and build hash-to-path lookup tables.
 //! Enables efficient embedding retrieval for search and clustering.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
 
 use rayon::prelude::*;
 use walkdir::WalkDir;
 
-use crate::config::SIDECAR_DIR;
-use crate::core::{FileHash, MediaType};
+use crate::config::{
+	ANN_FILE, CLUSTERS_FILE, INDEX_FILE, SCAN_JOURNAL_FILE, SIDECAR_DIR, SIDECAR_LOAD_RETRIES,
+	SIDECAR_RETRY_BACKOFF_MS, STORE_DIR_FILE,
+};
+use crate::core::{FileHash, HashMode, MediaType};
+use crate::processing::scan::{follow_symlinks, is_linked_dir};
 use crate::storage::Sidecar;
 
 pub fn find(media_dir: &Path, hash: &FileHash) -> Option<PathBuf> {
@@ -23,60 +27,296 @@ pub fn find(media_dir: &Path, hash: &FileHash) -> Option<PathBuf> {
 	}
 }
 
+/// Sidecar files of the media under `root`, each with its media directory.
+///
+/// With a central [`store`](super::sidecar::store), see [`scan_store`].
 pub fn scan(root: &Path, recursive: bool) -> Vec<(PathBuf, PathBuf)> {
+	if let Some(store) = super::sidecar::store() {
+		return scan_store(&store, root, recursive);
+	}
+
 	let mut results = Vec::new();
-	scan_recursive(root, root, recursive, &mut results);
+	let mut visited = HashSet::new();
+	scan_recursive(root, root, recursive, &mut visited, &mut results);
+	results
+}
+
+/// Sidecars in a central store of the media directories under `root`.
+///
+/// Each store folder names its media directory in a [`STORE_DIR_FILE`], so
+/// only those small files are read, not the sidecars themselves.
+fn scan_store(store: &Path, root: &Path, recursive: bool) -> Vec<(PathBuf, PathBuf)> {
+	let root = root.canonicalize().unwrap_or_else(|_| root.to_path_buf());
+	let mut results = Vec::new();
+	if !recursive {
+		let folder = super::sidecar::sidecar_dir_in(Some(store), &root);
+		scan_sidecar_dir(&folder, &root, &mut results);
+		return results;
+	}
+
+	let Ok(entries) = fs::read_dir(store) else {
+		return results;
+	};
+	for entry in entries.filter_map(|e| e.ok()) {
+		let folder = entry.path();
+		let Ok(media_dir) = fs::read_to_string(folder.join(STORE_DIR_FILE)) else {
+			continue;
+		};
+		let media_dir = PathBuf::from(media_dir);
+		if media_dir.starts_with(&root) {
+			scan_sidecar_dir(&folder, &media_dir, &mut results);
+		}
+	}
 	results
 }
 
+/// Sidecars of a directory tree, see [`load_all_sidecars_checked`]
+#[derive(Default)]
+pub struct LoadedSidecars {
+	/// Media path and sidecar for every sidecar whose media file was found
+	pub sidecars: Vec<(PathBuf, Sidecar)>,
+	/// Hash to media path for every media file under the directory
+	pub hash_cache: HashMap<String, PathBuf>,
+	/// Sidecar files that could not be read after retrying, with the reason
+	pub unreadable: Vec<(PathBuf, String)>,
+}
+
 /// Load all sidecars and return them with hash cache
 /// Returns (Vec<(media_path, sidecar)>, hash_to_path_cache)
+///
+/// Unreadable sidecars are skipped with a warning; use
+/// [`load_all_sidecars_checked`] to handle them yourself.
 pub fn load_all_sidecars(
 	dir: &Path,
 	recursive: bool,
 ) -> (Vec<(PathBuf, Sidecar)>, HashMap<String, PathBuf>) {
-	let sidecar_paths = scan(dir, recursive);
+	load_found_sidecars(dir, recursive, &scan(dir, recursive))
+}
+
+/// [`load_all_sidecars`] for the sidecar files [`scan`] already found under `dir`
+pub fn load_found_sidecars(
+	dir: &Path,
+	recursive: bool,
+	sidecar_paths: &[(PathBuf, PathBuf)],
+) -> (Vec<(PathBuf, Sidecar)>, HashMap<String, PathBuf>) {
+	let loaded = load_found_sidecars_checked(dir, recursive, sidecar_paths);
+
+	if !loaded.unreadable.is_empty() {
+		crate::ui::warn(&format!(
+			"Skipped {} unreadable sidecars. Run 'scout clean --repair' to remove them",
+			loaded.unreadable.len()
+		));
+		for (path, error) in &loaded.unreadable {
+			crate::ui::debug(&format!("Unreadable sidecar {}: {}", path.display(), error));
+		}
+	}
+
+	(loaded.sidecars, loaded.hash_cache)
+}
 
+/// Like [`load_all_sidecars`], but reports sidecars that failed to load.
+///
+/// Failed sidecars are retried [`SIDECAR_LOAD_RETRIES`] times with
+/// exponential backoff, in case they were being written concurrently.
+/// Sidecars are decoded on the rayon pool while the hash cache is built, and
+/// come back in the order [`scan`] found them.
+pub fn load_all_sidecars_checked(dir: &Path, recursive: bool) -> LoadedSidecars {
+	load_found_sidecars_checked(dir, recursive, &scan(dir, recursive))
+}
+
+fn load_found_sidecars_checked(
+	dir: &Path,
+	recursive: bool,
+	sidecar_paths: &[(PathBuf, PathBuf)],
+) -> LoadedSidecars {
 	if sidecar_paths.is_empty() {
-		return (Vec::new(), HashMap::new());
+		return LoadedSidecars::default();
 	}
 
-	crate::ui::debug("Building file hash cache...");
-	let cache_start = std::time::Instant::now();
-	let hash_cache = build_hash_cache(dir, recursive);
-	let cache_duration = cache_start.elapsed();
+	// Hashing media and decoding sidecars don't depend on each other
+	let load_start = std::time::Instant::now();
+	let (mut hash_cache, (loaded, unreadable)) = rayon::join(
+		|| build_hash_cache(dir, recursive),
+		|| load_sidecars(sidecar_paths),
+	);
 	crate::ui::debug(&format!(
-		"Built hash cache ({} files) in {:.2}s",
+		"Loaded {} sidecars and hashed {} files in {:.2}s",
+		loaded.len(),
 		hash_cache.len(),
-		cache_duration.as_secs_f32()
+		load_start.elapsed().as_secs_f32()
 	));
 
-	let mut results = Vec::with_capacity(sidecar_paths.len());
+	// Sidecars from `scan --hash full` need whole-file hashes to find their media
+	if loaded
+		.iter()
+		.any(|sidecar| sidecar.hash_mode() == HashMode::Full)
+	{
+		crate::ui::debug("Building full-content hash cache...");
+		hash_cache.extend(build_hash_cache_with(dir, recursive, HashMode::Full));
+	}
 
-	let loaded_sidecars: Vec<(PathBuf, Sidecar)> = sidecar_paths
-		.par_iter()
-		.filter_map(|(sidecar_path, _media_dir)| {
-			if let Ok(sidecar) = super::sidecar::load(sidecar_path) {
-				let hash = sidecar.hash();
-				if let Some(media_path) = hash_cache.get(hash) {
-					return Some((media_path.clone(), sidecar));
-				}
-			}
-			None
+	let sidecars = loaded
+		.into_iter()
+		.filter_map(|sidecar| {
+			hash_cache
+				.get(sidecar.hash())
+				.map(|media_path| (media_path.clone(), sidecar))
 		})
 		.collect();
 
-	results.extend(loaded_sidecars);
+	LoadedSidecars {
+		sidecars,
+		hash_cache,
+		unreadable,
+	}
+}
+
+/// Decode sidecar files in parallel, keeping their order and retrying failures.
+///
+/// Returns the loaded sidecars and the files that still failed, with the reason.
+fn load_sidecars(sidecar_paths: &[(PathBuf, PathBuf)]) -> (Vec<Sidecar>, Vec<(PathBuf, String)>) {
+	let mut slots: Vec<Option<Sidecar>> = Vec::new();
+	slots.resize_with(sidecar_paths.len(), || None);
+	let mut pending: Vec<usize> = (0..sidecar_paths.len()).collect();
+	let mut unreadable = Vec::new();
 
-	(results, hash_cache)
+	for attempt in 0..=SIDECAR_LOAD_RETRIES {
+		if pending.is_empty() {
+			break;
+		}
+		if attempt > 0 {
+			let backoff = SIDECAR_RETRY_BACKOFF_MS << (attempt - 1);
+			std::thread::sleep(std::time::Duration::from_millis(backoff));
+		}
+
+		let attempts: Vec<(usize, anyhow::Result<Sidecar>)> = pending
+			.par_iter()
+			.map(|&i| (i, super::sidecar::load(&sidecar_paths[i].0)))
+			.collect();
+
+		pending.clear();
+		unreadable.clear();
+		for (i, result) in attempts {
+			match result {
+				Ok(sidecar) => slots[i] = Some(sidecar),
+				Err(e) => {
+					pending.push(i);
+					unreadable.push((sidecar_paths[i].0.clone(), format!("{:#}", e)));
+				}
+			}
+		}
+	}
+
+	(slots.into_iter().flatten().collect(), unreadable)
+}
+
+/// Ensure all sidecars were produced by models with the same embedding size.
+///
+/// Returns the shared dimension, or `None` when there are no embeddings.
+/// Sidecars without any embedding are ignored.
+pub fn check_dimensions(sidecars: &[(PathBuf, Sidecar)]) -> anyhow::Result<Option<usize>> {
+	let mut expected: Option<(usize, &Path)> = None;
+
+	for (path, sidecar) in sidecars {
+		let dim = sidecar.dimension();
+		if dim == 0 {
+			continue;
+		}
+
+		match expected {
+			None => expected = Some((dim, path)),
+			Some((expected_dim, first)) if expected_dim != dim => anyhow::bail!(
+				"Mixed embedding dimensions: {} is {}D but {} is {}D. \
+				 Rescan with a single model before searching or clustering",
+				first.display(),
+				expected_dim,
+				path.display(),
+				dim
+			),
+			Some(_) => {}
+		}
+	}
+
+	Ok(expected.map(|(dim, _)| dim))
+}
+
+/// Model fingerprints among `sidecars` with how many sidecars carry each,
+/// most common first. Sidecars written before fingerprints existed are skipped.
+pub fn model_fingerprints<'a>(
+	sidecars: impl IntoIterator<Item = &'a Sidecar>,
+) -> Vec<(String, usize)> {
+	let mut counts: HashMap<String, usize> = HashMap::new();
+	for fingerprint in sidecars.into_iter().filter_map(Sidecar::fingerprint) {
+		*counts.entry(fingerprint.to_string()).or_default() += 1;
+	}
+	sorted_models(counts)
+}
+
+fn sorted_models(counts: HashMap<String, usize>) -> Vec<(String, usize)> {
+	let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+	counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+	counts
+}
+
+/// Warn when sidecars were embedded by more than one model, or fail if `strict`.
+///
+/// Catches what [`check_dimensions`] cannot: an upgraded model with the same
+/// embedding size, rescanned into only part of a library with `--force`.
+/// Its vectors compare without error but rank against the old ones wrongly.
+pub fn check_models<'a>(
+	sidecars: impl IntoIterator<Item = &'a Sidecar>,
+	strict: bool,
+) -> anyhow::Result<()> {
+	report_models(&model_fingerprints(sidecars), strict)
+}
+
+/// [`check_models`] for fingerprint counts gathered while streaming sidecars
+pub fn check_model_counts(counts: HashMap<String, usize>, strict: bool) -> anyhow::Result<()> {
+	report_models(&sorted_models(counts), strict)
+}
+
+fn report_models(models: &[(String, usize)], strict: bool) -> anyhow::Result<()> {
+	if models.len() < 2 {
+		return Ok(());
+	}
+
+	let message = format!(
+		"Embeddings come from {} different models ({}), so their scores are not comparable",
+		models.len(),
+		models
+			.iter()
+			.map(|(fingerprint, count)| format!("{}: {} files", fingerprint, count))
+			.collect::<Vec<_>>()
+			.join(", ")
+	);
+	if strict {
+		anyhow::bail!(
+			"{}. Run 'scout scan' to re-embed them with the current model",
+			message
+		);
+	}
+
+	crate::ui::warn(&message);
+	crate::ui::info("Run 'scout scan' to re-embed them with the current model");
+	Ok(())
 }
 
 pub fn build_hash_cache(dir: &Path, recursive: bool) -> HashMap<String, PathBuf> {
+	build_hash_cache_with(dir, recursive, HashMode::Fast)
+}
+
+pub fn build_hash_cache_with(
+	dir: &Path,
+	recursive: bool,
+	mode: HashMode,
+) -> HashMap<String, PathBuf> {
 	let walker = if recursive {
 		WalkDir::new(dir)
 	} else {
 		WalkDir::new(dir).max_depth(1)
 	};
+	// Loops are reported as errors by walkdir and skipped
+	let walker = walker.follow_links(follow_symlinks());
 
 	let media_files: Vec<PathBuf> = walker
 		.into_iter()
@@ -89,7 +329,7 @@ pub fn build_hash_cache(dir: &Path, recursive: bool) -> HashMap<String, PathBuf>
 	media_files
 		.par_iter()
 		.filter_map(|path| {
-			FileHash::compute(path)
+			FileHash::compute_with(path, mode)
 				.ok()
 				.map(|hash| (hash.as_str().to_string(), path.clone()))
 		})
@@ -100,8 +340,16 @@ fn scan_recursive(
 	current: &Path,
 	root: &Path,
 	recursive: bool,
+	visited: &mut HashSet<PathBuf>,
 	results: &mut Vec<(PathBuf, PathBuf)>,
 ) {
+	// Symlinks can lead back to a directory that was already scanned
+	let Ok(canonical) = current.canonicalize() else {
+		return;
+	};
+	if !visited.insert(canonical) {
+		return;
+	}
 	let Ok(entries) = fs::read_dir(current) else {
 		return;
 	};
@@ -113,8 +361,8 @@ fn scan_recursive(
 			if path.file_name() == Some(std::ffi::OsStr::new(SIDECAR_DIR)) {
 				let media_dir = path.parent().unwrap_or(root).to_path_buf();
 				scan_sidecar_dir(&path, &media_dir, results);
-			} else if recursive {
-				scan_recursive(&path, root, recursive, results);
+			} else if recursive && (follow_symlinks() || !is_linked_dir(&entry)) {
+				scan_recursive(&path, root, recursive, visited, results);
 			}
 		}
 	}
@@ -127,9 +375,20 @@ fn scan_sidecar_dir(scout_dir: &Path, media_dir: &Path, results: &mut Vec<(PathB
 
 	for entry in entries.filter_map(|e| e.ok()) {
 		let path = entry.path();
-		if path.extension().and_then(|s| s.to_str()) == Some("msgpack") {
-			results.push((path, media_dir.to_path_buf()));
+		if path.extension().and_then(|s| s.to_str()) != Some("msgpack") {
+			continue;
+		}
+
+		// Skip directory-level databases that share the sidecar extension
+		let name = path.file_name().and_then(|n| n.to_str());
+		if matches!(
+			name,
+			Some(CLUSTERS_FILE | INDEX_FILE | ANN_FILE | SCAN_JOURNAL_FILE)
+		) {
+			continue;
 		}
+
+		results.push((path, media_dir.to_path_buf()));
 	}
 }
 
@@ -145,7 +404,7 @@ pub fn find_file_by_hash(media_dir: &Path, hash: &str) -> Option<PathBuf> {
 			continue;
 		}
 
-		if let Ok(file_hash) = FileHash::compute(&path) {
+		if let Ok(file_hash) = FileHash::compute_with(&path, HashMode::of(hash)) {
 			if file_hash.as_str() == hash {
 				return Some(path);
 			}
@@ -154,3 +413,109 @@ pub fn find_file_by_hash(media_dir: &Path, hash: &str) -> Option<PathBuf> {
 
 	None
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::core::Embedding;
+	use crate::storage::sidecar::{self, ImageSidecar};
+
+	#[cfg(unix)]
+	#[test]
+	fn test_store_indexes_read_only_directories() {
+		use std::os::unix::fs::PermissionsExt;
+
+		let temp = tempfile::tempdir().unwrap();
+		let base = temp.path();
+		let media = base.join("media");
+		let store = base.join("store");
+		fs::create_dir_all(media.join("nested")).unwrap();
+		for (name, shade) in [("top.png", 0), ("nested/deep.png", 255)] {
+			image::RgbImage::from_pixel(2, 2, image::Rgb([shade, 0, 0]))
+				.save(media.join(name))
+				.unwrap();
+		}
+		fs::set_permissions(&media, fs::Permissions::from_mode(0o555)).unwrap();
+
+		for name in ["top.png", "nested/deep.png"] {
+			let path = media.join(name);
+			let hash = FileHash::compute(&path).unwrap();
+			let sidecar =
+				ImageSidecar::new(hash.clone(), Embedding::new(vec![1.0, 0.0])).with_source(&path);
+			let target = sidecar::save_path(Some(&store), path.parent().unwrap(), &hash).unwrap();
+			sidecar::write(&sidecar, &target).unwrap();
+		}
+		let other = base.join("elsewhere");
+		let hash = FileHash::from_hex("f".repeat(16));
+		let stray = ImageSidecar::new(hash.clone(), Embedding::new(vec![0.0, 1.0]));
+		let target = sidecar::save_path(Some(&store), &other, &hash).unwrap();
+		sidecar::write(&stray, &target).unwrap();
+
+		let media_dir = media.canonicalize().unwrap();
+		let shallow = scan_store(&store, &media, false);
+		let deep = scan_store(&store, &media, true);
+		let written = media.join(SIDECAR_DIR).exists();
+		let found = shallow
+			.first()
+			.and_then(|(path, dir)| find_file_by_hash(dir, sidecar::load(path).unwrap().hash()));
+
+		fs::set_permissions(&media, fs::Permissions::from_mode(0o755)).unwrap();
+
+		assert!(!written, "nothing is written beside the media");
+		assert_eq!(shallow.len(), 1);
+		assert_eq!(shallow[0].1, media_dir);
+		assert_eq!(found, Some(media_dir.join("top.png")));
+		assert_eq!(deep.len(), 2);
+	}
+
+	#[test]
+	fn test_parallel_load_matches_sequential() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		for i in 0..24u8 {
+			let media_dir = if i % 3 == 0 {
+				dir.join("nested")
+			} else {
+				dir.to_path_buf()
+			};
+			fs::create_dir_all(&media_dir).unwrap();
+			let path = media_dir.join(format!("{}.png", i));
+			image::RgbImage::from_pixel(2, 2, image::Rgb([i * 10, 0, 255 - i * 10]))
+				.save(&path)
+				.unwrap();
+			let hash = FileHash::compute(&path).unwrap();
+			let embedding = Embedding::new(vec![i as f32, 1.0]);
+			sidecar::save_image(
+				&ImageSidecar::new(hash.clone(), embedding),
+				&media_dir,
+				&hash,
+			)
+			.unwrap();
+		}
+		fs::write(
+			dir.join(SIDECAR_DIR).join("broken.msgpack"),
+			b"not a sidecar",
+		)
+		.unwrap();
+
+		let loaded = load_all_sidecars_checked(dir, true);
+		let hash_cache = build_hash_cache(dir, true);
+		let sequential: Vec<(PathBuf, String)> = scan(dir, true)
+			.iter()
+			.filter_map(|(path, _)| sidecar::load(path).ok())
+			.filter_map(|sidecar| {
+				let media_path = hash_cache.get(sidecar.hash())?;
+				Some((media_path.clone(), sidecar.hash().to_string()))
+			})
+			.collect();
+
+		let parallel: Vec<(PathBuf, String)> = loaded
+			.sidecars
+			.iter()
+			.map(|(path, sidecar)| (path.clone(), sidecar.hash().to_string()))
+			.collect();
+		assert_eq!(parallel.len(), 24);
+		assert_eq!(parallel, sequential);
+		assert_eq!(loaded.unreadable.len(), 1);
+	}
+}
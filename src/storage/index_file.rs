@@ -0,0 +1,181 @@
+//! # Consolidated Index
+//!
+//! Optional single-file snapshot of every sidecar under a directory.
+//! Avoids reading thousands of sidecars and rehashing media on each search.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use crate::config::INDEX_FILE;
+use crate::storage::Sidecar;
+use crate::ui;
+
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct IndexFile {
+	version: String,
+	timestamp: String,
+	recursive: bool,
+	entries: Vec<IndexEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexEntry {
+	/// Media path relative to the indexed root
+	path: PathBuf,
+	sidecar: Sidecar,
+}
+
+impl IndexFile {
+	/// Collect all sidecars under `dir` into a single index
+	pub fn build(dir: &Path, recursive: bool) -> Self {
+		let (sidecars, _) = super::load_all_sidecars(dir, recursive);
+
+		let entries = sidecars
+			.into_iter()
+			.map(|(path, sidecar)| IndexEntry {
+				path: path.strip_prefix(dir).unwrap_or(&path).to_path_buf(),
+				sidecar,
+			})
+			.collect();
+
+		Self {
+			version: VERSION.to_string(),
+			timestamp: chrono::Utc::now().to_rfc3339(),
+			recursive,
+			entries,
+		}
+	}
+
+	pub fn path(dir: &Path) -> PathBuf {
+		super::sidecar_dir(dir).join(INDEX_FILE)
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Add or replace the entry for `media_path` (resolved against `dir`)
+	pub fn upsert(&mut self, dir: &Path, media_path: &Path, sidecar: Sidecar) {
+		let path = media_path
+			.strip_prefix(dir)
+			.unwrap_or(media_path)
+			.to_path_buf();
+
+		match self.entries.iter_mut().find(|e| e.path == path) {
+			Some(entry) => entry.sidecar = sidecar,
+			None => self.entries.push(IndexEntry { path, sidecar }),
+		}
+		self.timestamp = chrono::Utc::now().to_rfc3339();
+	}
+
+	pub fn save(&self, dir: &Path) -> Result<()> {
+		let path = Self::path(dir);
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)
+				.with_context(|| format!("Failed to create {}", parent.display()))?;
+		}
+		let bytes = rmp_serde::to_vec(self).context("Serialize failed")?;
+		fs::write(&path, bytes).context("Write failed")?;
+		Ok(())
+	}
+
+	/// Load the index, rejecting files written by a different Scout version
+	pub fn load(dir: &Path) -> Result<Self> {
+		let bytes = fs::read(Self::path(dir)).context("Read failed")?;
+		let index: Self = rmp_serde::from_slice(&bytes).context("Deserialize failed")?;
+
+		if index.version != VERSION {
+			anyhow::bail!(
+				"Index was built by Scout {} (current {})",
+				index.version,
+				VERSION
+			);
+		}
+
+		Ok(index)
+	}
+
+	/// Load the index only if it still reflects the sidecars on disk.
+	///
+	/// The index is considered stale when it was built with a different
+	/// `recursive` setting, the sidecar count changed, or any sidecar is
+	/// newer than the index file.
+	pub fn load_fresh(dir: &Path, recursive: bool) -> Option<Self> {
+		Self::load_fresh_for(dir, recursive, &super::scan(dir, recursive))
+	}
+
+	/// [`IndexFile::load_fresh`] against the sidecar files [`super::scan`]
+	/// already found under `dir`
+	pub fn load_fresh_for(
+		dir: &Path,
+		recursive: bool,
+		sidecar_paths: &[(PathBuf, PathBuf)],
+	) -> Option<Self> {
+		let index_path = Self::path(dir);
+		let index_modified = fs::metadata(&index_path).and_then(|m| m.modified()).ok()?;
+
+		let index = match Self::load(dir) {
+			Ok(index) => index,
+			Err(e) => {
+				ui::debug(&format!("Ignoring index: {}", e));
+				return None;
+			}
+		};
+
+		if index.recursive != recursive {
+			ui::debug("Ignoring index: built with a different --recursive setting");
+			return None;
+		}
+
+		if sidecar_paths.len() != index.entries.len() {
+			ui::debug(&format!(
+				"Ignoring index: {} entries but {} sidecars on disk",
+				index.entries.len(),
+				sidecar_paths.len()
+			));
+			return None;
+		}
+
+		let newest_sidecar = sidecar_paths
+			.iter()
+			.filter_map(|(path, _)| fs::metadata(path).and_then(|m| m.modified()).ok())
+			.max()
+			.unwrap_or(SystemTime::UNIX_EPOCH);
+
+		if newest_sidecar > index_modified {
+			ui::debug("Ignoring index: sidecars changed since it was built (run 'scout reindex')");
+			return None;
+		}
+
+		Some(index)
+	}
+
+	/// Convert into the same shape as [`super::load_all_sidecars`].
+	///
+	/// Entries whose media file no longer exists are dropped.
+	pub fn into_sidecars(self, dir: &Path) -> (Vec<(PathBuf, Sidecar)>, HashMap<String, PathBuf>) {
+		let mut sidecars = Vec::with_capacity(self.entries.len());
+		let mut hash_cache = HashMap::with_capacity(self.entries.len());
+
+		for entry in self.entries {
+			let path = dir.join(&entry.path);
+			if !path.exists() {
+				continue;
+			}
+			hash_cache.insert(entry.sidecar.hash().to_string(), path.clone());
+			sidecars.push((path, entry.sidecar));
+		}
+
+		(sidecars, hash_cache)
+	}
+}
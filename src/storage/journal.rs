@@ -0,0 +1,135 @@
+//! # Scan Journal
+//!
+//! Remembers the hash of every file seen by `scan`, keyed by path and
+//! validated by size and modification time. Lets repeated or resumed scans
+//! of large trees skip rehashing files that have not changed.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::config::SCAN_JOURNAL_FILE;
+use crate::core::FileHash;
+use crate::ui;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ScanJournal {
+	entries: HashMap<PathBuf, JournalEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JournalEntry {
+	hash: String,
+	size: u64,
+	/// Modification time as (seconds, nanoseconds) since the Unix epoch
+	modified: (u64, u32),
+}
+
+impl JournalEntry {
+	/// Describe the file's current state, or `None` if its metadata is unreadable
+	pub fn new(metadata: &fs::Metadata, hash: &FileHash) -> Option<Self> {
+		Some(Self {
+			hash: hash.to_string(),
+			size: metadata.len(),
+			modified: modified(metadata)?,
+		})
+	}
+}
+
+fn modified(metadata: &fs::Metadata) -> Option<(u64, u32)> {
+	let since_epoch = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+	Some((since_epoch.as_secs(), since_epoch.subsec_nanos()))
+}
+
+impl ScanJournal {
+	pub fn path(root: &Path) -> PathBuf {
+		super::sidecar_dir(root).join(SCAN_JOURNAL_FILE)
+	}
+
+	/// Load the journal under `root`, starting empty if it is missing or unreadable
+	pub fn load(root: &Path) -> Self {
+		let Ok(bytes) = fs::read(Self::path(root)) else {
+			return Self::default();
+		};
+
+		match rmp_serde::from_slice(&bytes) {
+			Ok(journal) => journal,
+			Err(e) => {
+				ui::debug(&format!("Ignoring scan journal: {}", e));
+				Self::default()
+			}
+		}
+	}
+
+	pub fn save(&self, root: &Path) -> Result<()> {
+		let path = Self::path(root);
+		if let Some(parent) = path.parent() {
+			fs::create_dir_all(parent)
+				.with_context(|| format!("Failed to create {}", parent.display()))?;
+		}
+		let bytes = rmp_serde::to_vec(self).context("Failed to serialize scan journal")?;
+		fs::write(&path, bytes).context("Failed to write scan journal")
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	/// Hash recorded for `path`, if the file's size and mtime are unchanged
+	pub fn lookup(&self, path: &Path, metadata: &fs::Metadata) -> Option<FileHash> {
+		let entry = self.entries.get(path)?;
+		(entry.size == metadata.len() && Some(entry.modified) == modified(metadata))
+			.then(|| FileHash::from_hex(entry.hash.clone()))
+	}
+
+	/// Hash recorded for `path` by the last scan, even if the file has changed since
+	pub fn recorded(&self, path: &Path) -> Option<FileHash> {
+		self.entries
+			.get(path)
+			.map(|entry| FileHash::from_hex(entry.hash.clone()))
+	}
+
+	pub fn record(&mut self, path: PathBuf, entry: JournalEntry) {
+		self.entries.insert(path, entry);
+	}
+
+	/// Drop entries for files that are no longer present
+	pub fn retain_paths(&mut self, paths: &HashSet<&Path>) {
+		self.entries
+			.retain(|path, _| paths.contains(path.as_path()));
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_entry_invalidated_when_file_changes() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		let file = dir.join("photo.jpg");
+		fs::write(&file, b"original").unwrap();
+
+		let hash = FileHash::compute(&file).unwrap();
+		let mut journal = ScanJournal::default();
+		let entry = JournalEntry::new(&fs::metadata(&file).unwrap(), &hash).unwrap();
+		journal.record(file.clone(), entry);
+		journal.save(dir).unwrap();
+
+		let journal = ScanJournal::load(dir);
+		let metadata = fs::metadata(&file).unwrap();
+		assert_eq!(journal.lookup(&file, &metadata), Some(hash));
+
+		fs::write(&file, b"edited, and longer").unwrap();
+		let metadata = fs::metadata(&file).unwrap();
+		assert_eq!(journal.lookup(&file, &metadata), None);
+	}
+}
@@ -3,8 +3,20 @@
 //! Sidecar file I/O for persisting embeddings alongside media.
 //! Uses MessagePack for compact binary storage.
 
+pub mod archive;
 pub mod index;
+pub mod index_file;
+pub mod journal;
 pub mod sidecar;
 
-pub use index::{find, find_file_by_hash, load_all_sidecars, scan};
-pub use sidecar::{load, save_image, save_video, ImageSidecar, Sidecar, VideoSidecar};
+pub use archive::Archive;
+pub use index::{
+	check_dimensions, check_model_counts, check_models, find, find_file_by_hash, load_all_sidecars,
+	load_all_sidecars_checked, load_found_sidecars, model_fingerprints, scan, LoadedSidecars,
+};
+pub use index_file::IndexFile;
+pub use journal::ScanJournal;
+pub use sidecar::{
+	load, save, save_image, save_video, set_store, sidecar_dir, store, ImageSidecar, MediaInfo,
+	Sidecar, SidecarFormat, VideoAggregation, VideoSidecar,
+};
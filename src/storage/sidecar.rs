@@ -1,23 +1,178 @@
 //! # Sidecar Files
 //!
 //! MessagePack-serialized embedding storage for images and videos.
-//! Version-tracked for compatibility during upgrades.
+//! Version-tracked for compatibility during upgrades, and checksummed so
+//! truncated or corrupt files are detected instead of mis-parsed.
 
 use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use xxhash_rust::xxh3::xxh3_64;
 
-use crate::config::{SIDECAR_DIR, SIDECAR_EXT};
-use crate::core::{Embedding, FileHash};
+use crate::config::{SIDECAR_DIR, SIDECAR_EXT, STORE_DIR_FILE};
+use crate::core::{Embedding, FileHash, HashMode, QuantizedEmbedding};
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
+/// Leads checksummed sidecars. Older files are bare MessagePack, which never
+/// starts with these bytes.
+const MAGIC: &[u8; 4] = b"SCT\x01";
+/// Magic followed by the xxh3 checksum of the payload
+const HEADER_LEN: usize = MAGIC.len() + 8;
+
+static STORE: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// How new sidecars store their embeddings
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SidecarFormat {
+	/// Store image embeddings as int8 (~4x smaller)
+	pub quantize: bool,
+	/// Store embeddings as f16 (lossy, ~2x smaller)
+	pub half: bool,
+}
+
+/// Keep all sidecars in one central directory, keyed by content hash,
+/// instead of a `.scout` directory beside the media. Read-only media can be
+/// indexed this way. `None` goes back to `SCOUT_STORE`, if set.
+pub fn set_store(store: Option<PathBuf>) {
+	*STORE.lock().unwrap() = store;
+}
+
+/// The central sidecar store from [`set_store`] or the `SCOUT_STORE`
+/// environment variable, or `None` for per-directory sidecars
+pub fn store() -> Option<PathBuf> {
+	STORE.lock().unwrap().clone().or_else(|| {
+		std::env::var_os("SCOUT_STORE")
+			.filter(|path| !path.is_empty())
+			.map(PathBuf::from)
+	})
+}
+
+/// An embedding as stored in a sidecar: a plain f32 array, or
+/// [`Embedding::serialize_compact`] bytes when `half` is set. Reads both, so
+/// older sidecars keep loading.
+#[derive(Debug, Clone, Default)]
+struct StoredEmbedding {
+	data: Vec<f32>,
+	half: bool,
+}
+
+impl StoredEmbedding {
+	fn new(data: Vec<f32>) -> Self {
+		Self { data, half: false }
+	}
+}
+
+impl Serialize for StoredEmbedding {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		if self.half && !self.data.is_empty() && !serializer.is_human_readable() {
+			serializer.serialize_bytes(&Embedding::raw(self.data.clone()).serialize_compact())
+		} else {
+			self.data.serialize(serializer)
+		}
+	}
+}
+
+impl<'de> Deserialize<'de> for StoredEmbedding {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		deserializer.deserialize_any(StoredVisitor)
+	}
+}
+
+struct StoredVisitor;
+
+impl<'de> serde::de::Visitor<'de> for StoredVisitor {
+	type Value = StoredEmbedding;
+
+	fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		f.write_str("an f32 array or compact embedding bytes")
+	}
+
+	fn visit_seq<A: serde::de::SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+		let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+		while let Some(v) = seq.next_element()? {
+			data.push(v);
+		}
+		Ok(StoredEmbedding::new(data))
+	}
+
+	fn visit_bytes<E: serde::de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+		Embedding::deserialize_compact(bytes)
+			.map(|embedding| StoredEmbedding {
+				data: embedding.0,
+				half: true,
+			})
+			.map_err(E::custom)
+	}
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ImageSidecar {
 	version: String,
 	hash: String,
-	embedding: Vec<f32>,
+	/// Full-precision embedding (empty when `quantized` is set)
+	embedding: StoredEmbedding,
+	/// Format flag: when present, the embedding is stored as int8
+	#[serde(default)]
+	quantized: Option<QuantizedEmbedding>,
+	/// How `hash` was computed (older sidecars are always fast hashes)
+	#[serde(default)]
+	hash_mode: HashMode,
+	/// Model and preprocessing that produced the embedding, if known
+	#[serde(default)]
+	fingerprint: Option<String>,
+	/// Source file details at scan time (absent in older sidecars)
+	#[serde(default)]
+	info: Option<MediaInfo>,
+	/// Coarse color histogram for `search --prefilter` (absent in older sidecars)
+	#[serde(default)]
+	histogram: Option<Vec<f32>>,
+	/// Absolute path of the media file at scan time (absent in older sidecars)
+	#[serde(default)]
+	source: Option<PathBuf>,
+}
+
+/// Image details recorded at scan time, so results can be described without
+/// reopening the original file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MediaInfo {
+	pub width: u32,
+	pub height: u32,
+	/// File size in bytes
+	pub size: u64,
+	/// Modification time in seconds since the Unix epoch
+	pub modified: u64,
+	/// EXIF capture time in seconds since the Unix epoch, read as UTC since
+	/// EXIF carries no timezone (absent in older sidecars and undated files)
+	#[serde(default)]
+	pub taken: Option<i64>,
+}
+
+impl MediaInfo {
+	/// Combine decoded dimensions with the file's size and mtime
+	pub fn read(path: &Path, width: u32, height: u32) -> Option<Self> {
+		let metadata = fs::metadata(path).ok()?;
+		let modified = metadata
+			.modified()
+			.ok()?
+			.duration_since(std::time::UNIX_EPOCH)
+			.ok()?
+			.as_secs();
+		Some(Self {
+			width,
+			height,
+			size: metadata.len(),
+			modified,
+			taken: crate::core::media::exif_date(path).map(|taken| taken.and_utc().timestamp()),
+		})
+	}
+
+	/// Capture time from EXIF, see [`crate::core::media::exif_date`]
+	pub fn taken(&self) -> Option<chrono::NaiveDateTime> {
+		chrono::DateTime::from_timestamp(self.taken?, 0).map(|taken| taken.naive_utc())
+	}
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -25,15 +180,35 @@ pub struct VideoSidecar {
 	version: String,
 	hash: String,
 	frames: Vec<VideoFrame>,
+	#[serde(default)]
+	hash_mode: HashMode,
+	#[serde(default)]
+	fingerprint: Option<String>,
+	#[serde(default)]
+	source: Option<PathBuf>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct VideoFrame {
 	pub timestamp: f64,
-	pub embedding: Vec<f32>,
+	embedding: StoredEmbedding,
 }
 
-#[derive(Debug)]
+/// How per-frame scores are combined into one score for a video
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoAggregation {
+	/// Highest scoring frame
+	#[default]
+	Best,
+	/// Average over all frames
+	Mean,
+	/// Average of the `k` highest scoring frames
+	#[value(name = "topk")]
+	TopK,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
 pub enum Sidecar {
 	Image(ImageSidecar),
 	Video(VideoSidecar),
@@ -44,12 +219,78 @@ impl ImageSidecar {
 		Self {
 			version: VERSION.to_string(),
 			hash: hash.as_str().to_string(),
-			embedding: embedding.as_slice().to_vec(),
+			embedding: StoredEmbedding::new(embedding.as_slice().to_vec()),
+			quantized: None,
+			hash_mode: hash.mode(),
+			fingerprint: None,
+			info: None,
+			histogram: None,
+			source: None,
+		}
+	}
+
+	/// Record where the media file lives, see [`Sidecar::source`]
+	pub fn with_source(mut self, path: &Path) -> Self {
+		self.source = Some(absolute(path));
+		self
+	}
+
+	/// Stamp the fingerprint of the model that produced the embedding,
+	/// see [`crate::models::model_fingerprint`]
+	pub fn with_fingerprint(mut self, fingerprint: Option<&str>) -> Self {
+		self.fingerprint = fingerprint.map(str::to_string);
+		self
+	}
+
+	/// Store the embedding as int8 instead of f32 (~4x smaller) when `enabled`
+	pub fn with_quantized(mut self, enabled: bool) -> Self {
+		if enabled && self.quantized.is_none() {
+			let data = std::mem::take(&mut self.embedding.data);
+			self.quantized = Some(Embedding::raw(data).quantize());
 		}
+		self
+	}
+
+	/// Store the embedding as f16 instead of f32 (lossy, ~2x smaller) when `enabled`
+	pub fn with_half(mut self, enabled: bool) -> Self {
+		self.embedding.half = enabled;
+		self
+	}
+
+	/// Apply both storage options of `format`
+	pub fn with_format(self, format: SidecarFormat) -> Self {
+		self.with_quantized(format.quantize).with_half(format.half)
+	}
+
+	/// Attach the source file's details
+	pub fn with_info(mut self, info: Option<MediaInfo>) -> Self {
+		self.info = info;
+		self
+	}
+
+	pub fn info(&self) -> Option<&MediaInfo> {
+		self.info.as_ref()
+	}
+
+	/// Attach the image's [`color_histogram`](crate::processing::image::color_histogram)
+	pub fn with_histogram(mut self, histogram: Vec<f32>) -> Self {
+		self.histogram = Some(histogram);
+		self
+	}
+
+	pub fn histogram(&self) -> Option<&[f32]> {
+		self.histogram.as_deref()
 	}
 
 	pub fn embedding(&self) -> Embedding {
-		Embedding::raw(self.embedding.clone())
+		match &self.quantized {
+			Some(quantized) => quantized.dequantize(),
+			None => Embedding::raw(self.embedding.data.clone()),
+		}
+	}
+
+	pub fn is_quantized(&self) -> bool {
+		self.quantized.is_some()
 	}
 
 	pub fn hash(&self) -> &str {
@@ -70,16 +311,45 @@ impl VideoSidecar {
 				.into_iter()
 				.map(|(ts, emb)| VideoFrame {
 					timestamp: ts,
-					embedding: emb.as_slice().to_vec(),
+					embedding: StoredEmbedding::new(emb.as_slice().to_vec()),
 				})
 				.collect(),
+			hash_mode: hash.mode(),
+			fingerprint: None,
+			source: None,
 		}
 	}
 
+	/// Record where the media file lives, see [`Sidecar::source`]
+	pub fn with_source(mut self, path: &Path) -> Self {
+		self.source = Some(absolute(path));
+		self
+	}
+
+	/// Stamp the fingerprint of the model that produced the embedding,
+	/// see [`crate::models::model_fingerprint`]
+	pub fn with_fingerprint(mut self, fingerprint: Option<&str>) -> Self {
+		self.fingerprint = fingerprint.map(str::to_string);
+		self
+	}
+
+	/// Store frame embeddings as f16 instead of f32 (lossy, ~2x smaller) when `enabled`
+	pub fn with_half(mut self, enabled: bool) -> Self {
+		for frame in &mut self.frames {
+			frame.embedding.half = enabled;
+		}
+		self
+	}
+
+	/// Number of embedded frames
+	pub fn frame_count(&self) -> usize {
+		self.frames.len()
+	}
+
 	pub fn frames(&self) -> Vec<(f64, Embedding)> {
 		self.frames
 			.iter()
-			.map(|f| (f.timestamp, Embedding::raw(f.embedding.clone())))
+			.map(|f| (f.timestamp, Embedding::raw(f.embedding.data.clone())))
 			.collect()
 	}
 
@@ -90,6 +360,116 @@ impl VideoSidecar {
 	pub fn is_current_version(&self) -> bool {
 		self.version == VERSION
 	}
+
+	/// Score the video against `query`, combining frames according to `mode`.
+	///
+	/// Returns `(score, timestamp)`, where the timestamp is always the single
+	/// best frame. `None` if the video has no frames.
+	pub fn aggregate_score(
+		&self,
+		query: &Embedding,
+		mode: VideoAggregation,
+		k: usize,
+	) -> Option<(f32, f64)> {
+		self.aggregate_by(|frame| query.similarity(frame), mode, k)
+	}
+
+	/// Like [`VideoSidecar::aggregate_score`] with a custom per-frame scorer
+	pub fn aggregate_by(
+		&self,
+		score: impl Fn(&Embedding) -> f32,
+		mode: VideoAggregation,
+		k: usize,
+	) -> Option<(f32, f64)> {
+		self.aggregate_between(score, mode, k, None, None, 1)
+	}
+
+	/// Like [`VideoSidecar::aggregate_by`], only considering frames with
+	/// `after <= timestamp <= before` (in seconds) and smoothing their scores
+	/// over `smooth` neighbouring frames, see [`VideoSidecar::frame_scores`].
+	///
+	/// Returns `None` when no frame falls in the range.
+	pub fn aggregate_between(
+		&self,
+		score: impl Fn(&Embedding) -> f32,
+		mode: VideoAggregation,
+		k: usize,
+		after: Option<f64>,
+		before: Option<f64>,
+		smooth: usize,
+	) -> Option<(f32, f64)> {
+		let scores = self.frame_scores(score, after, before, smooth);
+		let &(best_timestamp, best_score) = scores.first()?;
+
+		let aggregated = match mode {
+			VideoAggregation::Best => best_score,
+			VideoAggregation::Mean => mean(scores.iter().map(|(_, s)| *s)),
+			VideoAggregation::TopK => mean(scores.iter().take(k.max(1)).map(|(_, s)| *s)),
+		};
+
+		Some((aggregated, best_timestamp))
+	}
+
+	/// `(timestamp, score)` of every frame with `after <= timestamp <= before`,
+	/// best first. Of equal scores the earliest frame comes first.
+	///
+	/// With `smooth` above 1, each score becomes a weighted average over a
+	/// window of that many frames around it, nearer frames counting more, so
+	/// a sustained match outranks a single-frame spike.
+	pub fn frame_scores(
+		&self,
+		score: impl Fn(&Embedding) -> f32,
+		after: Option<f64>,
+		before: Option<f64>,
+		smooth: usize,
+	) -> Vec<(f64, f32)> {
+		let mut scores: Vec<(f64, f32)> = self
+			.frames
+			.iter()
+			.filter(|f| after.map_or(true, |t| f.timestamp >= t))
+			.filter(|f| before.map_or(true, |t| f.timestamp <= t))
+			.map(|f| {
+				(
+					f.timestamp,
+					score(&Embedding::raw(f.embedding.data.clone())),
+				)
+			})
+			.collect();
+
+		scores.sort_by(|a, b| a.0.total_cmp(&b.0));
+		let mut scores = smooth_scores(&scores, smooth);
+		scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.total_cmp(&b.0)));
+		scores
+	}
+}
+
+/// Triangular moving average over `window` frames of time-ordered scores.
+/// Frames near the ends average over the neighbours they have.
+fn smooth_scores(scores: &[(f64, f32)], window: usize) -> Vec<(f64, f32)> {
+	let radius = window / 2;
+	if radius == 0 {
+		return scores.to_vec();
+	}
+
+	(0..scores.len())
+		.map(|i| {
+			let neighbours = i.saturating_sub(radius)..=(i + radius).min(scores.len() - 1);
+			let (sum, total) = neighbours.fold((0.0, 0.0), |(sum, total), j| {
+				let weight = (radius + 1 - i.abs_diff(j)) as f32;
+				(sum + weight * scores[j].1, total + weight)
+			});
+			(scores[i].0, sum / total)
+		})
+		.collect()
+}
+
+fn mean(scores: impl Iterator<Item = f32>) -> f32 {
+	let (sum, count) = scores.fold((0.0, 0), |(sum, count), s| (sum + s, count + 1));
+	if count > 0 {
+		sum / count as f32
+	} else {
+		0.0
+	}
 }
 
 impl Sidecar {
@@ -100,6 +480,13 @@ impl Sidecar {
 		}
 	}
 
+	pub fn hash_mode(&self) -> HashMode {
+		match self {
+			Sidecar::Image(img) => img.hash_mode,
+			Sidecar::Video(vid) => vid.hash_mode,
+		}
+	}
+
 	pub fn is_current_version(&self) -> bool {
 		match self {
 			Sidecar::Image(img) => img.is_current_version(),
@@ -107,6 +494,45 @@ impl Sidecar {
 		}
 	}
 
+	pub fn fingerprint(&self) -> Option<&str> {
+		match self {
+			Sidecar::Image(img) => img.fingerprint.as_deref(),
+			Sidecar::Video(vid) => vid.fingerprint.as_deref(),
+		}
+	}
+
+	/// Absolute path of the media file when it was scanned.
+	///
+	/// With a central [`store`] this is how a sidecar finds its media; it may
+	/// be stale if the file was moved since.
+	pub fn source(&self) -> Option<&Path> {
+		match self {
+			Sidecar::Image(img) => img.source.as_deref(),
+			Sidecar::Video(vid) => vid.source.as_deref(),
+		}
+	}
+
+	/// Record where the media file lives, see [`Sidecar::source`]
+	pub fn with_source(self, path: &Path) -> Self {
+		match self {
+			Sidecar::Image(img) => Sidecar::Image(img.with_source(path)),
+			Sidecar::Video(vid) => Sidecar::Video(vid.with_source(path)),
+		}
+	}
+
+	/// Whether the embeddings can be kept instead of re-encoded.
+	///
+	/// When both this sidecar and the `current` model fingerprint are known,
+	/// only the fingerprint matters. A sidecar without one predates pixel
+	/// normalization and is re-encoded. Otherwise the Scout version must match.
+	pub fn is_current(&self, current: Option<&str>) -> bool {
+		match (self.fingerprint(), current) {
+			(Some(stored), Some(current)) => stored == current,
+			(None, Some(_)) => false,
+			_ => self.is_current_version(),
+		}
+	}
+
 	/// Get primary embedding for clustering (first frame for videos)
 	pub fn primary_embedding(&self) -> Embedding {
 		match self {
@@ -115,54 +541,452 @@ impl Sidecar {
 				// Use first frame embedding for videos
 				vid.frames
 					.first()
-					.map(|f| Embedding::raw(f.embedding.clone()))
-					.unwrap_or_else(|| Embedding::raw(vec![0.0; 1024]))
+					.map(|f| Embedding::raw(f.embedding.data.clone()))
+					.unwrap_or_else(|| Embedding::raw(Vec::new()))
 			}
 		}
 	}
+
+	/// Length of the stored embeddings (0 if the sidecar holds none)
+	pub fn dimension(&self) -> usize {
+		match self {
+			Sidecar::Image(img) => match &img.quantized {
+				Some(quantized) => quantized.data.len(),
+				None => img.embedding.data.len(),
+			},
+			Sidecar::Video(vid) => vid.frames.first().map_or(0, |f| f.embedding.data.len()),
+		}
+	}
 }
 
 /// Save image sidecar
 pub fn save_image(sidecar: &ImageSidecar, media_dir: &Path, hash: &FileHash) -> Result<()> {
-	let path = build_path(media_dir, hash);
-	ensure_dir(&path)?;
-	let bytes = rmp_serde::to_vec(sidecar).context("Serialize failed")?;
-	fs::write(&path, bytes).context("Write failed")?;
-	Ok(())
+	write(sidecar, &save_path(store().as_deref(), media_dir, hash)?)
 }
 
 /// Save video sidecar
 pub fn save_video(sidecar: &VideoSidecar, media_dir: &Path, hash: &FileHash) -> Result<()> {
-	let path = build_path(media_dir, hash);
-	ensure_dir(&path)?;
-	let bytes = rmp_serde::to_vec(sidecar).context("Serialize failed")?;
-	fs::write(&path, bytes).context("Write failed")?;
+	write(sidecar, &save_path(store().as_deref(), media_dir, hash)?)
+}
+
+/// Save a sidecar of either kind
+pub fn save(sidecar: &Sidecar, media_dir: &Path, hash: &FileHash) -> Result<()> {
+	match sidecar {
+		Sidecar::Image(img) => save_image(img, media_dir, hash),
+		Sidecar::Video(vid) => save_video(vid, media_dir, hash),
+	}
+}
+
+/// Serialize with a checksum header, writing to a temporary file first so an
+/// interrupted write never leaves a truncated sidecar behind
+pub(super) fn write(sidecar: &impl Serialize, path: &Path) -> Result<()> {
+	ensure_dir(path)?;
+	let payload = rmp_serde::to_vec(sidecar).context("Serialize failed")?;
+
+	let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+	bytes.extend_from_slice(MAGIC);
+	bytes.extend_from_slice(&xxh3_64(&payload).to_le_bytes());
+	bytes.extend_from_slice(&payload);
+
+	let tmp = path.with_extension(format!("{}.tmp", SIDECAR_EXT));
+	fs::write(&tmp, bytes).context("Write failed")?;
+	fs::rename(&tmp, path).context("Write failed")?;
 	Ok(())
 }
 
+/// Strip and verify the checksum header (legacy sidecars pass through unchanged)
+fn verify(bytes: &[u8]) -> Result<&[u8]> {
+	let Some(rest) = bytes.strip_prefix(MAGIC) else {
+		return Ok(bytes);
+	};
+	if rest.len() < 8 {
+		anyhow::bail!("Sidecar is truncated");
+	}
+
+	let (checksum, payload) = rest.split_at(8);
+	let expected = u64::from_le_bytes(checksum.try_into().expect("8-byte checksum"));
+	if xxh3_64(payload) != expected {
+		anyhow::bail!("Sidecar checksum mismatch (truncated or corrupt)");
+	}
+	Ok(payload)
+}
+
 /// Load sidecar (auto-detect type)
 pub fn load(path: &Path) -> Result<Sidecar> {
 	let bytes = fs::read(path).context("Read failed")?;
+	let bytes = verify(&bytes)?;
 
-	// Try video first
-	if let Ok(video) = rmp_serde::from_slice::<VideoSidecar>(&bytes) {
-		return Ok(Sidecar::Video(video));
+	// Try video first (quantized images have an empty float array that
+	// would otherwise parse as a video without frames)
+	if let Ok(video) = rmp_serde::from_slice::<VideoSidecar>(bytes) {
+		if !video.frames.is_empty() {
+			return Ok(Sidecar::Video(video));
+		}
 	}
 
 	// Fall back to image
-	let image = rmp_serde::from_slice::<ImageSidecar>(&bytes).context("Deserialize failed")?;
+	let image = rmp_serde::from_slice::<ImageSidecar>(bytes).context("Deserialize failed")?;
 	Ok(Sidecar::Image(image))
 }
 
+/// Where the sidecar for `hash` lives, in the [`sidecar_dir`] of `media_dir`
 pub fn build_path(media_dir: &Path, hash: &FileHash) -> PathBuf {
-	media_dir
-		.join(SIDECAR_DIR)
-		.join(format!("{}.{}", hash.as_str(), SIDECAR_EXT))
+	build_path_in(store().as_deref(), media_dir, hash)
+}
+
+fn build_path_in(store: Option<&Path>, media_dir: &Path, hash: &FileHash) -> PathBuf {
+	sidecar_dir_in(store, media_dir).join(format!("{}.{}", hash.as_str(), SIDECAR_EXT))
+}
+
+/// Directory holding the sidecars and directory databases (scan journal,
+/// consolidated index, caches) of `media_dir`: its `.scout` folder, or with
+/// a central [`store`] a folder in the store named after its path
+pub fn sidecar_dir(media_dir: &Path) -> PathBuf {
+	sidecar_dir_in(store().as_deref(), media_dir)
+}
+
+pub(super) fn sidecar_dir_in(store: Option<&Path>, media_dir: &Path) -> PathBuf {
+	match store {
+		Some(store) => {
+			let path = absolute(media_dir);
+			store.join(format!(
+				"{:016x}",
+				xxh3_64(path.as_os_str().as_encoded_bytes())
+			))
+		}
+		None => media_dir.join(SIDECAR_DIR),
+	}
+}
+
+/// [`build_path_in`], first recording `media_dir` in its store folder so
+/// recursive scans of the store can tell which directory it belongs to
+pub(super) fn save_path(
+	store: Option<&Path>,
+	media_dir: &Path,
+	hash: &FileHash,
+) -> Result<PathBuf> {
+	let path = build_path_in(store, media_dir, hash);
+	if store.is_some() {
+		let marker = path.with_file_name(STORE_DIR_FILE);
+		if !marker.exists() {
+			ensure_dir(&marker)?;
+			let media_dir = absolute(media_dir);
+			fs::write(&marker, media_dir.to_string_lossy().as_bytes())
+				.with_context(|| format!("Failed to write {}", marker.display()))?;
+		}
+	}
+	Ok(path)
 }
 
 fn ensure_dir(path: &Path) -> Result<()> {
 	if let Some(parent) = path.parent() {
-		fs::create_dir_all(parent).context("Failed to create .scout directory")?;
+		fs::create_dir_all(parent)
+			.with_context(|| format!("Failed to create {}", parent.display()))?;
 	}
 	Ok(())
 }
+
+fn absolute(path: &Path) -> PathBuf {
+	path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Video whose frames score 0.9, 0.5, 0.4 and 0.2 against the x axis
+	fn synthetic_video() -> (VideoSidecar, Embedding) {
+		let frame = |x: f32| Embedding::new(vec![x, (1.0 - x * x).sqrt()]);
+		let video = VideoSidecar {
+			version: VERSION.to_string(),
+			hash: "0".repeat(16),
+			frames: [(0.0, 0.5), (1.5, 0.9), (3.0, 0.2), (4.5, 0.4)]
+				.into_iter()
+				.map(|(timestamp, x)| VideoFrame {
+					timestamp,
+					embedding: StoredEmbedding::new(frame(x).0),
+				})
+				.collect(),
+			hash_mode: HashMode::Fast,
+			fingerprint: None,
+			source: None,
+		};
+		(video, Embedding::new(vec![1.0, 0.0]))
+	}
+
+	#[test]
+	fn test_video_aggregation_modes() {
+		let (video, query) = synthetic_video();
+
+		let (best, ts) = video
+			.aggregate_score(&query, VideoAggregation::Best, 2)
+			.unwrap();
+		assert!((best - 0.9).abs() < 1e-5);
+		assert_eq!(ts, 1.5);
+
+		let (mean, ts) = video
+			.aggregate_score(&query, VideoAggregation::Mean, 2)
+			.unwrap();
+		assert!((mean - 0.5).abs() < 1e-5);
+		assert_eq!(ts, 1.5);
+
+		let (topk, ts) = video
+			.aggregate_score(&query, VideoAggregation::TopK, 2)
+			.unwrap();
+		assert!((topk - 0.7).abs() < 1e-5);
+		assert_eq!(ts, 1.5);
+	}
+
+	#[test]
+	fn test_video_aggregation_in_time_range() {
+		let (video, query) = synthetic_video();
+		let score = |frame: &Embedding| query.similarity(frame);
+
+		// The 0.9 frame at 1.5s is outside, so 0.4 at 4.5s wins
+		let (best, ts) = video
+			.aggregate_between(score, VideoAggregation::Best, 1, Some(2.0), None, 1)
+			.unwrap();
+		assert!((best - 0.4).abs() < 1e-5);
+		assert_eq!(ts, 4.5);
+
+		// Bounds are inclusive
+		let (mean, _) = video
+			.aggregate_between(score, VideoAggregation::Mean, 1, Some(0.0), Some(1.5), 1)
+			.unwrap();
+		assert!((mean - 0.7).abs() < 1e-5);
+
+		assert!(video
+			.aggregate_between(score, VideoAggregation::Best, 1, Some(5.0), Some(10.0), 1)
+			.is_none());
+	}
+
+	/// Video whose frames score `scores` against the x axis, one per second
+	fn video_scoring(scores: &[f32]) -> VideoSidecar {
+		let frames = scores
+			.iter()
+			.enumerate()
+			.map(|(t, &x)| (t as f64, Embedding::new(vec![x, (1.0 - x * x).sqrt()])))
+			.collect();
+		VideoSidecar::new(FileHash::from_hex("0".repeat(16)), frames)
+	}
+
+	#[test]
+	fn test_equal_frames_pick_earliest() {
+		let mut video = video_scoring(&[0.2, 0.8, 0.5, 0.8]);
+		video.frames.reverse();
+		let score = |frame: &Embedding| frame.0[0];
+
+		let scores = video.frame_scores(score, None, None, 1);
+		assert_eq!(scores[0].0, 1.0);
+		assert_eq!(scores[1].0, 3.0);
+		let (_, timestamp) = video
+			.aggregate_between(score, VideoAggregation::Best, 1, None, None, 1)
+			.unwrap();
+		assert_eq!(timestamp, 1.0);
+	}
+
+	#[test]
+	fn test_smoothing_prefers_sustained_match() {
+		// A one-frame spike at 1s, and a sustained match around 5s
+		let video = video_scoring(&[0.1, 0.9, 0.1, 0.1, 0.7, 0.75, 0.7, 0.1]);
+		let score = |frame: &Embedding| frame.0[0];
+		let best = |smooth: usize| {
+			video
+				.aggregate_between(score, VideoAggregation::Best, 1, None, None, smooth)
+				.unwrap()
+		};
+
+		assert_eq!(best(1).1, 1.0);
+		let (smoothed, timestamp) = best(3);
+		assert_eq!(timestamp, 5.0);
+		assert!((smoothed - 0.725).abs() < 1e-3, "{}", smoothed);
+	}
+
+	#[test]
+	fn test_video_aggregation_without_frames() {
+		let video = VideoSidecar {
+			version: VERSION.to_string(),
+			hash: "0".repeat(16),
+			frames: Vec::new(),
+			hash_mode: HashMode::Fast,
+			fingerprint: None,
+			source: None,
+		};
+		let query = Embedding::new(vec![1.0, 0.0]);
+		assert!(video
+			.aggregate_score(&query, VideoAggregation::Mean, 3)
+			.is_none());
+	}
+
+	#[test]
+	fn test_half_sidecars_load_alongside_f32() {
+		let embedding = Embedding::new((0..1024).map(|i| (i as f32).sin()).collect());
+		let hash = FileHash::from_hex("0".repeat(16));
+
+		let full = rmp_serde::to_vec(&ImageSidecar::new(hash.clone(), embedding.clone())).unwrap();
+		let half =
+			rmp_serde::to_vec(&ImageSidecar::new(hash, embedding.clone()).with_half(true)).unwrap();
+
+		assert!(half.len() < full.len() * 6 / 10);
+		for bytes in [full, half] {
+			let sidecar: ImageSidecar = rmp_serde::from_slice(&bytes).unwrap();
+			assert_eq!(sidecar.embedding().as_slice().len(), 1024);
+			assert!(sidecar.embedding().similarity(&embedding) > 0.999);
+		}
+	}
+
+	#[test]
+	fn test_quantized_sidecars_load_alongside_f32() {
+		let embedding = Embedding::new((0..1024).map(|i| (i as f32).cos()).collect());
+		let hash = FileHash::from_hex("0".repeat(16));
+
+		let full = ImageSidecar::new(hash.clone(), embedding.clone()).with_quantized(false);
+		let quantized = ImageSidecar::new(hash, embedding.clone()).with_quantized(true);
+		assert!(!full.is_quantized());
+		assert!(quantized.is_quantized());
+
+		let quantized: ImageSidecar =
+			rmp_serde::from_slice(&rmp_serde::to_vec(&quantized).unwrap()).unwrap();
+		assert!(quantized.embedding().similarity(&embedding) > 0.99);
+		assert_eq!(full.embedding().as_slice(), embedding.as_slice());
+	}
+
+	#[test]
+	fn test_fingerprint_outlives_version_bumps() {
+		let hash = FileHash::from_hex("0".repeat(16));
+		let mut image = ImageSidecar::new(hash, Embedding::new(vec![0.6, 0.8]))
+			.with_fingerprint(Some("model-a"));
+		image.version = "0.0.1".to_string();
+		let sidecar = Sidecar::Image(image);
+
+		let legacy = Sidecar::Image(ImageSidecar::new(
+			FileHash::from_hex("0".repeat(16)),
+			Embedding::new(vec![0.6, 0.8]),
+		));
+
+		assert!(
+			sidecar.is_current(Some("model-a")),
+			"version bump alone must not force a re-embed"
+		);
+		assert!(!sidecar.is_current(Some("model-b")));
+		assert!(!sidecar.is_current(None), "falls back to the version check");
+		assert!(
+			!legacy.is_current(Some("model-a")),
+			"written before fingerprints and normalization"
+		);
+	}
+
+	#[test]
+	fn test_mixed_models_are_detected() {
+		let sidecar = |i: usize, fingerprint: Option<&str>| {
+			let hash = FileHash::from_hex(format!("{:016x}", i));
+			let mut image = ImageSidecar::new(hash, Embedding::new(vec![0.6, 0.8]));
+			image.fingerprint = fingerprint.map(str::to_string);
+			Sidecar::Image(image)
+		};
+		let old = [sidecar(0, Some("model-a")), sidecar(1, Some("model-a"))];
+		let new = [sidecar(2, Some("model-b")), sidecar(3, None)];
+
+		assert_eq!(
+			crate::storage::model_fingerprints(old.iter().chain(&new)),
+			vec![("model-a".to_string(), 2), ("model-b".to_string(), 1)]
+		);
+		assert!(crate::storage::check_models(&old, true).is_ok());
+		assert!(crate::storage::check_models(&new, true).is_ok());
+		assert!(crate::storage::check_models(old.iter().chain(&new), true).is_err());
+		assert!(crate::storage::check_models(old.iter().chain(&new), false).is_ok());
+	}
+
+	#[test]
+	fn test_media_info_is_optional() {
+		let hash = FileHash::from_hex("0".repeat(16));
+		let info = MediaInfo {
+			width: 1920,
+			height: 1080,
+			size: 524_288,
+			modified: 1_700_000_000,
+			taken: Some(1_686_735_000),
+		};
+		let image =
+			ImageSidecar::new(hash.clone(), Embedding::new(vec![0.6, 0.8])).with_info(Some(info));
+		let bytes = rmp_serde::to_vec(&image).unwrap();
+		let loaded: ImageSidecar = rmp_serde::from_slice(&bytes).unwrap();
+		assert_eq!(loaded.info(), Some(&info));
+
+		// Sidecars written before the field existed end at the fingerprint
+		let older = (
+			VERSION,
+			hash.as_str(),
+			vec![0.6f32, 0.8],
+			None::<QuantizedEmbedding>,
+			HashMode::Fast,
+			None::<String>,
+		);
+		let bytes = rmp_serde::to_vec(&older).unwrap();
+		let loaded: ImageSidecar = rmp_serde::from_slice(&bytes).unwrap();
+		assert_eq!(loaded.info(), None);
+
+		// Info written before the capture date was recorded has no date
+		let older_info = (1920u32, 1080u32, 524_288u64, 1_700_000_000u64);
+		let bytes = rmp_serde::to_vec(&older_info).unwrap();
+		let loaded: MediaInfo = rmp_serde::from_slice(&bytes).unwrap();
+		assert_eq!(loaded.taken, None);
+		assert_eq!(info.taken().unwrap().to_string(), "2023-06-14 09:30:00");
+	}
+
+	#[test]
+	fn test_central_store_round_trip() {
+		let temp = tempfile::tempdir().unwrap();
+		let base = temp.path();
+		let media = base.join("photos");
+		let store = base.join("store");
+		let hash = FileHash::from_hex("1".repeat(16));
+		let source = media.join("cat.jpg");
+		let sidecar =
+			ImageSidecar::new(hash.clone(), Embedding::new(vec![0.6, 0.8])).with_source(&source);
+
+		let path = save_path(Some(&store), &media, &hash).unwrap();
+		write(&sidecar, &path).unwrap();
+		let loaded = load(&path).unwrap();
+		let marker = fs::read_to_string(path.with_file_name(STORE_DIR_FILE)).unwrap();
+		let local = build_path_in(None, &media, &hash);
+
+		assert_eq!(
+			path.parent(),
+			Some(sidecar_dir_in(Some(&store), &media).as_path())
+		);
+		assert_eq!(path.parent().and_then(Path::parent), Some(store.as_path()));
+		assert_eq!(marker, media.to_string_lossy());
+		assert_eq!(
+			local,
+			media
+				.join(SIDECAR_DIR)
+				.join(format!("{}.{}", hash.as_str(), SIDECAR_EXT))
+		);
+		assert!(!media.exists(), "nothing is written beside the media");
+		assert_eq!(loaded.hash(), hash.as_str());
+		assert_eq!(loaded.source(), Some(source.as_path()));
+	}
+
+	#[test]
+	fn test_truncated_sidecar_is_detected() {
+		let temp = tempfile::tempdir().unwrap();
+		let dir = temp.path();
+		let hash = FileHash::from_hex("0".repeat(16));
+		let embedding = Embedding::new(vec![0.6, 0.8]);
+		save_image(&ImageSidecar::new(hash.clone(), embedding), dir, &hash).unwrap();
+
+		let path = build_path(dir, &hash);
+		assert!(load(&path).is_ok());
+
+		let bytes = fs::read(&path).unwrap();
+		fs::write(&path, &bytes[..bytes.len() - 3]).unwrap();
+		let error = load(&path).unwrap_err();
+		assert!(error.to_string().contains("checksum"), "{}", error);
+
+		// Sidecars written before checksums still load
+		let legacy = ImageSidecar::new(hash, Embedding::new(vec![1.0, 0.0]));
+		fs::write(&path, rmp_serde::to_vec(&legacy).unwrap()).unwrap();
+		assert!(load(&path).is_ok());
+	}
+}
@@ -5,10 +5,18 @@
 
 use colored::*;
 use rand::Rng;
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+
+use super::progress::suspend;
 
 static VERBOSE: AtomicBool = AtomicBool::new(false);
 
+/// Whether [`path_link`] emits OSC 8 hyperlinks, detected on first use
+static HYPERLINKS: AtomicU8 = AtomicU8::new(LINKS_DETECT);
+const LINKS_DETECT: u8 = 0;
+const LINKS_ON: u8 = 1;
+const LINKS_OFF: u8 = 2;
+
 const LOGO: &str = r#"
    _____                  __ 
   / ___/_________  __  __/ /_
@@ -52,46 +60,85 @@ impl Log {
 	pub fn is_verbose() -> bool {
 		VERBOSE.load(Ordering::Relaxed)
 	}
+
+	/// Turn OSC 8 hyperlinks in [`path_link`] on or off, overriding detection
+	pub fn set_hyperlinks(enabled: bool) {
+		let mode = if enabled { LINKS_ON } else { LINKS_OFF };
+		HYPERLINKS.store(mode, Ordering::Relaxed);
+	}
+
+	/// Whether [`path_link`] emits hyperlinks. Unless set, they are on when
+	/// stdout is a terminal, `TERM` isn't `dumb` and neither `NO_HYPERLINKS`
+	/// nor `NO_COLOR` is set.
+	pub fn hyperlinks() -> bool {
+		match HYPERLINKS.load(Ordering::Relaxed) {
+			LINKS_ON => true,
+			LINKS_OFF => false,
+			_ => {
+				let detected = detect_hyperlinks();
+				Self::set_hyperlinks(detected);
+				detected
+			}
+		}
+	}
 }
 
+fn detect_hyperlinks() -> bool {
+	use std::io::IsTerminal;
+
+	let set = |name: &str| std::env::var_os(name).is_some_and(|value| !value.is_empty());
+	let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+	!set("NO_HYPERLINKS") && !set("NO_COLOR") && !dumb && std::io::stdout().is_terminal()
+}
+
+// Log lines are printed above an active progress bar instead of through it
+
 pub fn info(msg: &str) {
-	eprintln!("{} {}", "ℹ".bright_blue().bold(), msg.bright_white());
+	suspend(|| eprintln!("{} {}", "ℹ".bright_blue().bold(), msg.bright_white()));
 }
 
 pub fn success(msg: &str) {
-	eprintln!("{} {}", "✓".bright_green().bold(), msg.bright_white());
+	suspend(|| eprintln!("{} {}", "✓".bright_green().bold(), msg.bright_white()));
 }
 
 pub fn warn(msg: &str) {
-	eprintln!("{} {}", "⚠".bright_yellow().bold(), msg.bright_white());
+	suspend(|| eprintln!("{} {}", "⚠".bright_yellow().bold(), msg.bright_white()));
 }
 
 pub fn error(msg: &str) {
-	eprintln!("{} {}", "✗".bright_red().bold(), msg.bright_white());
+	suspend(|| eprintln!("{} {}", "✗".bright_red().bold(), msg.bright_white()));
 }
 
 pub fn debug(msg: &str) {
 	if Log::is_verbose() {
-		eprintln!("{} {}", "⚙".bright_black().bold(), msg.dimmed());
+		suspend(|| eprintln!("{} {}", "⚙".bright_black().bold(), msg.dimmed()));
 	}
 }
 
+/// Ask a yes/no question on stderr, defaulting to no
+pub fn confirm(prompt: &str) -> bool {
+	eprint!(
+		"{} {} {} ",
+		"?".bright_yellow().bold(),
+		prompt.bright_white(),
+		"[y/N]".dimmed()
+	);
+	let _ = std::io::Write::flush(&mut std::io::stderr());
+
+	let mut answer = String::new();
+	if std::io::stdin().read_line(&mut answer).is_err() {
+		return false;
+	}
+	matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+}
+
 pub fn header(text: &str) {
 	eprintln!("\n{}", text.bright_blue().bold());
 }
 
-/// Clickable file path (OSC 8 terminal hyperlink)
+/// Clickable file path (OSC 8 terminal hyperlink), or just the shortened
+/// filename when hyperlinks are off (see [`Log::hyperlinks`])
 pub fn path_link(path: &std::path::Path, max_len: usize) -> String {
-	let absolute = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
-
-	let uri = if cfg!(windows) {
-		let path_str = absolute.to_string_lossy();
-		let cleaned = path_str.strip_prefix(r"\\?\").unwrap_or(&path_str);
-		format!("file:///{}", cleaned.replace('\\', "/"))
-	} else {
-		format!("file://{}", absolute.display())
-	};
-
 	let filename = path
 		.file_name()
 		.and_then(|n| n.to_str())
@@ -107,6 +154,19 @@ pub fn path_link(path: &std::path::Path, max_len: usize) -> String {
 		filename.to_string()
 	};
 
+	if !Log::hyperlinks() {
+		return display_name;
+	}
+
+	let absolute = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+	let uri = if cfg!(windows) {
+		let path_str = absolute.to_string_lossy();
+		let cleaned = path_str.strip_prefix(r"\\?\").unwrap_or(&path_str);
+		format!("file:///{}", cleaned.replace('\\', "/"))
+	} else {
+		format!("file://{}", absolute.display())
+	};
+
 	format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", uri, display_name)
 }
 
@@ -157,3 +217,23 @@ pub fn color_gradient(value: f32, min: f32, max: f32, is_dimmed: bool) -> Colore
 
 	text.truecolor(r, g, b)
 }
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn test_path_link_without_hyperlinks_is_plain() {
+		let path = std::path::Path::new("photos/a-very-long-holiday-photo-name-from-the-beach.jpg");
+
+		Log::set_hyperlinks(false);
+		let plain = path_link(path, 20);
+		Log::set_hyperlinks(true);
+		let linked = path_link(path, 20);
+
+		assert!(!plain.contains('\x1b'), "{:?}", plain);
+		assert_eq!(plain, "a-very-lon...ach.jpg");
+		assert!(linked.starts_with("\x1b]8;;file://"), "{:?}", linked);
+		assert!(linked.contains(&plain));
+	}
+}
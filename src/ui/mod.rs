@@ -1,7 +1,9 @@
 //! # User Interface
 //!
-//! Colored terminal output with clickable file links.
+//! Colored terminal output with clickable file links and progress bars.
 
 pub mod log;
+pub mod progress;
 
-pub use log::{debug, error, header, info, path_link, success, warn, Log};
+pub use log::{confirm, debug, error, header, info, path_link, success, warn, Log};
+pub use progress::Progress;
@@ -0,0 +1,92 @@
+//! # Progress Bar
+//!
+//! Processed/total count, rate and ETA for long-running commands, drawn on
+//! stderr. Only shown in interactive terminals.
+
+use indicatif::{ProgressBar, ProgressStyle};
+use std::io::IsTerminal;
+use std::sync::Mutex;
+
+/// Bar currently on screen, so log lines can be printed above it
+static ACTIVE: Mutex<Option<ProgressBar>> = Mutex::new(None);
+
+const TEMPLATE: &str =
+	"{spinner:.blue} [{bar:30.blue/bright_black}] {pos}/{len} {msg} ({per_sec}, ETA {eta})";
+const BYTES_TEMPLATE: &str = "{spinner:.blue} [{bar:30.blue/bright_black}] {bytes}/{total_bytes} {msg} ({bytes_per_sec}, ETA {eta})";
+
+/// Progress bar that clears itself when finished or dropped.
+///
+/// Hidden when stdout or stderr isn't a terminal, so piped output stays clean.
+pub struct Progress {
+	bar: Option<ProgressBar>,
+}
+
+impl Progress {
+	/// Start a bar counting `unit` (e.g. "files") up to `total`
+	pub fn start(total: usize, unit: &str) -> Self {
+		Self::with_template(TEMPLATE, total as u64, unit)
+	}
+
+	/// Start a bar counting bytes of `label` (e.g. a file name) up to `total`
+	pub fn bytes(total: u64, label: &str) -> Self {
+		Self::with_template(BYTES_TEMPLATE, total, label)
+	}
+
+	fn with_template(template: &str, total: u64, message: &str) -> Self {
+		let interactive = std::io::stdout().is_terminal() && std::io::stderr().is_terminal();
+		if !interactive || total == 0 {
+			return Self { bar: None };
+		}
+
+		let style = ProgressStyle::with_template(template)
+			.expect("valid progress template")
+			.progress_chars("━╸ ");
+		let bar = ProgressBar::new(total)
+			.with_style(style)
+			.with_message(message.to_string());
+		bar.enable_steady_tick(std::time::Duration::from_millis(100));
+
+		*ACTIVE.lock().unwrap() = Some(bar.clone());
+		Self { bar: Some(bar) }
+	}
+
+	/// Whether the bar is drawn (false when output is redirected)
+	pub fn is_visible(&self) -> bool {
+		self.bar.is_some()
+	}
+
+	/// Count one more finished item
+	pub fn inc(&self) {
+		self.inc_by(1);
+	}
+
+	/// Count `n` more finished items or bytes
+	pub fn inc_by(&self, n: u64) {
+		if let Some(bar) = &self.bar {
+			bar.inc(n);
+		}
+	}
+
+	/// Remove the bar from the terminal
+	pub fn finish(self) {
+		drop(self);
+	}
+}
+
+impl Drop for Progress {
+	fn drop(&mut self) {
+		if let Some(bar) = self.bar.take() {
+			*ACTIVE.lock().unwrap() = None;
+			bar.finish_and_clear();
+		}
+	}
+}
+
+/// Run `f` with the active bar (if any) hidden, for printing log lines
+pub(crate) fn suspend<R>(f: impl FnOnce() -> R) -> R {
+	let active = ACTIVE.lock().unwrap().clone();
+	match active {
+		Some(bar) => bar.suspend(f),
+		None => f(),
+	}
+}
@@ -0,0 +1,64 @@
+//! Starts `scout serve` and talks to it over its JSON-lines protocol.
+
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpStream;
+use std::process::{Command, Stdio};
+use std::time::{Duration, Instant};
+
+#[test]
+#[ignore = "requires model files (set SCOUT_MODELS_DIR)"]
+fn test_serve_round_trips_encode_text() {
+	let addr = format!("127.0.0.1:{}", 20_000 + std::process::id() % 20_000);
+	let mut server = Command::new(env!("CARGO_BIN_EXE_scout"))
+		.args(["serve", "--addr", &addr])
+		.stdout(Stdio::null())
+		.stderr(Stdio::null())
+		.spawn()
+		.unwrap();
+
+	// Models take a while to load before the server starts listening
+	let started = Instant::now();
+	let stream = loop {
+		match TcpStream::connect(&addr) {
+			Ok(stream) => break stream,
+			Err(_) if started.elapsed() < Duration::from_secs(120) => {
+				std::thread::sleep(Duration::from_millis(250))
+			}
+			Err(e) => {
+				server.kill().unwrap();
+				server.wait().unwrap();
+				panic!("server never came up: {}", e);
+			}
+		}
+	};
+
+	let mut writer = stream.try_clone().unwrap();
+	let mut reader = BufReader::new(stream);
+	let mut request = |line: &str| -> serde_json::Value {
+		writeln!(writer, "{}", line).unwrap();
+		let mut response = String::new();
+		reader.read_line(&mut response).unwrap();
+		serde_json::from_str(&response).unwrap()
+	};
+
+	let encoded = request(r#"{"v":1,"op":"encode_text","text":"a photo of a dog"}"#);
+	let bad = request(r#"{"op":"encode_text"}"#);
+	server.kill().unwrap();
+	server.wait().unwrap();
+
+	assert_eq!(encoded["v"], 1);
+	let embedding = encoded["embedding"].as_array().unwrap();
+	assert!(!embedding.is_empty());
+	let norm: f64 = embedding
+		.iter()
+		.map(|x| x.as_f64().unwrap().powi(2))
+		.sum::<f64>()
+		.sqrt();
+	assert!((norm - 1.0).abs() < 1e-3);
+
+	// The connection stays usable after a bad request
+	assert!(bad["error"]
+		.as_str()
+		.unwrap()
+		.starts_with("Invalid request"));
+}
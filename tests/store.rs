@@ -0,0 +1,73 @@
+//! Scans a read-only folder into a central store and searches it.
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+fn scout(store: &Path, args: &[&str]) -> Output {
+	Command::new(env!("CARGO_BIN_EXE_scout"))
+		.arg("--store")
+		.arg(store)
+		.args(args)
+		.output()
+		.unwrap()
+}
+
+#[cfg(unix)]
+#[test]
+#[ignore = "requires model files (set SCOUT_MODELS_DIR)"]
+fn test_scan_into_store_leaves_media_untouched() {
+	use std::os::unix::fs::PermissionsExt;
+
+	let temp = tempfile::tempdir().unwrap();
+	let base = temp.path();
+	let media = base.join("media");
+	let store = base.join("store");
+	std::fs::create_dir_all(media.join("nested")).unwrap();
+	for (name, color) in [
+		("red.png", [220, 30, 30]),
+		("nested/blue.png", [30, 30, 220]),
+	] {
+		image::RgbImage::from_pixel(64, 64, image::Rgb(color))
+			.save(media.join(name))
+			.unwrap();
+	}
+	let dir = media.to_str().unwrap();
+	std::fs::set_permissions(&media, std::fs::Permissions::from_mode(0o555)).unwrap();
+
+	let scan = scout(&store, &["-r", "scan", "-d", dir]);
+	let search = scout(&store, &["-r", "search", "red", "-d", dir, "--paths"]);
+	let rescan = scout(&store, &["-r", "scan", "-d", dir]);
+
+	let beside_media = media.join(".scout").exists() || media.join("nested/.scout").exists();
+	let journals = std::fs::read_dir(&store)
+		.unwrap()
+		.filter(|entry| {
+			let path = entry.as_ref().unwrap().path();
+			path.join("scan_journal.msgpack").exists()
+		})
+		.count();
+	std::fs::set_permissions(&media, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+	assert!(
+		scan.status.success(),
+		"{}",
+		String::from_utf8_lossy(&scan.stderr)
+	);
+	assert!(!beside_media, "nothing is written beside the media");
+	assert_eq!(journals, 1, "the scan journal goes into the store");
+
+	let paths = String::from_utf8_lossy(&search.stdout);
+	assert!(
+		paths.lines().next().is_some_and(|p| p.ends_with("red.png")),
+		"{}",
+		paths
+	);
+
+	// The second scan finds both sidecars in the store
+	let rescanned = String::from_utf8_lossy(&rescan.stderr);
+	assert!(
+		rescanned.contains("All 2 files already indexed"),
+		"{}",
+		rescanned
+	);
+}